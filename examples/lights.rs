@@ -41,7 +41,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
         .await
         .expect("Unable to fetch adapter list.")
         .into_iter()
-        .nth(0)
+        .next()
         .expect("Unable to find adapters.");
 
     // start scanning for devices
@@ -71,7 +71,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
     for _ in 0..20 {
         let color_cmd = vec![0x56, rng.gen(), rng.gen(), rng.gen(), 0x00, 0xF0, 0xAA];
         light
-            .write(&cmd_char, &color_cmd, WriteType::WithoutResponse)
+            .write(cmd_char, &color_cmd, WriteType::WithoutResponse)
             .await?;
         time::sleep(Duration::from_millis(200)).await;
     }
@@ -0,0 +1,807 @@
+//! Parsing and building of raw advertising data, as found in BLE advertising and scan response
+//! packets and in Classic extended inquiry responses.
+//!
+//! The data is a sequence of AD structures, each made of a length byte, an AD type and the data for
+//! that type. [`AdvertisingData::parse`] decodes a whole buffer into typed [`AdStructure`]s, and
+//! [`AdvertisingData::to_bytes`] encodes them back. Structures with types not known here are kept
+//! as [`AdStructure::Unknown`], so parsing and building round-trip.
+//!
+//! # Example
+//!
+//! ```
+//! use btleplug::api::advertisement::{AdStructure, AdvertisingData, AdvertisingFlags};
+//!
+//! let data = AdvertisingData::parse(&[
+//!     0x02, 0x01, 0x06, // Flags: LE General Discoverable, BR/EDR not supported
+//!     0x05, 0x09, b'T', b'e', b's', b't', // Complete local name
+//!     0x03, 0x03, 0x0D, 0x18, // Complete list of 16-bit service UUIDs: 0x180D
+//! ])
+//! .unwrap();
+//! assert_eq!(
+//!     data.flags(),
+//!     Some(AdvertisingFlags::LE_GENERAL_DISCOVERABLE | AdvertisingFlags::BR_EDR_NOT_SUPPORTED)
+//! );
+//! assert_eq!(data.local_name(), Some("Test"));
+//! assert_eq!(data.structures.len(), 3);
+//! ```
+
+use super::bleuuid::{uuid_from_u16, uuid_from_u32};
+use super::{AddressType, BDAddr, PeripheralProperties};
+use bitflags::bitflags;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// The assigned numbers of the AD types handled by this module, from the Bluetooth
+/// [Assigned Numbers](https://www.bluetooth.com/specifications/assigned-numbers/) document.
+pub mod ad_type {
+    pub const FLAGS: u8 = 0x01;
+    pub const INCOMPLETE_SERVICE_UUIDS_16: u8 = 0x02;
+    pub const COMPLETE_SERVICE_UUIDS_16: u8 = 0x03;
+    pub const INCOMPLETE_SERVICE_UUIDS_32: u8 = 0x04;
+    pub const COMPLETE_SERVICE_UUIDS_32: u8 = 0x05;
+    pub const INCOMPLETE_SERVICE_UUIDS_128: u8 = 0x06;
+    pub const COMPLETE_SERVICE_UUIDS_128: u8 = 0x07;
+    pub const SHORTENED_LOCAL_NAME: u8 = 0x08;
+    pub const COMPLETE_LOCAL_NAME: u8 = 0x09;
+    pub const TX_POWER_LEVEL: u8 = 0x0A;
+    pub const PERIPHERAL_CONNECTION_INTERVAL_RANGE: u8 = 0x12;
+    pub const SERVICE_SOLICITATION_UUIDS_16: u8 = 0x14;
+    pub const SERVICE_SOLICITATION_UUIDS_128: u8 = 0x15;
+    pub const SERVICE_DATA_16: u8 = 0x16;
+    pub const PUBLIC_TARGET_ADDRESS: u8 = 0x17;
+    pub const RANDOM_TARGET_ADDRESS: u8 = 0x18;
+    pub const APPEARANCE: u8 = 0x19;
+    pub const ADVERTISING_INTERVAL: u8 = 0x1A;
+    pub const LE_DEVICE_ADDRESS: u8 = 0x1B;
+    pub const LE_ROLE: u8 = 0x1C;
+    pub const SERVICE_SOLICITATION_UUIDS_32: u8 = 0x1F;
+    pub const SERVICE_DATA_32: u8 = 0x20;
+    pub const SERVICE_DATA_128: u8 = 0x21;
+    pub const URI: u8 = 0x24;
+    pub const MANUFACTURER_SPECIFIC_DATA: u8 = 0xFF;
+}
+
+/// An error parsing or building advertising data.
+#[derive(Debug, thiserror::Error, Clone, PartialEq, Eq)]
+pub enum AdvertisingDataError {
+    #[error("AD structure at offset {offset} runs past the end of the data")]
+    Truncated { offset: usize },
+    #[error("Invalid length {length} for AD type {ad_type:#04x}")]
+    InvalidLength { ad_type: u8, length: usize },
+    #[error("Invalid value for AD type {ad_type:#04x}")]
+    InvalidValue { ad_type: u8 },
+    #[error("Data for AD type {ad_type:#04x} is too long to encode")]
+    TooLong { ad_type: u8 },
+}
+
+bitflags! {
+    /// The contents of the Flags AD type.
+    #[derive(Default)]
+    pub struct AdvertisingFlags: u8 {
+        const LE_LIMITED_DISCOVERABLE = 0x01;
+        const LE_GENERAL_DISCOVERABLE = 0x02;
+        const BR_EDR_NOT_SUPPORTED = 0x04;
+        const SIMULTANEOUS_LE_BR_EDR_CONTROLLER = 0x08;
+        const SIMULTANEOUS_LE_BR_EDR_HOST = 0x10;
+    }
+}
+
+/// The LE role capabilities and preferences of a device.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum LeRole {
+    PeripheralOnly,
+    CentralOnly,
+    PeripheralPreferred,
+    CentralPreferred,
+}
+
+/// A single typed AD structure.
+///
+/// Short service UUIDs are kept in their advertised size, so that they are encoded back the same
+/// way; [`AdvertisingData`] provides accessors which convert them to full UUIDs.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum AdStructure {
+    Flags(AdvertisingFlags),
+    ServiceUuids16 {
+        uuids: Vec<u16>,
+        /// Whether this is the complete list of services of this size.
+        complete: bool,
+    },
+    ServiceUuids32 {
+        uuids: Vec<u32>,
+        /// Whether this is the complete list of services of this size.
+        complete: bool,
+    },
+    ServiceUuids128 {
+        uuids: Vec<Uuid>,
+        /// Whether this is the complete list of services of this size.
+        complete: bool,
+    },
+    ShortenedLocalName(String),
+    CompleteLocalName(String),
+    /// The transmission power level, in dBm.
+    TxPowerLevel(i8),
+    /// The preferred range of connection intervals, in units of 1.25 ms.
+    PeripheralConnectionIntervalRange {
+        min: u16,
+        max: u16,
+    },
+    ServiceSolicitation16(Vec<u16>),
+    ServiceSolicitation32(Vec<u32>),
+    ServiceSolicitation128(Vec<Uuid>),
+    ServiceData16 {
+        uuid: u16,
+        data: Vec<u8>,
+    },
+    ServiceData32 {
+        uuid: u32,
+        data: Vec<u8>,
+    },
+    ServiceData128 {
+        uuid: Uuid,
+        data: Vec<u8>,
+    },
+    PublicTargetAddresses(Vec<BDAddr>),
+    RandomTargetAddresses(Vec<BDAddr>),
+    /// The external appearance of the device, as an assigned Appearance value.
+    Appearance(u16),
+    /// The advertising interval, in units of 0.625 ms.
+    AdvertisingInterval(u16),
+    LeDeviceAddress {
+        address: BDAddr,
+        address_type: AddressType,
+    },
+    LeRole(LeRole),
+    /// A URI. Well-known schemes are expanded from their encoded form; URIs with other scheme codes
+    /// keep the raw code as their first character.
+    Uri(String),
+    ManufacturerSpecificData {
+        company_id: u16,
+        data: Vec<u8>,
+    },
+    /// An AD structure of a type which isn't decoded by this module.
+    Unknown {
+        ad_type: u8,
+        data: Vec<u8>,
+    },
+}
+
+/// The URI scheme name string mappings we expand. The code is encoded as a single UTF-8
+/// character; code 0x01 means the URI is used as is.
+const URI_SCHEMES: [(char, &str); 3] = [('\u{01}', ""), ('\u{16}', "http:"), ('\u{17}', "https:")];
+
+impl AdStructure {
+    /// Parses the data of a single AD structure of the given type, without its length and type
+    /// bytes.
+    pub fn parse(ad_type: u8, data: &[u8]) -> Result<Self, AdvertisingDataError> {
+        let invalid_length = || AdvertisingDataError::InvalidLength {
+            ad_type,
+            length: data.len(),
+        };
+        let exact = |length: usize| {
+            if data.len() == length {
+                Ok(data)
+            } else {
+                Err(invalid_length())
+            }
+        };
+        let at_least = |length: usize| {
+            if data.len() >= length {
+                Ok(data)
+            } else {
+                Err(invalid_length())
+            }
+        };
+        let list = |size: usize| {
+            let chunks = data.chunks_exact(size);
+            if chunks.remainder().is_empty() {
+                Ok(chunks)
+            } else {
+                Err(invalid_length())
+            }
+        };
+
+        Ok(match ad_type {
+            ad_type::FLAGS => {
+                // Only the first octet is defined; any others are reserved.
+                AdStructure::Flags(AdvertisingFlags::from_bits_truncate(at_least(1)?[0]))
+            }
+            ad_type::INCOMPLETE_SERVICE_UUIDS_16 | ad_type::COMPLETE_SERVICE_UUIDS_16 => {
+                AdStructure::ServiceUuids16 {
+                    uuids: list(2)?.map(read_u16).collect(),
+                    complete: ad_type == ad_type::COMPLETE_SERVICE_UUIDS_16,
+                }
+            }
+            ad_type::INCOMPLETE_SERVICE_UUIDS_32 | ad_type::COMPLETE_SERVICE_UUIDS_32 => {
+                AdStructure::ServiceUuids32 {
+                    uuids: list(4)?.map(read_u32).collect(),
+                    complete: ad_type == ad_type::COMPLETE_SERVICE_UUIDS_32,
+                }
+            }
+            ad_type::INCOMPLETE_SERVICE_UUIDS_128 | ad_type::COMPLETE_SERVICE_UUIDS_128 => {
+                AdStructure::ServiceUuids128 {
+                    uuids: list(16)?.map(read_uuid).collect(),
+                    complete: ad_type == ad_type::COMPLETE_SERVICE_UUIDS_128,
+                }
+            }
+            ad_type::SHORTENED_LOCAL_NAME => {
+                AdStructure::ShortenedLocalName(String::from_utf8_lossy(data).into_owned())
+            }
+            ad_type::COMPLETE_LOCAL_NAME => {
+                AdStructure::CompleteLocalName(String::from_utf8_lossy(data).into_owned())
+            }
+            ad_type::TX_POWER_LEVEL => AdStructure::TxPowerLevel(exact(1)?[0] as i8),
+            ad_type::PERIPHERAL_CONNECTION_INTERVAL_RANGE => {
+                let data = exact(4)?;
+                AdStructure::PeripheralConnectionIntervalRange {
+                    min: read_u16(&data[..2]),
+                    max: read_u16(&data[2..]),
+                }
+            }
+            ad_type::SERVICE_SOLICITATION_UUIDS_16 => {
+                AdStructure::ServiceSolicitation16(list(2)?.map(read_u16).collect())
+            }
+            ad_type::SERVICE_SOLICITATION_UUIDS_32 => {
+                AdStructure::ServiceSolicitation32(list(4)?.map(read_u32).collect())
+            }
+            ad_type::SERVICE_SOLICITATION_UUIDS_128 => {
+                AdStructure::ServiceSolicitation128(list(16)?.map(read_uuid).collect())
+            }
+            ad_type::SERVICE_DATA_16 => {
+                let (uuid, data) = at_least(2)?.split_at(2);
+                AdStructure::ServiceData16 {
+                    uuid: read_u16(uuid),
+                    data: data.to_vec(),
+                }
+            }
+            ad_type::SERVICE_DATA_32 => {
+                let (uuid, data) = at_least(4)?.split_at(4);
+                AdStructure::ServiceData32 {
+                    uuid: read_u32(uuid),
+                    data: data.to_vec(),
+                }
+            }
+            ad_type::SERVICE_DATA_128 => {
+                let (uuid, data) = at_least(16)?.split_at(16);
+                AdStructure::ServiceData128 {
+                    uuid: read_uuid(uuid),
+                    data: data.to_vec(),
+                }
+            }
+            ad_type::PUBLIC_TARGET_ADDRESS => {
+                AdStructure::PublicTargetAddresses(list(6)?.map(read_address).collect())
+            }
+            ad_type::RANDOM_TARGET_ADDRESS => {
+                AdStructure::RandomTargetAddresses(list(6)?.map(read_address).collect())
+            }
+            ad_type::APPEARANCE => AdStructure::Appearance(read_u16(exact(2)?)),
+            ad_type::ADVERTISING_INTERVAL => AdStructure::AdvertisingInterval(read_u16(exact(2)?)),
+            ad_type::LE_DEVICE_ADDRESS => {
+                let data = exact(7)?;
+                AdStructure::LeDeviceAddress {
+                    address: read_address(&data[..6]),
+                    address_type: if data[6] & 0x01 == 0 {
+                        AddressType::Public
+                    } else {
+                        AddressType::Random
+                    },
+                }
+            }
+            ad_type::LE_ROLE => AdStructure::LeRole(match exact(1)?[0] {
+                0x00 => LeRole::PeripheralOnly,
+                0x01 => LeRole::CentralOnly,
+                0x02 => LeRole::PeripheralPreferred,
+                0x03 => LeRole::CentralPreferred,
+                _ => return Err(AdvertisingDataError::InvalidValue { ad_type }),
+            }),
+            ad_type::URI => {
+                let uri = std::str::from_utf8(data)
+                    .map_err(|_| AdvertisingDataError::InvalidValue { ad_type })?;
+                let mut chars = uri.chars();
+                let scheme = chars
+                    .next()
+                    .and_then(|code| URI_SCHEMES.iter().find(|(c, _)| *c == code));
+                AdStructure::Uri(match scheme {
+                    Some((_, scheme)) => format!("{}{}", scheme, chars.as_str()),
+                    None => uri.to_string(),
+                })
+            }
+            ad_type::MANUFACTURER_SPECIFIC_DATA => {
+                let (company_id, data) = at_least(2)?.split_at(2);
+                AdStructure::ManufacturerSpecificData {
+                    company_id: read_u16(company_id),
+                    data: data.to_vec(),
+                }
+            }
+            _ => AdStructure::Unknown {
+                ad_type,
+                data: data.to_vec(),
+            },
+        })
+    }
+
+    /// Returns the AD type of this structure.
+    pub fn ad_type(&self) -> u8 {
+        match self {
+            AdStructure::Flags(_) => ad_type::FLAGS,
+            AdStructure::ServiceUuids16 { complete, .. } => {
+                if *complete {
+                    ad_type::COMPLETE_SERVICE_UUIDS_16
+                } else {
+                    ad_type::INCOMPLETE_SERVICE_UUIDS_16
+                }
+            }
+            AdStructure::ServiceUuids32 { complete, .. } => {
+                if *complete {
+                    ad_type::COMPLETE_SERVICE_UUIDS_32
+                } else {
+                    ad_type::INCOMPLETE_SERVICE_UUIDS_32
+                }
+            }
+            AdStructure::ServiceUuids128 { complete, .. } => {
+                if *complete {
+                    ad_type::COMPLETE_SERVICE_UUIDS_128
+                } else {
+                    ad_type::INCOMPLETE_SERVICE_UUIDS_128
+                }
+            }
+            AdStructure::ShortenedLocalName(_) => ad_type::SHORTENED_LOCAL_NAME,
+            AdStructure::CompleteLocalName(_) => ad_type::COMPLETE_LOCAL_NAME,
+            AdStructure::TxPowerLevel(_) => ad_type::TX_POWER_LEVEL,
+            AdStructure::PeripheralConnectionIntervalRange { .. } => {
+                ad_type::PERIPHERAL_CONNECTION_INTERVAL_RANGE
+            }
+            AdStructure::ServiceSolicitation16(_) => ad_type::SERVICE_SOLICITATION_UUIDS_16,
+            AdStructure::ServiceSolicitation32(_) => ad_type::SERVICE_SOLICITATION_UUIDS_32,
+            AdStructure::ServiceSolicitation128(_) => ad_type::SERVICE_SOLICITATION_UUIDS_128,
+            AdStructure::ServiceData16 { .. } => ad_type::SERVICE_DATA_16,
+            AdStructure::ServiceData32 { .. } => ad_type::SERVICE_DATA_32,
+            AdStructure::ServiceData128 { .. } => ad_type::SERVICE_DATA_128,
+            AdStructure::PublicTargetAddresses(_) => ad_type::PUBLIC_TARGET_ADDRESS,
+            AdStructure::RandomTargetAddresses(_) => ad_type::RANDOM_TARGET_ADDRESS,
+            AdStructure::Appearance(_) => ad_type::APPEARANCE,
+            AdStructure::AdvertisingInterval(_) => ad_type::ADVERTISING_INTERVAL,
+            AdStructure::LeDeviceAddress { .. } => ad_type::LE_DEVICE_ADDRESS,
+            AdStructure::LeRole(_) => ad_type::LE_ROLE,
+            AdStructure::Uri(_) => ad_type::URI,
+            AdStructure::ManufacturerSpecificData { .. } => ad_type::MANUFACTURER_SPECIFIC_DATA,
+            AdStructure::Unknown { ad_type, .. } => *ad_type,
+        }
+    }
+
+    /// Encodes the data of this structure, without its length and type bytes.
+    pub fn data(&self) -> Vec<u8> {
+        let mut data = vec![];
+        match self {
+            AdStructure::Flags(flags) => data.push(flags.bits()),
+            AdStructure::ServiceUuids16 { uuids, .. }
+            | AdStructure::ServiceSolicitation16(uuids) => {
+                uuids
+                    .iter()
+                    .for_each(|uuid| data.extend_from_slice(&uuid.to_le_bytes()));
+            }
+            AdStructure::ServiceUuids32 { uuids, .. }
+            | AdStructure::ServiceSolicitation32(uuids) => {
+                uuids
+                    .iter()
+                    .for_each(|uuid| data.extend_from_slice(&uuid.to_le_bytes()));
+            }
+            AdStructure::ServiceUuids128 { uuids, .. }
+            | AdStructure::ServiceSolicitation128(uuids) => {
+                uuids
+                    .iter()
+                    .for_each(|uuid| data.extend(uuid.as_bytes().iter().rev()));
+            }
+            AdStructure::ShortenedLocalName(name) | AdStructure::CompleteLocalName(name) => {
+                data.extend_from_slice(name.as_bytes());
+            }
+            AdStructure::TxPowerLevel(level) => data.push(*level as u8),
+            AdStructure::PeripheralConnectionIntervalRange { min, max } => {
+                data.extend_from_slice(&min.to_le_bytes());
+                data.extend_from_slice(&max.to_le_bytes());
+            }
+            AdStructure::ServiceData16 { uuid, data: value } => {
+                data.extend_from_slice(&uuid.to_le_bytes());
+                data.extend_from_slice(value);
+            }
+            AdStructure::ServiceData32 { uuid, data: value } => {
+                data.extend_from_slice(&uuid.to_le_bytes());
+                data.extend_from_slice(value);
+            }
+            AdStructure::ServiceData128 { uuid, data: value } => {
+                data.extend(uuid.as_bytes().iter().rev());
+                data.extend_from_slice(value);
+            }
+            AdStructure::PublicTargetAddresses(addresses)
+            | AdStructure::RandomTargetAddresses(addresses) => {
+                addresses
+                    .iter()
+                    .for_each(|address| data.extend(address.as_ref().iter().rev()));
+            }
+            AdStructure::Appearance(value) | AdStructure::AdvertisingInterval(value) => {
+                data.extend_from_slice(&value.to_le_bytes());
+            }
+            AdStructure::LeDeviceAddress {
+                address,
+                address_type,
+            } => {
+                data.extend(address.as_ref().iter().rev());
+                data.push(match address_type {
+                    AddressType::Public => 0x00,
+                    AddressType::Random => 0x01,
+                });
+            }
+            AdStructure::LeRole(role) => data.push(match role {
+                LeRole::PeripheralOnly => 0x00,
+                LeRole::CentralOnly => 0x01,
+                LeRole::PeripheralPreferred => 0x02,
+                LeRole::CentralPreferred => 0x03,
+            }),
+            AdStructure::Uri(uri) => {
+                let starts_with_code = uri.starts_with(|c: char| c.is_control());
+                let scheme = URI_SCHEMES
+                    .iter()
+                    .filter(|(_, scheme)| !scheme.is_empty())
+                    .find(|(_, scheme)| uri.starts_with(scheme));
+                let uri = match scheme {
+                    Some((code, scheme)) => format!("{}{}", code, &uri[scheme.len()..]),
+                    None if starts_with_code => uri.clone(),
+                    None => format!("{}{}", URI_SCHEMES[0].0, uri),
+                };
+                data.extend_from_slice(uri.as_bytes());
+            }
+            AdStructure::ManufacturerSpecificData {
+                company_id,
+                data: value,
+            } => {
+                data.extend_from_slice(&company_id.to_le_bytes());
+                data.extend_from_slice(value);
+            }
+            AdStructure::Unknown { data: value, .. } => data.extend_from_slice(value),
+        }
+        data
+    }
+}
+
+/// A sequence of AD structures, such as the contents of an advertising or scan response packet.
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct AdvertisingData {
+    pub structures: Vec<AdStructure>,
+}
+
+impl AdvertisingData {
+    /// Parses a raw advertising data buffer. Parsing stops at the first zero length byte, which
+    /// marks the start of padding in extended inquiry responses.
+    pub fn parse(mut bytes: &[u8]) -> Result<Self, AdvertisingDataError> {
+        let mut structures = vec![];
+        let mut offset = 0;
+        while let Some((&length, rest)) = bytes.split_first() {
+            let length = length as usize;
+            if length == 0 {
+                break;
+            }
+            if rest.len() < length {
+                return Err(AdvertisingDataError::Truncated { offset });
+            }
+            structures.push(AdStructure::parse(rest[0], &rest[1..length])?);
+            bytes = &rest[length..];
+            offset += 1 + length;
+        }
+        Ok(Self { structures })
+    }
+
+    /// Encodes the AD structures into a raw advertising data buffer. Note that this doesn't check
+    /// the buffer against the size limit of any particular kind of packet.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, AdvertisingDataError> {
+        let mut bytes = vec![];
+        for structure in &self.structures {
+            let ad_type = structure.ad_type();
+            let data = structure.data();
+            let length = u8::try_from(data.len() + 1)
+                .map_err(|_| AdvertisingDataError::TooLong { ad_type })?;
+            bytes.push(length);
+            bytes.push(ad_type);
+            bytes.extend_from_slice(&data);
+        }
+        Ok(bytes)
+    }
+
+    /// Returns the advertised flags, if any.
+    pub fn flags(&self) -> Option<AdvertisingFlags> {
+        self.structures
+            .iter()
+            .find_map(|structure| match structure {
+                AdStructure::Flags(flags) => Some(*flags),
+                _ => None,
+            })
+    }
+
+    /// Returns the complete local name if there is one, or else the shortened local name.
+    pub fn local_name(&self) -> Option<&str> {
+        let mut shortened = None;
+        for structure in &self.structures {
+            match structure {
+                AdStructure::CompleteLocalName(name) => return Some(name),
+                AdStructure::ShortenedLocalName(name) => shortened = Some(name.as_str()),
+                _ => {}
+            }
+        }
+        shortened
+    }
+
+    /// Returns all the advertised service UUIDs, whatever their size.
+    pub fn services(&self) -> Vec<Uuid> {
+        let mut services = vec![];
+        for structure in &self.structures {
+            match structure {
+                AdStructure::ServiceUuids16 { uuids, .. } => {
+                    services.extend(uuids.iter().map(|uuid| uuid_from_u16(*uuid)))
+                }
+                AdStructure::ServiceUuids32 { uuids, .. } => {
+                    services.extend(uuids.iter().map(|uuid| uuid_from_u32(*uuid)))
+                }
+                AdStructure::ServiceUuids128 { uuids, .. } => services.extend(uuids),
+                _ => {}
+            }
+        }
+        services
+    }
+
+    /// Returns all the solicited service UUIDs, whatever their size.
+    pub fn solicited_services(&self) -> Vec<Uuid> {
+        let mut services = vec![];
+        for structure in &self.structures {
+            match structure {
+                AdStructure::ServiceSolicitation16(uuids) => {
+                    services.extend(uuids.iter().map(|uuid| uuid_from_u16(*uuid)))
+                }
+                AdStructure::ServiceSolicitation32(uuids) => {
+                    services.extend(uuids.iter().map(|uuid| uuid_from_u32(*uuid)))
+                }
+                AdStructure::ServiceSolicitation128(uuids) => services.extend(uuids),
+                _ => {}
+            }
+        }
+        services
+    }
+
+    /// Returns the advertised service data, keyed by full service UUID.
+    pub fn service_data(&self) -> HashMap<Uuid, Vec<u8>> {
+        self.structures
+            .iter()
+            .filter_map(|structure| match structure {
+                AdStructure::ServiceData16 { uuid, data } => {
+                    Some((uuid_from_u16(*uuid), data.clone()))
+                }
+                AdStructure::ServiceData32 { uuid, data } => {
+                    Some((uuid_from_u32(*uuid), data.clone()))
+                }
+                AdStructure::ServiceData128 { uuid, data } => Some((*uuid, data.clone())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Returns the advertised manufacturer data, keyed by company identifier.
+    pub fn manufacturer_data(&self) -> HashMap<u16, Vec<u8>> {
+        self.structures
+            .iter()
+            .filter_map(|structure| match structure {
+                AdStructure::ManufacturerSpecificData { company_id, data } => {
+                    Some((*company_id, data.clone()))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Returns the advertised transmission power level in dBm, if any.
+    pub fn tx_power_level(&self) -> Option<i8> {
+        self.structures
+            .iter()
+            .find_map(|structure| match structure {
+                AdStructure::TxPowerLevel(level) => Some(*level),
+                _ => None,
+            })
+    }
+
+    /// Returns the advertised appearance, if any.
+    pub fn appearance(&self) -> Option<u16> {
+        self.structures
+            .iter()
+            .find_map(|structure| match structure {
+                AdStructure::Appearance(appearance) => Some(*appearance),
+                _ => None,
+            })
+    }
+
+    /// Merges the data which [`PeripheralProperties`] keeps into it. As advertisements are
+    /// cumulative, fields are only replaced when this data has a value for them, and services are
+    /// added to those already known.
+    pub fn update_properties(&self, properties: &mut PeripheralProperties) {
+        if let Some(name) = self.local_name() {
+            properties.local_name = Some(name.to_string());
+        }
+        if let Some(level) = self.tx_power_level() {
+            properties.tx_power_level = Some(level.into());
+        }
+        properties
+            .manufacturer_data
+            .extend(self.manufacturer_data());
+        properties.service_data.extend(self.service_data());
+        for service in self.services() {
+            if !properties.services.contains(&service) {
+                properties.services.push(service);
+            }
+        }
+    }
+}
+
+fn read_u16(bytes: &[u8]) -> u16 {
+    u16::from_le_bytes(bytes.try_into().unwrap())
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes(bytes.try_into().unwrap())
+}
+
+fn read_uuid(bytes: &[u8]) -> Uuid {
+    let mut uuid: [u8; 16] = bytes.try_into().unwrap();
+    uuid.reverse();
+    Uuid::from_bytes(uuid)
+}
+
+fn read_address(bytes: &[u8]) -> BDAddr {
+    let mut address: [u8; 6] = bytes.try_into().unwrap();
+    address.reverse();
+    address.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_build_round_trip() {
+        let bytes = [
+            0x02, 0x01, 0x06, // Flags
+            0x05, 0x03, 0x0F, 0x18, 0x0A, 0x18, // Complete 16-bit services
+            0x04, 0x08, b'a', b'b', b'c', // Shortened local name
+            0x02, 0x0A, 0xF4, // Tx power level: -12 dBm
+            0x05, 0x16, 0xAA, 0xFE, 0x10, 0x20, // Service data for 0xFEAA
+            0x05, 0xFF, 0x4C, 0x00, 0x02, 0x15, // Manufacturer data for Apple
+            0x03, 0x19, 0xC1, 0x03, // Appearance
+            0x02, 0x1C, 0x02, // LE role
+            0x08, 0x1B, 0x06, 0x05, 0x04, 0x03, 0x02, 0x01, 0x01, // LE device address
+            0x03, 0x42, 0x01, 0x02, // Unknown type
+        ];
+        let data = AdvertisingData::parse(&bytes).unwrap();
+        assert_eq!(
+            data.structures,
+            vec![
+                AdStructure::Flags(
+                    AdvertisingFlags::LE_GENERAL_DISCOVERABLE
+                        | AdvertisingFlags::BR_EDR_NOT_SUPPORTED
+                ),
+                AdStructure::ServiceUuids16 {
+                    uuids: vec![0x180F, 0x180A],
+                    complete: true,
+                },
+                AdStructure::ShortenedLocalName("abc".to_string()),
+                AdStructure::TxPowerLevel(-12),
+                AdStructure::ServiceData16 {
+                    uuid: 0xFEAA,
+                    data: vec![0x10, 0x20],
+                },
+                AdStructure::ManufacturerSpecificData {
+                    company_id: 0x004C,
+                    data: vec![0x02, 0x15],
+                },
+                AdStructure::Appearance(0x03C1),
+                AdStructure::LeRole(LeRole::PeripheralPreferred),
+                AdStructure::LeDeviceAddress {
+                    address: [1, 2, 3, 4, 5, 6].into(),
+                    address_type: AddressType::Random,
+                },
+                AdStructure::Unknown {
+                    ad_type: 0x42,
+                    data: vec![0x01, 0x02],
+                },
+            ]
+        );
+        assert_eq!(data.to_bytes().unwrap(), bytes);
+    }
+
+    #[test]
+    fn uuids_are_little_endian() {
+        let uuid = Uuid::parse_str("6e400001-b5a3-f393-e0a9-e50e24dcca9e").unwrap();
+        let mut bytes = vec![0x11, 0x07];
+        bytes.extend(uuid.as_bytes().iter().rev());
+        bytes.extend([0x07, 0x20, 0x78, 0x56, 0x34, 0x12, 0xAB, 0xCD]);
+        let data = AdvertisingData::parse(&bytes).unwrap();
+        assert_eq!(data.services(), vec![uuid]);
+        assert_eq!(
+            data.service_data(),
+            HashMap::from([(uuid_from_u32(0x12345678), vec![0xAB, 0xCD])])
+        );
+        assert_eq!(data.to_bytes().unwrap(), bytes);
+    }
+
+    #[test]
+    fn uri_schemes() {
+        let data = AdvertisingData::parse(&[
+            0x0A, 0x24, 0x17, b'/', b'/', b'a', b'.', b'b', b'/', b'c', b'd',
+        ])
+        .unwrap();
+        assert_eq!(
+            data.structures,
+            vec![AdStructure::Uri("https://a.b/cd".to_string())]
+        );
+        assert_eq!(
+            AdStructure::Uri("urn:x".to_string()).data(),
+            b"\x01urn:x".to_vec()
+        );
+        assert_eq!(
+            AdStructure::parse(ad_type::URI, b"\x02aaa").unwrap(),
+            AdStructure::Uri("\u{02}aaa".to_string())
+        );
+        assert_eq!(AdStructure::Uri("\u{02}aaa".to_string()).data(), b"\x02aaa");
+    }
+
+    #[test]
+    fn padding_and_errors() {
+        let data = AdvertisingData::parse(&[0x02, 0x01, 0x04, 0x00, 0x00, 0x00]).unwrap();
+        assert_eq!(data.flags(), Some(AdvertisingFlags::BR_EDR_NOT_SUPPORTED));
+        assert_eq!(
+            AdvertisingData::parse(&[0x02, 0x01, 0x04, 0x05, 0x09, b'a']),
+            Err(AdvertisingDataError::Truncated { offset: 3 })
+        );
+        assert_eq!(
+            AdvertisingData::parse(&[0x04, 0x03, 0x0F, 0x18, 0x0A]),
+            Err(AdvertisingDataError::InvalidLength {
+                ad_type: ad_type::COMPLETE_SERVICE_UUIDS_16,
+                length: 3
+            })
+        );
+        assert_eq!(
+            AdStructure::parse(ad_type::LE_ROLE, &[0x09]),
+            Err(AdvertisingDataError::InvalidValue {
+                ad_type: ad_type::LE_ROLE
+            })
+        );
+        let too_long = AdvertisingData {
+            structures: vec![AdStructure::CompleteLocalName("a".repeat(255))],
+        };
+        assert_eq!(
+            too_long.to_bytes(),
+            Err(AdvertisingDataError::TooLong {
+                ad_type: ad_type::COMPLETE_LOCAL_NAME
+            })
+        );
+    }
+
+    #[test]
+    fn update_properties_merges() {
+        let mut properties = PeripheralProperties {
+            local_name: Some("Old".to_string()),
+            services: vec![uuid_from_u16(0x180F)],
+            ..Default::default()
+        };
+        AdvertisingData {
+            structures: vec![
+                AdStructure::ServiceUuids16 {
+                    uuids: vec![0x180F, 0x180D],
+                    complete: false,
+                },
+                AdStructure::TxPowerLevel(4),
+            ],
+        }
+        .update_properties(&mut properties);
+        assert_eq!(properties.local_name, Some("Old".to_string()));
+        assert_eq!(properties.tx_power_level, Some(4));
+        assert_eq!(
+            properties.services,
+            vec![uuid_from_u16(0x180F), uuid_from_u16(0x180D)]
+        );
+    }
+}
@@ -0,0 +1,87 @@
+// btleplug Source Code File
+//
+// Copyright 2023 Nonpolynomial Labs LLC. All rights reserved.
+//
+// Licensed under the BSD 3-Clause license. See LICENSE file in the project root
+// for full license information.
+
+use super::Central;
+use crate::Result;
+use async_trait::async_trait;
+use std::{collections::HashMap, fmt::Debug, time::Duration};
+use uuid::Uuid;
+
+/// The contents and parameters of an advertisement broadcast by the local adapter through an
+/// [`Advertiser`].
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct LocalAdvertisement {
+    /// The local name to advertise.
+    pub local_name: Option<String>,
+    /// The service UUIDs to advertise.
+    pub services: Vec<Uuid>,
+    /// Manufacturer-specific data, keyed by company identifier.
+    pub manufacturer_data: HashMap<u16, Vec<u8>>,
+    /// Service data, keyed by service UUID.
+    pub service_data: HashMap<Uuid, Vec<u8>>,
+    /// Whether to include the transmission power level of the adapter.
+    pub include_tx_power: bool,
+    /// Whether remote centrals may connect to the adapter in response to this advertisement.
+    pub connectable: bool,
+    /// Whether remote scanners may request a scan response. This must currently be the same as
+    /// [`connectable`](Self::connectable): connectable advertisements are always scannable, and
+    /// others can't be as scan response data isn't supported yet. Other combinations are rejected
+    /// with [`Error::NotSupported`](crate::Error::NotSupported).
+    pub scannable: bool,
+    /// The shortest interval between advertising events to use, if the platform allows choosing it.
+    pub min_interval: Option<Duration>,
+    /// The longest interval between advertising events to use, if the platform allows choosing it.
+    pub max_interval: Option<Duration>,
+}
+
+impl LocalAdvertisement {
+    /// Checks that the parameters are consistent with each other.
+    #[cfg(any(target_os = "linux", test, feature = "mock"))]
+    pub(crate) fn validate(&self) -> Result<()> {
+        match (self.connectable, self.scannable) {
+            (true, false) => {
+                return Err(crate::Error::NotSupported(
+                    "Connectable advertisements must also be scannable".to_string(),
+                ))
+            }
+            (false, true) => {
+                return Err(crate::Error::NotSupported(
+                    "Non-connectable advertisements can't be scannable without scan response data"
+                        .to_string(),
+                ))
+            }
+            _ => {}
+        }
+        if let (Some(min), Some(max)) = (self.min_interval, self.max_interval) {
+            if min > max {
+                return Err(crate::Error::Other(
+                    format!(
+                        "Minimum advertising interval {:?} is longer than maximum {:?}",
+                        min, max
+                    )
+                    .into(),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A [`Central`] which can also broadcast advertisements from the local adapter.
+#[async_trait]
+pub trait Advertiser: Central {
+    /// The handle returned for a registered advertisement. The advertisement is broadcast until
+    /// this is dropped.
+    type AdvertisementHandle: Send + Sync + Debug;
+
+    /// Starts broadcasting the given advertisement, alongside any others already registered on the
+    /// adapter if it supports several at once.
+    async fn start_advertising(
+        &self,
+        advertisement: LocalAdvertisement,
+    ) -> Result<Self::AdvertisementHandle>;
+}
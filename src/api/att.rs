@@ -0,0 +1,199 @@
+// btleplug Source Code File
+//
+// Copyright 2023 Nonpolynomial Labs LLC. All rights reserved.
+//
+// Licensed under the BSD 3-Clause license. See LICENSE file in the project root
+// for full license information.
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "serde")]
+use serde_cr as serde;
+use std::fmt::{self, Display, Formatter};
+
+/// An error code of the Attribute Protocol, which a device responds with when it can't carry out a
+/// GATT request, as defined in the Core Specification (Vol 3, Part F, 3.4.1.1) and the Core
+/// Specification Supplement (Part B).
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_cr")
+)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum AttErrorCode {
+    /// The attribute handle given was not valid on this server.
+    InvalidHandle,
+    /// The attribute cannot be read.
+    ReadNotPermitted,
+    /// The attribute cannot be written.
+    WriteNotPermitted,
+    /// The attribute PDU was invalid.
+    InvalidPdu,
+    /// The attribute requires authentication before it can be read or written.
+    InsufficientAuthentication,
+    /// The server does not support the request received from the client.
+    RequestNotSupported,
+    /// The offset specified was past the end of the attribute.
+    InvalidOffset,
+    /// The attribute requires authorization before it can be read or written.
+    InsufficientAuthorization,
+    /// Too many prepare writes have been queued.
+    PrepareQueueFull,
+    /// No attribute found within the given attribute handle range.
+    AttributeNotFound,
+    /// The attribute cannot be read using the Read Blob Request.
+    AttributeNotLong,
+    /// The encryption key size used for encrypting this link is too short.
+    EncryptionKeySizeTooShort,
+    /// The attribute value length is invalid for the operation.
+    InvalidAttributeValueLength,
+    /// The request encountered an error that was unlikely, and therefore could not be completed.
+    UnlikelyError,
+    /// The attribute requires encryption before it can be read or written.
+    InsufficientEncryption,
+    /// The attribute type is not a supported grouping attribute as defined by a higher layer
+    /// specification.
+    UnsupportedGroupType,
+    /// Insufficient resources to complete the request.
+    InsufficientResources,
+    /// The server requests the client to rediscover the database.
+    DatabaseOutOfSync,
+    /// The attribute parameter value was not allowed.
+    ValueNotAllowed,
+    /// An error defined by the profile or application the attribute belongs to, with a code
+    /// between 0x80 and 0x9F.
+    Application(u8),
+    /// The write request was rejected.
+    WriteRequestRejected,
+    /// The Client Characteristic Configuration descriptor is improperly configured.
+    CccdImproperlyConfigured,
+    /// A request cannot be serviced because an operation that has been previously triggered is
+    /// still in progress.
+    ProcedureAlreadyInProgress,
+    /// The attribute value is out of range as defined by a profile or service specification.
+    OutOfRange,
+    /// A code which is reserved for future use.
+    Reserved(u8),
+}
+
+impl From<u8> for AttErrorCode {
+    fn from(code: u8) -> Self {
+        match code {
+            0x01 => AttErrorCode::InvalidHandle,
+            0x02 => AttErrorCode::ReadNotPermitted,
+            0x03 => AttErrorCode::WriteNotPermitted,
+            0x04 => AttErrorCode::InvalidPdu,
+            0x05 => AttErrorCode::InsufficientAuthentication,
+            0x06 => AttErrorCode::RequestNotSupported,
+            0x07 => AttErrorCode::InvalidOffset,
+            0x08 => AttErrorCode::InsufficientAuthorization,
+            0x09 => AttErrorCode::PrepareQueueFull,
+            0x0a => AttErrorCode::AttributeNotFound,
+            0x0b => AttErrorCode::AttributeNotLong,
+            0x0c => AttErrorCode::EncryptionKeySizeTooShort,
+            0x0d => AttErrorCode::InvalidAttributeValueLength,
+            0x0e => AttErrorCode::UnlikelyError,
+            0x0f => AttErrorCode::InsufficientEncryption,
+            0x10 => AttErrorCode::UnsupportedGroupType,
+            0x11 => AttErrorCode::InsufficientResources,
+            0x12 => AttErrorCode::DatabaseOutOfSync,
+            0x13 => AttErrorCode::ValueNotAllowed,
+            0x80..=0x9f => AttErrorCode::Application(code),
+            0xfc => AttErrorCode::WriteRequestRejected,
+            0xfd => AttErrorCode::CccdImproperlyConfigured,
+            0xfe => AttErrorCode::ProcedureAlreadyInProgress,
+            0xff => AttErrorCode::OutOfRange,
+            _ => AttErrorCode::Reserved(code),
+        }
+    }
+}
+
+impl From<AttErrorCode> for u8 {
+    fn from(code: AttErrorCode) -> Self {
+        match code {
+            AttErrorCode::InvalidHandle => 0x01,
+            AttErrorCode::ReadNotPermitted => 0x02,
+            AttErrorCode::WriteNotPermitted => 0x03,
+            AttErrorCode::InvalidPdu => 0x04,
+            AttErrorCode::InsufficientAuthentication => 0x05,
+            AttErrorCode::RequestNotSupported => 0x06,
+            AttErrorCode::InvalidOffset => 0x07,
+            AttErrorCode::InsufficientAuthorization => 0x08,
+            AttErrorCode::PrepareQueueFull => 0x09,
+            AttErrorCode::AttributeNotFound => 0x0a,
+            AttErrorCode::AttributeNotLong => 0x0b,
+            AttErrorCode::EncryptionKeySizeTooShort => 0x0c,
+            AttErrorCode::InvalidAttributeValueLength => 0x0d,
+            AttErrorCode::UnlikelyError => 0x0e,
+            AttErrorCode::InsufficientEncryption => 0x0f,
+            AttErrorCode::UnsupportedGroupType => 0x10,
+            AttErrorCode::InsufficientResources => 0x11,
+            AttErrorCode::DatabaseOutOfSync => 0x12,
+            AttErrorCode::ValueNotAllowed => 0x13,
+            AttErrorCode::WriteRequestRejected => 0xfc,
+            AttErrorCode::CccdImproperlyConfigured => 0xfd,
+            AttErrorCode::ProcedureAlreadyInProgress => 0xfe,
+            AttErrorCode::OutOfRange => 0xff,
+            AttErrorCode::Application(code) | AttErrorCode::Reserved(code) => code,
+        }
+    }
+}
+
+impl Display for AttErrorCode {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let description = match self {
+            AttErrorCode::InvalidHandle => "Invalid handle",
+            AttErrorCode::ReadNotPermitted => "Read not permitted",
+            AttErrorCode::WriteNotPermitted => "Write not permitted",
+            AttErrorCode::InvalidPdu => "Invalid PDU",
+            AttErrorCode::InsufficientAuthentication => "Insufficient authentication",
+            AttErrorCode::RequestNotSupported => "Request not supported",
+            AttErrorCode::InvalidOffset => "Invalid offset",
+            AttErrorCode::InsufficientAuthorization => "Insufficient authorization",
+            AttErrorCode::PrepareQueueFull => "Prepare queue full",
+            AttErrorCode::AttributeNotFound => "Attribute not found",
+            AttErrorCode::AttributeNotLong => "Attribute not long",
+            AttErrorCode::EncryptionKeySizeTooShort => "Encryption key size too short",
+            AttErrorCode::InvalidAttributeValueLength => "Invalid attribute value length",
+            AttErrorCode::UnlikelyError => "Unlikely error",
+            AttErrorCode::InsufficientEncryption => "Insufficient encryption",
+            AttErrorCode::UnsupportedGroupType => "Unsupported group type",
+            AttErrorCode::InsufficientResources => "Insufficient resources",
+            AttErrorCode::DatabaseOutOfSync => "Database out of sync",
+            AttErrorCode::ValueNotAllowed => "Value not allowed",
+            AttErrorCode::Application(_) => "Application error",
+            AttErrorCode::WriteRequestRejected => "Write request rejected",
+            AttErrorCode::CccdImproperlyConfigured => {
+                "Client characteristic configuration descriptor improperly configured"
+            }
+            AttErrorCode::ProcedureAlreadyInProgress => "Procedure already in progress",
+            AttErrorCode::OutOfRange => "Out of range",
+            AttErrorCode::Reserved(_) => "Reserved error",
+        };
+        write!(f, "{} (0x{:02x})", description, u8::from(*self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codes_round_trip() {
+        for code in 0..=u8::MAX {
+            assert_eq!(u8::from(AttErrorCode::from(code)), code);
+        }
+    }
+
+    #[test]
+    fn application_and_reserved_codes() {
+        assert_eq!(AttErrorCode::from(0x80), AttErrorCode::Application(0x80));
+        assert_eq!(AttErrorCode::from(0x9f), AttErrorCode::Application(0x9f));
+        assert_eq!(AttErrorCode::from(0xa0), AttErrorCode::Reserved(0xa0));
+        assert_eq!(AttErrorCode::from(0x14), AttErrorCode::Reserved(0x14));
+        assert_eq!(
+            AttErrorCode::InsufficientAuthentication.to_string(),
+            "Insufficient authentication (0x05)"
+        );
+    }
+}
@@ -0,0 +1,529 @@
+//! Decoders for common beacon formats carried in advertisement data.
+//!
+//! Apple iBeacon and AltBeacon frames are sent as manufacturer-specific data, while Eddystone
+//! frames are sent as service data for the Eddystone service UUID, [`EDDYSTONE_SERVICE_UUID`].
+//! Beacons can be decoded from the data of individual advertisements with
+//! [`Beacon::from_manufacturer_data`] and [`Beacon::from_service_data`], or from everything known
+//! about a peripheral with [`Beacon::from_properties`]. To have each beacon frame received while
+//! scanning reported as a [`CentralEvent::BeaconAdvertisement`], wrap the event stream of an
+//! adapter with [`with_beacon_events`].
+//!
+//! # Example
+//!
+//! ```
+//! use btleplug::api::beacon::{Beacon, IBeacon};
+//! use uuid::uuid;
+//!
+//! let data = [
+//!     0x02, 0x15, 0xE2, 0xC5, 0x6D, 0xB5, 0xDF, 0xFB, 0x48, 0xD2, 0xB0, 0x60, 0xD0, 0xF5, 0xA7,
+//!     0x10, 0x96, 0xE0, 0x00, 0x01, 0x00, 0x02, 0xC5,
+//! ];
+//! assert_eq!(
+//!     Beacon::from_manufacturer_data(0x004C, &data),
+//!     Some(Beacon::IBeacon(IBeacon {
+//!         uuid: uuid!("e2c56db5-dffb-48d2-b060-d0f5a71096e0"),
+//!         major: 1,
+//!         minor: 2,
+//!         measured_power: -59,
+//!     }))
+//! );
+//! ```
+
+use super::bleuuid::uuid_from_u16;
+use super::{CentralEvent, PeripheralProperties};
+use futures::stream::{self, Stream, StreamExt};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "serde")]
+use serde_cr as serde;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// The company identifier of Apple, which iBeacon frames are sent under.
+pub const APPLE_COMPANY_ID: u16 = 0x004C;
+
+/// The service UUID which Eddystone frames are sent as service data for.
+pub const EDDYSTONE_SERVICE_UUID: Uuid = uuid_from_u16(0xFEAA);
+
+/// A decoded beacon frame.
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_cr")
+)]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Beacon {
+    IBeacon(IBeacon),
+    AltBeacon(AltBeacon),
+    Eddystone(Eddystone),
+}
+
+impl Beacon {
+    /// Decodes a beacon from the manufacturer-specific data for the given company, if it holds one.
+    pub fn from_manufacturer_data(company_id: u16, data: &[u8]) -> Option<Self> {
+        IBeacon::from_manufacturer_data(company_id, data)
+            .map(Beacon::IBeacon)
+            .or_else(|| AltBeacon::from_manufacturer_data(company_id, data).map(Beacon::AltBeacon))
+    }
+
+    /// Decodes a beacon from the service data for the given service, if it holds one.
+    pub fn from_service_data(uuid: &Uuid, data: &[u8]) -> Option<Self> {
+        if *uuid == EDDYSTONE_SERVICE_UUID {
+            Eddystone::from_service_data(data).map(Beacon::Eddystone)
+        } else {
+            None
+        }
+    }
+
+    /// Decodes all the beacons in the latest manufacturer and service data of a peripheral.
+    pub fn from_properties(properties: &PeripheralProperties) -> Vec<Self> {
+        let mut beacons: Vec<_> = properties
+            .manufacturer_data
+            .iter()
+            .filter_map(|(company_id, data)| Self::from_manufacturer_data(*company_id, data))
+            .collect();
+        beacons.extend(
+            properties
+                .service_data
+                .iter()
+                .filter_map(|(uuid, data)| Self::from_service_data(uuid, data)),
+        );
+        beacons
+    }
+}
+
+/// An Apple iBeacon frame.
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_cr")
+)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct IBeacon {
+    /// The proximity UUID, which usually identifies the organisation deploying the beacons.
+    pub uuid: Uuid,
+    pub major: u16,
+    pub minor: u16,
+    /// The calibrated RSSI at 1 m from the beacon, in dBm.
+    pub measured_power: i8,
+}
+
+impl IBeacon {
+    /// Decodes an iBeacon from the manufacturer-specific data for the given company, if it holds
+    /// one.
+    pub fn from_manufacturer_data(company_id: u16, data: &[u8]) -> Option<Self> {
+        if company_id != APPLE_COMPANY_ID || data.len() != 23 || data[..2] != [0x02, 0x15] {
+            return None;
+        }
+        Some(Self {
+            uuid: Uuid::from_slice(&data[2..18]).unwrap(),
+            major: u16::from_be_bytes([data[18], data[19]]),
+            minor: u16::from_be_bytes([data[20], data[21]]),
+            measured_power: data[22] as i8,
+        })
+    }
+}
+
+/// An AltBeacon frame. These may be sent under any company identifier.
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_cr")
+)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct AltBeacon {
+    /// The company identifier of the manufacturer data the frame was sent in.
+    pub company_id: u16,
+    /// The beacon identifier. By convention the first 16 bytes are an organisational unit
+    /// identifier and the last 4 are two 16-bit identifiers; see [`uuid`](Self::uuid),
+    /// [`major`](Self::major) and [`minor`](Self::minor).
+    pub beacon_id: [u8; 20],
+    /// The average RSSI at 1 m from the beacon, in dBm.
+    pub reference_rssi: i8,
+    /// A byte reserved for use by the manufacturer.
+    pub manufacturer_reserved: u8,
+}
+
+impl AltBeacon {
+    /// Decodes an AltBeacon from the manufacturer-specific data for the given company, if it holds
+    /// one.
+    pub fn from_manufacturer_data(company_id: u16, data: &[u8]) -> Option<Self> {
+        if data.len() != 24 || data[..2] != [0xBE, 0xAC] {
+            return None;
+        }
+        Some(Self {
+            company_id,
+            beacon_id: data[2..22].try_into().unwrap(),
+            reference_rssi: data[22] as i8,
+            manufacturer_reserved: data[23],
+        })
+    }
+
+    /// Returns the first 16 bytes of the beacon identifier as a UUID.
+    pub fn uuid(&self) -> Uuid {
+        Uuid::from_slice(&self.beacon_id[..16]).unwrap()
+    }
+
+    /// Returns bytes 16 and 17 of the beacon identifier as a big-endian integer.
+    pub fn major(&self) -> u16 {
+        u16::from_be_bytes([self.beacon_id[16], self.beacon_id[17]])
+    }
+
+    /// Returns bytes 18 and 19 of the beacon identifier as a big-endian integer.
+    pub fn minor(&self) -> u16 {
+        u16::from_be_bytes([self.beacon_id[18], self.beacon_id[19]])
+    }
+}
+
+/// An Eddystone frame.
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_cr")
+)]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Eddystone {
+    Uid(EddystoneUid),
+    Url(EddystoneUrl),
+    Tlm(EddystoneTlm),
+    Eid(EddystoneEid),
+}
+
+impl Eddystone {
+    /// Decodes an Eddystone frame from the service data for [`EDDYSTONE_SERVICE_UUID`], if it is
+    /// a valid frame of a known type.
+    pub fn from_service_data(data: &[u8]) -> Option<Self> {
+        match *data.first()? {
+            0x00 => EddystoneUid::decode(data).map(Eddystone::Uid),
+            0x10 => EddystoneUrl::decode(data).map(Eddystone::Url),
+            0x20 => EddystoneTlm::decode(data).map(Eddystone::Tlm),
+            0x30 => EddystoneEid::decode(data).map(Eddystone::Eid),
+            _ => None,
+        }
+    }
+}
+
+/// An Eddystone-UID frame, which broadcasts a fixed beacon identifier.
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_cr")
+)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct EddystoneUid {
+    /// The calibrated transmission power at 0 m, in dBm.
+    pub tx_power: i8,
+    pub namespace: [u8; 10],
+    pub instance: [u8; 6],
+}
+
+impl EddystoneUid {
+    fn decode(data: &[u8]) -> Option<Self> {
+        // The two trailing reserved bytes are often left out.
+        if data.len() != 18 && data.len() != 20 {
+            return None;
+        }
+        Some(Self {
+            tx_power: data[1] as i8,
+            namespace: data[2..12].try_into().unwrap(),
+            instance: data[12..18].try_into().unwrap(),
+        })
+    }
+}
+
+/// An Eddystone-URL frame, which broadcasts a compressed URL.
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_cr")
+)]
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct EddystoneUrl {
+    /// The calibrated transmission power at 0 m, in dBm.
+    pub tx_power: i8,
+    /// The URL, with its scheme prefix and any text expansions expanded.
+    pub url: String,
+}
+
+const URL_SCHEME_PREFIXES: [&str; 4] = ["http://www.", "https://www.", "http://", "https://"];
+
+const URL_EXPANSIONS: [&str; 14] = [
+    ".com/", ".org/", ".edu/", ".net/", ".info/", ".biz/", ".gov/", ".com", ".org", ".edu", ".net",
+    ".info", ".biz", ".gov",
+];
+
+impl EddystoneUrl {
+    fn decode(data: &[u8]) -> Option<Self> {
+        let mut url = URL_SCHEME_PREFIXES.get(*data.get(2)? as usize)?.to_string();
+        for &byte in &data[3..] {
+            match byte {
+                0x00..=0x0D => url.push_str(URL_EXPANSIONS[byte as usize]),
+                // Other control characters, space, DEL and non-ASCII bytes are reserved.
+                0x21..=0x7E => url.push(byte as char),
+                _ => return None,
+            }
+        }
+        Some(Self {
+            tx_power: data[1] as i8,
+            url,
+        })
+    }
+}
+
+/// An unencrypted Eddystone-TLM frame, which broadcasts telemetry about the beacon itself.
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_cr")
+)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct EddystoneTlm {
+    /// The battery voltage in mV, if the beacon reports it.
+    pub battery_voltage: Option<u16>,
+    /// The beacon temperature in °C, if the beacon reports it.
+    pub temperature: Option<f32>,
+    /// The number of advertising frames sent since the beacon was powered on or rebooted.
+    pub advertising_count: u32,
+    /// The time since the beacon was powered on or rebooted.
+    pub uptime: Duration,
+}
+
+impl EddystoneTlm {
+    fn decode(data: &[u8]) -> Option<Self> {
+        // Only version 0 is unencrypted.
+        if data.len() != 14 || data[1] != 0x00 {
+            return None;
+        }
+        let battery_voltage = u16::from_be_bytes([data[2], data[3]]);
+        let temperature = i16::from_be_bytes([data[4], data[5]]);
+        Some(Self {
+            battery_voltage: (battery_voltage != 0).then_some(battery_voltage),
+            // Signed 8.8 fixed point, with 0x8000 meaning not supported.
+            temperature: (temperature != i16::MIN).then(|| f32::from(temperature) / 256.0),
+            advertising_count: u32::from_be_bytes(data[6..10].try_into().unwrap()),
+            uptime: Duration::from_millis(
+                u64::from(u32::from_be_bytes(data[10..14].try_into().unwrap())) * 100,
+            ),
+        })
+    }
+}
+
+/// An Eddystone-EID frame, which broadcasts a periodically changing encrypted identifier.
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_cr")
+)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct EddystoneEid {
+    /// The calibrated transmission power at 0 m, in dBm.
+    pub tx_power: i8,
+    pub eid: [u8; 8],
+}
+
+impl EddystoneEid {
+    fn decode(data: &[u8]) -> Option<Self> {
+        if data.len() != 10 {
+            return None;
+        }
+        Some(Self {
+            tx_power: data[1] as i8,
+            eid: data[2..10].try_into().unwrap(),
+        })
+    }
+}
+
+/// Adds a [`CentralEvent::BeaconAdvertisement`] for each beacon frame in the data of the
+/// manufacturer and service data advertisement events of `events`, right after the event for the
+/// advertisement itself. Adapters don't decode beacons unless their event stream is wrapped with
+/// this:
+///
+/// ```no_run
+/// use btleplug::api::{beacon::with_beacon_events, Central, CentralEvent};
+/// use futures::stream::StreamExt;
+/// # use std::error::Error;
+///
+/// # async fn example(central: impl Central) -> Result<(), Box<dyn Error>> {
+/// let mut events = with_beacon_events(central.events().await?);
+/// while let Some(event) = events.next().await {
+///     if let CentralEvent::BeaconAdvertisement { id, beacon } = event {
+///         println!("{:?} is a beacon: {:?}", id, beacon);
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn with_beacon_events<S>(events: S) -> impl Stream<Item = CentralEvent>
+where
+    S: Stream<Item = CentralEvent>,
+{
+    events.flat_map(|event| {
+        let beacon_events = beacon_events(&event);
+        stream::iter(std::iter::once(event).chain(beacon_events))
+    })
+}
+
+/// Returns a [`CentralEvent::BeaconAdvertisement`] for each beacon in the data of a manufacturer
+/// or service data advertisement event.
+fn beacon_events(event: &CentralEvent) -> Vec<CentralEvent> {
+    let (id, beacons): (_, Vec<_>) = match event {
+        CentralEvent::ManufacturerDataAdvertisement {
+            id,
+            manufacturer_data,
+        } => (
+            id,
+            manufacturer_data
+                .iter()
+                .filter_map(|(company_id, data)| Beacon::from_manufacturer_data(*company_id, data))
+                .collect(),
+        ),
+        CentralEvent::ServiceDataAdvertisement { id, service_data } => (
+            id,
+            service_data
+                .iter()
+                .filter_map(|(uuid, data)| Beacon::from_service_data(uuid, data))
+                .collect(),
+        ),
+        _ => return vec![],
+    };
+    beacons
+        .into_iter()
+        .map(|beacon| CentralEvent::BeaconAdvertisement {
+            id: id.clone(),
+            beacon,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn altbeacon() {
+        let mut data = vec![0xBE, 0xAC];
+        data.extend(1..=20);
+        data.extend([0xC4, 0x00]);
+        let beacon = AltBeacon::from_manufacturer_data(0x0118, &data).unwrap();
+        assert_eq!(beacon.company_id, 0x0118);
+        assert_eq!(beacon.reference_rssi, -60);
+        assert_eq!(
+            beacon.uuid(),
+            Uuid::from_bytes([1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16])
+        );
+        assert_eq!(beacon.major(), 0x1112);
+        assert_eq!(beacon.minor(), 0x1314);
+        assert_eq!(AltBeacon::from_manufacturer_data(0x0118, &data[..23]), None);
+    }
+
+    #[test]
+    fn ibeacon_requires_apple() {
+        let mut data = vec![0x02, 0x15];
+        data.extend([0; 21]);
+        assert!(IBeacon::from_manufacturer_data(APPLE_COMPANY_ID, &data).is_some());
+        assert_eq!(IBeacon::from_manufacturer_data(0x0059, &data), None);
+    }
+
+    #[test]
+    fn eddystone_uid_and_eid() {
+        let mut data = vec![0x00, 0xEE];
+        data.extend(0..16);
+        assert_eq!(
+            Eddystone::from_service_data(&data),
+            Some(Eddystone::Uid(EddystoneUid {
+                tx_power: -18,
+                namespace: [0, 1, 2, 3, 4, 5, 6, 7, 8, 9],
+                instance: [10, 11, 12, 13, 14, 15],
+            }))
+        );
+        data.extend([0, 0]);
+        assert!(Eddystone::from_service_data(&data).is_some());
+
+        assert_eq!(
+            Eddystone::from_service_data(&[0x30, 0xF0, 1, 2, 3, 4, 5, 6, 7, 8]),
+            Some(Eddystone::Eid(EddystoneEid {
+                tx_power: -16,
+                eid: [1, 2, 3, 4, 5, 6, 7, 8],
+            }))
+        );
+    }
+
+    #[test]
+    fn eddystone_url() {
+        let data = [
+            0x10, 0xF8, 0x03, b'g', b'o', b'o', b'.', b'g', b'l', 0x07, b'a',
+        ];
+        assert_eq!(
+            Eddystone::from_service_data(&data),
+            Some(Eddystone::Url(EddystoneUrl {
+                tx_power: -8,
+                url: "https://goo.gl.coma".to_string(),
+            }))
+        );
+        assert_eq!(
+            Eddystone::from_service_data(&[0x10, 0x00, 0x00, b'x', 0x00]),
+            Some(Eddystone::Url(EddystoneUrl {
+                tx_power: 0,
+                url: "http://www.x.com/".to_string(),
+            }))
+        );
+        assert_eq!(Eddystone::from_service_data(&[0x10, 0x00, 0x04]), None);
+        assert_eq!(
+            Eddystone::from_service_data(&[0x10, 0x00, 0x00, b' ']),
+            None
+        );
+    }
+
+    #[test]
+    fn eddystone_tlm() {
+        let data = [
+            0x20, 0x00, 0x0B, 0xB8, 0x17, 0x80, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x0A,
+        ];
+        assert_eq!(
+            Eddystone::from_service_data(&data),
+            Some(Eddystone::Tlm(EddystoneTlm {
+                battery_voltage: Some(3000),
+                temperature: Some(23.5),
+                advertising_count: 256,
+                uptime: Duration::from_secs(1),
+            }))
+        );
+        let unsupported = [
+            0x20, 0x00, 0x00, 0x00, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+        assert!(matches!(
+            Eddystone::from_service_data(&unsupported),
+            Some(Eddystone::Tlm(EddystoneTlm {
+                battery_voltage: None,
+                temperature: None,
+                ..
+            }))
+        ));
+        let mut encrypted = data;
+        encrypted[1] = 0x01;
+        assert_eq!(Eddystone::from_service_data(&encrypted), None);
+    }
+
+    #[test]
+    fn from_properties() {
+        let properties = PeripheralProperties {
+            manufacturer_data: [(0x0059, vec![1, 2, 3])].into(),
+            service_data: [
+                (
+                    EDDYSTONE_SERVICE_UUID,
+                    vec![0x30, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+                ),
+                (uuid_from_u16(0x180F), vec![0x30, 0, 0, 0, 0, 0, 0, 0, 0, 0]),
+            ]
+            .into(),
+            ..Default::default()
+        };
+        assert_eq!(
+            Beacon::from_properties(&properties),
+            vec![Beacon::Eddystone(Eddystone::Eid(EddystoneEid {
+                tx_power: 0,
+                eid: [0; 8],
+            }))]
+        );
+    }
+}
@@ -1,5 +1,10 @@
-//! Utilities for dealing with BLE UUIDs, converting to and from their short formats.
+//! Utilities for dealing with BLE UUIDs, converting to and from their short formats and looking up
+//! their names.
 
+use super::assigned_numbers::{
+    lookup, CHARACTERISTICS, COMPANY_IDENTIFIERS, DECLARATIONS, DESCRIPTORS, MEMBER_SERVICES,
+    SERVICES,
+};
 use uuid::Uuid;
 
 const BLUETOOTH_BASE_UUID: u128 = 0x00000000_0000_1000_8000_00805f9b34fb;
@@ -19,6 +24,48 @@ pub const fn uuid_from_u16(short: u16) -> Uuid {
     uuid_from_u32(short as u32)
 }
 
+/// The tables of SIG-assigned 16-bit UUIDs. Their numbers don't overlap with each other, but their
+/// names do, e.g. the Current Time service and characteristic.
+const UUID_TABLES: [&[(u16, &str)]; 5] = [
+    DECLARATIONS,
+    DESCRIPTORS,
+    SERVICES,
+    CHARACTERISTICS,
+    MEMBER_SERVICES,
+];
+
+/// Look up the UUID of a SIG-assigned service by its name, ignoring case. This is the reverse of
+/// [`BleUuid::name`] for services. The UUIDs assigned to member companies aren't looked up, as a
+/// company may have several.
+pub fn service_uuid_from_name(name: &str) -> Option<Uuid> {
+    find_name(SERVICES, name)
+}
+
+/// Look up the UUID of a SIG-assigned characteristic by its name, ignoring case. This is the
+/// reverse of [`BleUuid::name`] for characteristics.
+pub fn characteristic_uuid_from_name(name: &str) -> Option<Uuid> {
+    find_name(CHARACTERISTICS, name)
+}
+
+/// Look up the UUID of a SIG-assigned descriptor by its name, ignoring case. This is the reverse of
+/// [`BleUuid::name`] for descriptors.
+pub fn descriptor_uuid_from_name(name: &str) -> Option<Uuid> {
+    find_name(DESCRIPTORS, name)
+}
+
+fn find_name(table: &[(u16, &str)], name: &str) -> Option<Uuid> {
+    table
+        .iter()
+        .find(|(_, n)| n.eq_ignore_ascii_case(name))
+        .map(|(short, _)| uuid_from_u16(*short))
+}
+
+/// Get the name of the company with the given SIG-assigned company identifier, as used for the keys
+/// of [`PeripheralProperties::manufacturer_data`](super::PeripheralProperties::manufacturer_data).
+pub fn company_name(company_id: u16) -> Option<&'static str> {
+    lookup(COMPANY_IDENTIFIERS, company_id)
+}
+
 /// An extension trait for `Uuid` which provides BLE-specific methods.
 pub trait BleUuid {
     /// If the UUID is a valid BLE short UUID then return its short form, otherwise return `None`.
@@ -30,6 +77,11 @@ pub trait BleUuid {
 
     /// Convert the UUID to a string, using short format if applicable.
     fn to_short_string(&self) -> String;
+
+    /// If the UUID is a 16-bit BLE short UUID assigned by the Bluetooth SIG to a known service,
+    /// characteristic, descriptor or declaration, or to a member company, then return its name.
+    /// Only the more common assigned numbers are known.
+    fn name(&self) -> Option<&'static str>;
 }
 
 impl BleUuid for Uuid {
@@ -60,6 +112,11 @@ impl BleUuid for Uuid {
             self.to_string()
         }
     }
+
+    fn name(&self) -> Option<&'static str> {
+        let short = self.to_ble_u16()?;
+        UUID_TABLES.iter().find_map(|table| lookup(table, short))
+    }
 }
 
 #[cfg(test)]
@@ -134,6 +191,49 @@ mod tests {
         assert_eq!(uuid.to_short_string(), "0x11223344");
     }
 
+    #[test]
+    fn names() {
+        assert_eq!(uuid_from_u16(0x2A37).name(), Some("Heart Rate Measurement"));
+        assert_eq!(uuid_from_u16(0x180F).name(), Some("Battery"));
+        assert_eq!(
+            uuid_from_u16(0x2902).name(),
+            Some("Client Characteristic Configuration")
+        );
+        assert_eq!(uuid_from_u16(0x2800).name(), Some("Primary Service"));
+        assert_eq!(uuid_from_u16(0xFEAA).name(), Some("Google LLC"));
+        assert_eq!(uuid_from_u16(0x1234).name(), None);
+        assert_eq!(uuid_from_u32(0x11112A37).name(), None);
+        assert_eq!(
+            characteristic_uuid_from_name("heart rate measurement"),
+            Some(uuid_from_u16(0x2A37))
+        );
+        assert_eq!(
+            service_uuid_from_name("Battery"),
+            Some(uuid_from_u16(0x180F))
+        );
+        assert_eq!(service_uuid_from_name("Google LLC"), None);
+        assert_eq!(
+            descriptor_uuid_from_name("Client Characteristic Configuration"),
+            Some(uuid_from_u16(0x2902))
+        );
+        assert_eq!(characteristic_uuid_from_name("Battery"), None);
+        assert_eq!(service_uuid_from_name("Not a name"), None);
+        assert_eq!(company_name(0x004C), Some("Apple, Inc."));
+        assert_eq!(company_name(0xFFFF), None);
+    }
+
+    #[test]
+    fn names_shared_between_tables() {
+        assert_eq!(
+            service_uuid_from_name("Current Time"),
+            Some(uuid_from_u16(0x1805))
+        );
+        assert_eq!(
+            characteristic_uuid_from_name("Current Time"),
+            Some(uuid_from_u16(0x2A2B))
+        );
+    }
+
     #[test]
     fn to_short_string_long() {
         let uuid_str = "12345678-9000-1000-8000-00805f9b34fb";
@@ -0,0 +1,486 @@
+//! Metadata about a characteristic from the standard descriptors a device may give it: its user
+//! description, the format and unit of its value, the range of values it accepts and its extended
+//! properties.
+//!
+//! [`Peripheral::characteristic_metadata`](crate::api::Peripheral::characteristic_metadata) reads
+//! and decodes all of these at once. Values read some other way can be decoded with
+//! [`CharacteristicMetadata::add_descriptor`], or with the `decode` function of each type.
+//!
+//! ```
+//! use btleplug::api::gatt::metadata::{CharacteristicMetadata, Format, PresentationFormat};
+//!
+//! let mut metadata = CharacteristicMetadata::default();
+//! metadata
+//!     .add_descriptor(PresentationFormat::UUID, &[0x0E, 0xFE, 0x2F, 0x27, 0x01, 0x00, 0x00])
+//!     .unwrap();
+//! let format = metadata.presentation_format().unwrap();
+//! assert_eq!(format.format, Format::Sint16);
+//! // 0x0929 hundredths of a degree Celsius.
+//! assert_eq!(format.value_to_f64(&[0x29, 0x09]), Some(23.45));
+//! ```
+
+use super::standard::{float_to_f32, sfloat_to_f32, DecodeError, Reader};
+use crate::api::bleuuid::uuid_from_u16;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "serde")]
+use serde_cr as serde;
+use uuid::Uuid;
+
+/// The namespace of the [`PresentationFormat::description`] values assigned by the Bluetooth SIG.
+pub const BLUETOOTH_SIG_NAMESPACE: u8 = 0x01;
+
+/// What the standard descriptors of a characteristic say about it. Fields are empty for descriptors
+/// the characteristic doesn't have.
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_cr")
+)]
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct CharacteristicMetadata {
+    /// The Characteristic User Description (0x2901), a name for the characteristic meant to be
+    /// shown to users.
+    pub user_description: Option<String>,
+    /// The Characteristic Presentation Formats (0x2904), in handle order. A characteristic usually
+    /// has at most one, but one whose value is made of several fields may have one per field.
+    pub presentation_formats: Vec<PresentationFormat>,
+    /// The Characteristic Extended Properties (0x2900).
+    pub extended_properties: Option<ExtendedProperties>,
+    /// The Valid Range (0x2906) of the characteristic's value.
+    pub valid_range: Option<ValidRange>,
+}
+
+impl CharacteristicMetadata {
+    /// Returns whether descriptors with the given UUID are decoded into the metadata.
+    pub fn is_metadata_descriptor(uuid: Uuid) -> bool {
+        [
+            UserDescription::UUID,
+            PresentationFormat::UUID,
+            ExtendedProperties::UUID,
+            ValidRange::UUID,
+        ]
+        .contains(&uuid)
+    }
+
+    /// Decodes the value of a descriptor into the metadata. Descriptors which aren't metadata are
+    /// ignored.
+    pub fn add_descriptor(&mut self, uuid: Uuid, value: &[u8]) -> Result<(), DecodeError> {
+        match uuid {
+            UserDescription::UUID => {
+                self.user_description = Some(UserDescription::decode(value)?.0);
+            }
+            PresentationFormat::UUID => {
+                self.presentation_formats
+                    .push(PresentationFormat::decode(value)?);
+            }
+            ExtendedProperties::UUID => {
+                self.extended_properties = Some(ExtendedProperties::decode(value)?);
+            }
+            ValidRange::UUID => {
+                self.valid_range = Some(ValidRange::decode(value)?);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// The presentation format of the value, if the characteristic has exactly one.
+    pub fn presentation_format(&self) -> Option<&PresentationFormat> {
+        match self.presentation_formats.as_slice() {
+            [format] => Some(format),
+            _ => None,
+        }
+    }
+}
+
+/// The Characteristic User Description descriptor (0x2901).
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct UserDescription(pub String);
+
+impl UserDescription {
+    pub const UUID: Uuid = uuid_from_u16(0x2901);
+
+    /// Decodes the UTF-8 description. Invalid sequences are replaced rather than rejected, and
+    /// trailing NUL characters, which some devices include, are dropped.
+    pub fn decode(value: &[u8]) -> Result<Self, DecodeError> {
+        let description = String::from_utf8_lossy(value);
+        Ok(UserDescription(
+            description.trim_end_matches('\0').to_string(),
+        ))
+    }
+}
+
+/// The Characteristic Extended Properties descriptor (0x2900).
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_cr")
+)]
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct ExtendedProperties {
+    /// Whether the characteristic can be written with queued writes, which are only applied once
+    /// all of them have been checked by the device.
+    pub reliable_write: bool,
+    /// Whether the Characteristic User Description can be written.
+    pub writable_auxiliaries: bool,
+}
+
+impl ExtendedProperties {
+    pub const UUID: Uuid = uuid_from_u16(0x2900);
+
+    pub fn decode(value: &[u8]) -> Result<Self, DecodeError> {
+        let flags = Reader(value).u16()?;
+        Ok(ExtendedProperties {
+            reliable_write: flags & 0x0001 != 0,
+            writable_auxiliaries: flags & 0x0002 != 0,
+        })
+    }
+}
+
+/// The format of a characteristic value, as given by its [`PresentationFormat`].
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_cr")
+)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Format {
+    Boolean,
+    Uint2,
+    Uint4,
+    Uint8,
+    Uint12,
+    Uint16,
+    Uint24,
+    Uint32,
+    Uint48,
+    Uint64,
+    Uint128,
+    Sint8,
+    Sint12,
+    Sint16,
+    Sint24,
+    Sint32,
+    Sint48,
+    Sint64,
+    Sint128,
+    /// An IEEE 754 single precision float.
+    Float32,
+    /// An IEEE 754 double precision float.
+    Float64,
+    /// An IEEE 11073 16-bit SFLOAT.
+    Sfloat,
+    /// An IEEE 11073 32-bit FLOAT.
+    Float,
+    /// Two unsigned 16-bit integers.
+    Duint16,
+    /// A UTF-8 string.
+    Utf8,
+    /// A UTF-16 string.
+    Utf16,
+    /// A structure defined by the characteristic's specification.
+    Struct,
+    /// A format not listed above.
+    Other(u8),
+}
+
+impl Format {
+    fn from_code(code: u8) -> Self {
+        match code {
+            0x01 => Format::Boolean,
+            0x02 => Format::Uint2,
+            0x03 => Format::Uint4,
+            0x04 => Format::Uint8,
+            0x05 => Format::Uint12,
+            0x06 => Format::Uint16,
+            0x07 => Format::Uint24,
+            0x08 => Format::Uint32,
+            0x09 => Format::Uint48,
+            0x0A => Format::Uint64,
+            0x0B => Format::Uint128,
+            0x0C => Format::Sint8,
+            0x0D => Format::Sint12,
+            0x0E => Format::Sint16,
+            0x0F => Format::Sint24,
+            0x10 => Format::Sint32,
+            0x11 => Format::Sint48,
+            0x12 => Format::Sint64,
+            0x13 => Format::Sint128,
+            0x14 => Format::Float32,
+            0x15 => Format::Float64,
+            0x16 => Format::Sfloat,
+            0x17 => Format::Float,
+            0x18 => Format::Duint16,
+            0x19 => Format::Utf8,
+            0x1A => Format::Utf16,
+            0x1B => Format::Struct,
+            code => Format::Other(code),
+        }
+    }
+
+    /// The number of bits in an integer of this format, and whether it is signed.
+    fn integer_bits(self) -> Option<(u32, bool)> {
+        match self {
+            Format::Boolean => Some((1, false)),
+            Format::Uint2 => Some((2, false)),
+            Format::Uint4 => Some((4, false)),
+            Format::Uint8 => Some((8, false)),
+            Format::Uint12 => Some((12, false)),
+            Format::Uint16 => Some((16, false)),
+            Format::Uint24 => Some((24, false)),
+            Format::Uint32 => Some((32, false)),
+            Format::Uint48 => Some((48, false)),
+            Format::Uint64 => Some((64, false)),
+            Format::Uint128 => Some((128, false)),
+            Format::Sint8 => Some((8, true)),
+            Format::Sint12 => Some((12, true)),
+            Format::Sint16 => Some((16, true)),
+            Format::Sint24 => Some((24, true)),
+            Format::Sint32 => Some((32, true)),
+            Format::Sint48 => Some((48, true)),
+            Format::Sint64 => Some((64, true)),
+            Format::Sint128 => Some((128, true)),
+            _ => None,
+        }
+    }
+
+    /// The size in bytes of a value of this format, if it is a number.
+    fn numeric_size(self) -> Option<usize> {
+        match self {
+            Format::Float32 | Format::Float => Some(4),
+            Format::Float64 => Some(8),
+            Format::Sfloat => Some(2),
+            _ => self.integer_bits().map(|(bits, _)| (bits as usize + 7) / 8),
+        }
+    }
+}
+
+/// The Characteristic Presentation Format descriptor (0x2904).
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_cr")
+)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct PresentationFormat {
+    /// The format of the value.
+    pub format: Format,
+    /// The power of ten integer values are multiplied by to give the value in [`unit`](Self::unit).
+    pub exponent: i8,
+    /// The unit of the value, from the units in the Bluetooth SIG's Assigned Numbers, such as
+    /// 0x272F for degrees Celsius.
+    pub unit: Uuid,
+    /// The organization which assigned [`description`](Self::description), usually
+    /// [`BLUETOOTH_SIG_NAMESPACE`].
+    pub namespace: u8,
+    /// Which part of the device the value is about, for characteristics which occur several times.
+    /// The Bluetooth SIG's values include 0x0001 to 0x00FF for "first", "second" and so on, and names
+    /// such as 0x010D for "left".
+    pub description: u16,
+}
+
+impl PresentationFormat {
+    pub const UUID: Uuid = uuid_from_u16(0x2904);
+
+    pub fn decode(value: &[u8]) -> Result<Self, DecodeError> {
+        let mut reader = Reader(value);
+        Ok(PresentationFormat {
+            format: Format::from_code(reader.u8()?),
+            exponent: reader.u8()? as i8,
+            unit: uuid_from_u16(reader.u16()?),
+            namespace: reader.u8()?,
+            description: reader.u16()?,
+        })
+    }
+
+    /// Converts a numeric value of this format to a number in [`unit`](Self::unit), applying the
+    /// exponent to integers. Returns `None` if the format is not a number or the value is too
+    /// short.
+    pub fn value_to_f64(&self, value: &[u8]) -> Option<f64> {
+        let size = self.format.numeric_size()?;
+        let bytes = value.get(..size)?;
+        match self.format {
+            Format::Float32 => Some(f32::from_le_bytes(bytes.try_into().ok()?).into()),
+            Format::Float64 => Some(f64::from_le_bytes(bytes.try_into().ok()?)),
+            Format::Sfloat => {
+                Some(sfloat_to_f32(u16::from_le_bytes(bytes.try_into().ok()?)).into())
+            }
+            Format::Float => Some(float_to_f32(u32::from_le_bytes(bytes.try_into().ok()?)).into()),
+            format => {
+                let (bits, signed) = format.integer_bits()?;
+                let mut raw = [0; 16];
+                raw[..size].copy_from_slice(bytes);
+                let unused = 128 - bits;
+                let integer = if signed {
+                    // Shift the sign bit to the top and back to extend it.
+                    ((i128::from_le_bytes(raw) << unused) >> unused) as f64
+                } else {
+                    ((u128::from_le_bytes(raw) << unused) >> unused) as f64
+                };
+                // Dividing by a power of ten rounds correctly where multiplying by its inverse
+                // wouldn't, so that e.g. 2345 with an exponent of -2 gives exactly 23.45.
+                let scale = 10f64.powi(self.exponent.unsigned_abs().into());
+                Some(if self.exponent < 0 {
+                    integer / scale
+                } else {
+                    integer * scale
+                })
+            }
+        }
+    }
+}
+
+/// The Valid Range descriptor (0x2906), holding the lowest and highest values the characteristic
+/// accepts. Both are in the format of the characteristic's value, so are kept as raw bytes; see
+/// [`bounds`](Self::bounds) to convert them.
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_cr")
+)]
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ValidRange {
+    pub lower: Vec<u8>,
+    pub upper: Vec<u8>,
+}
+
+impl ValidRange {
+    pub const UUID: Uuid = uuid_from_u16(0x2906);
+
+    pub fn decode(value: &[u8]) -> Result<Self, DecodeError> {
+        if value.is_empty() {
+            return Err(DecodeError::Truncated);
+        }
+        if value.len() % 2 != 0 {
+            return Err(DecodeError::Invalid);
+        }
+        let mut reader = Reader(value);
+        Ok(ValidRange {
+            lower: reader.take(value.len() / 2)?.to_vec(),
+            upper: reader.take(value.len() / 2)?.to_vec(),
+        })
+    }
+
+    /// Converts the bounds to numbers with the given presentation format, as with
+    /// [`PresentationFormat::value_to_f64`].
+    pub fn bounds(&self, format: &PresentationFormat) -> Option<(f64, f64)> {
+        Some((
+            format.value_to_f64(&self.lower)?,
+            format.value_to_f64(&self.upper)?,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(format: Format, exponent: i8) -> PresentationFormat {
+        PresentationFormat {
+            format,
+            exponent,
+            unit: uuid_from_u16(0x2700),
+            namespace: BLUETOOTH_SIG_NAMESPACE,
+            description: 0,
+        }
+    }
+
+    #[test]
+    fn presentation_format() {
+        assert_eq!(
+            PresentationFormat::decode(&[0x06, 0xFF, 0xAD, 0x27, 0x01, 0x0D, 0x01]).unwrap(),
+            PresentationFormat {
+                format: Format::Uint16,
+                exponent: -1,
+                unit: uuid_from_u16(0x27AD),
+                namespace: BLUETOOTH_SIG_NAMESPACE,
+                description: 0x010D,
+            }
+        );
+        assert_eq!(
+            PresentationFormat::decode(&[0x06, 0xFF]),
+            Err(DecodeError::Truncated)
+        );
+        assert_eq!(
+            PresentationFormat::decode(&[0xF0, 0, 0, 0, 0, 0, 0])
+                .unwrap()
+                .format,
+            Format::Other(0xF0)
+        );
+    }
+
+    #[test]
+    fn numeric_values() {
+        assert_eq!(format(Format::Uint8, 0).value_to_f64(&[200]), Some(200.0));
+        assert_eq!(format(Format::Sint8, 0).value_to_f64(&[0xF6]), Some(-10.0));
+        assert_eq!(
+            format(Format::Sint12, 0).value_to_f64(&[0xFF, 0x0F]),
+            Some(-1.0)
+        );
+        assert_eq!(
+            format(Format::Uint12, 0).value_to_f64(&[0xFF, 0xFF]),
+            Some(4095.0)
+        );
+        assert_eq!(format(Format::Uint4, 0).value_to_f64(&[0xF3]), Some(3.0));
+        assert_eq!(
+            format(Format::Sint24, 0).value_to_f64(&[0x00, 0x00, 0x80]),
+            Some(-8388608.0)
+        );
+        assert_eq!(
+            format(Format::Uint16, 2).value_to_f64(&[0x05, 0x00]),
+            Some(500.0)
+        );
+        assert_eq!(
+            format(Format::Sfloat, 2).value_to_f64(&[0x48, 0x00]),
+            Some(72.0)
+        );
+        assert_eq!(
+            format(Format::Float32, 0).value_to_f64(&1.5f32.to_le_bytes()),
+            Some(1.5)
+        );
+        assert_eq!(format(Format::Uint16, 0).value_to_f64(&[0x05]), None);
+        assert_eq!(format(Format::Utf8, 0).value_to_f64(b"12"), None);
+    }
+
+    #[test]
+    fn valid_range() {
+        let range = ValidRange::decode(&[0xF6, 0xFF, 0x64, 0x00]).unwrap();
+        assert_eq!(range.lower, [0xF6, 0xFF]);
+        assert_eq!(range.upper, [0x64, 0x00]);
+        assert_eq!(
+            range.bounds(&format(Format::Sint16, -1)),
+            Some((-1.0, 10.0))
+        );
+        assert_eq!(ValidRange::decode(&[1, 2, 3]), Err(DecodeError::Invalid));
+        assert_eq!(ValidRange::decode(&[]), Err(DecodeError::Truncated));
+    }
+
+    #[test]
+    fn metadata() {
+        let mut metadata = CharacteristicMetadata::default();
+        metadata
+            .add_descriptor(UserDescription::UUID, b"Setpoint\0")
+            .unwrap();
+        metadata
+            .add_descriptor(ExtendedProperties::UUID, &[0x02, 0x00])
+            .unwrap();
+        metadata
+            .add_descriptor(uuid_from_u16(0x2902), &[0x01, 0x00])
+            .unwrap();
+        assert_eq!(metadata.user_description.as_deref(), Some("Setpoint"));
+        assert_eq!(
+            metadata.extended_properties,
+            Some(ExtendedProperties {
+                reliable_write: false,
+                writable_auxiliaries: true,
+            })
+        );
+        assert!(metadata.presentation_format().is_none());
+        assert!(metadata.valid_range.is_none());
+        assert_eq!(
+            metadata.add_descriptor(ExtendedProperties::UUID, &[0x01]),
+            Err(DecodeError::Truncated)
+        );
+    }
+}
@@ -0,0 +1,4 @@
+//! Helpers for working with the values of GATT characteristics.
+
+pub mod metadata;
+pub mod standard;
@@ -0,0 +1,888 @@
+//! Decoders and encoders for the values of some standard characteristics defined by the Bluetooth
+//! SIG.
+//!
+//! Each supported characteristic has a type implementing [`StandardCharacteristic`], which knows its
+//! UUID and how to convert to and from its raw value. Values can then be decoded from a read or a
+//! notification:
+//!
+//! ```
+//! use btleplug::api::bleuuid::uuid_from_u16;
+//! use btleplug::api::gatt::standard::{HeartRateMeasurement, StandardCharacteristic};
+//! use btleplug::api::ValueNotification;
+//!
+//! let notification = ValueNotification {
+//!     uuid: HeartRateMeasurement::UUID,
+//!     service_uuid: uuid_from_u16(0x180D),
+//!     handle: 0x0012,
+//!     value: vec![0x06, 0x48],
+//! };
+//! let measurement = notification.decode::<HeartRateMeasurement>().unwrap().unwrap();
+//! assert_eq!(measurement.heart_rate, 72);
+//! assert_eq!(measurement.sensor_contact, Some(true));
+//! ```
+//!
+//! Medical characteristics use the IEEE 11073-20601 SFLOAT and FLOAT formats, which can also be
+//! converted directly with [`sfloat_to_f32`], [`f32_to_sfloat`], [`float_to_f32`] and
+//! [`f32_to_float`].
+
+use crate::api::bleuuid::uuid_from_u16;
+use crate::api::ValueNotification;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "serde")]
+use serde_cr as serde;
+use uuid::Uuid;
+
+/// An error decoding the value of a characteristic.
+#[derive(Debug, thiserror::Error, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    #[error("Characteristic value is too short")]
+    Truncated,
+    #[error("Invalid characteristic value")]
+    Invalid,
+}
+
+/// A standard characteristic whose value can be converted to and from its raw bytes.
+pub trait StandardCharacteristic: Sized {
+    /// The UUID of the characteristic.
+    const UUID: Uuid;
+
+    /// Decodes a raw characteristic value. Any bytes after the fields defined by the specification
+    /// are ignored, as later versions may add more.
+    fn decode(value: &[u8]) -> Result<Self, DecodeError>;
+
+    /// Encodes the value as it would be sent by a peripheral.
+    fn encode(&self) -> Vec<u8>;
+}
+
+impl ValueNotification {
+    /// Decodes the value of the notification as the given standard characteristic, if it is from
+    /// that characteristic.
+    pub fn decode<T: StandardCharacteristic>(&self) -> Option<Result<T, DecodeError>> {
+        (self.uuid == T::UUID).then(|| T::decode(&self.value))
+    }
+}
+
+const SFLOAT_NAN: u16 = 0x07FF;
+const SFLOAT_NRES: u16 = 0x0800;
+const SFLOAT_POSITIVE_INFINITY: u16 = 0x07FE;
+const SFLOAT_NEGATIVE_INFINITY: u16 = 0x0802;
+const SFLOAT_RESERVED: u16 = 0x0801;
+
+const FLOAT_NAN: u32 = 0x007FFFFF;
+const FLOAT_NRES: u32 = 0x00800000;
+const FLOAT_POSITIVE_INFINITY: u32 = 0x007FFFFE;
+const FLOAT_NEGATIVE_INFINITY: u32 = 0x00800002;
+const FLOAT_RESERVED: u32 = 0x00800001;
+
+/// Converts an IEEE 11073 16-bit SFLOAT to an `f32`. NaN, NRes (not at this resolution) and the
+/// reserved value all become NaN.
+pub fn sfloat_to_f32(value: u16) -> f32 {
+    match value {
+        SFLOAT_NAN | SFLOAT_NRES | SFLOAT_RESERVED => f32::NAN,
+        SFLOAT_POSITIVE_INFINITY => f32::INFINITY,
+        SFLOAT_NEGATIVE_INFINITY => f32::NEG_INFINITY,
+        _ => {
+            // A 12-bit signed mantissa and a 4-bit signed exponent.
+            let mantissa = ((value << 4) as i16) >> 4;
+            let exponent = (value as i16) >> 12;
+            (f64::from(mantissa) * 10f64.powi(exponent.into())) as f32
+        }
+    }
+}
+
+/// Converts an `f32` to an IEEE 11073 16-bit SFLOAT, with as much precision as fits. Values too
+/// large to represent become infinite.
+pub fn f32_to_sfloat(value: f32) -> u16 {
+    match encode_float(value, 2045, -8, 7) {
+        Encoded::Nan => SFLOAT_NAN,
+        Encoded::PositiveInfinity => SFLOAT_POSITIVE_INFINITY,
+        Encoded::NegativeInfinity => SFLOAT_NEGATIVE_INFINITY,
+        Encoded::Finite { mantissa, exponent } => {
+            ((exponent as u16) << 12) | (mantissa as u16 & 0x0FFF)
+        }
+    }
+}
+
+/// Converts an IEEE 11073 32-bit FLOAT to an `f32`. NaN, NRes (not at this resolution) and the
+/// reserved value all become NaN.
+pub fn float_to_f32(value: u32) -> f32 {
+    match value {
+        FLOAT_NAN | FLOAT_NRES | FLOAT_RESERVED => f32::NAN,
+        FLOAT_POSITIVE_INFINITY => f32::INFINITY,
+        FLOAT_NEGATIVE_INFINITY => f32::NEG_INFINITY,
+        _ => {
+            // A 24-bit signed mantissa and an 8-bit signed exponent.
+            let mantissa = ((value << 8) as i32) >> 8;
+            let exponent = (value as i32) >> 24;
+            (f64::from(mantissa) * 10f64.powi(exponent)) as f32
+        }
+    }
+}
+
+/// Converts an `f32` to an IEEE 11073 32-bit FLOAT, with as much precision as fits. Values too
+/// large to represent become infinite.
+pub fn f32_to_float(value: f32) -> u32 {
+    match encode_float(value, 8388605, -128, 127) {
+        Encoded::Nan => FLOAT_NAN,
+        Encoded::PositiveInfinity => FLOAT_POSITIVE_INFINITY,
+        Encoded::NegativeInfinity => FLOAT_NEGATIVE_INFINITY,
+        Encoded::Finite { mantissa, exponent } => {
+            ((exponent as u32) << 24) | (mantissa as u32 & 0x00FF_FFFF)
+        }
+    }
+}
+
+enum Encoded {
+    Nan,
+    PositiveInfinity,
+    NegativeInfinity,
+    Finite { mantissa: i32, exponent: i32 },
+}
+
+/// Finds the smallest exponent for which the mantissa of `value` fits within `max_mantissa`, so as
+/// to keep as much precision as possible, then drops trailing zeros from the mantissa down to an
+/// exponent of zero. The special values at the ends of the mantissa range are avoided.
+fn encode_float(value: f32, max_mantissa: i32, min_exponent: i32, max_exponent: i32) -> Encoded {
+    if value.is_nan() {
+        return Encoded::Nan;
+    }
+    // Go through the decimal representation of the f32, so that values like 36.6 are encoded
+    // exactly rather than as the nearest binary value.
+    let value: f64 = value.to_string().parse().unwrap();
+    for exponent in min_exponent..=max_exponent {
+        let mantissa = (value / 10f64.powi(exponent)).round();
+        if mantissa == 0.0 {
+            return Encoded::Finite {
+                mantissa: 0,
+                exponent: 0,
+            };
+        }
+        if mantissa.abs() <= f64::from(max_mantissa) {
+            let (mut mantissa, mut exponent) = (mantissa as i32, exponent);
+            while mantissa % 10 == 0 && exponent < 0 {
+                mantissa /= 10;
+                exponent += 1;
+            }
+            return Encoded::Finite { mantissa, exponent };
+        }
+    }
+    if value > 0.0 {
+        Encoded::PositiveInfinity
+    } else {
+        Encoded::NegativeInfinity
+    }
+}
+
+/// Reads little-endian fields from a characteristic value.
+pub(super) struct Reader<'a>(pub(super) &'a [u8]);
+
+impl<'a> Reader<'a> {
+    pub(super) fn take(&mut self, n: usize) -> Result<&'a [u8], DecodeError> {
+        if self.0.len() < n {
+            return Err(DecodeError::Truncated);
+        }
+        let (taken, rest) = self.0.split_at(n);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    pub(super) fn u8(&mut self) -> Result<u8, DecodeError> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub(super) fn u16(&mut self) -> Result<u16, DecodeError> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, DecodeError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn sfloat(&mut self) -> Result<f32, DecodeError> {
+        Ok(sfloat_to_f32(self.u16()?))
+    }
+
+    fn float(&mut self) -> Result<f32, DecodeError> {
+        Ok(float_to_f32(self.u32()?))
+    }
+
+    fn date_time(&mut self) -> Result<DateTime, DecodeError> {
+        Ok(DateTime {
+            year: self.u16()?,
+            month: self.u8()?,
+            day: self.u8()?,
+            hours: self.u8()?,
+            minutes: self.u8()?,
+            seconds: self.u8()?,
+        })
+    }
+}
+
+/// A date and time, as used by several characteristics and by the Date Time characteristic
+/// (0x2A08) itself. Zero in the year, month or day means that it is not known.
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_cr")
+)]
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct DateTime {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hours: u8,
+    pub minutes: u8,
+    pub seconds: u8,
+}
+
+impl DateTime {
+    fn encode_to(&self, value: &mut Vec<u8>) {
+        value.extend_from_slice(&self.year.to_le_bytes());
+        value.extend_from_slice(&[self.month, self.day, self.hours, self.minutes, self.seconds]);
+    }
+}
+
+impl StandardCharacteristic for DateTime {
+    const UUID: Uuid = uuid_from_u16(0x2A08);
+
+    fn decode(value: &[u8]) -> Result<Self, DecodeError> {
+        Reader(value).date_time()
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut value = vec![];
+        self.encode_to(&mut value);
+        value
+    }
+}
+
+/// The Battery Level characteristic (0x2A19), as a percentage.
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_cr")
+)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct BatteryLevel(pub u8);
+
+impl StandardCharacteristic for BatteryLevel {
+    const UUID: Uuid = uuid_from_u16(0x2A19);
+
+    fn decode(value: &[u8]) -> Result<Self, DecodeError> {
+        let level = Reader(value).u8()?;
+        if level > 100 {
+            return Err(DecodeError::Invalid);
+        }
+        Ok(BatteryLevel(level))
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        vec![self.0]
+    }
+}
+
+/// The Heart Rate Measurement characteristic (0x2A37).
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_cr")
+)]
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct HeartRateMeasurement {
+    /// The heart rate, in beats per minute.
+    pub heart_rate: u16,
+    /// Whether the sensor is in contact with the skin, if it can tell.
+    pub sensor_contact: Option<bool>,
+    /// The energy expended since the last reset, in kJ.
+    pub energy_expended: Option<u16>,
+    /// The intervals between consecutive beats, in units of 1/1024 s, oldest first.
+    pub rr_intervals: Vec<u16>,
+}
+
+impl StandardCharacteristic for HeartRateMeasurement {
+    const UUID: Uuid = uuid_from_u16(0x2A37);
+
+    fn decode(value: &[u8]) -> Result<Self, DecodeError> {
+        let mut reader = Reader(value);
+        let flags = reader.u8()?;
+        let heart_rate = if flags & 0x01 != 0 {
+            reader.u16()?
+        } else {
+            reader.u8()?.into()
+        };
+        let sensor_contact = (flags & 0x04 != 0).then_some(flags & 0x02 != 0);
+        let energy_expended = if flags & 0x08 != 0 {
+            Some(reader.u16()?)
+        } else {
+            None
+        };
+        let mut rr_intervals = vec![];
+        if flags & 0x10 != 0 {
+            while !reader.0.is_empty() {
+                rr_intervals.push(reader.u16()?);
+            }
+        }
+        Ok(Self {
+            heart_rate,
+            sensor_contact,
+            energy_expended,
+            rr_intervals,
+        })
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut value = vec![0];
+        match u8::try_from(self.heart_rate) {
+            Ok(heart_rate) => value.push(heart_rate),
+            Err(_) => {
+                value[0] |= 0x01;
+                value.extend_from_slice(&self.heart_rate.to_le_bytes());
+            }
+        }
+        match self.sensor_contact {
+            Some(true) => value[0] |= 0x06,
+            Some(false) => value[0] |= 0x04,
+            None => {}
+        }
+        if let Some(energy_expended) = self.energy_expended {
+            value[0] |= 0x08;
+            value.extend_from_slice(&energy_expended.to_le_bytes());
+        }
+        if !self.rr_intervals.is_empty() {
+            value[0] |= 0x10;
+            for interval in &self.rr_intervals {
+                value.extend_from_slice(&interval.to_le_bytes());
+            }
+        }
+        value
+    }
+}
+
+/// The unit of a [`TemperatureMeasurement`].
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_cr")
+)]
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub enum TemperatureUnit {
+    #[default]
+    Celsius,
+    Fahrenheit,
+}
+
+/// The Temperature Measurement characteristic (0x2A1C) of the Health Thermometer service.
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_cr")
+)]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TemperatureMeasurement {
+    pub temperature: f32,
+    pub unit: TemperatureUnit,
+    pub timestamp: Option<DateTime>,
+    /// Where on the body the temperature was measured, as a Temperature Type value.
+    pub temperature_type: Option<u8>,
+}
+
+impl StandardCharacteristic for TemperatureMeasurement {
+    const UUID: Uuid = uuid_from_u16(0x2A1C);
+
+    fn decode(value: &[u8]) -> Result<Self, DecodeError> {
+        let mut reader = Reader(value);
+        let flags = reader.u8()?;
+        Ok(Self {
+            temperature: reader.float()?,
+            unit: if flags & 0x01 != 0 {
+                TemperatureUnit::Fahrenheit
+            } else {
+                TemperatureUnit::Celsius
+            },
+            timestamp: if flags & 0x02 != 0 {
+                Some(reader.date_time()?)
+            } else {
+                None
+            },
+            temperature_type: if flags & 0x04 != 0 {
+                Some(reader.u8()?)
+            } else {
+                None
+            },
+        })
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut value = vec![0];
+        value.extend_from_slice(&f32_to_float(self.temperature).to_le_bytes());
+        if self.unit == TemperatureUnit::Fahrenheit {
+            value[0] |= 0x01;
+        }
+        if let Some(timestamp) = &self.timestamp {
+            value[0] |= 0x02;
+            timestamp.encode_to(&mut value);
+        }
+        if let Some(temperature_type) = self.temperature_type {
+            value[0] |= 0x04;
+            value.push(temperature_type);
+        }
+        value
+    }
+}
+
+/// The unit of a [`BloodPressureMeasurement`].
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_cr")
+)]
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub enum PressureUnit {
+    #[default]
+    MillimetresOfMercury,
+    Kilopascals,
+}
+
+/// The Blood Pressure Measurement characteristic (0x2A35).
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_cr")
+)]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BloodPressureMeasurement {
+    pub systolic: f32,
+    pub diastolic: f32,
+    pub mean_arterial_pressure: f32,
+    pub unit: PressureUnit,
+    pub timestamp: Option<DateTime>,
+    /// The pulse rate, in beats per minute.
+    pub pulse_rate: Option<f32>,
+    pub user_id: Option<u8>,
+    /// The Measurement Status flags, such as body movement or irregular pulse detection.
+    pub measurement_status: Option<u16>,
+}
+
+impl StandardCharacteristic for BloodPressureMeasurement {
+    const UUID: Uuid = uuid_from_u16(0x2A35);
+
+    fn decode(value: &[u8]) -> Result<Self, DecodeError> {
+        let mut reader = Reader(value);
+        let flags = reader.u8()?;
+        Ok(Self {
+            systolic: reader.sfloat()?,
+            diastolic: reader.sfloat()?,
+            mean_arterial_pressure: reader.sfloat()?,
+            unit: if flags & 0x01 != 0 {
+                PressureUnit::Kilopascals
+            } else {
+                PressureUnit::MillimetresOfMercury
+            },
+            timestamp: if flags & 0x02 != 0 {
+                Some(reader.date_time()?)
+            } else {
+                None
+            },
+            pulse_rate: if flags & 0x04 != 0 {
+                Some(reader.sfloat()?)
+            } else {
+                None
+            },
+            user_id: if flags & 0x08 != 0 {
+                Some(reader.u8()?)
+            } else {
+                None
+            },
+            measurement_status: if flags & 0x10 != 0 {
+                Some(reader.u16()?)
+            } else {
+                None
+            },
+        })
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut value = vec![0];
+        for pressure in [self.systolic, self.diastolic, self.mean_arterial_pressure] {
+            value.extend_from_slice(&f32_to_sfloat(pressure).to_le_bytes());
+        }
+        if self.unit == PressureUnit::Kilopascals {
+            value[0] |= 0x01;
+        }
+        if let Some(timestamp) = &self.timestamp {
+            value[0] |= 0x02;
+            timestamp.encode_to(&mut value);
+        }
+        if let Some(pulse_rate) = self.pulse_rate {
+            value[0] |= 0x04;
+            value.extend_from_slice(&f32_to_sfloat(pulse_rate).to_le_bytes());
+        }
+        if let Some(user_id) = self.user_id {
+            value[0] |= 0x08;
+            value.push(user_id);
+        }
+        if let Some(measurement_status) = self.measurement_status {
+            value[0] |= 0x10;
+            value.extend_from_slice(&measurement_status.to_le_bytes());
+        }
+        value
+    }
+}
+
+/// Cumulative wheel revolution data from a [`CscMeasurement`].
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_cr")
+)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct WheelRevolutions {
+    pub cumulative_revolutions: u32,
+    /// The time of the last wheel event, in units of 1/1024 s, which wraps around.
+    pub last_event_time: u16,
+}
+
+/// Cumulative crank revolution data from a [`CscMeasurement`].
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_cr")
+)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct CrankRevolutions {
+    pub cumulative_revolutions: u16,
+    /// The time of the last crank event, in units of 1/1024 s, which wraps around.
+    pub last_event_time: u16,
+}
+
+/// The CSC Measurement characteristic (0x2A5B) of the Cycling Speed and Cadence service.
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_cr")
+)]
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct CscMeasurement {
+    pub wheel: Option<WheelRevolutions>,
+    pub crank: Option<CrankRevolutions>,
+}
+
+impl StandardCharacteristic for CscMeasurement {
+    const UUID: Uuid = uuid_from_u16(0x2A5B);
+
+    fn decode(value: &[u8]) -> Result<Self, DecodeError> {
+        let mut reader = Reader(value);
+        let flags = reader.u8()?;
+        let wheel = if flags & 0x01 != 0 {
+            Some(WheelRevolutions {
+                cumulative_revolutions: reader.u32()?,
+                last_event_time: reader.u16()?,
+            })
+        } else {
+            None
+        };
+        let crank = if flags & 0x02 != 0 {
+            Some(CrankRevolutions {
+                cumulative_revolutions: reader.u16()?,
+                last_event_time: reader.u16()?,
+            })
+        } else {
+            None
+        };
+        Ok(Self { wheel, crank })
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut value = vec![0];
+        if let Some(wheel) = &self.wheel {
+            value[0] |= 0x01;
+            value.extend_from_slice(&wheel.cumulative_revolutions.to_le_bytes());
+            value.extend_from_slice(&wheel.last_event_time.to_le_bytes());
+        }
+        if let Some(crank) = &self.crank {
+            value[0] |= 0x02;
+            value.extend_from_slice(&crank.cumulative_revolutions.to_le_bytes());
+            value.extend_from_slice(&crank.last_event_time.to_le_bytes());
+        }
+        value
+    }
+}
+
+/// The Current Time characteristic (0x2A2B) of the Current Time service.
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_cr")
+)]
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct CurrentTime {
+    pub date_time: DateTime,
+    /// The day of the week, from 1 for Monday to 7 for Sunday, or 0 if not known.
+    pub day_of_week: u8,
+    /// The fraction of the current second, in units of 1/256 s.
+    pub fractions256: u8,
+    /// Flags for why the time was last adjusted: manually, from an external reference, a time zone
+    /// change or a DST change.
+    pub adjust_reason: u8,
+}
+
+impl StandardCharacteristic for CurrentTime {
+    const UUID: Uuid = uuid_from_u16(0x2A2B);
+
+    fn decode(value: &[u8]) -> Result<Self, DecodeError> {
+        let mut reader = Reader(value);
+        let current_time = Self {
+            date_time: reader.date_time()?,
+            day_of_week: reader.u8()?,
+            fractions256: reader.u8()?,
+            adjust_reason: reader.u8()?,
+        };
+        if current_time.day_of_week > 7 {
+            return Err(DecodeError::Invalid);
+        }
+        Ok(current_time)
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut value = vec![];
+        self.date_time.encode_to(&mut value);
+        value.extend_from_slice(&[self.day_of_week, self.fractions256, self.adjust_reason]);
+        value
+    }
+}
+
+/// The organisation which assigned the vendor ID in a [`PnpId`].
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_cr")
+)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum VendorIdSource {
+    /// A Bluetooth SIG company identifier.
+    Bluetooth,
+    /// A USB Implementer's Forum vendor ID.
+    Usb,
+    Unknown(u8),
+}
+
+/// The PnP ID characteristic (0x2A50) of the Device Information service.
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_cr")
+)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct PnpId {
+    pub vendor_id_source: VendorIdSource,
+    pub vendor_id: u16,
+    pub product_id: u16,
+    pub product_version: u16,
+}
+
+impl StandardCharacteristic for PnpId {
+    const UUID: Uuid = uuid_from_u16(0x2A50);
+
+    fn decode(value: &[u8]) -> Result<Self, DecodeError> {
+        let mut reader = Reader(value);
+        Ok(Self {
+            vendor_id_source: match reader.u8()? {
+                1 => VendorIdSource::Bluetooth,
+                2 => VendorIdSource::Usb,
+                source => VendorIdSource::Unknown(source),
+            },
+            vendor_id: reader.u16()?,
+            product_id: reader.u16()?,
+            product_version: reader.u16()?,
+        })
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut value = vec![match self.vendor_id_source {
+            VendorIdSource::Bluetooth => 1,
+            VendorIdSource::Usb => 2,
+            VendorIdSource::Unknown(source) => source,
+        }];
+        value.extend_from_slice(&self.vendor_id.to_le_bytes());
+        value.extend_from_slice(&self.product_id.to_le_bytes());
+        value.extend_from_slice(&self.product_version.to_le_bytes());
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip<T: StandardCharacteristic + PartialEq + std::fmt::Debug>(
+        value: &[u8],
+        decoded: T,
+    ) {
+        assert_eq!(T::decode(value).unwrap(), decoded);
+        assert_eq!(decoded.encode(), value);
+    }
+
+    #[test]
+    fn sfloat() {
+        assert_eq!(sfloat_to_f32(0x0048), 72.0);
+        assert_eq!(sfloat_to_f32(0xF16E), 36.6);
+        assert_eq!(sfloat_to_f32(0xFFFE), -0.2);
+        assert!(sfloat_to_f32(SFLOAT_NRES).is_nan());
+        assert_eq!(sfloat_to_f32(SFLOAT_NEGATIVE_INFINITY), f32::NEG_INFINITY);
+
+        assert_eq!(f32_to_sfloat(72.0), 0x0048);
+        assert_eq!(f32_to_sfloat(36.6), 0xF16E);
+        assert_eq!(f32_to_sfloat(-0.2), 0xFFFE);
+        assert_eq!(f32_to_sfloat(0.0), 0x0000);
+        assert_eq!(f32_to_sfloat(f32::NAN), SFLOAT_NAN);
+        assert_eq!(f32_to_sfloat(1e12), SFLOAT_POSITIVE_INFINITY);
+        assert_eq!(sfloat_to_f32(f32_to_sfloat(2046.0)), 2050.0);
+    }
+
+    #[test]
+    fn float() {
+        assert_eq!(float_to_f32(0xFF00016E), 36.6);
+        assert_eq!(f32_to_float(36.6), 0xFF00016E);
+        assert_eq!(f32_to_float(-1.5), 0xFFFFFFF1);
+        assert_eq!(float_to_f32(0xFFFFFFF1), -1.5);
+        assert_eq!(float_to_f32(FLOAT_POSITIVE_INFINITY), f32::INFINITY);
+        assert!(float_to_f32(FLOAT_RESERVED).is_nan());
+    }
+
+    #[test]
+    fn heart_rate_measurement() {
+        round_trip(
+            &[0x14, 0x48, 0x00, 0x04, 0x10, 0x04],
+            HeartRateMeasurement {
+                heart_rate: 72,
+                sensor_contact: Some(false),
+                energy_expended: None,
+                rr_intervals: vec![0x0400, 0x0410],
+            },
+        );
+        round_trip(
+            &[0x09, 0x2C, 0x01, 0xE8, 0x03],
+            HeartRateMeasurement {
+                heart_rate: 300,
+                sensor_contact: None,
+                energy_expended: Some(1000),
+                rr_intervals: vec![],
+            },
+        );
+        assert_eq!(
+            HeartRateMeasurement::decode(&[0x10, 0x48, 0x00]),
+            Err(DecodeError::Truncated)
+        );
+    }
+
+    #[test]
+    fn temperature_measurement() {
+        let timestamp = DateTime {
+            year: 2023,
+            month: 5,
+            day: 17,
+            hours: 9,
+            minutes: 30,
+            seconds: 0,
+        };
+        round_trip(
+            &[
+                0x06, 0x6E, 0x01, 0x00, 0xFF, 0xE7, 0x07, 0x05, 0x11, 0x09, 0x1E, 0x00, 0x02,
+            ],
+            TemperatureMeasurement {
+                temperature: 36.6,
+                unit: TemperatureUnit::Celsius,
+                timestamp: Some(timestamp),
+                temperature_type: Some(2),
+            },
+        );
+    }
+
+    #[test]
+    fn blood_pressure_measurement() {
+        round_trip(
+            &[
+                0x1C, 0x78, 0x00, 0x50, 0x00, 0x5D, 0x00, 0x48, 0x00, 0x01, 0x04, 0x00,
+            ],
+            BloodPressureMeasurement {
+                systolic: 120.0,
+                diastolic: 80.0,
+                mean_arterial_pressure: 93.0,
+                unit: PressureUnit::MillimetresOfMercury,
+                timestamp: None,
+                pulse_rate: Some(72.0),
+                user_id: Some(1),
+                measurement_status: Some(0x0004),
+            },
+        );
+    }
+
+    #[test]
+    fn csc_measurement() {
+        round_trip(
+            &[
+                0x03, 0x10, 0x00, 0x00, 0x00, 0x00, 0x04, 0x05, 0x00, 0x00, 0x08,
+            ],
+            CscMeasurement {
+                wheel: Some(WheelRevolutions {
+                    cumulative_revolutions: 16,
+                    last_event_time: 1024,
+                }),
+                crank: Some(CrankRevolutions {
+                    cumulative_revolutions: 5,
+                    last_event_time: 2048,
+                }),
+            },
+        );
+        round_trip(&[0x00], CscMeasurement::default());
+    }
+
+    #[test]
+    fn current_time_and_pnp_id() {
+        round_trip(
+            &[0xE7, 0x07, 0x0C, 0x1F, 0x17, 0x3B, 0x3B, 0x07, 0x80, 0x01],
+            CurrentTime {
+                date_time: DateTime {
+                    year: 2023,
+                    month: 12,
+                    day: 31,
+                    hours: 23,
+                    minutes: 59,
+                    seconds: 59,
+                },
+                day_of_week: 7,
+                fractions256: 128,
+                adjust_reason: 1,
+            },
+        );
+        assert_eq!(
+            CurrentTime::decode(&[0, 0, 0, 0, 0, 0, 0, 8, 0, 0]),
+            Err(DecodeError::Invalid)
+        );
+        round_trip(
+            &[0x01, 0x0D, 0x00, 0x34, 0x12, 0x00, 0x01],
+            PnpId {
+                vendor_id_source: VendorIdSource::Bluetooth,
+                vendor_id: 0x000D,
+                product_id: 0x1234,
+                product_version: 0x0100,
+            },
+        );
+    }
+
+    #[test]
+    fn decode_notification() {
+        let notification = ValueNotification {
+            uuid: BatteryLevel::UUID,
+            service_uuid: uuid_from_u16(0x180F),
+            handle: 0x0012,
+            value: vec![101],
+        };
+        assert_eq!(
+            notification.decode::<BatteryLevel>(),
+            Some(Err(DecodeError::Invalid))
+        );
+        assert_eq!(notification.decode::<CscMeasurement>(), None);
+    }
+}
@@ -0,0 +1,150 @@
+// btleplug Source Code File
+//
+// Copyright 2023 Nonpolynomial Labs LLC. All rights reserved.
+//
+// Licensed under the BSD 3-Clause license. See LICENSE file in the project root
+// for full license information.
+
+use super::{AttErrorCode, Central, CharPropFlags, Characteristic, WriteType};
+use crate::Result;
+use async_trait::async_trait;
+use futures::stream::Stream;
+use std::{collections::BTreeSet, fmt::Debug, pin::Pin, sync::Arc};
+use uuid::Uuid;
+
+/// A GATT service published by the local adapter through a [`GattServer`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct LocalService {
+    /// The UUID for this service.
+    pub uuid: Uuid,
+    /// Whether this is a primary service.
+    pub primary: bool,
+    /// The characteristics of this service.
+    pub characteristics: Vec<LocalCharacteristic>,
+}
+
+/// A characteristic of a [`LocalService`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct LocalCharacteristic {
+    /// The UUID for this characteristic.
+    pub uuid: Uuid,
+    /// The operations remote centrals may perform on this characteristic. Requests for any other
+    /// operation are rejected without reaching the application.
+    pub properties: CharPropFlags,
+    /// The initial value of this characteristic. The current value is what the
+    /// [`GattRequestHandler`] answers reads from remote centrals with by default. It changes when
+    /// the handler accepts a write from a remote central or when the application calls
+    /// [`GattApplication::set_value`].
+    pub value: Vec<u8>,
+    /// The descriptors of this characteristic. The Client Characteristic Configuration descriptor
+    /// is managed by the platform for characteristics which support notifications, and must not be
+    /// included here.
+    pub descriptors: Vec<LocalDescriptor>,
+}
+
+/// A read-only descriptor of a [`LocalCharacteristic`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct LocalDescriptor {
+    /// The UUID for this descriptor.
+    pub uuid: Uuid,
+    /// The value returned to remote centrals reading this descriptor.
+    pub value: Vec<u8>,
+}
+
+/// A request from a remote central which changed the state of a [`GattApplication`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum GattServerEvent {
+    /// A remote central wrote to a characteristic, and the [`GattRequestHandler`] accepted it.
+    CharacteristicWritten {
+        characteristic: Characteristic,
+        /// The whole value of the characteristic after the write.
+        value: Vec<u8>,
+        write_type: WriteType,
+    },
+    /// A remote central enabled notifications or indications for a characteristic.
+    Subscribed { characteristic: Characteristic },
+    /// The last remote central subscribed to a characteristic disabled notifications or
+    /// indications, or disconnected.
+    Unsubscribed { characteristic: Characteristic },
+}
+
+/// Answers the requests of remote centrals to a [`GattApplication`].
+///
+/// Requests are only passed on if the properties of the characteristic allow them; the others are
+/// rejected without reaching the handler. Each method has a default implementation which answers
+/// from the value the application stores for the characteristic, so a handler only needs to
+/// implement those it wants to decide itself.
+#[async_trait]
+pub trait GattRequestHandler: Send + Sync + Debug {
+    /// Returns the value to answer a read of a characteristic with, given its current value, or the
+    /// error to reject the read with. The default implementation returns the current value.
+    ///
+    /// A value too long for a single response is read in several parts, each of which calls this
+    /// again and takes the part from the value returned then.
+    async fn read(
+        &self,
+        _characteristic: &Characteristic,
+        value: Vec<u8>,
+    ) -> std::result::Result<Vec<u8>, AttErrorCode> {
+        Ok(value)
+    }
+
+    /// Decides whether to accept a write to a characteristic, given the whole value the
+    /// characteristic would have after it. An accepted write becomes the current value and is
+    /// reported as a [`GattServerEvent::CharacteristicWritten`]; a rejected one is answered with
+    /// the returned error, unless it was written without response. The default implementation
+    /// accepts every write.
+    async fn write(
+        &self,
+        _characteristic: &Characteristic,
+        _value: &[u8],
+        _write_type: WriteType,
+    ) -> std::result::Result<(), AttErrorCode> {
+        Ok(())
+    }
+}
+
+/// A [`Central`] which can also act as a GATT server (the peripheral role), publishing local
+/// services for remote centrals to use.
+///
+/// On Linux, the services are exported to BlueZ over a D-Bus connection to the system bus which
+/// btleplug opens for this in addition to the one of its BlueZ session, as that one isn't
+/// accessible for exporting objects. BlueZ ties the services to the connection they were
+/// registered from, so they're also unpublished if that connection is lost.
+#[async_trait]
+pub trait GattServer: Central {
+    /// The concrete type of the [`GattApplication`] implementation.
+    type Application: GattApplication;
+
+    /// Publishes the given services on this adapter, with `handler` answering the requests of
+    /// remote centrals to them. They remain available to remote centrals until the returned
+    /// application is dropped.
+    async fn register_application(
+        &self,
+        services: Vec<LocalService>,
+        handler: Arc<dyn GattRequestHandler>,
+    ) -> Result<Self::Application>;
+}
+
+/// A set of services published by a [`GattServer`]. Dropping it unpublishes them.
+///
+/// Its characteristics are identified by their [`handle`](Characteristic::handle), so that several
+/// with the same UUID can be told apart. These handles are assigned in the order the services,
+/// characteristics and descriptors were given in, starting from 1, rather than being those the
+/// platform gives the attributes.
+#[async_trait]
+pub trait GattApplication: Send + Sync + Debug {
+    /// Returns the characteristics of all the services of the application.
+    fn characteristics(&self) -> BTreeSet<Characteristic>;
+
+    /// Retrieve a stream of the requests from remote centrals which change the state of the
+    /// application. See [`GattServerEvent`] for the full set of possible events.
+    async fn events(&self) -> Result<Pin<Box<dyn Stream<Item = GattServerEvent> + Send>>>;
+
+    /// Returns the current value of a characteristic.
+    async fn value(&self, characteristic: &Characteristic) -> Result<Vec<u8>>;
+
+    /// Changes the value of a characteristic. If any remote central is subscribed to it, it is sent
+    /// a notification or indication with the new value.
+    async fn set_value(&self, characteristic: &Characteristic, value: Vec<u8>) -> Result<()>;
+}
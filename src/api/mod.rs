@@ -21,13 +21,26 @@
 //! use btleplug::platform::{Adapter, Manager, Peripheral};
 //! ```
 
+pub mod advertisement;
+mod advertising;
+mod assigned_numbers;
+mod att;
 pub(crate) mod bdaddr;
+pub mod beacon;
 pub mod bleuuid;
-
-use crate::Result;
+pub mod gatt;
+mod gatt_server;
+mod pairing;
+pub mod proximity;
+pub mod sensor;
+mod transfer;
+
+use self::bleuuid::BleUuid;
+use crate::{common, Result};
 use async_trait::async_trait;
 use bitflags::bitflags;
 use futures::stream::Stream;
+use log::debug;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 #[cfg(feature = "serde")]
@@ -36,10 +49,19 @@ use std::{
     collections::{BTreeSet, HashMap},
     fmt::{self, Debug, Display, Formatter},
     pin::Pin,
+    time::{Duration, Instant},
 };
 use uuid::Uuid;
 
+pub use self::advertising::{Advertiser, LocalAdvertisement};
+pub use self::att::AttErrorCode;
 pub use self::bdaddr::{BDAddr, ParseBDAddrError};
+pub use self::gatt_server::{
+    GattApplication, GattRequestHandler, GattServer, GattServerEvent, LocalCharacteristic,
+    LocalDescriptor, LocalService,
+};
+pub use self::pairing::{Agent, IoCapability, Pairing};
+pub use self::transfer::{Progress, ProgressCallback, TransferOptions};
 
 use crate::platform::PeripheralId;
 
@@ -48,19 +70,15 @@ use crate::platform::PeripheralId;
     derive(Serialize, Deserialize),
     serde(crate = "serde_cr")
 )]
-#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
 pub enum AddressType {
     Random,
+    #[default]
     Public,
 }
 
-impl Default for AddressType {
-    fn default() -> Self {
-        AddressType::Public
-    }
-}
-
 impl AddressType {
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(v: &str) -> Option<AddressType> {
         match v {
             "public" => Some(AddressType::Public),
@@ -86,16 +104,32 @@ impl AddressType {
 }
 
 /// A notification sent from a peripheral due to a change in a value.
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_cr")
+)]
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct ValueNotification {
     /// UUID of the characteristic that fired the notification.
     pub uuid: Uuid,
+    /// UUID of the service containing the characteristic.
+    pub service_uuid: Uuid,
+    /// Handle of the characteristic that fired the notification, which tells it apart from other
+    /// instances of the same characteristic. See [`Characteristic::handle`].
+    pub handle: u16,
     /// The new value of the characteristic.
     pub value: Vec<u8>,
 }
 
 bitflags! {
     /// A set of properties that indicate what operations are supported by a Characteristic.
+    #[cfg_attr(
+        feature = "serde",
+        derive(Serialize, Deserialize),
+        serde(crate = "serde_cr")
+    )]
+    #[derive(Default)]
     pub struct CharPropFlags: u8 {
         const BROADCAST = 0x01;
         const READ = 0x02;
@@ -108,20 +142,25 @@ bitflags! {
     }
 }
 
-impl Default for CharPropFlags {
-    fn default() -> Self {
-        Self { bits: 0 }
-    }
-}
-
 /// A GATT service. Services are groups of characteristics, which may be standard or
 /// device-specific.
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_cr")
+)]
 #[derive(Debug, Ord, PartialOrd, Eq, PartialEq, Clone)]
 pub struct Service {
     /// The UUID for this service.
     pub uuid: Uuid,
+    /// The ATT handle of the service declaration, which identifies this instance of the service on
+    /// the device. See [`Characteristic::handle`] for platforms which don't expose handles.
+    pub handle: u16,
     /// Whether this is a primary service.
     pub primary: bool,
+    /// The handles of the services this service includes. Each of them is also listed in
+    /// [`Peripheral::services`], where it can be found by its [`handle`](Service::handle).
+    pub included_services: BTreeSet<u16>,
     /// The characteristics of this service.
     pub characteristics: BTreeSet<Characteristic>,
 }
@@ -134,12 +173,27 @@ pub struct Service {
 ///
 /// A characteristic may be interacted with in various ways depending on its properties. You may be
 /// able to write to it, read from it, set its notify or indicate status, or send a command to it.
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_cr")
+)]
 #[derive(Debug, Ord, PartialOrd, Eq, PartialEq, Clone)]
 pub struct Characteristic {
     /// The UUID for this characteristic. This uniquely identifies its behavior.
     pub uuid: Uuid,
     /// The UUID of the service this characteristic belongs to.
     pub service_uuid: Uuid,
+    /// The ATT handle of this characteristic, which identifies it among the attributes of the
+    /// device even if the device has several characteristics with the same UUID. This is what
+    /// operations on the characteristic look it up by.
+    ///
+    /// CoreBluetooth doesn't expose handles, so on macOS and iOS this is instead an identifier
+    /// assigned in the order the attributes were discovered, which is only stable until the
+    /// services are discovered again.
+    pub handle: u16,
+    /// The handle of the service this characteristic belongs to.
+    pub service_handle: u16,
     /// The set of properties for this characteristic, which indicate what functionality it
     /// supports. If you attempt an operation that is not supported by the characteristics (for
     /// example setting notify on one without the NOTIFY flag), that operation will fail.
@@ -148,17 +202,34 @@ pub struct Characteristic {
     pub descriptors: BTreeSet<Descriptor>,
 }
 
+impl Display for Service {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "uuid: {}, primary: {}",
+            DisplayUuid(&self.uuid),
+            self.primary
+        )
+    }
+}
+
 impl Display for Characteristic {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         write!(
             f,
-            "uuid: {:?}, char properties: {:?}",
-            self.uuid, self.properties
+            "uuid: {}, char properties: {:?}",
+            DisplayUuid(&self.uuid),
+            self.properties
         )
     }
 }
 
 /// Add doc
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_cr")
+)]
 #[derive(Debug, Ord, PartialOrd, Eq, PartialEq, Clone)]
 pub struct Descriptor {
     /// The UUID for this descriptor. This uniquely identifies its behavior.
@@ -167,11 +238,29 @@ pub struct Descriptor {
     pub service_uuid: Uuid,
     /// The UUID of the characteristic this descriptor belongs to.
     pub characteristic_uuid: Uuid,
+    /// The ATT handle of this descriptor. See [`Characteristic::handle`].
+    pub handle: u16,
+    /// The handle of the service this descriptor belongs to.
+    pub service_handle: u16,
+    /// The handle of the characteristic this descriptor belongs to.
+    pub characteristic_handle: u16,
 }
 
 impl Display for Descriptor {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        write!(f, "uuid: {:?}", self.uuid)
+        write!(f, "uuid: {}", DisplayUuid(&self.uuid))
+    }
+}
+
+/// Displays a UUID followed by its assigned name, if it has one.
+struct DisplayUuid<'a>(&'a Uuid);
+
+impl Display for DisplayUuid<'_> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self.0.name() {
+            Some(name) => write!(f, "{} ({})", self.0, name),
+            None => write!(f, "{}", self.0),
+        }
     }
 }
 
@@ -210,14 +299,267 @@ pub struct PeripheralProperties {
     serde(crate = "serde_cr")
 )]
 /// The filter used when scanning for BLE devices.
+///
+/// A device is reported if it passes every criterion which is set; those left empty or `None`
+/// (the default) let every device through. Criteria which the platform can apply natively are
+/// passed on to it, and all of them are applied to the events of the adapter while the scan is in
+/// progress, so the same devices are reported on every platform. [`Central::peripherals`] may still
+/// return devices which don't match.
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
 pub struct ScanFilter {
     /// If the filter contains at least one service UUID, only devices supporting at least one of
     /// the given services will be available.
     pub services: Vec<Uuid>,
+    /// Only devices received with at least this signal strength, in dBm, are reported.
+    pub rssi: Option<i16>,
+    /// Only devices whose path loss (advertised TX power minus RSSI), in dB, is at most this are
+    /// reported. Devices which don't advertise their TX power are left out.
+    pub pathloss: Option<u16>,
+    /// Only devices with a matching local name are reported.
+    pub name: Option<NameFilter>,
+    /// If not empty, only devices with manufacturer data matching at least one of these are
+    /// reported.
+    pub manufacturer_data: Vec<ManufacturerDataFilter>,
+    /// If not empty, only devices advertising service data for at least one of these services are
+    /// reported.
+    pub service_data: Vec<Uuid>,
+    /// If not empty, only devices with one of these addresses are reported.
+    pub addresses: Vec<BDAddr>,
+    /// Reports each advertisement of a device only once per scan, unless its content changes. RSSI
+    /// changes alone are not reported either.
+    pub suppress_duplicates: bool,
+    /// Whether to scan actively or passively. [`Central::start_scan`] fails with
+    /// [`Error::NotSupported`](crate::Error::NotSupported) if the platform can't scan passively.
+    pub mode: ScanMode,
+}
+
+impl ScanFilter {
+    /// Returns whether a device with the given properties passes the filter, ignoring
+    /// [`suppress_duplicates`](Self::suppress_duplicates).
+    pub fn matches(&self, properties: &PeripheralProperties) -> bool {
+        (self.services.is_empty()
+            || self
+                .services
+                .iter()
+                .any(|uuid| properties.services.contains(uuid)))
+            && self.rssi.map_or(true, |threshold| {
+                properties.rssi.is_some_and(|rssi| rssi >= threshold)
+            })
+            && self.pathloss.map_or(true, |threshold| {
+                match (properties.tx_power_level, properties.rssi) {
+                    (Some(tx_power), Some(rssi)) => {
+                        i32::from(tx_power) - i32::from(rssi) <= i32::from(threshold)
+                    }
+                    _ => false,
+                }
+            })
+            && self.name.as_ref().map_or(true, |name| {
+                properties
+                    .local_name
+                    .as_deref()
+                    .is_some_and(|local_name| name.matches(local_name))
+            })
+            && (self.manufacturer_data.is_empty()
+                || self.manufacturer_data.iter().any(|filter| {
+                    properties
+                        .manufacturer_data
+                        .get(&filter.company_id)
+                        .is_some_and(|data| filter.matches(data))
+                }))
+            && (self.service_data.is_empty()
+                || self
+                    .service_data
+                    .iter()
+                    .any(|uuid| properties.service_data.contains_key(uuid)))
+            && (self.addresses.is_empty() || self.addresses.contains(&properties.address))
+    }
+}
+
+/// How the adapter scans for devices.
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_cr")
+)]
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum ScanMode {
+    /// Scan requests are sent to advertising devices, so that their scan responses are received
+    /// too. These often carry the name of the device.
+    #[default]
+    Active,
+    /// Only advertisements are received, which saves power on both sides, in particular for
+    /// battery-powered devices. Data which a device only sends in scan responses is missed.
+    ///
+    /// On Linux this uses BlueZ advertisement monitors, which need a filter on services, name,
+    /// manufacturer data or service data to derive their patterns from.
+    Passive,
+}
+
+/// How [`ScanFilter::name`] matches the local name of a device.
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_cr")
+)]
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum NameFilter {
+    /// The name must be exactly this.
+    Exact(String),
+    /// The name must start with this.
+    Prefix(String),
+}
+
+impl NameFilter {
+    fn matches(&self, name: &str) -> bool {
+        match self {
+            NameFilter::Exact(expected) => name == expected,
+            NameFilter::Prefix(prefix) => name.starts_with(prefix.as_str()),
+        }
+    }
+}
+
+/// Matches the manufacturer data a device advertises for a company.
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_cr")
+)]
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct ManufacturerDataFilter {
+    /// The company identifier assigned by the Bluetooth SIG.
+    pub company_id: u16,
+    /// The data must start with these bytes, in the bits set in `mask`. Empty to match any data.
+    pub data: Vec<u8>,
+    /// Which bits of `data` must match. Bytes beyond the end of the mask must match entirely, so
+    /// an empty mask compares `data` as it is.
+    pub mask: Vec<u8>,
+}
+
+impl ManufacturerDataFilter {
+    /// Matches any manufacturer data for the given company.
+    pub fn company(company_id: u16) -> Self {
+        Self {
+            company_id,
+            ..Default::default()
+        }
+    }
+
+    fn matches(&self, data: &[u8]) -> bool {
+        data.len() >= self.data.len()
+            && self
+                .data
+                .iter()
+                .zip(data)
+                .enumerate()
+                .all(|(i, (expected, actual))| {
+                    let mask = self.mask.get(i).copied().unwrap_or(0xff);
+                    expected & mask == actual & mask
+                })
+    }
+}
+
+/// When a device was last heard from, as returned by [`Central::presence`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Presence {
+    /// When the last advertisement or other sign of life was received from the device.
+    pub last_seen: Instant,
+    /// How many advertisements have been received from the device. On platforms which only report
+    /// changes to what a device advertises, such as Linux, this counts those changes.
+    pub advertisement_count: u64,
+}
+
+/// When devices are reported lost, as set with [`Central::set_device_expiry`].
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_cr")
+)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct DeviceExpiry {
+    /// How long a device may go without being heard from during a scan before it is lost.
+    pub timeout: Duration,
+    /// Whether lost devices are removed from the adapter, so that [`Central::peripherals`] no
+    /// longer returns them. Ignored on Linux, where BlueZ keeps the devices and removes unpaired
+    /// ones it hasn't seen for a while on its own.
+    pub remove: bool,
+}
+
+impl DeviceExpiry {
+    /// Reports devices lost after `timeout`, without removing them.
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            remove: false,
+        }
+    }
+}
+
+/// Timeouts for the operations on a peripheral, after which they are canceled and fail with
+/// [`Error::TimedOut`](crate::Error::TimedOut). An operation without a timeout waits for as long
+/// as the platform does, which may be a long time.
+///
+/// Default timeouts are set on the [`Manager`] or [`Central`], and can be overridden for some calls
+/// with [`Peripheral::with_timeouts`].
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_cr")
+)]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Timeouts {
+    /// Timeout for [`Peripheral::connect`]. A connection attempt which times out is aborted.
+    pub connect: Option<Duration>,
+    /// Timeout for [`Peripheral::discover_services`].
+    pub discover_services: Option<Duration>,
+    /// Timeout for [`Peripheral::read`].
+    pub read: Option<Duration>,
+    /// Timeout for [`Peripheral::write`].
+    pub write: Option<Duration>,
+    /// Timeout for [`Peripheral::subscribe`] and [`Peripheral::unsubscribe`].
+    pub subscribe: Option<Duration>,
+    /// Timeout for [`Peripheral::read_descriptor`] and [`Peripheral::write_descriptor`].
+    pub descriptor: Option<Duration>,
+}
+
+impl Timeouts {
+    /// Uses the same timeout for every operation.
+    pub fn all(timeout: Duration) -> Self {
+        Self {
+            connect: Some(timeout),
+            discover_services: Some(timeout),
+            read: Some(timeout),
+            write: Some(timeout),
+            subscribe: Some(timeout),
+            descriptor: Some(timeout),
+        }
+    }
+
+    /// Returns these timeouts, with the ones set in `overrides` replacing them.
+    pub fn merge(self, overrides: Timeouts) -> Self {
+        Self {
+            connect: overrides.connect.or(self.connect),
+            discover_services: overrides.discover_services.or(self.discover_services),
+            read: overrides.read.or(self.read),
+            write: overrides.write.or(self.write),
+            subscribe: overrides.subscribe.or(self.subscribe),
+            descriptor: overrides.descriptor.or(self.descriptor),
+        }
+    }
 }
 
+/// The ATT MTU which every device supports, and which is used until a larger one has been
+/// negotiated.
+pub const DEFAULT_MTU: u16 = 23;
+
+/// The number of bytes of each ATT packet taken by the opcode and attribute handle.
+const ATT_HEADER_SIZE: u16 = 3;
+
 /// The type of write operation to use.
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_cr")
+)]
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum WriteType {
     /// A write operation where the device is expected to respond with a confirmation or error. Also
@@ -242,6 +584,10 @@ pub trait Peripheral: Send + Sync + Clone + Debug {
     /// as additional advertising reports are received.
     async fn properties(&self) -> Result<Option<PeripheralProperties>>;
 
+    /// Returns how close the device is estimated to be from the RSSI measured while scanning, or
+    /// `None` if it hasn't been measured during a scan.
+    fn proximity(&self) -> Option<proximity::Proximity>;
+
     /// The set of services we've discovered for this device. This will be empty until
     /// `discover_services` is called.
     fn services(&self) -> BTreeSet<Service>;
@@ -255,6 +601,27 @@ pub trait Peripheral: Send + Sync + Clone + Debug {
             .collect()
     }
 
+    /// Returns the timeouts used for operations on this peripheral: those of its adapter, replaced
+    /// by any set with [`with_timeouts`](Peripheral::with_timeouts).
+    fn timeouts(&self) -> Timeouts;
+
+    /// Returns a handle to the same peripheral whose operations use the timeouts set in `timeouts`
+    /// instead of the adapter's, e.g. for a single slow call:
+    ///
+    /// ```no_run
+    /// # use btleplug::api::{Characteristic, Peripheral, Timeouts};
+    /// # use std::time::Duration;
+    /// # async fn example(peripheral: impl Peripheral, characteristic: Characteristic) -> btleplug::Result<()> {
+    /// let timeouts = Timeouts {
+    ///     read: Some(Duration::from_secs(30)),
+    ///     ..Default::default()
+    /// };
+    /// let value = peripheral.with_timeouts(timeouts).read(&characteristic).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn with_timeouts(&self, timeouts: Timeouts) -> Self;
+
     /// Returns true iff we are currently connected to the device.
     async fn is_connected(&self) -> Result<bool>;
 
@@ -269,6 +636,36 @@ pub trait Peripheral: Send + Sync + Clone + Debug {
     /// Discovers all services for the device, including their characteristics.
     async fn discover_services(&self) -> Result<()>;
 
+    /// Pairs with the device, connecting to it first if needed. Any authentication the pairing
+    /// requires goes through the [`Agent`] registered with [`Pairing::register_agent`], or through
+    /// the platform's own user interface on platforms without agents.
+    async fn pair(&self) -> Result<()>;
+
+    /// Removes the pairing with the device, along with any keys stored for it. On Linux BlueZ
+    /// forgets the device entirely, so it has to be discovered again before it can be used.
+    async fn unpair(&self) -> Result<()>;
+
+    /// Returns whether the device is paired.
+    async fn is_paired(&self) -> Result<bool>;
+
+    /// Returns whether the keys exchanged while pairing with the device have been stored, so that
+    /// future connections are encrypted without pairing again.
+    async fn is_bonded(&self) -> Result<bool>;
+
+    /// Returns the ATT MTU negotiated with the device, in bytes. This is [`DEFAULT_MTU`] until the
+    /// device and the platform have agreed on a larger one, which usually happens shortly after
+    /// connecting; [`CentralEvent::MtuChanged`] is emitted when it does, on platforms which report
+    /// it.
+    async fn mtu(&self) -> Result<u16>;
+
+    /// Returns the largest value which can be written to a characteristic in a single
+    /// write without response, i.e. the MTU less the 3 bytes of the ATT header.
+    async fn max_write_without_response_size(&self) -> Result<usize> {
+        Ok(usize::from(
+            self.mtu().await?.saturating_sub(ATT_HEADER_SIZE),
+        ))
+    }
+
     /// Write some data to the characteristic. Returns an error if the write couldn't be sent or (in
     /// the case of a write-with-response) if the device returns an error.
     async fn write(
@@ -282,6 +679,40 @@ pub trait Peripheral: Send + Sync + Clone + Debug {
     /// or the response from the device.
     async fn read(&self, characteristic: &Characteristic) -> Result<Vec<u8>>;
 
+    /// Reads the value of the characteristic from `offset` on. Depending on the platform this
+    /// returns either as much as fits in one packet or the whole remainder; platforms which can't
+    /// read at an offset read the whole value and return the requested part of it.
+    async fn read_at(&self, characteristic: &Characteristic, offset: usize) -> Result<Vec<u8>>;
+
+    /// Writes `data` to the characteristic in chunks which each fit in a single packet, so that
+    /// values larger than the MTU can be sent. Each chunk is a separate write, so the device has to
+    /// put them back together itself, as bulk transfer protocols such as firmware updates do.
+    ///
+    /// Each chunk is written once the platform has accepted the one before: once CoreBluetooth is
+    /// ready to send more writes without response on macOS and iOS, once `onCharacteristicWrite`
+    /// has been called for it on Android, and once BlueZ or WinRT has completed the write on Linux
+    /// and Windows. Writes without response can also be paced with `options`, for devices which
+    /// can't keep up with that.
+    async fn write_all(
+        &self,
+        characteristic: &Characteristic,
+        data: &[u8],
+        write_type: WriteType,
+        options: TransferOptions,
+    ) -> Result<()> {
+        common::transfer::write_all(self, characteristic, data, write_type, &options).await
+    }
+
+    /// Reads the whole value of the characteristic however large it is, reading it a chunk at a
+    /// time with [`read_at`](Peripheral::read_at) until the device has no more to send.
+    async fn read_all(
+        &self,
+        characteristic: &Characteristic,
+        options: TransferOptions,
+    ) -> Result<Vec<u8>> {
+        common::transfer::read_all(self, characteristic, &options).await
+    }
+
     /// Enables either notify or indicate (depending on support) for the specified characteristic.
     async fn subscribe(&self, characteristic: &Characteristic) -> Result<()>;
 
@@ -294,6 +725,24 @@ pub trait Peripheral: Send + Sync + Clone + Debug {
     /// is made.
     async fn notifications(&self) -> Result<Pin<Box<dyn Stream<Item = ValueNotification> + Send>>>;
 
+    /// Returns a stream of the values notified for the given characteristic only, matched on its
+    /// [`handle`](Characteristic::handle).
+    ///
+    /// If `subscribe` is true the characteristic is subscribed to when the stream is first polled,
+    /// unless another such stream for it already exists, and unsubscribed from when the last of
+    /// them is dropped. The stream ends straight away if subscribing fails. This lets independent
+    /// parts of an application each own a stream for the characteristics they care about without
+    /// coordinating calls to [`subscribe`] and [`unsubscribe`]; mixing the two for the same
+    /// characteristic is best avoided.
+    ///
+    /// [`subscribe`]: Peripheral::subscribe
+    /// [`unsubscribe`]: Peripheral::unsubscribe
+    async fn notifications_for(
+        &self,
+        characteristic: &Characteristic,
+        subscribe: bool,
+    ) -> Result<Pin<Box<dyn Stream<Item = Vec<u8>> + Send>>>;
+
     /// Write some data to the descriptor. Returns an error if the write couldn't be sent or (in
     /// the case of a write-with-response) if the device returns an error.
     async fn write_descriptor(&self, descriptor: &Descriptor, data: &[u8]) -> Result<()>;
@@ -301,6 +750,59 @@ pub trait Peripheral: Send + Sync + Clone + Debug {
     /// Sends a read descriptor request to the device. Returns either an error if the request
     /// was not accepted or the response from the device.
     async fn read_descriptor(&self, descriptor: &Descriptor) -> Result<Vec<u8>>;
+
+    /// Reads the standard descriptors which describe the characteristic, i.e. its user
+    /// description, presentation formats, extended properties and valid range, and decodes them.
+    /// Only the descriptors found by [`discover_services`](Peripheral::discover_services) are
+    /// read, so fields are empty for descriptors the characteristic doesn't have. Descriptors which
+    /// can't be read, or whose values can't be decoded, are skipped, leaving their fields empty too.
+    async fn characteristic_metadata(
+        &self,
+        characteristic: &Characteristic,
+    ) -> Result<gatt::metadata::CharacteristicMetadata> {
+        let mut descriptors: Vec<_> = characteristic
+            .descriptors
+            .iter()
+            .filter(|descriptor| {
+                gatt::metadata::CharacteristicMetadata::is_metadata_descriptor(descriptor.uuid)
+            })
+            .collect();
+        // Presentation formats are listed in the order of their handles.
+        descriptors.sort_by_key(|descriptor| descriptor.handle);
+        let mut metadata = gatt::metadata::CharacteristicMetadata::default();
+        for descriptor in descriptors {
+            let value = match self.read_descriptor(descriptor).await {
+                Ok(value) => value,
+                Err(e) => {
+                    debug!("Skipping descriptor {:?}: {}", descriptor.uuid, e);
+                    continue;
+                }
+            };
+            if let Err(e) = metadata.add_descriptor(descriptor.uuid, &value) {
+                debug!("Skipping descriptor {:?}: {}", descriptor.uuid, e);
+            }
+        }
+        Ok(metadata)
+    }
+}
+
+/// Whether the Bluetooth adapter behind a [`Central`] can be used.
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_cr")
+)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CentralState {
+    /// The adapter is on and ready for use.
+    PoweredOn,
+    /// The adapter is off, usually because the user turned Bluetooth off.
+    PoweredOff,
+    /// The adapter can't be used, e.g. because it has been unplugged, is resetting, or its state
+    /// isn't known yet.
+    Unavailable,
+    /// The application isn't allowed to use Bluetooth.
+    Unauthorized,
 }
 
 #[cfg_attr(
@@ -329,6 +831,30 @@ pub enum CentralEvent {
         id: PeripheralId,
         services: Vec<Uuid>,
     },
+    /// Emitted for each beacon frame recognised in a Manufacturer Data or Service Data
+    /// advertisement, after the event for the advertisement itself. Only emitted by event streams
+    /// wrapped with [`beacon::with_beacon_events`].
+    BeaconAdvertisement {
+        id: PeripheralId,
+        beacon: beacon::Beacon,
+    },
+    /// Emitted when the ATT MTU negotiated with a connected device has changed
+    MtuChanged {
+        id: PeripheralId,
+        mtu: u16,
+    },
+    /// Emitted when the state of the adapter has changed, e.g. when Bluetooth is turned on or off
+    StateUpdate(CentralState),
+    /// Emitted during a scan when a device hasn't been heard from within the
+    /// [expiry](Central::set_device_expiry) set on the adapter. A lost device which is heard from
+    /// again is reported with `DeviceUpdated`, or `DeviceDiscovered` if it was removed.
+    DeviceLost(PeripheralId),
+    /// Emitted during a scan when the estimated [zone](proximity::ProximityZone) of a device has
+    /// changed, and with `ProximityZone::Unknown` when a device in another zone is lost
+    ProximityChanged {
+        id: PeripheralId,
+        zone: proximity::ProximityZone,
+    },
 }
 
 /// Central is the "client" of BLE. It's able to scan for and establish connections to peripherals.
@@ -354,7 +880,8 @@ pub trait Central: Send + Sync + Clone {
     async fn stop_scan(&self) -> Result<()>;
 
     /// Returns the list of [`Peripheral`]s that have been discovered so far. Note that this list
-    /// may contain peripherals that are no longer available.
+    /// may contain peripherals that are no longer available, unless lost devices are removed as
+    /// set with [`set_device_expiry`](Self::set_device_expiry).
     async fn peripherals(&self) -> Result<Vec<Self::Peripheral>>;
 
     /// Returns a particular [`Peripheral`] by its address if it has been discovered.
@@ -368,6 +895,36 @@ pub trait Central: Send + Sync + Clone {
     /// The details of this are platform-specific andyou should not attempt to parse it, but it may
     /// be useful for debug logs.
     async fn adapter_info(&self) -> Result<String>;
+
+    /// Returns whether the adapter can be used. Changes are reported with
+    /// [`CentralEvent::StateUpdate`], so applications can wait for the user to turn Bluetooth on
+    /// before scanning.
+    async fn adapter_state(&self) -> Result<CentralState>;
+
+    /// Turns the adapter on or off. Only supported on Linux and Windows; elsewhere applications
+    /// have to ask the user to do it.
+    async fn set_powered(&self, powered: bool) -> Result<()>;
+
+    /// Returns the default timeouts for operations on the peripherals of this adapter.
+    fn timeouts(&self) -> Timeouts;
+
+    /// Sets the default timeouts for operations on the peripherals of this adapter, including ones
+    /// which have already been returned. Adapters share their timeouts with the [`Manager`] they
+    /// came from, so this also applies to the other adapters of that manager.
+    fn set_timeouts(&self, timeouts: Timeouts);
+
+    /// Returns when the given device was last heard from and how often, or `None` if it hasn't
+    /// been heard from since the adapter was obtained.
+    fn presence(&self, id: &PeripheralId) -> Option<Presence>;
+
+    /// Sets after how long without hearing from a device during a scan it is reported with
+    /// [`CentralEvent::DeviceLost`], or `None`, the default, to never report devices lost. Connected
+    /// devices are never lost.
+    fn set_device_expiry(&self, expiry: Option<DeviceExpiry>);
+
+    /// Sets how the RSSI of devices is smoothed and their distance estimated. Applies to
+    /// measurements from then on.
+    fn set_proximity_config(&self, config: proximity::ProximityConfig);
 }
 
 /// The Manager is the entry point to the library, providing access to all the Bluetooth adapters on
@@ -395,4 +952,11 @@ pub trait Manager {
 
     /// Get a list of all Bluetooth adapters on the system. Each adapter implements [`Central`].
     async fn adapters(&self) -> Result<Vec<Self::Adapter>>;
+
+    /// Returns the default timeouts for operations on peripherals, shared by all adapters of this
+    /// manager. No operations time out unless this or [`Central::set_timeouts`] is used.
+    fn timeouts(&self) -> Timeouts;
+
+    /// Sets the default timeouts for operations on peripherals of all adapters of this manager.
+    fn set_timeouts(&self, timeouts: Timeouts);
 }
@@ -0,0 +1,93 @@
+// btleplug Source Code File
+//
+// Copyright 2023 Nonpolynomial Labs LLC. All rights reserved.
+//
+// Licensed under the BSD 3-Clause license. See LICENSE file in the project root
+// for full license information.
+
+use super::Central;
+use crate::{platform::PeripheralId, Result};
+use async_trait::async_trait;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "serde")]
+use serde_cr as serde;
+use std::{fmt::Debug, sync::Arc};
+use uuid::Uuid;
+
+/// The input and output the user has at hand while pairing, which decides how pairing is
+/// authenticated.
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_cr")
+)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum IoCapability {
+    /// A display, but no way to answer.
+    DisplayOnly,
+    /// A display and a way to answer yes or no.
+    DisplayYesNo,
+    /// A keyboard, but no display.
+    KeyboardOnly,
+    /// Neither; pairing is unauthenticated ("Just Works").
+    NoInputNoOutput,
+    /// A keyboard and a display.
+    KeyboardDisplay,
+}
+
+/// Takes part in pairing on behalf of the user, implemented by the application and registered with
+/// [`Pairing::register_agent`].
+///
+/// Which methods are called depends on the [`IoCapability`] the agent was registered with and on
+/// that of the device. Each has a default implementation which rejects the request, so an agent
+/// only needs to implement those its capability calls for.
+#[async_trait]
+pub trait Agent: Send + Sync + Debug {
+    /// Returns the passkey shown by the device, as entered by the user, or `None` to reject the
+    /// pairing.
+    async fn request_passkey(&self, _peripheral: &PeripheralId) -> Option<u32> {
+        None
+    }
+
+    /// Shows the user the passkey to enter on the device. This may be called again while the user
+    /// is typing.
+    async fn display_passkey(&self, _peripheral: &PeripheralId, _passkey: u32) {}
+
+    /// Returns whether the user confirmed that the device shows the same passkey (numeric
+    /// comparison).
+    async fn request_confirmation(&self, _peripheral: &PeripheralId, _passkey: u32) -> bool {
+        false
+    }
+
+    /// Returns whether the user allows a device to pair without any other authentication.
+    async fn request_authorization(&self, _peripheral: &PeripheralId) -> bool {
+        false
+    }
+
+    /// Returns whether a paired device may use the given service.
+    async fn authorize_service(&self, _peripheral: &PeripheralId, _service: Uuid) -> bool {
+        false
+    }
+
+    /// Called when a request to the agent has been canceled, e.g. because the device went away.
+    fn cancel(&self) {}
+}
+
+/// A [`Central`] which can pair with devices through an [`Agent`] provided by the application.
+#[async_trait]
+pub trait Pairing: Central {
+    /// The concrete type of the handle returned by [`register_agent`](Pairing::register_agent).
+    type AgentHandle: Send + Sync + Debug;
+
+    /// Registers an agent which handles the pairing requests of this adapter until the returned
+    /// handle is dropped.
+    ///
+    /// On Linux BlueZ has a single default agent for all adapters, which this agent becomes, and
+    /// only one agent can be registered at a time.
+    async fn register_agent(
+        &self,
+        agent: Arc<dyn Agent>,
+        capability: IoCapability,
+    ) -> Result<Self::AgentHandle>;
+}
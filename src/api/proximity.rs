@@ -0,0 +1,293 @@
+//! Smoothing of RSSI measurements and estimation of the distance to a device from them.
+//!
+//! The RSSI of a device typically jumps by several dB between advertisements, so it is smoothed
+//! with an [`RssiFilter`] before a distance is estimated with a log-distance path loss
+//! [`DistanceModel`]. Adapters do this for every device while scanning, as configured with
+//! [`Central::set_proximity_config`](super::Central::set_proximity_config): the result is
+//! returned by [`Peripheral::proximity`](super::Peripheral::proximity), and changes of
+//! [`ProximityZone`] are reported with [`CentralEvent::ProximityChanged`](super::CentralEvent).
+//!
+//! # Example
+//!
+//! ```
+//! use btleplug::api::proximity::{DistanceModel, RssiFilter, RssiSmoother};
+//!
+//! let mut smoother = RssiSmoother::new(RssiFilter::MovingAverage { window: 3 });
+//! for rssi in [-60, -70, -50] {
+//!     smoother.update(rssi);
+//! }
+//! assert_eq!(smoother.value(), Some(-60.0));
+//!
+//! let model = DistanceModel {
+//!     reference_rssi: Some(-60),
+//!     path_loss_exponent: 2.0,
+//! };
+//! assert_eq!(model.estimate(-80.0, None), Some(10.0));
+//! ```
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "serde")]
+use serde_cr as serde;
+use std::collections::VecDeque;
+use std::time::Instant;
+
+/// How much weaker, in dB, the signal of a device is 1 m away than the TX power level it
+/// advertises, in free space at 2.4 GHz.
+const PATH_LOSS_AT_ONE_METRE: f64 = 41.0;
+
+/// How successive RSSI measurements of a device are smoothed.
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_cr")
+)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RssiFilter {
+    /// The latest measurement is used as it is.
+    None,
+    /// The mean of the last `window` measurements.
+    MovingAverage { window: usize },
+    /// A one-dimensional Kalman filter, which assumes the RSSI changes slowly, by the square root
+    /// of `process_noise` dB per measurement, and that measurements are off by the square root of
+    /// `measurement_noise` dB. The larger the ratio of the latter to the former, the smoother.
+    Kalman {
+        process_noise: f64,
+        measurement_noise: f64,
+    },
+}
+
+impl Default for RssiFilter {
+    fn default() -> Self {
+        RssiFilter::Kalman {
+            process_noise: 0.5,
+            measurement_noise: 16.0,
+        }
+    }
+}
+
+/// Applies an [`RssiFilter`] to successive RSSI measurements of a device.
+#[derive(Clone, Debug)]
+pub struct RssiSmoother {
+    filter: RssiFilter,
+    window: VecDeque<i16>,
+    /// The estimate of the Kalman filter and its variance.
+    estimate: Option<(f64, f64)>,
+}
+
+impl RssiSmoother {
+    pub fn new(filter: RssiFilter) -> Self {
+        Self {
+            filter,
+            window: VecDeque::new(),
+            estimate: None,
+        }
+    }
+
+    pub fn filter(&self) -> RssiFilter {
+        self.filter
+    }
+
+    /// Adds a measurement, and returns the smoothed RSSI.
+    pub fn update(&mut self, rssi: i16) -> f64 {
+        let window = match self.filter {
+            RssiFilter::None => 1,
+            RssiFilter::MovingAverage { window } => window.max(1),
+            RssiFilter::Kalman {
+                process_noise,
+                measurement_noise,
+            } => {
+                let measurement = f64::from(rssi);
+                let (estimate, variance) = match self.estimate {
+                    None => (measurement, measurement_noise),
+                    Some((estimate, variance)) => {
+                        let variance = variance + process_noise;
+                        let gain = variance / (variance + measurement_noise);
+                        (
+                            estimate + gain * (measurement - estimate),
+                            (1.0 - gain) * variance,
+                        )
+                    }
+                };
+                self.estimate = Some((estimate, variance));
+                return estimate;
+            }
+        };
+        if self.window.len() == window {
+            self.window.pop_front();
+        }
+        self.window.push_back(rssi);
+        self.value().unwrap()
+    }
+
+    /// Returns the smoothed RSSI, or `None` before the first measurement.
+    pub fn value(&self) -> Option<f64> {
+        match self.filter {
+            RssiFilter::Kalman { .. } => self.estimate.map(|(estimate, _)| estimate),
+            _ if self.window.is_empty() => None,
+            _ => {
+                let sum: f64 = self.window.iter().copied().map(f64::from).sum();
+                Some(sum / self.window.len() as f64)
+            }
+        }
+    }
+}
+
+/// Estimates the distance to a device from its RSSI with the log-distance path loss model.
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_cr")
+)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DistanceModel {
+    /// The RSSI of the device measured 1 m away from it. If `None`, it is derived from the TX
+    /// power level the device advertises, which is less accurate.
+    pub reference_rssi: Option<i16>,
+    /// How quickly the signal weakens with distance: 2 in free space, and usually between 2.5 and
+    /// 4 indoors.
+    pub path_loss_exponent: f64,
+}
+
+impl Default for DistanceModel {
+    fn default() -> Self {
+        Self {
+            reference_rssi: None,
+            path_loss_exponent: 2.0,
+        }
+    }
+}
+
+impl DistanceModel {
+    /// Returns the estimated distance in metres to a device received with the given RSSI, or
+    /// `None` if there is no reference RSSI and the device doesn't advertise its TX power level.
+    pub fn estimate(&self, rssi: f64, tx_power_level: Option<i16>) -> Option<f64> {
+        let reference = match self.reference_rssi {
+            Some(reference) => f64::from(reference),
+            None => f64::from(tx_power_level?) - PATH_LOSS_AT_ONE_METRE,
+        };
+        Some(10f64.powf((reference - rssi) / (10.0 * self.path_loss_exponent)))
+    }
+}
+
+/// A rough indication of how close a device is.
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_cr")
+)]
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum ProximityZone {
+    /// Closer than [`ProximityConfig::immediate_distance`].
+    Immediate,
+    /// Closer than [`ProximityConfig::near_distance`].
+    Near,
+    /// Further away.
+    Far,
+    /// The distance can't be estimated, or the device hasn't been heard from.
+    #[default]
+    Unknown,
+}
+
+/// How an adapter estimates the proximity of devices.
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_cr")
+)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ProximityConfig {
+    pub filter: RssiFilter,
+    pub distance: DistanceModel,
+    /// The distance in metres under which a device is in the [`ProximityZone::Immediate`] zone.
+    pub immediate_distance: f64,
+    /// The distance in metres under which a device is in the [`ProximityZone::Near`] zone.
+    pub near_distance: f64,
+    /// How many of the latest RSSI measurements of each device are kept.
+    pub history_length: usize,
+}
+
+impl Default for ProximityConfig {
+    fn default() -> Self {
+        Self {
+            filter: RssiFilter::default(),
+            distance: DistanceModel::default(),
+            immediate_distance: 0.5,
+            near_distance: 3.0,
+            history_length: 20,
+        }
+    }
+}
+
+impl ProximityConfig {
+    /// Returns the zone of a device at the given distance.
+    pub fn zone(&self, distance: Option<f64>) -> ProximityZone {
+        match distance {
+            None => ProximityZone::Unknown,
+            Some(distance) if distance < self.immediate_distance => ProximityZone::Immediate,
+            Some(distance) if distance < self.near_distance => ProximityZone::Near,
+            Some(_) => ProximityZone::Far,
+        }
+    }
+}
+
+/// An RSSI measurement of a device.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct RssiSample {
+    pub time: Instant,
+    pub rssi: i16,
+}
+
+/// How close a device is estimated to be, as returned by
+/// [`Peripheral::proximity`](super::Peripheral::proximity).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Proximity {
+    /// The smoothed RSSI of the device.
+    pub rssi: f64,
+    /// The estimated distance to the device in metres, if it can be estimated.
+    pub distance: Option<f64>,
+    pub zone: ProximityZone,
+    /// The latest RSSI measurements of the device, oldest first.
+    pub history: Vec<RssiSample>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn moving_average_forgets_old_measurements() {
+        let mut smoother = RssiSmoother::new(RssiFilter::MovingAverage { window: 2 });
+        assert_eq!(smoother.value(), None);
+        assert_eq!(smoother.update(-50), -50.0);
+        assert_eq!(smoother.update(-60), -55.0);
+        assert_eq!(smoother.update(-80), -70.0);
+    }
+
+    #[test]
+    fn kalman_filter_dampens_outliers() {
+        let mut smoother = RssiSmoother::new(RssiFilter::default());
+        for _ in 0..10 {
+            smoother.update(-60);
+        }
+        let smoothed = smoother.update(-80);
+        assert!(smoothed < -60.0 && smoothed > -65.0, "{}", smoothed);
+    }
+
+    #[test]
+    fn distance_from_advertised_tx_power() {
+        let model = DistanceModel::default();
+        assert_eq!(model.estimate(-60.0, None), None);
+        let distance = model.estimate(-61.0, Some(0)).unwrap();
+        assert!((distance - 10.0).abs() < 1e-9, "{}", distance);
+    }
+
+    #[test]
+    fn zones() {
+        let config = ProximityConfig::default();
+        assert_eq!(config.zone(None), ProximityZone::Unknown);
+        assert_eq!(config.zone(Some(0.2)), ProximityZone::Immediate);
+        assert_eq!(config.zone(Some(1.0)), ProximityZone::Near);
+        assert_eq!(config.zone(Some(10.0)), ProximityZone::Far);
+    }
+}
@@ -0,0 +1,644 @@
+//! Decoders for sensor readings broadcast in advertisement data.
+//!
+//! Two formats are supported:
+//!
+//! - [BTHome v2](https://bthome.io/format/), sent as service data for [`BTHOME_SERVICE_UUID`].
+//!   Encrypted BTHome payloads can be decrypted with the device's bind key when the
+//!   `bthome-encryption` feature is enabled.
+//! - Ruuvi [data format 5](https://docs.ruuvi.com/communication/bluetooth-advertisements/data-format-5-rawv2)
+//!   (RAWv2), sent as manufacturer-specific data for [`RUUVI_COMPANY_ID`].
+//!
+//! # Example
+//!
+//! ```
+//! use btleplug::api::sensor::{Measurement, SensorData, BTHOME_SERVICE_UUID};
+//!
+//! let data = [0x40, 0x00, 0x05, 0x02, 0xCA, 0x09, 0x03, 0xBF, 0x13];
+//! let reading = SensorData::from_service_data(&BTHOME_SERVICE_UUID, &data)
+//!     .unwrap()
+//!     .unwrap();
+//! assert_eq!(
+//!     reading.measurements,
+//!     vec![
+//!         Measurement::PacketId(5),
+//!         Measurement::Temperature(25.06),
+//!         Measurement::Humidity(50.55),
+//!     ]
+//! );
+//! ```
+
+use super::bleuuid::uuid_from_u16;
+use super::{BDAddr, CentralEvent};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "serde")]
+use serde_cr as serde;
+use uuid::Uuid;
+
+/// The service UUID which BTHome payloads are sent as service data for.
+pub const BTHOME_SERVICE_UUID: Uuid = uuid_from_u16(0xFCD2);
+
+/// The company identifier of Ruuvi Innovations, which RuuviTag payloads are sent under.
+pub const RUUVI_COMPANY_ID: u16 = 0x0499;
+
+/// Standard gravity, for converting accelerations reported in g.
+const STANDARD_GRAVITY: f32 = 9.80665;
+
+/// An error decoding sensor data.
+#[derive(Debug, thiserror::Error, Clone, PartialEq, Eq)]
+pub enum SensorDataError {
+    #[error("Sensor data is truncated")]
+    Truncated,
+    #[error("Unsupported sensor data format version {0}")]
+    UnsupportedVersion(u8),
+    #[error("Unsupported BTHome object ID {0:#04x}")]
+    UnsupportedObject(u8),
+    #[error("Sensor data is encrypted")]
+    Encrypted,
+    #[error("Failed to decrypt sensor data")]
+    DecryptionFailed,
+}
+
+/// The format some sensor data was decoded from.
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_cr")
+)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum SensorFormat {
+    BtHome {
+        /// Whether the payload was encrypted.
+        encrypted: bool,
+        /// Whether the device sends data when something happens rather than at regular intervals.
+        trigger_based: bool,
+    },
+    RuuviRawV2,
+}
+
+/// A button event reported by a BTHome device.
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_cr")
+)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ButtonEvent {
+    None,
+    Press,
+    DoublePress,
+    TriplePress,
+    LongPress,
+    LongDoublePress,
+    LongTriplePress,
+    HoldPress,
+    Unknown(u8),
+}
+
+impl From<u8> for ButtonEvent {
+    fn from(value: u8) -> Self {
+        match value {
+            0x00 => ButtonEvent::None,
+            0x01 => ButtonEvent::Press,
+            0x02 => ButtonEvent::DoublePress,
+            0x03 => ButtonEvent::TriplePress,
+            0x04 => ButtonEvent::LongPress,
+            0x05 => ButtonEvent::LongDoublePress,
+            0x06 => ButtonEvent::LongTriplePress,
+            0x80 => ButtonEvent::HoldPress,
+            _ => ButtonEvent::Unknown(value),
+        }
+    }
+}
+
+/// A single measurement from a sensor.
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_cr")
+)]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Measurement {
+    /// A packet or measurement sequence number, which can be used to ignore duplicates.
+    PacketId(u16),
+    /// Battery level, in %.
+    Battery(u8),
+    /// Temperature, in °C.
+    Temperature(f32),
+    /// Relative humidity, in %.
+    Humidity(f32),
+    /// Pressure, in hPa.
+    Pressure(f32),
+    /// Illuminance, in lux.
+    Illuminance(f32),
+    /// Voltage, in V. This is usually the battery voltage.
+    Voltage(f32),
+    /// Magnitude of acceleration, in m/s².
+    Acceleration(f32),
+    /// Acceleration along each axis, in m/s².
+    AccelerationXyz {
+        x: f32,
+        y: f32,
+        z: f32,
+    },
+    /// Transmission power, in dBm.
+    TxPower(i8),
+    /// The number of movements detected by the sensor, which wraps around.
+    MovementCount(u8),
+    Button(ButtonEvent),
+    /// A dimmer rotation: event 1 is to the left and 2 is to the right, by the given number of steps.
+    Dimmer {
+        event: u8,
+        steps: u8,
+    },
+    /// A BTHome binary sensor, identified by its object ID.
+    Binary {
+        object_id: u8,
+        value: bool,
+    },
+    /// A BTHome numeric sensor without a more specific variant, identified by its object ID. The
+    /// value is scaled to the unit defined by the BTHome specification.
+    Other {
+        object_id: u8,
+        value: f64,
+    },
+    Text(String),
+    Raw(Vec<u8>),
+}
+
+/// A set of measurements decoded from a single advertisement.
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_cr")
+)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct SensorData {
+    pub format: SensorFormat,
+    pub measurements: Vec<Measurement>,
+}
+
+impl SensorData {
+    /// Decodes sensor data from the manufacturer-specific data for the given company, if it is in a
+    /// supported format.
+    pub fn from_manufacturer_data(
+        company_id: u16,
+        data: &[u8],
+    ) -> Option<Result<Self, SensorDataError>> {
+        (company_id == RUUVI_COMPANY_ID).then(|| decode_ruuvi(data))
+    }
+
+    /// Decodes sensor data from the service data for the given service, if it is in a supported
+    /// format. Encrypted BTHome payloads give [`SensorDataError::Encrypted`].
+    pub fn from_service_data(uuid: &Uuid, data: &[u8]) -> Option<Result<Self, SensorDataError>> {
+        (*uuid == BTHOME_SERVICE_UUID).then(|| decode_bthome(data, None))
+    }
+
+    /// Decodes sensor data from the service data for the given service, if it is in a supported
+    /// format, decrypting encrypted BTHome payloads with the bind key of the device with the given
+    /// address.
+    #[cfg(feature = "bthome-encryption")]
+    pub fn from_encrypted_service_data(
+        uuid: &Uuid,
+        data: &[u8],
+        address: BDAddr,
+        key: &[u8; 16],
+    ) -> Option<Result<Self, SensorDataError>> {
+        (*uuid == BTHOME_SERVICE_UUID).then(|| decode_bthome(data, Some((address, key))))
+    }
+
+    /// Decodes all the sensor data in a Manufacturer Data or Service Data advertisement event.
+    pub fn from_event(event: &CentralEvent) -> Vec<Result<Self, SensorDataError>> {
+        match event {
+            CentralEvent::ManufacturerDataAdvertisement {
+                manufacturer_data, ..
+            } => manufacturer_data
+                .iter()
+                .filter_map(|(company_id, data)| Self::from_manufacturer_data(*company_id, data))
+                .collect(),
+            CentralEvent::ServiceDataAdvertisement { service_data, .. } => service_data
+                .iter()
+                .filter_map(|(uuid, data)| Self::from_service_data(uuid, data))
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    /// Returns the first temperature measurement, in °C.
+    pub fn temperature(&self) -> Option<f32> {
+        self.measurements.iter().find_map(|m| match m {
+            Measurement::Temperature(value) => Some(*value),
+            _ => None,
+        })
+    }
+
+    /// Returns the first humidity measurement, in %.
+    pub fn humidity(&self) -> Option<f32> {
+        self.measurements.iter().find_map(|m| match m {
+            Measurement::Humidity(value) => Some(*value),
+            _ => None,
+        })
+    }
+
+    /// Returns the first pressure measurement, in hPa.
+    pub fn pressure(&self) -> Option<f32> {
+        self.measurements.iter().find_map(|m| match m {
+            Measurement::Pressure(value) => Some(*value),
+            _ => None,
+        })
+    }
+
+    /// Returns the first battery level measurement, in %.
+    pub fn battery(&self) -> Option<u8> {
+        self.measurements.iter().find_map(|m| match m {
+            Measurement::Battery(value) => Some(*value),
+            _ => None,
+        })
+    }
+}
+
+/// How the value of a BTHome object is encoded.
+enum ObjectFormat {
+    /// A little-endian integer of the given size and signedness, multiplied by a factor.
+    Numeric {
+        size: usize,
+        signed: bool,
+        factor: f64,
+    },
+    /// A single byte which is 0 or 1.
+    Binary,
+    Button,
+    Dimmer,
+    /// A length byte followed by that many bytes.
+    Variable,
+}
+
+fn object_format(object_id: u8) -> Option<ObjectFormat> {
+    let numeric = |size, signed, factor| ObjectFormat::Numeric {
+        size,
+        signed,
+        factor,
+    };
+    Some(match object_id {
+        0x00 | 0x01 | 0x09 | 0x2E | 0x2F | 0x60 => numeric(1, false, 1.0),
+        0x02 | 0x08 => numeric(2, true, 0.01),
+        0x03 | 0x06 | 0x07 | 0x14 | 0x44 | 0x5E => numeric(2, false, 0.01),
+        0x04 | 0x05 | 0x0B => numeric(3, false, 0.01),
+        0x0A | 0x42 | 0x4B => numeric(3, false, 0.001),
+        0x0C | 0x43 | 0x49 | 0x51 | 0x52 => numeric(2, false, 0.001),
+        0x0D | 0x0E | 0x12 | 0x13 | 0x3D | 0x40 | 0x48 | 0x56 | 0x61 | 0xF0 => {
+            numeric(2, false, 1.0)
+        }
+        0x0F..=0x11 | 0x15..=0x2D => ObjectFormat::Binary,
+        0x3A => ObjectFormat::Button,
+        0x3C => ObjectFormat::Dimmer,
+        0x3E | 0x50 | 0xF1 => numeric(4, false, 1.0),
+        0x3F | 0x45 => numeric(2, true, 0.1),
+        0x41 | 0x47 | 0x4A | 0x5F => numeric(2, false, 0.1),
+        0x46 => numeric(1, false, 0.1),
+        0x4C | 0x4D | 0x4E | 0x4F | 0x55 => numeric(4, false, 0.001),
+        0x53 | 0x54 => ObjectFormat::Variable,
+        0x57 | 0x59 => numeric(1, true, 1.0),
+        0x58 => numeric(1, true, 0.35),
+        0x5A => numeric(2, true, 1.0),
+        0x5B => numeric(4, true, 1.0),
+        0x5C => numeric(4, true, 0.01),
+        0x5D => numeric(2, true, 0.001),
+        0xF2 => numeric(3, false, 1.0),
+        _ => return None,
+    })
+}
+
+fn numeric_measurement(object_id: u8, raw: i64, value: f64) -> Measurement {
+    match object_id {
+        0x00 => Measurement::PacketId(raw as u16),
+        0x01 => Measurement::Battery(raw as u8),
+        0x02 | 0x45 | 0x57 | 0x58 => Measurement::Temperature(value as f32),
+        0x03 | 0x2E => Measurement::Humidity(value as f32),
+        0x04 => Measurement::Pressure(value as f32),
+        0x05 => Measurement::Illuminance(value as f32),
+        0x0C | 0x4A => Measurement::Voltage(value as f32),
+        0x51 => Measurement::Acceleration(value as f32),
+        _ => Measurement::Other { object_id, value },
+    }
+}
+
+fn decode_bthome(
+    data: &[u8],
+    key: Option<(BDAddr, &[u8; 16])>,
+) -> Result<SensorData, SensorDataError> {
+    let (&device_info, payload) = data.split_first().ok_or(SensorDataError::Truncated)?;
+    let version = device_info >> 5;
+    if version != 2 {
+        return Err(SensorDataError::UnsupportedVersion(version));
+    }
+    let encrypted = device_info & 0x01 != 0;
+    let format = SensorFormat::BtHome {
+        encrypted,
+        trigger_based: device_info & 0x04 != 0,
+    };
+
+    #[cfg(feature = "bthome-encryption")]
+    let decrypted;
+    let mut payload = payload;
+    if encrypted {
+        #[cfg(feature = "bthome-encryption")]
+        {
+            let (address, key) = key.ok_or(SensorDataError::Encrypted)?;
+            decrypted = decrypt_bthome(device_info, payload, address, key)?;
+            payload = &decrypted;
+        }
+        #[cfg(not(feature = "bthome-encryption"))]
+        {
+            let _ = key;
+            return Err(SensorDataError::Encrypted);
+        }
+    }
+
+    let mut measurements = vec![];
+    while let Some((&object_id, rest)) = payload.split_first() {
+        let format =
+            object_format(object_id).ok_or(SensorDataError::UnsupportedObject(object_id))?;
+        let size = match format {
+            ObjectFormat::Numeric { size, .. } => size,
+            ObjectFormat::Binary | ObjectFormat::Button => 1,
+            ObjectFormat::Dimmer => 2,
+            ObjectFormat::Variable => 1 + *rest.first().ok_or(SensorDataError::Truncated)? as usize,
+        };
+        if rest.len() < size {
+            return Err(SensorDataError::Truncated);
+        }
+        let (value, rest) = rest.split_at(size);
+        measurements.push(match format {
+            ObjectFormat::Numeric { signed, factor, .. } => {
+                let mut bytes = [0; 8];
+                bytes[..size].copy_from_slice(value);
+                let mut raw = i64::from_le_bytes(bytes);
+                if signed {
+                    // Sign-extend from the encoded size.
+                    let shift = 64 - 8 * size;
+                    raw = (raw << shift) >> shift;
+                }
+                numeric_measurement(object_id, raw, raw as f64 * factor)
+            }
+            ObjectFormat::Binary => Measurement::Binary {
+                object_id,
+                value: value[0] != 0,
+            },
+            ObjectFormat::Button => Measurement::Button(value[0].into()),
+            ObjectFormat::Dimmer => Measurement::Dimmer {
+                event: value[0],
+                steps: value[1],
+            },
+            ObjectFormat::Variable if object_id == 0x53 => {
+                Measurement::Text(String::from_utf8_lossy(&value[1..]).into_owned())
+            }
+            ObjectFormat::Variable => Measurement::Raw(value[1..].to_vec()),
+        });
+        payload = rest;
+    }
+    Ok(SensorData {
+        format,
+        measurements,
+    })
+}
+
+/// Decrypts an encrypted BTHome payload, which is the ciphertext followed by a 4-byte counter and a
+/// 4-byte message integrity check.
+#[cfg(feature = "bthome-encryption")]
+fn decrypt_bthome(
+    device_info: u8,
+    payload: &[u8],
+    address: BDAddr,
+    key: &[u8; 16],
+) -> Result<Vec<u8>, SensorDataError> {
+    use aes::Aes128;
+    use ccm::aead::{generic_array::GenericArray, AeadInPlace, KeyInit};
+    use ccm::consts::{U13, U4};
+
+    if payload.len() < 8 {
+        return Err(SensorDataError::Truncated);
+    }
+    let (ciphertext, rest) = payload.split_at(payload.len() - 8);
+    let (counter, mic) = rest.split_at(4);
+    let mut nonce = Vec::with_capacity(13);
+    nonce.extend_from_slice(address.as_ref());
+    nonce.extend_from_slice(&0xFCD2u16.to_le_bytes());
+    nonce.push(device_info);
+    nonce.extend_from_slice(counter);
+
+    let mut plaintext = ciphertext.to_vec();
+    ccm::Ccm::<Aes128, U4, U13>::new(GenericArray::from_slice(key))
+        .decrypt_in_place_detached(
+            GenericArray::from_slice(&nonce),
+            &[],
+            &mut plaintext,
+            GenericArray::from_slice(mic),
+        )
+        .map_err(|_| SensorDataError::DecryptionFailed)?;
+    Ok(plaintext)
+}
+
+fn decode_ruuvi(data: &[u8]) -> Result<SensorData, SensorDataError> {
+    let format = *data.first().ok_or(SensorDataError::Truncated)?;
+    if format != 5 {
+        return Err(SensorDataError::UnsupportedVersion(format));
+    }
+    if data.len() < 24 {
+        return Err(SensorDataError::Truncated);
+    }
+    let u16_at = |i: usize| u16::from_be_bytes([data[i], data[i + 1]]);
+    let i16_at = |i: usize| i16::from_be_bytes([data[i], data[i + 1]]);
+
+    // Each field has a reserved value meaning that it is not available.
+    let mut measurements = vec![];
+    if i16_at(1) != i16::MIN {
+        measurements.push(Measurement::Temperature(f32::from(i16_at(1)) / 200.0));
+    }
+    if u16_at(3) != u16::MAX {
+        measurements.push(Measurement::Humidity(f32::from(u16_at(3)) / 400.0));
+    }
+    if u16_at(5) != u16::MAX {
+        measurements.push(Measurement::Pressure(
+            (f32::from(u16_at(5)) + 50000.0) / 100.0,
+        ));
+    }
+    if [7, 9, 11].iter().all(|&i| i16_at(i) != i16::MIN) {
+        let acceleration = |i| f32::from(i16_at(i)) / 1000.0 * STANDARD_GRAVITY;
+        measurements.push(Measurement::AccelerationXyz {
+            x: acceleration(7),
+            y: acceleration(9),
+            z: acceleration(11),
+        });
+    }
+    let power = u16_at(13);
+    if power >> 5 != 0x7FF {
+        measurements.push(Measurement::Voltage(
+            (f32::from(power >> 5) + 1600.0) / 1000.0,
+        ));
+    }
+    if power & 0x1F != 0x1F {
+        measurements.push(Measurement::TxPower(-40 + 2 * (power & 0x1F) as i8));
+    }
+    if data[15] != u8::MAX {
+        measurements.push(Measurement::MovementCount(data[15]));
+    }
+    if u16_at(16) != u16::MAX {
+        measurements.push(Measurement::PacketId(u16_at(16)));
+    }
+    Ok(SensorData {
+        format: SensorFormat::RuuviRawV2,
+        measurements,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bthome(data: &[u8]) -> Result<SensorData, SensorDataError> {
+        SensorData::from_service_data(&BTHOME_SERVICE_UUID, data).unwrap()
+    }
+
+    #[test]
+    fn bthome_objects() {
+        let data = bthome(&[
+            0x44, // Version 2, trigger based
+            0x01, 0x61, // Battery 97%
+            0x45, 0x11, 0xFF, // Temperature -23.9°C
+            0x04, 0x13, 0x8A, 0x01, // Pressure 1008.83 hPa
+            0x3A, 0x02, // Button double press
+            0x21, 0x01, // Motion detected
+            0x53, 0x02, b'h', b'i', // Text
+            0x5A, 0x0C, 0xFF, // Count -244
+        ])
+        .unwrap();
+        assert_eq!(
+            data.format,
+            SensorFormat::BtHome {
+                encrypted: false,
+                trigger_based: true,
+            }
+        );
+        assert_eq!(
+            data.measurements,
+            vec![
+                Measurement::Battery(97),
+                Measurement::Temperature(-23.9),
+                Measurement::Pressure(1008.83),
+                Measurement::Button(ButtonEvent::DoublePress),
+                Measurement::Binary {
+                    object_id: 0x21,
+                    value: true,
+                },
+                Measurement::Text("hi".to_string()),
+                Measurement::Other {
+                    object_id: 0x5A,
+                    value: -244.0,
+                },
+            ]
+        );
+        assert_eq!(data.battery(), Some(97));
+        assert_eq!(data.temperature(), Some(-23.9));
+    }
+
+    #[test]
+    fn bthome_errors() {
+        assert_eq!(bthome(&[]), Err(SensorDataError::Truncated));
+        assert_eq!(
+            bthome(&[0x20, 0x01, 0x61]),
+            Err(SensorDataError::UnsupportedVersion(1))
+        );
+        assert_eq!(bthome(&[0x40, 0x02, 0xCA]), Err(SensorDataError::Truncated));
+        assert_eq!(
+            bthome(&[0x40, 0x01, 0x61, 0xEE, 0x00]),
+            Err(SensorDataError::UnsupportedObject(0xEE))
+        );
+        assert_eq!(
+            bthome(&[0x41, 0xA4, 0x72, 0x66, 0xC9, 0x5F, 0x73, 0, 0x11, 0x22, 0x33, 0, 0, 0, 0]),
+            Err(SensorDataError::Encrypted)
+        );
+        assert_eq!(
+            SensorData::from_service_data(&uuid_from_u16(0xFEAA), &[0x40]),
+            None
+        );
+    }
+
+    #[cfg(feature = "bthome-encryption")]
+    #[test]
+    fn bthome_encrypted() {
+        // Example from the BTHome specification.
+        let key = [
+            0x23, 0x1D, 0x39, 0xC1, 0xD7, 0xCC, 0x1A, 0xB1, 0xAE, 0xE2, 0x24, 0xCD, 0x09, 0x6D,
+            0xB9, 0x32,
+        ];
+        let address: BDAddr = [0x54, 0x48, 0xE6, 0x8F, 0x80, 0xA5].into();
+        let data = [
+            0x41, 0xA4, 0x72, 0x66, 0xC9, 0x5F, 0x73, 0x00, 0x11, 0x22, 0x33, 0x78, 0x23, 0x72,
+            0x14,
+        ];
+        let reading =
+            SensorData::from_encrypted_service_data(&BTHOME_SERVICE_UUID, &data, address, &key)
+                .unwrap()
+                .unwrap();
+        assert_eq!(
+            reading.measurements,
+            vec![
+                Measurement::Temperature(25.06),
+                Measurement::Humidity(50.55),
+            ]
+        );
+
+        let mut tampered = data;
+        tampered[1] ^= 0x01;
+        assert_eq!(
+            SensorData::from_encrypted_service_data(&BTHOME_SERVICE_UUID, &tampered, address, &key),
+            Some(Err(SensorDataError::DecryptionFailed))
+        );
+    }
+
+    #[test]
+    fn ruuvi_raw_v2() {
+        // Valid data example from the Ruuvi documentation.
+        let data = [
+            0x05, 0x12, 0xFC, 0x53, 0x94, 0xC3, 0x7C, 0x00, 0x04, 0xFF, 0xFC, 0x04, 0x0C, 0xAC,
+            0x36, 0x42, 0x00, 0xCD, 0xCB, 0xB8, 0x33, 0x4C, 0x88, 0x4F,
+        ];
+        let reading = SensorData::from_manufacturer_data(RUUVI_COMPANY_ID, &data)
+            .unwrap()
+            .unwrap();
+        assert_eq!(reading.format, SensorFormat::RuuviRawV2);
+        assert_eq!(reading.temperature(), Some(24.3));
+        assert_eq!(reading.humidity(), Some(53.49));
+        assert_eq!(reading.pressure(), Some(1000.44));
+        assert!(matches!(
+            reading.measurements[3],
+            Measurement::AccelerationXyz { x, .. } if (x - 0.004 * STANDARD_GRAVITY).abs() < 1e-6
+        ));
+        assert_eq!(
+            reading.measurements[4..],
+            [
+                Measurement::Voltage(2.977),
+                Measurement::TxPower(4),
+                Measurement::MovementCount(66),
+                Measurement::PacketId(205),
+            ]
+        );
+    }
+
+    #[test]
+    fn ruuvi_invalid_values() {
+        let mut data = vec![0x05, 0x80, 0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0x80, 0x00];
+        data.extend([0x80, 0x00, 0x80, 0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]);
+        data.extend([0; 6]);
+        let reading = SensorData::from_manufacturer_data(RUUVI_COMPANY_ID, &data)
+            .unwrap()
+            .unwrap();
+        assert_eq!(reading.measurements, vec![]);
+        assert_eq!(
+            SensorData::from_manufacturer_data(RUUVI_COMPANY_ID, &[0x03, 0x00]),
+            Some(Err(SensorDataError::UnsupportedVersion(3)))
+        );
+        assert_eq!(SensorData::from_manufacturer_data(0x004C, &data), None);
+    }
+}
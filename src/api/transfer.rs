@@ -0,0 +1,71 @@
+// btleplug Source Code File
+//
+// Copyright 2023 Nonpolynomial Labs LLC. All rights reserved.
+//
+// Licensed under the BSD 3-Clause license. See LICENSE file in the project root
+// for full license information.
+
+use std::{
+    fmt::{self, Debug, Formatter},
+    sync::Arc,
+    time::Duration,
+};
+
+/// How far a transfer started with [`Peripheral::write_all`] or [`Peripheral::read_all`] has got.
+///
+/// [`Peripheral::write_all`]: super::Peripheral::write_all
+/// [`Peripheral::read_all`]: super::Peripheral::read_all
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Progress {
+    /// The number of bytes transferred so far.
+    pub transferred: usize,
+    /// The total number of bytes to transfer, if known. This is never known when reading.
+    pub total: Option<usize>,
+}
+
+/// A function called with the [`Progress`] of a transfer after each chunk.
+pub type ProgressCallback = Arc<dyn Fn(Progress) + Send + Sync>;
+
+/// Options for [`Peripheral::write_all`] and [`Peripheral::read_all`].
+///
+/// [`Peripheral::write_all`]: super::Peripheral::write_all
+/// [`Peripheral::read_all`]: super::Peripheral::read_all
+#[derive(Clone, Default)]
+pub struct TransferOptions {
+    /// The number of bytes to write at a time. Defaults to the largest which fits in one packet at
+    /// the negotiated MTU; see [`Peripheral::max_write_without_response_size`]. Not used when
+    /// reading.
+    ///
+    /// [`Peripheral::max_write_without_response_size`]: super::Peripheral::max_write_without_response_size
+    pub chunk_size: Option<usize>,
+    /// How long to wait between two writes without response. Chunks are otherwise sent as fast as
+    /// the platform accepts them, which may be faster than some devices can process them. Not
+    /// used when reading.
+    pub pacing: Option<Duration>,
+    /// Called after each chunk.
+    pub progress: Option<ProgressCallback>,
+}
+
+impl TransferOptions {
+    /// Sets the function called with the progress of the transfer after each chunk.
+    pub fn with_progress(mut self, progress: impl Fn(Progress) + Send + Sync + 'static) -> Self {
+        self.progress = Some(Arc::new(progress));
+        self
+    }
+
+    pub(crate) fn report(&self, transferred: usize, total: Option<usize>) {
+        if let Some(progress) = &self.progress {
+            progress(Progress { transferred, total });
+        }
+    }
+}
+
+impl Debug for TransferOptions {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("TransferOptions")
+            .field("chunk_size", &self.chunk_size)
+            .field("pacing", &self.pacing)
+            .field("progress", &self.progress.is_some())
+            .finish()
+    }
+}
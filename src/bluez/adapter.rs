@@ -1,27 +1,250 @@
-use super::peripheral::{Peripheral, PeripheralId};
-use crate::api::{Central, CentralEvent, ScanFilter};
+use super::advertising::AdvertisementHandle;
+use super::agent::AgentHandle;
+use super::bus::SystemBus;
+use super::gatt_server::GattApplication;
+use super::monitor::MonitorHandle;
+use super::peripheral::{device_properties, Peripheral, PeripheralId};
+use crate::api::{
+    proximity::ProximityConfig, Advertiser, Agent, Central, CentralEvent, CentralState,
+    DeviceExpiry, GattRequestHandler, GattServer, IoCapability, LocalAdvertisement, LocalService,
+    NameFilter, Pairing, Presence, ScanFilter, ScanMode, Timeouts,
+};
+use crate::common::presence::DeviceTracker;
+use crate::common::proximity::ProximityTracker;
+use crate::common::scan_filter::{filter_events, ActiveScanFilter};
+use crate::common::subscriptions::PeripheralSubscriptions;
+use crate::common::timeouts::SharedTimeouts;
 use crate::{Error, Result};
 use async_trait::async_trait;
 use bluez_async::{
-    AdapterId, BluetoothError, BluetoothEvent, BluetoothSession, DeviceEvent, DiscoveryFilter,
-    Transport,
+    AdapterEvent, AdapterId, BluetoothEvent, BluetoothSession, DeviceEvent, DeviceId,
+    DiscoveryFilter, Transport,
 };
+use dbus::Path;
+use futures::future::{self, ready};
 use futures::stream::{self, Stream, StreamExt};
+use log::debug;
+use std::collections::HashMap;
 use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use tokio_stream::wrappers::BroadcastStream;
 
 /// Implementation of [api::Central](crate::api::Central).
 #[derive(Clone, Debug)]
 pub struct Adapter {
     session: BluetoothSession,
     adapter: AdapterId,
+    timeouts: SharedTimeouts,
+    bus: SystemBus,
+    scan_filter: Arc<ActiveScanFilter>,
+    /// The advertisement monitor of the passive scan in progress, if any.
+    monitor: Arc<Mutex<Option<MonitorHandle>>>,
+    devices: Arc<DeviceTracker>,
+    proximity: Arc<ProximityTracker>,
+    /// Shared by all the handles for each device, as a new one is created on every lookup.
+    notification_streams: Arc<PeripheralSubscriptions>,
+    /// Events about devices going quiet or changing zone, which aren't reported by BlueZ.
+    scan_events: broadcast::Sender<CentralEvent>,
+    scan_task: Arc<Mutex<Option<ScanTask>>>,
+}
+
+/// The task tracking the devices of the adapter while a scan is in progress, to report those which
+/// go quiet or change zone. It is aborted when this is dropped.
+#[derive(Debug)]
+struct ScanTask(JoinHandle<()>);
+
+impl Drop for ScanTask {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
 }
 
 impl Adapter {
-    pub(crate) fn new(session: BluetoothSession, adapter: AdapterId) -> Self {
-        Self { session, adapter }
+    pub(crate) fn new(
+        session: BluetoothSession,
+        adapter: AdapterId,
+        timeouts: SharedTimeouts,
+        bus: SystemBus,
+    ) -> Self {
+        Self {
+            session,
+            adapter,
+            timeouts,
+            bus,
+            scan_filter: Default::default(),
+            monitor: Default::default(),
+            devices: Default::default(),
+            proximity: Default::default(),
+            notification_streams: Default::default(),
+            scan_events: broadcast::channel(16).0,
+            scan_task: Default::default(),
+        }
     }
 }
 
+impl Adapter {
+    /// Starts tracking when devices are heard from and how close they are, and reporting those
+    /// which go quiet or change zone on the event streams of the adapter.
+    async fn watch_devices(&self) -> Result<()> {
+        let events = self.session.adapter_event_stream(&self.adapter).await?;
+        let session = self.session.clone();
+        let adapter_id = self.adapter.clone();
+        let devices = self.devices.clone();
+        let events = events
+            .filter_map(move |event| central_event(event, session.clone(), adapter_id.clone()))
+            .inspect(move |event| devices.record(event));
+        let devices = self.devices.clone();
+        let proximity = self.proximity.clone();
+        let scan_events = self.scan_events.clone();
+        let session = self.session.clone();
+        let task = tokio::spawn(async move {
+            // Nothing may be subscribed, which is fine.
+            let send = |event| {
+                let _ = scan_events.send(event);
+            };
+            let watch = devices.watch(|id, _| {
+                if let Some(zone) = proximity.forget(&id) {
+                    send(CentralEvent::ProximityChanged {
+                        id: id.clone(),
+                        zone,
+                    });
+                }
+                send(CentralEvent::DeviceLost(id));
+            });
+            let track = proximity.track(
+                events,
+                |id: PeripheralId| {
+                    let session = session.clone();
+                    async move {
+                        session
+                            .get_device_info(&id.0)
+                            .await
+                            .ok()
+                            .map(device_properties)
+                    }
+                },
+                &send,
+            );
+            future::join(watch, track).await;
+        });
+        *self.scan_task.lock().unwrap() = Some(ScanTask(task));
+        Ok(())
+    }
+
+    async fn mtu_events(&self) -> Result<impl Stream<Item = CentralEvent>> {
+        let changes = self
+            .bus
+            .properties_changed(
+                Path::from(self.adapter.clone()),
+                "org.bluez.GattCharacteristic1",
+            )
+            .await?
+            .filter_map(|(path, properties)| {
+                let mtu = properties
+                    .get("MTU")
+                    .and_then(|mtu| mtu.0.as_u64())
+                    .and_then(|mtu| u16::try_from(mtu).ok());
+                ready(mtu.map(|mtu| MtuUpdate::Changed(path, mtu)))
+            });
+        let connections = self
+            .session
+            .adapter_event_stream(&self.adapter)
+            .await?
+            .filter_map(|event| {
+                ready(match event {
+                    BluetoothEvent::Device {
+                        id,
+                        event: DeviceEvent::Connected { connected },
+                    } => Some(MtuUpdate::Connected(id, connected)),
+                    _ => None,
+                })
+            });
+        let session = self.session.clone();
+        let adapter = self.adapter.clone();
+        let mtus = Arc::new(Mutex::new(DeviceMtus::default()));
+        Ok(
+            stream::select(changes, connections).filter_map(move |update| {
+                let session = session.clone();
+                let adapter = adapter.clone();
+                let mtus = mtus.clone();
+                async move {
+                    let (path, mtu) = match update {
+                        MtuUpdate::Connected(device, connected) => {
+                            let mut mtus = mtus.lock().unwrap();
+                            if !connected {
+                                // The MTU is negotiated afresh on each connection.
+                                mtus.mtus.remove(&device);
+                            }
+                            mtus.devices
+                                .insert(Path::from(device.clone()).to_string(), device);
+                            return None;
+                        }
+                        MtuUpdate::Changed(path, mtu) => (path, mtu),
+                    };
+                    let device_path = device_path(&path)?;
+                    let known = mtus.lock().unwrap().devices.get(device_path).cloned();
+                    let device = match known {
+                        Some(device) => device,
+                        // The device connected before we started watching, so look it up once.
+                        None => {
+                            let devices = session.get_devices_on_adapter(&adapter).await.ok()?;
+                            let mut mtus = mtus.lock().unwrap();
+                            for device in devices {
+                                mtus.devices
+                                    .insert(Path::from(device.id.clone()).to_string(), device.id);
+                            }
+                            mtus.devices.get(device_path).cloned()?
+                        }
+                    };
+                    let changed =
+                        mtus.lock().unwrap().mtus.insert(device.clone(), mtu) != Some(mtu);
+                    changed.then(|| CentralEvent::MtuChanged {
+                        id: device.into(),
+                        mtu,
+                    })
+                }
+            }),
+        )
+    }
+
+    /// Returns a stream which reports the adapter as unavailable when it goes away, e.g. because
+    /// it was unplugged.
+    async fn removed_events(&self) -> Result<impl Stream<Item = CentralEvent>> {
+        Ok(self
+            .bus
+            .interface_removed(Path::from(self.adapter.clone()), "org.bluez.Adapter1")
+            .await?
+            .map(|()| CentralEvent::StateUpdate(CentralState::Unavailable)))
+    }
+}
+
+/// What the stream of MTU changes of an adapter watches.
+enum MtuUpdate {
+    /// The MTU reported on the characteristic at the path changed.
+    Changed(Path<'static>, u16),
+    /// A device connected or disconnected.
+    Connected(DeviceId, bool),
+}
+
+/// The devices of the adapter by path, and the MTUs last reported for them.
+#[derive(Default)]
+struct DeviceMtus {
+    devices: HashMap<String, DeviceId>,
+    mtus: HashMap<DeviceId, u16>,
+}
+
+/// Returns the path of the device the attribute at `path` belongs to, which is the object its
+/// services are under.
+fn device_path(path: &str) -> Option<&str> {
+    let start = path.find("/dev_")?;
+    let end = path[start + 1..]
+        .find('/')
+        .map_or(path.len(), |end| start + 1 + end);
+    Some(&path[..end])
+}
+
 #[async_trait]
 impl Central for Adapter {
     type Peripheral = Peripheral;
@@ -53,26 +276,101 @@ impl Central for Adapter {
         let events = events
             .filter_map(move |event| central_event(event, session.clone(), adapter_id.clone()));
 
-        Ok(Box::pin(initial_events.chain(events)))
+        // BlueZ only filters discovery by services, RSSI or pathloss and a name prefix, so the
+        // rest of the scan filter is applied here.
+        let session = self.session.clone();
+        let scan_events = BroadcastStream::new(self.scan_events.subscribe())
+            .filter_map(|event| ready(event.ok()));
+        let events = filter_events(
+            stream::select(initial_events.chain(events), scan_events),
+            self.scan_filter.clone(),
+            move |id: PeripheralId| {
+                let session = session.clone();
+                async move {
+                    session
+                        .get_device_info(&id.0)
+                        .await
+                        .ok()
+                        .map(device_properties)
+                }
+            },
+        );
+
+        // bluez-async doesn't report the adapter going away, so that is watched on our own
+        // connection to D-Bus too, if it can be opened.
+        let events: Pin<Box<dyn Stream<Item = CentralEvent> + Send>> =
+            match self.removed_events().await {
+                Ok(removed_events) => Box::pin(stream::select(events, removed_events)),
+                Err(e) => {
+                    debug!("Not watching for the adapter being removed: {:?}", e);
+                    events
+                }
+            };
+
+        // BlueZ reports the MTU on each GATT characteristic of a device, so the changes are only
+        // watched if our own connection to D-Bus can be opened, and deduplicated per device.
+        match self.mtu_events().await {
+            Ok(mtu_events) => Ok(Box::pin(stream::select(events, mtu_events))),
+            Err(e) => {
+                debug!("Not watching MTU changes: {:?}", e);
+                Ok(events)
+            }
+        }
     }
 
     async fn start_scan(&self, filter: ScanFilter) -> Result<()> {
-        let filter = DiscoveryFilter {
-            service_uuids: filter.services,
-            duplicate_data: Some(true),
+        // Discovery always sends scan requests, but BlueZ scans passively for advertisement
+        // monitors while no discovery is in progress.
+        if filter.mode == ScanMode::Passive {
+            let monitor = MonitorHandle::register(&self.bus, self.adapter.clone(), &filter).await?;
+            // An active scan of ours would keep BlueZ from scanning passively.
+            let discovering =
+                self.scan_task.lock().unwrap().is_some() && self.monitor.lock().unwrap().is_none();
+            if discovering {
+                self.session
+                    .stop_discovery_on_adapter(&self.adapter)
+                    .await?;
+            }
+            *self.monitor.lock().unwrap() = Some(monitor);
+            self.scan_filter.start(filter);
+            return self.watch_devices().await;
+        }
+        self.monitor.lock().unwrap().take();
+
+        // BlueZ doesn't accept both an RSSI and a pathloss threshold, so only the RSSI threshold
+        // is applied natively if both are set.
+        let discovery_filter = DiscoveryFilter {
+            service_uuids: filter.services.clone(),
+            rssi_threshold: filter.rssi,
+            pathloss_threshold: filter.pathloss.filter(|_| filter.rssi.is_none()),
+            duplicate_data: Some(!filter.suppress_duplicates),
             transport: Some(Transport::Auto),
+            pattern: filter.name.as_ref().map(|name| match name {
+                NameFilter::Exact(name) | NameFilter::Prefix(name) => name.clone(),
+            }),
             ..Default::default()
         };
-        self.session
-            .start_discovery_on_adapter_with_filter(&self.adapter, &filter)
-            .await?;
-        Ok(())
+        self.scan_filter.start(filter);
+        if let Err(e) = self
+            .session
+            .start_discovery_on_adapter_with_filter(&self.adapter, &discovery_filter)
+            .await
+        {
+            self.scan_filter.stop();
+            return Err(e.into());
+        }
+        self.watch_devices().await
     }
 
     async fn stop_scan(&self) -> Result<()> {
-        self.session
-            .stop_discovery_on_adapter(&self.adapter)
-            .await?;
+        let monitor = self.monitor.lock().unwrap().take();
+        if monitor.is_none() {
+            self.session
+                .stop_discovery_on_adapter(&self.adapter)
+                .await?;
+        }
+        self.scan_filter.stop();
+        self.scan_task.lock().unwrap().take();
         Ok(())
     }
 
@@ -80,19 +378,29 @@ impl Central for Adapter {
         let devices = self.session.get_devices_on_adapter(&self.adapter).await?;
         Ok(devices
             .into_iter()
-            .map(|device| Peripheral::new(self.session.clone(), device))
+            .map(|device| {
+                Peripheral::new(
+                    self.session.clone(),
+                    device,
+                    self.timeouts.clone(),
+                    self.bus.clone(),
+                    self.proximity.clone(),
+                    &self.notification_streams,
+                )
+            })
             .collect())
     }
 
     async fn peripheral(&self, id: &PeripheralId) -> Result<Peripheral> {
-        let device = self.session.get_device_info(&id.0).await.map_err(|e| {
-            if let BluetoothError::DbusError(_) = e {
-                Error::DeviceNotFound
-            } else {
-                e.into()
-            }
-        })?;
-        Ok(Peripheral::new(self.session.clone(), device))
+        let device = self.session.get_device_info(&id.0).await?;
+        Ok(Peripheral::new(
+            self.session.clone(),
+            device,
+            self.timeouts.clone(),
+            self.bus.clone(),
+            self.proximity.clone(),
+            &self.notification_streams,
+        ))
     }
 
     async fn add_peripheral(&self, _address: &PeripheralId) -> Result<Peripheral> {
@@ -105,11 +413,93 @@ impl Central for Adapter {
         let adapter_info = self.session.get_adapter_info(&self.adapter).await?;
         Ok(format!("{} ({})", adapter_info.id, adapter_info.modalias))
     }
+
+    async fn adapter_state(&self) -> Result<CentralState> {
+        match self.session.get_adapter_info(&self.adapter).await {
+            Ok(adapter_info) => Ok(powered_state(adapter_info.powered)),
+            Err(e) => match Error::from(e) {
+                // The adapter has gone away, e.g. because it was unplugged.
+                Error::DeviceNotFound => Ok(CentralState::Unavailable),
+                e => Err(e),
+            },
+        }
+    }
+
+    async fn set_powered(&self, powered: bool) -> Result<()> {
+        self.bus
+            .set_property(
+                self.adapter.clone().into(),
+                "org.bluez.Adapter1",
+                "Powered",
+                powered,
+            )
+            .await
+    }
+
+    fn timeouts(&self) -> Timeouts {
+        *self.timeouts.read().unwrap()
+    }
+
+    fn set_timeouts(&self, timeouts: Timeouts) {
+        *self.timeouts.write().unwrap() = timeouts;
+    }
+
+    fn presence(&self, id: &PeripheralId) -> Option<Presence> {
+        self.devices.presence(id)
+    }
+
+    fn set_device_expiry(&self, expiry: Option<DeviceExpiry>) {
+        self.devices.set_expiry(expiry);
+    }
+
+    fn set_proximity_config(&self, config: ProximityConfig) {
+        self.proximity.set_config(config);
+    }
 }
 
-impl From<BluetoothError> for Error {
-    fn from(error: BluetoothError) -> Self {
-        Error::Other(Box::new(error))
+#[async_trait]
+impl GattServer for Adapter {
+    type Application = GattApplication;
+
+    async fn register_application(
+        &self,
+        services: Vec<LocalService>,
+        handler: Arc<dyn GattRequestHandler>,
+    ) -> Result<GattApplication> {
+        GattApplication::register(&self.bus, self.adapter.clone(), services, handler).await
+    }
+}
+
+#[async_trait]
+impl Advertiser for Adapter {
+    type AdvertisementHandle = AdvertisementHandle;
+
+    async fn start_advertising(
+        &self,
+        advertisement: LocalAdvertisement,
+    ) -> Result<AdvertisementHandle> {
+        AdvertisementHandle::register(&self.bus, self.adapter.clone(), advertisement).await
+    }
+}
+
+#[async_trait]
+impl Pairing for Adapter {
+    type AgentHandle = AgentHandle;
+
+    async fn register_agent(
+        &self,
+        agent: Arc<dyn Agent>,
+        capability: IoCapability,
+    ) -> Result<AgentHandle> {
+        AgentHandle::register(&self.bus, self.session.clone(), agent, capability).await
+    }
+}
+
+fn powered_state(powered: bool) -> CentralState {
+    if powered {
+        CentralState::PoweredOn
+    } else {
+        CentralState::PoweredOff
     }
 }
 
@@ -119,6 +509,10 @@ async fn central_event(
     adapter_id: AdapterId,
 ) -> Option<CentralEvent> {
     match event {
+        BluetoothEvent::Adapter {
+            id,
+            event: AdapterEvent::Powered { powered },
+        } if id == adapter_id => Some(CentralEvent::StateUpdate(powered_state(powered))),
         BluetoothEvent::Device {
             id,
             event: device_event,
@@ -165,3 +559,21 @@ async fn central_event(
         _ => None,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn device_paths() {
+        assert_eq!(
+            device_path("/org/bluez/hci0/dev_11_22_33_44_55_66/service0010/char0011"),
+            Some("/org/bluez/hci0/dev_11_22_33_44_55_66")
+        );
+        assert_eq!(
+            device_path("/org/bluez/hci0/dev_11_22_33_44_55_66"),
+            Some("/org/bluez/hci0/dev_11_22_33_44_55_66")
+        );
+        assert_eq!(device_path("/org/bluez/hci0"), None);
+    }
+}
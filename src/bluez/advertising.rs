@@ -0,0 +1,144 @@
+use super::bus::SystemBus;
+use super::export::{unique_path, ExportedObjects};
+use crate::api::LocalAdvertisement;
+use crate::Result;
+use bluez_async::AdapterId;
+use dbus::arg::Variant;
+use dbus_crossroads::{Crossroads, IfaceBuilder};
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Formatter};
+
+const ADVERTISEMENT_PATH: &str = "/org/btleplug/advertisement";
+const ADVERTISEMENT_INTERFACE: &str = "org.bluez.LEAdvertisement1";
+
+/// A handle to an advertisement registered with BlueZ. The advertisement is unregistered when this
+/// is dropped.
+pub struct AdvertisementHandle {
+    advertisement: LocalAdvertisement,
+    _objects: ExportedObjects,
+}
+
+impl AdvertisementHandle {
+    pub(crate) async fn register(
+        bus: &SystemBus,
+        adapter: AdapterId,
+        advertisement: LocalAdvertisement,
+    ) -> Result<Self> {
+        advertisement.validate()?;
+        let advertisement_type = advertisement_type(&advertisement);
+        let mut crossroads = Crossroads::new();
+        let token = crossroads.register(
+            ADVERTISEMENT_INTERFACE,
+            |b: &mut IfaceBuilder<LocalAdvertisement>| {
+                b.property("Type")
+                    .get(move |_, _| Ok(advertisement_type.to_string()));
+                // BlueZ treats missing properties differently from empty ones, so only add those
+                // which have a value.
+                if advertisement.local_name.is_some() {
+                    b.property("LocalName")
+                        .get(|_, advertisement| Ok(advertisement.local_name.clone().unwrap()));
+                }
+                if !advertisement.services.is_empty() {
+                    b.property("ServiceUUIDs").get(|_, advertisement| {
+                        Ok(advertisement
+                            .services
+                            .iter()
+                            .map(|uuid| uuid.to_string())
+                            .collect::<Vec<_>>())
+                    });
+                }
+                if !advertisement.manufacturer_data.is_empty() {
+                    b.property("ManufacturerData").get(|_, advertisement| {
+                        Ok(advertisement
+                            .manufacturer_data
+                            .iter()
+                            .map(|(id, data)| (*id, Variant(data.clone())))
+                            .collect::<HashMap<_, _>>())
+                    });
+                }
+                if !advertisement.service_data.is_empty() {
+                    b.property("ServiceData").get(|_, advertisement| {
+                        Ok(advertisement
+                            .service_data
+                            .iter()
+                            .map(|(uuid, data)| (uuid.to_string(), Variant(data.clone())))
+                            .collect::<HashMap<_, _>>())
+                    });
+                }
+                if advertisement.include_tx_power {
+                    b.property("Includes")
+                        .get(|_, _| Ok(vec!["tx-power".to_string()]));
+                }
+                if advertisement.min_interval.is_some() {
+                    b.property("MinInterval").get(|_, advertisement| {
+                        Ok(milliseconds(advertisement.min_interval.unwrap()))
+                    });
+                }
+                if advertisement.max_interval.is_some() {
+                    b.property("MaxInterval").get(|_, advertisement| {
+                        Ok(milliseconds(advertisement.max_interval.unwrap()))
+                    });
+                }
+                // Called by BlueZ when it removes the advertisement on its own, e.g. because the
+                // adapter was powered off. There's nothing for us to clean up.
+                b.method("Release", (), (), |_, _, ()| Ok(()));
+            },
+        );
+        let advertisement_path = unique_path(ADVERTISEMENT_PATH);
+        crossroads.insert(advertisement_path.clone(), &[token], advertisement.clone());
+
+        let objects = ExportedObjects::new(bus, advertisement_path.clone(), crossroads).await?;
+        objects
+            .register_with(
+                &adapter,
+                "org.bluez.LEAdvertisingManager1",
+                "RegisterAdvertisement",
+                advertisement_path,
+            )
+            .await?;
+        Ok(Self {
+            advertisement,
+            _objects: objects,
+        })
+    }
+}
+
+impl Debug for AdvertisementHandle {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("AdvertisementHandle")
+            .field("advertisement", &self.advertisement)
+            .finish()
+    }
+}
+
+/// Returns the BlueZ advertisement type for the advertisement. BlueZ sends "peripheral"
+/// advertisements as connectable and scannable, and "broadcast" ones as neither unless they have
+/// scan response data, which is why [`LocalAdvertisement::validate`] only accepts those two
+/// combinations.
+fn advertisement_type(advertisement: &LocalAdvertisement) -> &'static str {
+    if advertisement.connectable {
+        "peripheral"
+    } else {
+        "broadcast"
+    }
+}
+
+fn milliseconds(interval: std::time::Duration) -> u32 {
+    interval.as_millis().try_into().unwrap_or(u32::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn advertisement_types() {
+        let advertisement = |connectable| LocalAdvertisement {
+            connectable,
+            scannable: connectable,
+            ..Default::default()
+        };
+        assert_eq!(advertisement_type(&advertisement(true)), "peripheral");
+        assert_eq!(advertisement_type(&advertisement(false)), "broadcast");
+    }
+}
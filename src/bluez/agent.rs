@@ -0,0 +1,223 @@
+use super::bus::SystemBus;
+use super::export::{unique_path, ExportedObjects};
+use super::peripheral::PeripheralId;
+use crate::api::{Agent, IoCapability};
+use crate::Result;
+use bluez_async::BluetoothSession;
+use dbus::arg::AppendAll;
+use dbus::Path;
+use dbus_crossroads::{Context, Crossroads, IfaceBuilder, MethodErr};
+use std::fmt::{self, Debug, Formatter};
+use std::future::Future;
+use std::marker::PhantomData;
+use std::sync::Arc;
+use uuid::Uuid;
+
+const AGENT_PATH: &str = "/org/btleplug/agent";
+const AGENT_INTERFACE: &str = "org.bluez.Agent1";
+const AGENT_MANAGER_PATH: &str = "/org/bluez";
+const AGENT_MANAGER_INTERFACE: &str = "org.bluez.AgentManager1";
+
+/// A handle to a pairing agent registered with BlueZ as its default agent. The agent is
+/// unregistered when this is dropped.
+pub struct AgentHandle {
+    agent: Arc<dyn Agent>,
+    capability: IoCapability,
+    _objects: ExportedObjects,
+}
+
+impl AgentHandle {
+    pub(crate) async fn register(
+        bus: &SystemBus,
+        session: BluetoothSession,
+        agent: Arc<dyn Agent>,
+        capability: IoCapability,
+    ) -> Result<Self> {
+        let mut crossroads = Crossroads::new();
+        let handler = Handler { session, agent };
+        let token = crossroads.register(AGENT_INTERFACE, |b: &mut IfaceBuilder<()>| {
+            // Called by BlueZ when it unregisters the agent on its own. There's nothing for us to
+            // clean up.
+            b.method("Release", (), (), |_, _, ()| Ok(()));
+            let h = handler.clone();
+            b.method_with_cr_async(
+                "RequestPasskey",
+                ("device",),
+                ("passkey",),
+                move |ctx, _, (device,): (Path<'static>,)| {
+                    h.forward(ctx, device, |agent, id| async move {
+                        agent
+                            .request_passkey(&id)
+                            .await
+                            .map(|passkey| (passkey,))
+                            .ok_or_else(rejected)
+                    })
+                },
+            );
+            let h = handler.clone();
+            b.method_with_cr_async(
+                "DisplayPasskey",
+                ("device", "passkey", "entered"),
+                (),
+                move |ctx, _, (device, passkey, _entered): (Path<'static>, u32, u16)| {
+                    h.forward(ctx, device, move |agent, id| async move {
+                        agent.display_passkey(&id, passkey).await;
+                        Ok(())
+                    })
+                },
+            );
+            let h = handler.clone();
+            b.method_with_cr_async(
+                "RequestConfirmation",
+                ("device", "passkey"),
+                (),
+                move |ctx, _, (device, passkey): (Path<'static>, u32)| {
+                    h.forward(ctx, device, move |agent, id| async move {
+                        accept(agent.request_confirmation(&id, passkey).await)
+                    })
+                },
+            );
+            let h = handler.clone();
+            b.method_with_cr_async(
+                "RequestAuthorization",
+                ("device",),
+                (),
+                move |ctx, _, (device,): (Path<'static>,)| {
+                    h.forward(ctx, device, |agent, id| async move {
+                        accept(agent.request_authorization(&id).await)
+                    })
+                },
+            );
+            let h = handler.clone();
+            b.method_with_cr_async(
+                "AuthorizeService",
+                ("device", "uuid"),
+                (),
+                move |ctx, _, (device, uuid): (Path<'static>, String)| {
+                    h.forward(ctx, device, move |agent, id| async move {
+                        let service = Uuid::parse_str(&uuid).map_err(|_| rejected())?;
+                        accept(agent.authorize_service(&id, service).await)
+                    })
+                },
+            );
+            // PIN codes are only used by BR/EDR legacy pairing.
+            b.method(
+                "RequestPinCode",
+                ("device",),
+                ("pincode",),
+                |_, _, (_,): (Path<'static>,)| Err::<(String,), _>(rejected()),
+            );
+            b.method(
+                "DisplayPinCode",
+                ("device", "pincode"),
+                (),
+                |_, _, (_, _): (Path<'static>, String)| Err::<(), _>(rejected()),
+            );
+            let agent = handler.agent.clone();
+            b.method("Cancel", (), (), move |_, _, ()| {
+                agent.cancel();
+                Ok(())
+            });
+        });
+        let agent_path = unique_path(AGENT_PATH);
+        crossroads.insert(agent_path.clone(), &[token], ());
+
+        let objects = ExportedObjects::new(bus, agent_path.clone(), crossroads).await?;
+        let agent_manager = Path::from(AGENT_MANAGER_PATH);
+        objects
+            .register(
+                agent_manager.clone(),
+                AGENT_MANAGER_INTERFACE,
+                "RegisterAgent",
+                agent_path.clone(),
+                (agent_path.clone(), capability_name(capability)),
+            )
+            .await?;
+        // BlueZ asks the agent of whoever started pairing, and falls back to the default agent.
+        // Pairing is started from another connection, and may be started by the device.
+        objects
+            .call(
+                agent_manager,
+                AGENT_MANAGER_INTERFACE,
+                "RequestDefaultAgent",
+                (agent_path,),
+            )
+            .await?;
+        Ok(Self {
+            agent: handler.agent,
+            capability,
+            _objects: objects,
+        })
+    }
+}
+
+impl Debug for AgentHandle {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("AgentHandle")
+            .field("agent", &self.agent)
+            .field("capability", &self.capability)
+            .finish()
+    }
+}
+
+/// Forwards the requests BlueZ makes of the agent about a device to the application's agent.
+#[derive(Clone)]
+struct Handler {
+    session: BluetoothSession,
+    agent: Arc<dyn Agent>,
+}
+
+impl Handler {
+    /// Replies to a method call about the device at `device` with the result of `handle`, or
+    /// rejects it if the device isn't one btleplug knows about.
+    fn forward<OA, F, R>(
+        &self,
+        mut ctx: Context,
+        device: Path<'static>,
+        handle: F,
+    ) -> impl Future<Output = PhantomData<OA>>
+    where
+        OA: AppendAll,
+        F: FnOnce(Arc<dyn Agent>, PeripheralId) -> R,
+        R: Future<Output = std::result::Result<OA, MethodErr>>,
+    {
+        let handler = self.clone();
+        async move {
+            let result = match peripheral_id(&handler.session, &device).await {
+                Some(id) => handle(handler.agent, id).await,
+                None => Err(rejected()),
+            };
+            ctx.reply(result)
+        }
+    }
+}
+
+async fn peripheral_id(session: &BluetoothSession, device: &Path<'static>) -> Option<PeripheralId> {
+    let devices = session.get_devices().await.ok()?;
+    devices
+        .into_iter()
+        .find(|info| Path::from(info.id.clone()) == *device)
+        .map(|info| PeripheralId(info.id))
+}
+
+fn accept(accepted: bool) -> std::result::Result<(), MethodErr> {
+    if accepted {
+        Ok(())
+    } else {
+        Err(rejected())
+    }
+}
+
+fn rejected() -> MethodErr {
+    ("org.bluez.Error.Rejected", "Rejected by the agent").into()
+}
+
+fn capability_name(capability: IoCapability) -> &'static str {
+    match capability {
+        IoCapability::DisplayOnly => "DisplayOnly",
+        IoCapability::DisplayYesNo => "DisplayYesNo",
+        IoCapability::KeyboardOnly => "KeyboardOnly",
+        IoCapability::NoInputNoOutput => "NoInputNoOutput",
+        IoCapability::KeyboardDisplay => "KeyboardDisplay",
+    }
+}
@@ -0,0 +1,228 @@
+//! Our own connection to the system bus, alongside the one of the BlueZ session.
+//!
+//! bluez-async keeps the D-Bus connection of its session to itself, so it can't be used to read the
+//! BlueZ properties and signals bluez-async doesn't expose, nor to export the objects which BlueZ
+//! calls back into: GATT applications, advertisements, agents and advertisement monitors. Those go
+//! through this second connection instead. BlueZ ties such objects to the connection which
+//! registered them, so they're registered through it too, and are dropped by BlueZ if it closes.
+
+use crate::{Error, Result};
+use dbus::arg::{Append, AppendAll, Arg, Get, PropMap};
+use dbus::message::SignalArgs;
+use dbus::nonblock::stdintf::org_freedesktop_dbus::{
+    ObjectManagerInterfacesRemoved, Properties, PropertiesPropertiesChanged,
+};
+use dbus::nonblock::{MsgMatch, Proxy, SyncConnection};
+use dbus::{Message, Path};
+use futures::channel::mpsc::UnboundedReceiver;
+use futures::future::ready;
+use futures::stream::{Stream, StreamExt};
+use log::error;
+use std::fmt::{self, Debug, Formatter};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::sync::OnceCell;
+use tokio::task::JoinHandle;
+
+const DBUS_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A D-Bus connection of our own to the system bus, for the BlueZ properties and signals which
+/// bluez-async doesn't expose and the objects we export to BlueZ. The connection is opened the
+/// first time it is needed, and shared by all clones.
+#[derive(Clone, Default)]
+pub(crate) struct SystemBus {
+    connection: Arc<OnceCell<Connection>>,
+}
+
+struct Connection {
+    connection: Arc<SyncConnection>,
+    connection_task: JoinHandle<()>,
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        self.connection_task.abort();
+    }
+}
+
+impl Debug for SystemBus {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("SystemBus")
+            .field("connected", &self.connection.initialized())
+            .finish()
+    }
+}
+
+impl SystemBus {
+    /// Returns the connection, opening it if this is the first time it is needed.
+    pub(super) async fn connection(&self) -> Result<Arc<SyncConnection>> {
+        let connection = self
+            .connection
+            .get_or_try_init(|| async {
+                let (resource, connection) =
+                    dbus_tokio::connection::new_system_sync().map_err(Error::from)?;
+                // Several streams may watch the same signals, and each needs its own copy.
+                connection.set_signal_match_mode(true);
+                let connection_task = tokio::spawn(async {
+                    let err = resource.await;
+                    error!("Lost connection to D-Bus: {}", err);
+                });
+                Ok::<_, Error>(Connection {
+                    connection,
+                    connection_task,
+                })
+            })
+            .await?;
+        Ok(connection.connection.clone())
+    }
+
+    /// Reads a property of a BlueZ object.
+    pub async fn get_property<T>(
+        &self,
+        path: Path<'static>,
+        interface: &str,
+        name: &str,
+    ) -> Result<T>
+    where
+        T: for<'b> Get<'b> + Arg + 'static,
+    {
+        let proxy = Proxy::new("org.bluez", path, DBUS_TIMEOUT, self.connection().await?);
+        proxy.get(interface, name).await.map_err(|e| {
+            if e.name() == Some("org.freedesktop.DBus.Error.InvalidArgs") {
+                Error::NotSupported(format!(
+                    "{}.{} is not supported by this version of BlueZ",
+                    interface, name
+                ))
+            } else {
+                e.into()
+            }
+        })
+    }
+
+    /// Sets a property of a BlueZ object.
+    pub async fn set_property<T>(
+        &self,
+        path: Path<'static>,
+        interface: &str,
+        name: &str,
+        value: T,
+    ) -> Result<()>
+    where
+        T: Arg + Append,
+    {
+        let proxy = Proxy::new("org.bluez", path, DBUS_TIMEOUT, self.connection().await?);
+        proxy.set(interface, name, value).await.map_err(Error::from)
+    }
+
+    /// Calls a method of a BlueZ object which returns nothing, waiting up to `timeout` for it to
+    /// finish.
+    pub async fn call_method(
+        &self,
+        path: Path<'static>,
+        interface: &str,
+        method: &str,
+        args: impl AppendAll,
+        timeout: Duration,
+    ) -> Result<()> {
+        let proxy = Proxy::new("org.bluez", path, timeout, self.connection().await?);
+        proxy
+            .method_call(interface, method, args)
+            .await
+            .map_err(Error::from)
+    }
+
+    /// Returns a stream of the changes to properties of the given interface, on the object at
+    /// `path` or any object under it. Each item is the path of the object and the properties which
+    /// changed.
+    pub async fn properties_changed(
+        &self,
+        path: Path<'static>,
+        interface: &'static str,
+    ) -> Result<impl Stream<Item = (Path<'static>, PropMap)>> {
+        let connection = self.connection().await?;
+        let mut match_rule =
+            PropertiesPropertiesChanged::match_rule(None, Some(&path)).static_clone();
+        match_rule.path_is_namespace = true;
+        let msg_match = connection
+            .add_match(match_rule)
+            .await
+            .map_err(Error::from)?;
+        Ok(
+            MessageStream::new(msg_match, connection).filter_map(move |message| {
+                let changed = PropertiesPropertiesChanged::from_message(&message)
+                    .filter(|changed| changed.interface_name == interface)
+                    .zip(message.path())
+                    .map(|(changed, path)| (path.into_static(), changed.changed_properties));
+                ready(changed)
+            }),
+        )
+    }
+
+    /// Returns a stream which yields an item each time the given interface is removed from the
+    /// object at `path`, e.g. because the object itself went away.
+    pub async fn interface_removed(
+        &self,
+        path: Path<'static>,
+        interface: &'static str,
+    ) -> Result<impl Stream<Item = ()>> {
+        let connection = self.connection().await?;
+        let match_rule =
+            ObjectManagerInterfacesRemoved::match_rule(Some(&"org.bluez".into()), None)
+                .static_clone();
+        let msg_match = connection
+            .add_match(match_rule)
+            .await
+            .map_err(Error::from)?;
+        Ok(
+            MessageStream::new(msg_match, connection).filter_map(move |message| {
+                let removed =
+                    ObjectManagerInterfacesRemoved::from_message(&message).filter(|removed| {
+                        removed.object == path
+                            && removed.interfaces.iter().any(|name| name == interface)
+                    });
+                ready(removed.map(|_| ()))
+            }),
+        )
+    }
+}
+
+/// A stream of D-Bus messages which removes its `MsgMatch` from the connection when it is dropped.
+struct MessageStream {
+    msg_match: Option<MsgMatch>,
+    messages: UnboundedReceiver<Message>,
+    connection: Arc<SyncConnection>,
+}
+
+impl MessageStream {
+    fn new(msg_match: MsgMatch, connection: Arc<SyncConnection>) -> Self {
+        let (msg_match, messages) = msg_match.msg_stream();
+        Self {
+            msg_match: Some(msg_match),
+            messages,
+            connection,
+        }
+    }
+}
+
+impl Stream for MessageStream {
+    type Item = Message;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Message>> {
+        Pin::new(&mut self.messages).poll_next(cx)
+    }
+}
+
+impl Drop for MessageStream {
+    fn drop(&mut self) {
+        if let (Some(msg_match), Ok(handle)) =
+            (self.msg_match.take(), tokio::runtime::Handle::try_current())
+        {
+            let connection = self.connection.clone();
+            handle.spawn(async move {
+                let _ = connection.remove_match(msg_match.token()).await;
+            });
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use crate::api::AttErrorCode;
+use crate::Error;
+use bluez_async::BluetoothError;
+use std::time::Duration;
+
+/// How long bluez-async waits for BlueZ to resolve the services of a device.
+const SERVICE_DISCOVERY_TIMEOUT: Duration = Duration::from_secs(5);
+
+impl From<BluetoothError> for Error {
+    fn from(error: BluetoothError) -> Self {
+        match error {
+            BluetoothError::DbusError(error) => error.into(),
+            BluetoothError::ServiceDiscoveryTimedOut => Error::TimedOut(SERVICE_DISCOVERY_TIMEOUT),
+            BluetoothError::UuidNotFound { .. } => Error::NotSupported(error.to_string()),
+            error => Error::Other(Box::new(error)),
+        }
+    }
+}
+
+/// Translates the errors BlueZ returns over D-Bus. Errors which don't correspond to any other
+/// variant, such as most of `org.bluez.Error.Failed`, are kept as they are in [`Error::Other`].
+impl From<dbus::Error> for Error {
+    fn from(error: dbus::Error) -> Self {
+        if let Some(code) = att_error(&error) {
+            return Error::Att(code);
+        }
+        let message = error.message().unwrap_or_default();
+        match error.name().unwrap_or_default() {
+            "org.bluez.Error.NotConnected" => Error::NotConnected,
+            // GATT methods on a device which isn't connected fail like this.
+            "org.bluez.Error.Failed" if message == "Not connected" => Error::NotConnected,
+            "org.bluez.Error.DoesNotExist" | "org.freedesktop.DBus.Error.UnknownObject" => {
+                Error::DeviceNotFound
+            }
+            "org.bluez.Error.NotPermitted" | "org.freedesktop.DBus.Error.AccessDenied" => {
+                Error::PermissionDenied
+            }
+            "org.bluez.Error.InProgress" => Error::InProgress,
+            "org.bluez.Error.NotSupported" => Error::NotSupported(message.to_string()),
+            "org.bluez.Error.AuthenticationFailed"
+            | "org.bluez.Error.AuthenticationCanceled"
+            | "org.bluez.Error.AuthenticationRejected"
+            | "org.bluez.Error.AuthenticationTimeout" => Error::AuthenticationFailed,
+            _ => Error::Other(Box::new(error)),
+        }
+    }
+}
+
+/// Returns the ATT error code a device responded with, if that's what the error BlueZ returned
+/// from a GATT method is.
+///
+/// BlueZ returns the code itself only for errors it has no D-Bus error for, and otherwise one of a
+/// few D-Bus errors whose message tells the codes apart. Insufficient encryption and an encryption
+/// key which is too short get the same error as insufficient authentication.
+fn att_error(error: &dbus::Error) -> Option<AttErrorCode> {
+    let name = error.name()?.strip_prefix("org.bluez.Error.")?;
+    let message = error.message().unwrap_or_default();
+    match (name, message) {
+        ("NotPermitted", "Read not permitted") => Some(AttErrorCode::ReadNotPermitted),
+        ("NotPermitted", "Write not permitted") => Some(AttErrorCode::WriteNotPermitted),
+        ("NotPermitted", "Not paired") => Some(AttErrorCode::InsufficientAuthentication),
+        ("InvalidArguments", "Invalid offset") | ("InvalidOffset", _) => {
+            Some(AttErrorCode::InvalidOffset)
+        }
+        ("InvalidArguments", "Invalid Length") | ("InvalidValueLength", _) => {
+            Some(AttErrorCode::InvalidAttributeValueLength)
+        }
+        ("NotAuthorized", _) => Some(AttErrorCode::InsufficientAuthorization),
+        ("Failed", message) => {
+            let code = message.strip_prefix("Operation failed with ATT error: 0x")?;
+            u8::from_str_radix(code, 16).ok().map(AttErrorCode::from)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn translate(name: &str, message: &str) -> Error {
+        BluetoothError::DbusError(dbus::Error::new_custom(name, message)).into()
+    }
+
+    #[test]
+    fn att_errors() {
+        assert!(matches!(
+            translate("org.bluez.Error.NotPermitted", "Write not permitted"),
+            Error::Att(AttErrorCode::WriteNotPermitted)
+        ));
+        assert!(matches!(
+            translate("org.bluez.Error.NotPermitted", "Not paired"),
+            Error::Att(AttErrorCode::InsufficientAuthentication)
+        ));
+        assert!(matches!(
+            translate("org.bluez.Error.InvalidArguments", "Invalid offset"),
+            Error::Att(AttErrorCode::InvalidOffset)
+        ));
+        assert!(matches!(
+            translate("org.bluez.Error.NotAuthorized", "Operation Not Authorized"),
+            Error::Att(AttErrorCode::InsufficientAuthorization)
+        ));
+        assert!(matches!(
+            translate(
+                "org.bluez.Error.Failed",
+                "Operation failed with ATT error: 0x80"
+            ),
+            Error::Att(AttErrorCode::Application(0x80))
+        ));
+    }
+
+    #[test]
+    fn dbus_errors() {
+        assert!(matches!(
+            translate("org.bluez.Error.NotConnected", "Not Connected"),
+            Error::NotConnected
+        ));
+        assert!(matches!(
+            translate("org.bluez.Error.Failed", "Not connected"),
+            Error::NotConnected
+        ));
+        assert!(matches!(
+            translate("org.bluez.Error.DoesNotExist", "Does Not Exist"),
+            Error::DeviceNotFound
+        ));
+        assert!(matches!(
+            translate("org.bluez.Error.NotPermitted", "Operation Not Permitted"),
+            Error::PermissionDenied
+        ));
+        assert!(matches!(
+            translate("org.bluez.Error.InProgress", "In Progress"),
+            Error::InProgress
+        ));
+        assert!(matches!(
+            translate("org.bluez.Error.NotSupported", "Operation is not supported"),
+            Error::NotSupported(message) if message == "Operation is not supported"
+        ));
+        assert!(matches!(
+            translate(
+                "org.bluez.Error.AuthenticationCanceled",
+                "Authentication Canceled"
+            ),
+            Error::AuthenticationFailed
+        ));
+        assert!(matches!(
+            translate("org.freedesktop.DBus.Error.UnknownObject", "Unknown object"),
+            Error::DeviceNotFound
+        ));
+        assert!(matches!(
+            translate("org.bluez.Error.Failed", "Software caused connection abort"),
+            Error::Other(_)
+        ));
+    }
+
+    #[test]
+    fn bluez_async_errors() {
+        assert!(matches!(
+            BluetoothError::ServiceDiscoveryTimedOut.into(),
+            Error::TimedOut(SERVICE_DISCOVERY_TIMEOUT)
+        ));
+        let uuid = uuid::Uuid::from_u128(0x00002a37_0000_1000_8000_00805f9b34fb);
+        assert!(matches!(
+            BluetoothError::UuidNotFound { uuid }.into(),
+            Error::NotSupported(message) if message.contains("00002a37")
+        ));
+        assert!(matches!(
+            BluetoothError::NoBluetoothAdapters.into(),
+            Error::Other(_)
+        ));
+    }
+}
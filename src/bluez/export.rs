@@ -0,0 +1,283 @@
+use super::bus::SystemBus;
+use crate::{Error, Result};
+use bluez_async::AdapterId;
+use dbus::arg::{AppendAll, PropMap};
+use dbus::channel::{MatchingReceiver, Sender, Token};
+use dbus::message::MatchRule;
+use dbus::nonblock::{Proxy, SyncConnection};
+use dbus::{Message, Path};
+use dbus_crossroads::Crossroads;
+use log::error;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+const DBUS_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Returns a path starting with `base` which no other objects we export use, so that several sets
+/// of objects of the same kind can be exported at once.
+pub(crate) fn unique_path(base: &str) -> Path<'static> {
+    static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+    Path::from(format!(
+        "{}{}",
+        base,
+        NEXT_ID.fetch_add(1, Ordering::Relaxed)
+    ))
+}
+
+/// Objects exported to BlueZ on our own connection to the system bus.
+///
+/// bluez-async doesn't give access to the connection of its session. Dropping this unregisters
+/// whatever was registered with [`register`](Self::register) and stops serving the objects.
+pub(crate) struct ExportedObjects {
+    // Dropped first, so the calls to unregister are sent before we stop serving the objects.
+    registrations: Registrations,
+    connection: Arc<SyncConnection>,
+    token: Token,
+}
+
+impl ExportedObjects {
+    /// Serves the objects of the given crossroads, which must all be at `root` or under it, on the
+    /// connection of `bus`. Methods of the objects may be asynchronous; they run on the Tokio
+    /// runtime.
+    pub async fn new(
+        bus: &SystemBus,
+        root: Path<'static>,
+        mut crossroads: Crossroads,
+    ) -> Result<Self> {
+        let connection = bus.connection().await?;
+        crossroads.set_async_support(Some((
+            connection.clone(),
+            Box::new(|method| {
+                tokio::spawn(method);
+            }),
+        )));
+        // The crossroads holds on to the connection, so this forms a cycle until `stop_receive`.
+        let crossroads = Mutex::new(crossroads);
+        let mut match_rule = MatchRule::new_method_call().with_path(root);
+        match_rule.path_is_namespace = true;
+        let token = connection.start_receive(
+            match_rule,
+            Box::new(move |message, connection| {
+                // Errors are sent back to the caller, there's nothing else to do with them.
+                let _ = crossroads
+                    .lock()
+                    .unwrap()
+                    .handle_message(message, connection);
+                true
+            }),
+        );
+        Ok(Self {
+            registrations: Registrations::new(connection.clone()),
+            connection,
+            token,
+        })
+    }
+
+    /// Calls a BlueZ method on the given adapter to register the object at `path`, which must be
+    /// one of ours, with no options.
+    pub async fn register_with(
+        &self,
+        adapter: &AdapterId,
+        interface: &'static str,
+        method: &'static str,
+        path: Path<'static>,
+    ) -> Result<()> {
+        self.register(
+            Path::from(adapter.clone()),
+            interface,
+            method,
+            path.clone(),
+            (path, PropMap::new()),
+        )
+        .await
+    }
+
+    /// Calls the BlueZ method `method` on the object at `object` to register the object at `path`,
+    /// which must be one of ours. It is unregistered with the matching `Unregister` method, which
+    /// takes only its path, when this is dropped.
+    pub async fn register(
+        &self,
+        object: Path<'static>,
+        interface: &'static str,
+        method: &'static str,
+        path: Path<'static>,
+        args: impl AppendAll,
+    ) -> Result<()> {
+        self.call(object.clone(), interface, method, args).await?;
+        self.registrations.add(Registration {
+            object,
+            interface,
+            unregister_method: format!(
+                "Unregister{}",
+                method
+                    .strip_prefix("Register")
+                    .expect("Not a BlueZ register method")
+            ),
+            path,
+        });
+        Ok(())
+    }
+
+    /// Calls a BlueZ method on the object at `object`. BlueZ ties whatever is registered this way
+    /// to the connection it was registered from, which is why this goes through ours.
+    pub async fn call(
+        &self,
+        object: Path<'static>,
+        interface: &str,
+        method: &str,
+        args: impl AppendAll,
+    ) -> Result<()> {
+        let proxy = Proxy::new("org.bluez", object, DBUS_TIMEOUT, self.connection.clone());
+        proxy
+            .method_call(interface, method, args)
+            .await
+            .map_err(Error::from)
+    }
+
+    /// Sends a message, such as a signal from one of our objects.
+    pub fn send(&self, message: Message) -> Result<()> {
+        self.connection
+            .send(message)
+            .map(|_| ())
+            .map_err(|()| Error::Other("Failed to send D-Bus message".into()))
+    }
+}
+
+impl Drop for ExportedObjects {
+    fn drop(&mut self) {
+        self.connection.stop_receive(self.token);
+    }
+}
+
+/// Something registered with BlueZ: the object at `path` with the BlueZ object at `object`.
+struct Registration {
+    object: Path<'static>,
+    interface: &'static str,
+    unregister_method: String,
+    path: Path<'static>,
+}
+
+impl Registration {
+    fn unregister_message(&self) -> Message {
+        Message::new_method_call(
+            "org.bluez",
+            &self.object,
+            self.interface,
+            &*self.unregister_method,
+        )
+        .expect("Invalid D-Bus method call")
+        .append1(&self.path)
+    }
+}
+
+/// The registrations made through a connection, which are undone in reverse order when this is
+/// dropped. The calls are sent without waiting for their replies, as there's nothing to do about
+/// failures.
+struct Registrations {
+    sender: Arc<dyn Sender + Send + Sync>,
+    registrations: Mutex<Vec<Registration>>,
+}
+
+impl Registrations {
+    fn new(sender: Arc<dyn Sender + Send + Sync>) -> Self {
+        Self {
+            sender,
+            registrations: Mutex::new(Vec::new()),
+        }
+    }
+
+    fn add(&self, registration: Registration) {
+        self.registrations.lock().unwrap().push(registration);
+    }
+}
+
+impl Drop for Registrations {
+    fn drop(&mut self) {
+        let registrations = self.registrations.get_mut().unwrap();
+        while let Some(registration) = registrations.pop() {
+            if self.sender.send(registration.unregister_message()).is_err() {
+                error!("Failed to unregister {} from BlueZ", registration.path);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct RecordingSender {
+        sent: Mutex<Vec<Message>>,
+    }
+
+    impl Sender for RecordingSender {
+        fn send(&self, message: Message) -> std::result::Result<u32, ()> {
+            self.sent.lock().unwrap().push(message);
+            Ok(0)
+        }
+    }
+
+    #[test]
+    fn unique_paths() {
+        let first = unique_path("/org/btleplug/gatt");
+        let second = unique_path("/org/btleplug/gatt");
+        assert!(first.starts_with("/org/btleplug/gatt"));
+        assert!(second.starts_with("/org/btleplug/gatt"));
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn dropping_unregisters_in_reverse_order() {
+        let sender = Arc::new(RecordingSender::default());
+        let registrations = Registrations::new(sender.clone());
+        registrations.add(Registration {
+            object: Path::from("/org/bluez"),
+            interface: "org.bluez.AgentManager1",
+            unregister_method: "UnregisterAgent".to_string(),
+            path: Path::from("/org/btleplug/agent"),
+        });
+        registrations.add(Registration {
+            object: Path::from("/org/bluez/hci0"),
+            interface: "org.bluez.GattManager1",
+            unregister_method: "UnregisterApplication".to_string(),
+            path: Path::from("/org/btleplug/gatt"),
+        });
+        assert!(sender.sent.lock().unwrap().is_empty());
+
+        drop(registrations);
+        let sent = sender.sent.lock().unwrap();
+        let calls: Vec<_> = sent
+            .iter()
+            .map(|message| {
+                (
+                    message.destination().unwrap().to_string(),
+                    message.path().unwrap().to_string(),
+                    message.interface().unwrap().to_string(),
+                    message.member().unwrap().to_string(),
+                    message.read1::<Path>().unwrap().to_string(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            calls,
+            vec![
+                (
+                    "org.bluez".to_string(),
+                    "/org/bluez/hci0".to_string(),
+                    "org.bluez.GattManager1".to_string(),
+                    "UnregisterApplication".to_string(),
+                    "/org/btleplug/gatt".to_string(),
+                ),
+                (
+                    "org.bluez".to_string(),
+                    "/org/bluez".to_string(),
+                    "org.bluez.AgentManager1".to_string(),
+                    "UnregisterAgent".to_string(),
+                    "/org/btleplug/agent".to_string(),
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,538 @@
+use super::bus::SystemBus;
+use super::export::{unique_path, ExportedObjects};
+use crate::api::{
+    self, AttErrorCode, CharPropFlags, Characteristic, GattRequestHandler, GattServerEvent,
+    LocalService, WriteType,
+};
+use crate::common::gatt_server::LocalDatabase;
+use crate::Result;
+use async_trait::async_trait;
+use bluez_async::AdapterId;
+use dbus::arg::{AppendAll, PropMap, RefArg, Variant};
+use dbus::message::SignalArgs;
+use dbus::nonblock::stdintf::org_freedesktop_dbus::PropertiesPropertiesChanged;
+use dbus::{MethodErr, Path};
+use dbus_crossroads::{Context, Crossroads, IfaceBuilder};
+use futures::stream::Stream;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::{self, Debug, Formatter};
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::sync::Arc;
+use uuid::Uuid;
+
+const APPLICATION_PATH: &str = "/org/btleplug/gatt";
+const SERVICE_INTERFACE: &str = "org.bluez.GattService1";
+const CHARACTERISTIC_INTERFACE: &str = "org.bluez.GattCharacteristic1";
+const DESCRIPTOR_INTERFACE: &str = "org.bluez.GattDescriptor1";
+
+/// Implementation of [api::GattApplication](crate::api::GattApplication).
+///
+/// The services are exported on the connection of the adapter's `SystemBus` rather than on that
+/// of its BlueZ session, which bluez-async keeps to itself.
+pub struct GattApplication {
+    database: Arc<LocalDatabase>,
+    characteristic_paths: HashMap<u16, Path<'static>>,
+    objects: ExportedObjects,
+}
+
+impl GattApplication {
+    pub(crate) async fn register(
+        bus: &SystemBus,
+        adapter: AdapterId,
+        services: Vec<LocalService>,
+        handler: Arc<dyn GattRequestHandler>,
+    ) -> Result<Self> {
+        let database = Arc::new(LocalDatabase::new(services, handler)?);
+        let application_path = unique_path(APPLICATION_PATH);
+        let mut crossroads = Crossroads::new();
+        let characteristic_paths = export(&mut crossroads, &application_path, &database);
+        let objects = ExportedObjects::new(bus, application_path.clone(), crossroads).await?;
+        // BlueZ only replies once it has fetched the objects of the application from us.
+        objects
+            .register_with(
+                &adapter,
+                "org.bluez.GattManager1",
+                "RegisterApplication",
+                application_path,
+            )
+            .await?;
+        Ok(GattApplication {
+            database,
+            characteristic_paths,
+            objects,
+        })
+    }
+}
+
+impl Debug for GattApplication {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("GattApplication")
+            .field("database", &self.database)
+            .finish()
+    }
+}
+
+#[async_trait]
+impl api::GattApplication for GattApplication {
+    fn characteristics(&self) -> BTreeSet<Characteristic> {
+        self.database.characteristics()
+    }
+
+    async fn events(&self) -> Result<Pin<Box<dyn Stream<Item = GattServerEvent> + Send>>> {
+        Ok(self.database.events())
+    }
+
+    async fn value(&self, characteristic: &Characteristic) -> Result<Vec<u8>> {
+        self.database.value(characteristic)
+    }
+
+    async fn set_value(&self, characteristic: &Characteristic, value: Vec<u8>) -> Result<()> {
+        if self.database.set_value(characteristic, value.clone())? {
+            // BlueZ sends a notification or indication whenever the value changes while notifying.
+            let path = &self.characteristic_paths[&characteristic.handle];
+            let mut changed_properties = PropMap::new();
+            changed_properties.insert("Value".to_string(), Variant(Box::new(value)));
+            let signal = PropertiesPropertiesChanged {
+                interface_name: CHARACTERISTIC_INTERFACE.to_string(),
+                changed_properties,
+                invalidated_properties: vec![],
+            };
+            self.objects.send(signal.to_emit_message(path))?;
+        }
+        Ok(())
+    }
+}
+
+struct ServiceObject {
+    uuid: Uuid,
+    primary: bool,
+}
+
+struct CharacteristicObject {
+    database: Arc<LocalDatabase>,
+    service_path: Path<'static>,
+    handle: u16,
+    uuid: Uuid,
+    flags: Vec<String>,
+}
+
+struct DescriptorObject {
+    database: Arc<LocalDatabase>,
+    characteristic_path: Path<'static>,
+    handle: u16,
+    uuid: Uuid,
+}
+
+/// Adds D-Bus objects for all the services, characteristics and descriptors of the database to the
+/// given crossroads, under an object manager at `application_path`. Returns the paths of the
+/// characteristics by handle.
+///
+/// Reads and writes of characteristics go through the application's handler, so they're answered
+/// asynchronously and the crossroads needs async support.
+fn export(
+    crossroads: &mut Crossroads,
+    application_path: &Path<'static>,
+    database: &Arc<LocalDatabase>,
+) -> HashMap<u16, Path<'static>> {
+    let service_token =
+        crossroads.register(SERVICE_INTERFACE, |b: &mut IfaceBuilder<ServiceObject>| {
+            b.property("UUID")
+                .get(|_, service| Ok(service.uuid.to_string()));
+            b.property("Primary").get(|_, service| Ok(service.primary));
+        });
+    let characteristic_token = crossroads.register(
+        CHARACTERISTIC_INTERFACE,
+        |b: &mut IfaceBuilder<CharacteristicObject>| {
+            b.property("UUID")
+                .get(|_, characteristic| Ok(characteristic.uuid.to_string()));
+            b.property("Service")
+                .get(|_, characteristic| Ok(characteristic.service_path.clone()));
+            b.property("Flags")
+                .get(|_, characteristic| Ok(characteristic.flags.clone()));
+            b.property("Notifying").get(|_, characteristic| {
+                Ok(characteristic.database.is_notifying(characteristic.handle))
+            });
+            b.method_with_cr_async(
+                "ReadValue",
+                ("options",),
+                ("value",),
+                |ctx, crossroads, (options,): (PropMap,)| {
+                    answer(ctx, crossroads, |database, handle| async move {
+                        let value = database.read(handle, offset(&options)).await?;
+                        Ok((value,))
+                    })
+                },
+            );
+            b.method_with_cr_async(
+                "WriteValue",
+                ("value", "options"),
+                (),
+                |ctx, crossroads, (value, options): (Vec<u8>, PropMap)| {
+                    let write_type = match options.get("type").and_then(|t| t.0.as_str()) {
+                        Some("command") => WriteType::WithoutResponse,
+                        _ => WriteType::WithResponse,
+                    };
+                    answer(ctx, crossroads, move |database, handle| async move {
+                        database
+                            .write(handle, &value, offset(&options), write_type)
+                            .await
+                    })
+                },
+            );
+            b.method("StartNotify", (), (), |_, characteristic, ()| {
+                characteristic
+                    .database
+                    .set_notifying(characteristic.handle, true)
+                    .map_err(method_error)
+            });
+            b.method("StopNotify", (), (), |_, characteristic, ()| {
+                characteristic
+                    .database
+                    .set_notifying(characteristic.handle, false)
+                    .map_err(method_error)
+            });
+        },
+    );
+    let descriptor_token = crossroads.register(
+        DESCRIPTOR_INTERFACE,
+        |b: &mut IfaceBuilder<DescriptorObject>| {
+            b.property("UUID")
+                .get(|_, descriptor| Ok(descriptor.uuid.to_string()));
+            b.property("Characteristic")
+                .get(|_, descriptor| Ok(descriptor.characteristic_path.clone()));
+            b.property("Flags").get(|_, _| Ok(vec!["read".to_string()]));
+            b.method(
+                "ReadValue",
+                ("options",),
+                ("value",),
+                |_, descriptor, (options,): (PropMap,)| {
+                    let value = descriptor
+                        .database
+                        .read_descriptor(descriptor.handle, offset(&options))
+                        .map_err(method_error)?;
+                    Ok((value,))
+                },
+            );
+        },
+    );
+
+    let object_manager = crossroads.object_manager();
+    crossroads.insert(application_path.clone(), &[object_manager], ());
+
+    let mut characteristic_paths = HashMap::new();
+    for (i, service) in database.services().iter().enumerate() {
+        let service_path = Path::from(format!("{}/service{}", application_path, i));
+        crossroads.insert(
+            service_path.clone(),
+            &[service_token],
+            ServiceObject {
+                uuid: service.uuid,
+                primary: service.primary,
+            },
+        );
+        for (j, characteristic) in service.characteristics.iter().enumerate() {
+            let characteristic_path = Path::from(format!("{}/char{}", service_path, j));
+            crossroads.insert(
+                characteristic_path.clone(),
+                &[characteristic_token],
+                CharacteristicObject {
+                    database: database.clone(),
+                    service_path: service_path.clone(),
+                    handle: characteristic.handle,
+                    uuid: characteristic.uuid,
+                    flags: flags(characteristic.properties),
+                },
+            );
+            for (k, descriptor) in characteristic.descriptors.iter().enumerate() {
+                crossroads.insert(
+                    format!("{}/desc{}", characteristic_path, k),
+                    &[descriptor_token],
+                    DescriptorObject {
+                        database: database.clone(),
+                        characteristic_path: characteristic_path.clone(),
+                        handle: descriptor.handle,
+                        uuid: descriptor.uuid,
+                    },
+                );
+            }
+            characteristic_paths.insert(characteristic.handle, characteristic_path);
+        }
+    }
+    characteristic_paths
+}
+
+/// Replies to a method call on a characteristic object with the result of `request`, which is
+/// given the database and handle of the characteristic.
+fn answer<OA, F, R>(
+    mut ctx: Context,
+    crossroads: &mut Crossroads,
+    request: F,
+) -> impl Future<Output = PhantomData<OA>>
+where
+    OA: AppendAll,
+    F: FnOnce(Arc<LocalDatabase>, u16) -> R,
+    R: Future<Output = std::result::Result<OA, AttErrorCode>>,
+{
+    let characteristic = crossroads
+        .data_mut::<CharacteristicObject>(ctx.path())
+        .map(|characteristic| (characteristic.database.clone(), characteristic.handle));
+    async move {
+        let result = match characteristic {
+            Some((database, handle)) => request(database, handle).await.map_err(method_error),
+            None => Err(MethodErr::no_path(ctx.path())),
+        };
+        ctx.reply(result)
+    }
+}
+/// Converts characteristic properties to the flags BlueZ expects.
+fn flags(properties: CharPropFlags) -> Vec<String> {
+    [
+        (CharPropFlags::BROADCAST, "broadcast"),
+        (CharPropFlags::READ, "read"),
+        (
+            CharPropFlags::WRITE_WITHOUT_RESPONSE,
+            "write-without-response",
+        ),
+        (CharPropFlags::WRITE, "write"),
+        (CharPropFlags::NOTIFY, "notify"),
+        (CharPropFlags::INDICATE, "indicate"),
+        (
+            CharPropFlags::AUTHENTICATED_SIGNED_WRITES,
+            "authenticated-signed-writes",
+        ),
+        (CharPropFlags::EXTENDED_PROPERTIES, "extended-properties"),
+    ]
+    .into_iter()
+    .filter(|(flag, _)| properties.contains(*flag))
+    .map(|(_, name)| name.to_string())
+    .collect()
+}
+
+fn offset(options: &PropMap) -> usize {
+    options
+        .get("offset")
+        .and_then(|offset| offset.0.as_u64())
+        .unwrap_or_default() as usize
+}
+
+/// Converts an ATT error code to the D-Bus error BlueZ answers the remote central with. BlueZ has
+/// D-Bus errors for a few codes, and otherwise only passes on application error codes, taken from
+/// the message of a `Failed` error; other codes become application error 0x80.
+fn method_error(error: AttErrorCode) -> MethodErr {
+    let name = match error {
+        AttErrorCode::ReadNotPermitted | AttErrorCode::WriteNotPermitted => {
+            "org.bluez.Error.NotPermitted"
+        }
+        AttErrorCode::InvalidOffset => "org.bluez.Error.InvalidOffset",
+        AttErrorCode::InvalidAttributeValueLength => "org.bluez.Error.InvalidValueLength",
+        AttErrorCode::InsufficientAuthorization => "org.bluez.Error.NotAuthorized",
+        AttErrorCode::RequestNotSupported => "org.bluez.Error.NotSupported",
+        _ => "org.bluez.Error.Failed",
+    };
+    (name, format!("0x{:02X}", u8::from(error))).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{LocalCharacteristic, LocalDescriptor};
+    use dbus::channel::Sender;
+    use dbus::Message;
+    use futures::channel::mpsc::{self, UnboundedReceiver, UnboundedSender};
+    use futures::StreamExt;
+
+    const SERVICE: Uuid = Uuid::from_u128(0x1);
+    const CHARACTERISTIC: Uuid = Uuid::from_u128(0x2);
+
+    /// Sends the replies of the crossroads to a channel.
+    struct Replies(UnboundedSender<Message>);
+
+    impl Sender for Replies {
+        fn send(&self, message: Message) -> std::result::Result<u32, ()> {
+            self.0.unbounded_send(message).map(|_| 0).map_err(|_| ())
+        }
+    }
+
+    #[derive(Debug)]
+    struct StoredValues;
+
+    impl GattRequestHandler for StoredValues {}
+
+    /// Rejects every write with an application error.
+    #[derive(Debug)]
+    struct ReadOnly;
+
+    #[async_trait]
+    impl GattRequestHandler for ReadOnly {
+        async fn write(
+            &self,
+            _characteristic: &Characteristic,
+            _value: &[u8],
+            _write_type: WriteType,
+        ) -> std::result::Result<(), AttErrorCode> {
+            Err(AttErrorCode::Application(0x81))
+        }
+    }
+
+    /// A GATT application exported to a crossroads, as BlueZ sees it.
+    struct Exported {
+        crossroads: Crossroads,
+        database: Arc<LocalDatabase>,
+        characteristic_path: Path<'static>,
+        replies: Arc<Replies>,
+        received: UnboundedReceiver<Message>,
+    }
+
+    impl Exported {
+        fn new(properties: CharPropFlags, handler: Arc<dyn GattRequestHandler>) -> Self {
+            let database = Arc::new(
+                LocalDatabase::new(
+                    vec![LocalService {
+                        uuid: SERVICE,
+                        primary: true,
+                        characteristics: vec![LocalCharacteristic {
+                            uuid: CHARACTERISTIC,
+                            properties,
+                            value: vec![1, 2, 3],
+                            descriptors: vec![LocalDescriptor {
+                                uuid: Uuid::from_u128(0x3),
+                                value: vec![4],
+                            }],
+                        }],
+                    }],
+                    handler,
+                )
+                .unwrap(),
+            );
+            let (sender, received) = mpsc::unbounded();
+            let replies = Arc::new(Replies(sender));
+            let mut crossroads = Crossroads::new();
+            crossroads.set_async_support(Some((
+                replies.clone(),
+                Box::new(|method| {
+                    tokio::spawn(method);
+                }),
+            )));
+            let application_path = Path::from("/org/btleplug/gatt");
+            let characteristic_paths = export(&mut crossroads, &application_path, &database);
+            Self {
+                crossroads,
+                database,
+                characteristic_path: characteristic_paths[&2].clone(),
+                replies,
+                received,
+            }
+        }
+
+        /// Calls a method of the characteristic, returning the reply.
+        async fn call(&mut self, method: &str, args: impl AppendAll) -> Message {
+            let mut message = Message::new_method_call(
+                ":1.0",
+                &self.characteristic_path,
+                CHARACTERISTIC_INTERFACE,
+                method,
+            )
+            .unwrap();
+            message.append_all(args);
+            message.set_serial(1);
+            self.crossroads
+                .handle_message(message, &*self.replies)
+                .unwrap();
+            self.received.next().await.unwrap()
+        }
+    }
+
+    fn options(offset: u16, write_type: Option<&str>) -> PropMap {
+        let mut options = PropMap::new();
+        options.insert("offset".to_string(), Variant(Box::new(offset)));
+        if let Some(write_type) = write_type {
+            options.insert(
+                "type".to_string(),
+                Variant(Box::new(write_type.to_string())),
+            );
+        }
+        options
+    }
+
+    fn error(mut reply: Message) -> (String, String) {
+        let error = reply.as_result().unwrap_err();
+        (
+            error.name().unwrap().to_string(),
+            error.message().unwrap().to_string(),
+        )
+    }
+
+    #[tokio::test]
+    async fn read_value() {
+        let mut exported = Exported::new(CharPropFlags::READ, Arc::new(StoredValues));
+        let reply = exported.call("ReadValue", (options(1, None),)).await;
+        assert_eq!(reply.read1::<Vec<u8>>().unwrap(), vec![2, 3]);
+        let reply = exported.call("ReadValue", (options(4, None),)).await;
+        assert_eq!(
+            error(reply),
+            (
+                "org.bluez.Error.InvalidOffset".to_string(),
+                "0x07".to_string()
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn write_value() {
+        let mut exported = Exported::new(CharPropFlags::WRITE, Arc::new(StoredValues));
+        let mut events = exported.database.events();
+        let mut reply = exported
+            .call("WriteValue", (vec![9u8], options(1, None)))
+            .await;
+        reply.as_result().unwrap();
+        assert!(matches!(
+            events.next().await,
+            Some(GattServerEvent::CharacteristicWritten {
+                value,
+                write_type: WriteType::WithResponse,
+                ..
+            }) if value == vec![1, 9]
+        ));
+        let reply = exported
+            .call("WriteValue", (vec![9u8], options(0, Some("command"))))
+            .await;
+        assert_eq!(error(reply).0, "org.bluez.Error.NotPermitted");
+    }
+
+    #[tokio::test]
+    async fn rejected_write_returns_application_error() {
+        let mut exported = Exported::new(CharPropFlags::WRITE, Arc::new(ReadOnly));
+        let reply = exported
+            .call("WriteValue", (vec![9u8], options(0, None)))
+            .await;
+        assert_eq!(
+            error(reply),
+            ("org.bluez.Error.Failed".to_string(), "0x81".to_string())
+        );
+        let characteristic = exported.database.characteristics().pop_first().unwrap();
+        assert_eq!(
+            exported.database.value(&characteristic).unwrap(),
+            vec![1, 2, 3]
+        );
+    }
+
+    #[tokio::test]
+    async fn start_notify() {
+        let mut exported = Exported::new(CharPropFlags::NOTIFY, Arc::new(StoredValues));
+        let mut events = exported.database.events();
+        exported.call("StartNotify", ()).await.as_result().unwrap();
+        assert!(exported.database.is_notifying(2));
+        assert!(matches!(
+            events.next().await,
+            Some(GattServerEvent::Subscribed { characteristic }) if characteristic.handle == 2
+        ));
+        exported.call("StopNotify", ()).await.as_result().unwrap();
+        assert!(!exported.database.is_notifying(2));
+
+        let mut exported = Exported::new(CharPropFlags::READ, Arc::new(StoredValues));
+        let reply = exported.call("StartNotify", ()).await;
+        assert_eq!(error(reply).0, "org.bluez.Error.NotSupported");
+    }
+}
@@ -1,5 +1,8 @@
 use super::adapter::Adapter;
-use crate::{api, Result};
+use super::bus::SystemBus;
+use crate::api::{self, Timeouts};
+use crate::common::timeouts::SharedTimeouts;
+use crate::Result;
 use async_trait::async_trait;
 use bluez_async::BluetoothSession;
 
@@ -7,12 +10,18 @@ use bluez_async::BluetoothSession;
 #[derive(Clone, Debug)]
 pub struct Manager {
     session: BluetoothSession,
+    timeouts: SharedTimeouts,
+    bus: SystemBus,
 }
 
 impl Manager {
     pub async fn new() -> Result<Self> {
         let (_, session) = BluetoothSession::new().await?;
-        Ok(Self { session })
+        Ok(Self {
+            session,
+            timeouts: Default::default(),
+            bus: Default::default(),
+        })
     }
 }
 
@@ -24,7 +33,22 @@ impl api::Manager for Manager {
         let adapters = self.session.get_adapters().await?;
         Ok(adapters
             .into_iter()
-            .map(|adapter| Adapter::new(self.session.clone(), adapter.id))
+            .map(|adapter| {
+                Adapter::new(
+                    self.session.clone(),
+                    adapter.id,
+                    self.timeouts.clone(),
+                    self.bus.clone(),
+                )
+            })
             .collect())
     }
+
+    fn timeouts(&self) -> Timeouts {
+        *self.timeouts.read().unwrap()
+    }
+
+    fn set_timeouts(&self, timeouts: Timeouts) {
+        *self.timeouts.write().unwrap() = timeouts;
+    }
 }
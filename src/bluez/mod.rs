@@ -1,3 +1,10 @@
 pub mod adapter;
+pub mod advertising;
+pub mod agent;
+mod bus;
+mod error;
+mod export;
+pub mod gatt_server;
 pub mod manager;
+mod monitor;
 pub mod peripheral;
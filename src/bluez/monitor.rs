@@ -0,0 +1,340 @@
+use super::bus::SystemBus;
+use super::export::{unique_path, ExportedObjects};
+use crate::api::advertisement::ad_type;
+use crate::api::bleuuid::BleUuid;
+use crate::api::{NameFilter, ScanFilter};
+use crate::{Error, Result};
+use bluez_async::AdapterId;
+use dbus::Path;
+use dbus_crossroads::{Crossroads, IfaceBuilder};
+use std::fmt::{self, Debug, Formatter};
+use uuid::Uuid;
+
+const APPLICATION_PATH: &str = "/org/btleplug/monitor";
+const MONITOR_INTERFACE: &str = "org.bluez.AdvertisementMonitor1";
+const MONITOR_MANAGER_INTERFACE: &str = "org.bluez.AdvertisementMonitorManager1";
+
+/// The most data an AD structure of a legacy advertisement can hold.
+const MAX_AD_DATA_LENGTH: usize = 29;
+
+/// The position in an AD structure to match, its AD type and the bytes to match there.
+type Pattern = (u8, u8, Vec<u8>);
+
+/// A handle to an advertisement monitor registered with BlueZ, which makes it scan passively for
+/// matching devices while no discovery is in progress. BlueZ adds the devices it finds to the
+/// adapter as discovery would. The monitor is unregistered when this is dropped.
+pub struct MonitorHandle {
+    patterns: Vec<Pattern>,
+    _objects: ExportedObjects,
+}
+
+impl MonitorHandle {
+    pub(crate) async fn register(
+        bus: &SystemBus,
+        adapter: AdapterId,
+        filter: &ScanFilter,
+    ) -> Result<Self> {
+        let monitor_types: Vec<String> = bus
+            .get_property(
+                Path::from(adapter.clone()),
+                MONITOR_MANAGER_INTERFACE,
+                "SupportedMonitorTypes",
+            )
+            .await?;
+        if !monitor_types.iter().any(|t| t == "or_patterns") {
+            return Err(Error::NotSupported(
+                "BlueZ doesn't support pattern monitors on this adapter".to_string(),
+            ));
+        }
+        let patterns = patterns(filter);
+        if patterns.is_empty() {
+            return Err(Error::NotSupported(
+                "Passive scanning needs a filter on services, name, manufacturer data or service data"
+                    .to_string(),
+            ));
+        }
+
+        let mut crossroads = Crossroads::new();
+        let token = crossroads.register(MONITOR_INTERFACE, |b: &mut IfaceBuilder<Vec<Pattern>>| {
+            b.property("Type").get(|_, _| Ok("or_patterns".to_string()));
+            b.property("Patterns")
+                .get(|_, patterns| Ok(patterns.clone()));
+            // BlueZ reports the devices it finds through the adapter anyway, so there's nothing to
+            // do on any of these.
+            b.method("Release", (), (), |_, _, ()| Ok(()));
+            b.method("Activate", (), (), |_, _, ()| Ok(()));
+            b.method(
+                "DeviceFound",
+                ("device",),
+                (),
+                |_, _, (_,): (Path<'static>,)| Ok(()),
+            );
+            b.method(
+                "DeviceLost",
+                ("device",),
+                (),
+                |_, _, (_,): (Path<'static>,)| Ok(()),
+            );
+        });
+        let object_manager = crossroads.object_manager();
+        let application_path = unique_path(APPLICATION_PATH);
+        let monitor_path = Path::from(format!("{}/monitor0", application_path));
+        crossroads.insert(
+            application_path.clone(),
+            &[object_manager],
+            patterns.clone(),
+        );
+        crossroads.insert(monitor_path, &[token], patterns.clone());
+
+        let objects = ExportedObjects::new(bus, application_path.clone(), crossroads).await?;
+        objects
+            .register(
+                Path::from(adapter),
+                MONITOR_MANAGER_INTERFACE,
+                "RegisterMonitor",
+                application_path.clone(),
+                (application_path,),
+            )
+            .await?;
+        Ok(Self {
+            patterns,
+            _objects: objects,
+        })
+    }
+}
+
+impl Debug for MonitorHandle {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("MonitorHandle")
+            .field("patterns", &self.patterns)
+            .finish()
+    }
+}
+
+/// Derives the patterns of a monitor from one of the criteria of the filter which can be matched
+/// against advertising data. Devices must match every criterion, so those matching the patterns of
+/// any one of them include all matching devices; the whole filter is applied to the events of the
+/// adapter afterwards. The criteria most likely to single out the devices are preferred.
+fn patterns(filter: &ScanFilter) -> Vec<Pattern> {
+    let criteria = [
+        manufacturer_data_patterns(filter),
+        service_data_patterns(filter),
+        service_patterns(filter),
+        name_patterns(filter),
+    ];
+    criteria
+        .into_iter()
+        .find(|patterns| !patterns.is_empty())
+        .unwrap_or_default()
+}
+
+fn manufacturer_data_patterns(filter: &ScanFilter) -> Vec<Pattern> {
+    filter
+        .manufacturer_data
+        .iter()
+        .map(|manufacturer_data| {
+            // Only the bytes up to the first which is masked can be matched.
+            let matched = manufacturer_data
+                .data
+                .iter()
+                .enumerate()
+                .take_while(|(i, _)| {
+                    manufacturer_data
+                        .mask
+                        .get(*i)
+                        .map_or(true, |&mask| mask == 0xff)
+                })
+                .map(|(_, byte)| *byte);
+            let content = manufacturer_data
+                .company_id
+                .to_le_bytes()
+                .into_iter()
+                .chain(matched)
+                .collect();
+            (0, ad_type::MANUFACTURER_SPECIFIC_DATA, truncate(content))
+        })
+        .collect()
+}
+
+fn service_data_patterns(filter: &ScanFilter) -> Vec<Pattern> {
+    filter
+        .service_data
+        .iter()
+        .map(|service| {
+            let (bytes, _) = uuid_bytes(service);
+            let ad_type = match bytes.len() {
+                2 => ad_type::SERVICE_DATA_16,
+                4 => ad_type::SERVICE_DATA_32,
+                _ => ad_type::SERVICE_DATA_128,
+            };
+            (0, ad_type, bytes)
+        })
+        .collect()
+}
+
+/// BlueZ matches a pattern at a fixed position in an AD structure, so there is one for each
+/// position a service can take in a list of services.
+fn service_patterns(filter: &ScanFilter) -> Vec<Pattern> {
+    let mut patterns = Vec::new();
+    for service in &filter.services {
+        let (bytes, ad_types) = uuid_bytes(service);
+        for ad_type in ad_types {
+            for position in (0..=MAX_AD_DATA_LENGTH - bytes.len()).step_by(bytes.len()) {
+                patterns.push((position as u8, ad_type, bytes.clone()));
+            }
+        }
+    }
+    patterns
+}
+
+fn name_patterns(filter: &ScanFilter) -> Vec<Pattern> {
+    match &filter.name {
+        // An empty prefix matches any name, which a pattern can't express.
+        Some(NameFilter::Exact(name) | NameFilter::Prefix(name)) if !name.is_empty() => {
+            [ad_type::SHORTENED_LOCAL_NAME, ad_type::COMPLETE_LOCAL_NAME]
+                .into_iter()
+                .map(|ad_type| (0, ad_type, truncate(name.as_bytes().to_vec())))
+                .collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Returns the shortest form of the UUID as it appears in advertising data, and the AD types of
+/// the lists of services it may appear in.
+fn uuid_bytes(uuid: &Uuid) -> (Vec<u8>, [u8; 2]) {
+    match uuid.to_ble_u32() {
+        Some(short) => match u16::try_from(short) {
+            Ok(short) => (
+                short.to_le_bytes().to_vec(),
+                [
+                    ad_type::INCOMPLETE_SERVICE_UUIDS_16,
+                    ad_type::COMPLETE_SERVICE_UUIDS_16,
+                ],
+            ),
+            Err(_) => (
+                short.to_le_bytes().to_vec(),
+                [
+                    ad_type::INCOMPLETE_SERVICE_UUIDS_32,
+                    ad_type::COMPLETE_SERVICE_UUIDS_32,
+                ],
+            ),
+        },
+        None => (
+            uuid.as_u128().to_le_bytes().to_vec(),
+            [
+                ad_type::INCOMPLETE_SERVICE_UUIDS_128,
+                ad_type::COMPLETE_SERVICE_UUIDS_128,
+            ],
+        ),
+    }
+}
+
+/// Patterns longer than an AD structure can't match, and matching their start is good enough.
+fn truncate(mut content: Vec<u8>) -> Vec<u8> {
+    content.truncate(MAX_AD_DATA_LENGTH);
+    content
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::bleuuid::uuid_from_u16;
+    use crate::api::ManufacturerDataFilter;
+
+    #[test]
+    fn manufacturer_data_is_matched_up_to_the_first_masked_byte() {
+        let filter = ScanFilter {
+            manufacturer_data: vec![ManufacturerDataFilter {
+                company_id: 0x004c,
+                data: vec![0x02, 0x15, 0xaa, 0xbb],
+                mask: vec![0xff, 0xff, 0x0f, 0xff],
+            }],
+            ..Default::default()
+        };
+        assert_eq!(
+            manufacturer_data_patterns(&filter),
+            vec![(0, 0xff, vec![0x4c, 0x00, 0x02, 0x15])]
+        );
+    }
+
+    #[test]
+    fn service_data_uses_the_shortest_uuid() {
+        let filter = ScanFilter {
+            service_data: vec![
+                uuid_from_u16(0xfcd2),
+                Uuid::from_u128(0x12345678_0000_1000_8000_00805f9b34fb),
+                Uuid::from_u128(0x0102030405060708090a0b0c0d0e0f10),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(
+            service_data_patterns(&filter),
+            vec![
+                (0, 0x16, vec![0xd2, 0xfc]),
+                (0, 0x20, vec![0x78, 0x56, 0x34, 0x12]),
+                (0, 0x21, (1..=16).rev().collect()),
+            ]
+        );
+    }
+
+    #[test]
+    fn services_are_matched_at_every_position_in_a_list() {
+        let filter = ScanFilter {
+            services: vec![uuid_from_u16(0x180d)],
+            ..Default::default()
+        };
+        let patterns = service_patterns(&filter);
+        // 14 positions in each of the incomplete and complete lists of 16-bit UUIDs.
+        assert_eq!(patterns.len(), 28);
+        assert_eq!(patterns[0], (0, 0x02, vec![0x0d, 0x18]));
+        assert_eq!(patterns[1], (2, 0x02, vec![0x0d, 0x18]));
+        assert_eq!(patterns[13], (26, 0x02, vec![0x0d, 0x18]));
+        assert_eq!(patterns[14], (0, 0x03, vec![0x0d, 0x18]));
+
+        // A 128-bit UUID only fits once.
+        let filter = ScanFilter {
+            services: vec![Uuid::from_u128(0x0102030405060708090a0b0c0d0e0f10)],
+            ..Default::default()
+        };
+        assert_eq!(
+            service_patterns(&filter),
+            vec![
+                (0, 0x06, (1..=16).rev().collect()),
+                (0, 0x07, (1..=16).rev().collect()),
+            ]
+        );
+    }
+
+    #[test]
+    fn most_specific_criterion_is_used() {
+        let filter = ScanFilter {
+            services: vec![uuid_from_u16(0x180d)],
+            name: Some(NameFilter::Prefix("Polar".to_string())),
+            manufacturer_data: vec![ManufacturerDataFilter::company(0x006b)],
+            ..Default::default()
+        };
+        assert_eq!(patterns(&filter), vec![(0, 0xff, vec![0x6b, 0x00])]);
+
+        let filter = ScanFilter {
+            name: Some(NameFilter::Exact(
+                "A name longer than an AD structure".to_string(),
+            )),
+            ..Default::default()
+        };
+        assert_eq!(
+            patterns(&filter),
+            vec![
+                (0, 0x08, b"A name longer than an AD stru".to_vec()),
+                (0, 0x09, b"A name longer than an AD stru".to_vec()),
+            ]
+        );
+
+        // An empty prefix can't be expressed as a pattern.
+        let filter = ScanFilter {
+            name: Some(NameFilter::Prefix(String::new())),
+            ..Default::default()
+        };
+        assert!(patterns(&filter).is_empty());
+    }
+}
@@ -4,8 +4,10 @@ use bluez_async::{
     CharacteristicInfo, DescriptorInfo, DeviceId, DeviceInfo, MacAddress, ServiceInfo,
     WriteOptions,
 };
+use dbus::Path;
 use futures::future::{join_all, ready};
 use futures::stream::{Stream, StreamExt};
+use log::warn;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 #[cfg(feature = "serde")]
@@ -14,22 +16,32 @@ use std::collections::{BTreeSet, HashMap};
 use std::fmt::{self, Display, Formatter};
 use std::pin::Pin;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use uuid::Uuid;
 
+use super::bus::SystemBus;
 use crate::api::{
-    self, AddressType, BDAddr, CharPropFlags, Characteristic, Descriptor, PeripheralProperties,
-    Service, ValueNotification, WriteType,
+    self, proximity::Proximity, AddressType, BDAddr, CharPropFlags, Characteristic, Descriptor,
+    PeripheralProperties, Service, Timeouts, ValueNotification, WriteType,
 };
+use crate::common::proximity::ProximityTracker;
+use crate::common::subscriptions::{PeripheralSubscriptions, Subscriptions};
+use crate::common::timeouts::{self, timeout, SharedTimeouts};
 use crate::{Error, Result};
 
+const DBUS_TIMEOUT: Duration = Duration::from_secs(30);
+/// Pairing waits on the user, who may take a while to enter or confirm a passkey. BlueZ gives up
+/// on its own well before this.
+const PAIRING_TIMEOUT: Duration = Duration::from_secs(120);
+
 #[derive(Clone, Debug)]
 struct CharacteristicInternal {
     info: CharacteristicInfo,
-    descriptors: HashMap<Uuid, DescriptorInfo>,
+    descriptors: HashMap<u16, DescriptorInfo>,
 }
 
 impl CharacteristicInternal {
-    fn new(info: CharacteristicInfo, descriptors: HashMap<Uuid, DescriptorInfo>) -> Self {
+    fn new(info: CharacteristicInfo, descriptors: HashMap<u16, DescriptorInfo>) -> Self {
         Self { info, descriptors }
     }
 }
@@ -37,7 +49,22 @@ impl CharacteristicInternal {
 #[derive(Clone, Debug)]
 struct ServiceInternal {
     info: ServiceInfo,
-    characteristics: HashMap<Uuid, CharacteristicInternal>,
+    included_services: BTreeSet<u16>,
+    characteristics: HashMap<u16, CharacteristicInternal>,
+}
+
+/// Returns the ATT handle of a GATT object. BlueZ doesn't report it as a property on the client
+/// side, but names each object after it, as in `.../service0010/char0011/desc0013`. Objects whose
+/// name doesn't end with a handle can't be told apart from the others, so they're left out with a
+/// warning.
+fn attribute_handle(id: impl Into<Path<'static>>) -> Option<u16> {
+    let path = id.into();
+    let name = path.rsplit('/').next().unwrap_or_default();
+    let handle = u16::from_str_radix(name.trim_start_matches(char::is_alphabetic), 16).ok();
+    if handle.is_none() {
+        warn!("Ignoring GATT object {} without a handle in its path", path);
+    }
+    handle
 }
 
 #[cfg_attr(
@@ -54,32 +81,57 @@ impl Display for PeripheralId {
     }
 }
 
+#[cfg(any(test, feature = "mock"))]
+impl PeripheralId {
+    /// Builds the ID BlueZ would give a device with the given address on the named adapter (e.g.
+    /// `hci0`). bluez-async doesn't let us construct a `DeviceId` directly, so this goes through its
+    /// `Deserialize` implementation instead.
+    pub(crate) fn from_address(adapter: &str, address: BDAddr) -> Self {
+        use serde_cr::{de::value, Deserialize};
+
+        let object_path = format!(
+            "/org/bluez/{}/dev_{}",
+            adapter,
+            address.to_string().replace(':', "_")
+        );
+        let fields = std::iter::once(("object_path", object_path));
+        let device = DeviceId::deserialize(value::MapDeserializer::<_, value::Error>::new(fields))
+            .expect("Adapter name is not a valid D-Bus path element.");
+        PeripheralId(device)
+    }
+}
+
 /// Implementation of [api::Peripheral](crate::api::Peripheral).
 #[derive(Clone, Debug)]
 pub struct Peripheral {
     session: BluetoothSession,
     device: DeviceId,
     mac_address: BDAddr,
-    services: Arc<Mutex<HashMap<Uuid, ServiceInternal>>>,
+    services: Arc<Mutex<HashMap<u16, ServiceInternal>>>,
+    notification_streams: Arc<Subscriptions>,
+    timeouts: SharedTimeouts,
+    timeout_overrides: Timeouts,
+    bus: SystemBus,
+    proximity: Arc<ProximityTracker>,
 }
 
-fn get_characteristic<'a>(
-    services: &'a HashMap<Uuid, ServiceInternal>,
-    service_uuid: &Uuid,
-    characteristic_uuid: &Uuid,
-) -> Result<&'a CharacteristicInternal> {
+fn get_characteristic(
+    services: &HashMap<u16, ServiceInternal>,
+    service_handle: u16,
+    characteristic_handle: u16,
+) -> Result<&CharacteristicInternal> {
     services
-        .get(service_uuid)
+        .get(&service_handle)
         .ok_or_else(|| {
-            Error::Other(format!("Service with UUID {} not found.", service_uuid).into())
+            Error::Other(format!("Service with handle {:#06x} not found.", service_handle).into())
         })?
         .characteristics
-        .get(characteristic_uuid)
+        .get(&characteristic_handle)
         .ok_or_else(|| {
             Error::Other(
                 format!(
-                    "Characteristic with UUID {} not found.",
-                    characteristic_uuid
+                    "Characteristic with handle {:#06x} not found.",
+                    characteristic_handle
                 )
                 .into(),
             )
@@ -87,12 +139,24 @@ fn get_characteristic<'a>(
 }
 
 impl Peripheral {
-    pub(crate) fn new(session: BluetoothSession, device: DeviceInfo) -> Self {
+    pub(crate) fn new(
+        session: BluetoothSession,
+        device: DeviceInfo,
+        timeouts: SharedTimeouts,
+        bus: SystemBus,
+        proximity: Arc<ProximityTracker>,
+        notification_streams: &PeripheralSubscriptions,
+    ) -> Self {
         Peripheral {
+            notification_streams: notification_streams.get(&device.id.clone().into()),
             session,
             device: device.id,
             mac_address: device.mac_address.into(),
             services: Arc::new(Mutex::new(HashMap::new())),
+            timeouts,
+            timeout_overrides: Timeouts::default(),
+            bus,
+            proximity,
         }
     }
 
@@ -100,8 +164,8 @@ impl Peripheral {
         let services = self.services.lock().unwrap();
         get_characteristic(
             &services,
-            &characteristic.service_uuid,
-            &characteristic.uuid,
+            characteristic.service_handle,
+            characteristic.handle,
         )
         .map(|c| &c.info)
         .cloned()
@@ -111,18 +175,67 @@ impl Peripheral {
         let services = self.services.lock().unwrap();
         let characteristic = get_characteristic(
             &services,
-            &descriptor.service_uuid,
-            &descriptor.characteristic_uuid,
+            descriptor.service_handle,
+            descriptor.characteristic_handle,
         )?;
         characteristic
             .descriptors
-            .get(&descriptor.uuid)
+            .get(&descriptor.handle)
             .ok_or_else(|| {
-                Error::Other(format!("Descriptor with UUID {} not found.", descriptor.uuid).into())
+                Error::Other(
+                    format!(
+                        "Descriptor with handle {:#06x} not found.",
+                        descriptor.handle
+                    )
+                    .into(),
+                )
             })
             .cloned()
     }
 
+    /// Returns the ID of any GATT characteristic of the device, preferably one which has already
+    /// been discovered.
+    async fn any_characteristic(&self) -> Result<CharacteristicId> {
+        let discovered = self
+            .services
+            .lock()
+            .unwrap()
+            .values()
+            .flat_map(|service| service.characteristics.values())
+            .map(|characteristic| characteristic.info.id.clone())
+            .next();
+        if let Some(id) = discovered {
+            return Ok(id);
+        }
+        for service in self.session.get_services(&self.device).await? {
+            let characteristics = self.session.get_characteristics(&service.id).await?;
+            if let Some(characteristic) = characteristics.into_iter().next() {
+                return Ok(characteristic.id);
+            }
+        }
+        Err(Error::NotSupported(
+            "BlueZ only reports the MTU for devices with GATT characteristics".to_string(),
+        ))
+    }
+
+    /// Returns the handles of the services included by the given one, from the `Includes` property
+    /// which bluez-async doesn't expose. Older versions of BlueZ don't have the property at all.
+    async fn included_services(&self, service: &ServiceInfo) -> Result<BTreeSet<u16>> {
+        let includes: Vec<Path<'static>> = match self
+            .bus
+            .get_property(
+                service.id.clone().into(),
+                "org.bluez.GattService1",
+                "Includes",
+            )
+            .await
+        {
+            Err(Error::NotSupported(_)) => Vec::new(),
+            includes => includes?,
+        };
+        Ok(includes.into_iter().filter_map(attribute_handle).collect())
+    }
+
     async fn device_info(&self) -> Result<DeviceInfo> {
         Ok(self.session.get_device_info(&self.device).await?)
     }
@@ -140,35 +253,44 @@ impl api::Peripheral for Peripheral {
 
     async fn properties(&self) -> Result<Option<PeripheralProperties>> {
         let device_info = self.device_info().await?;
-        Ok(Some(PeripheralProperties {
-            address: device_info.mac_address.into(),
-            address_type: Some(device_info.address_type.into()),
-            local_name: device_info.name,
-            tx_power_level: device_info.tx_power,
-            rssi: device_info.rssi,
-            manufacturer_data: device_info.manufacturer_data,
-            service_data: device_info.service_data,
-            services: device_info.services,
-        }))
+        Ok(Some(device_properties(device_info)))
+    }
+
+    fn proximity(&self) -> Option<Proximity> {
+        self.proximity.proximity(&self.id())
     }
 
     fn services(&self) -> BTreeSet<Service> {
         self.services
             .lock()
             .unwrap()
-            .values()
-            .map(|service| service.into())
+            .iter()
+            .map(|(&handle, service)| make_service(service, handle))
             .collect()
     }
 
+    fn timeouts(&self) -> Timeouts {
+        self.timeouts.read().unwrap().merge(self.timeout_overrides)
+    }
+
+    fn with_timeouts(&self, timeouts: Timeouts) -> Self {
+        Self {
+            timeout_overrides: self.timeout_overrides.merge(timeouts),
+            ..self.clone()
+        }
+    }
+
     async fn is_connected(&self) -> Result<bool> {
         let device_info = self.device_info().await?;
         Ok(device_info.connected)
     }
 
     async fn connect(&self) -> Result<()> {
-        self.session.connect(&self.device).await?;
-        Ok(())
+        timeouts::connect(self, async {
+            self.session.connect(&self.device).await?;
+            Ok(())
+        })
+        .await
     }
 
     async fn disconnect(&self) -> Result<()> {
@@ -177,36 +299,106 @@ impl api::Peripheral for Peripheral {
     }
 
     async fn discover_services(&self) -> Result<()> {
-        let mut services_internal = HashMap::new();
-        let services = self.session.get_services(&self.device).await?;
-        for service in services {
-            let characteristics = self.session.get_characteristics(&service.id).await?;
-            let characteristics =
-                join_all(characteristics.into_iter().map(|characteristic| async {
-                    let descriptors = self
-                        .session
-                        .get_descriptors(&characteristic.id)
-                        .await
-                        .unwrap_or(Vec::new())
-                        .into_iter()
-                        .map(|descriptor| (descriptor.uuid, descriptor))
-                        .collect();
-                    CharacteristicInternal::new(characteristic, descriptors)
-                }))
-                .await;
-            services_internal.insert(
-                service.uuid,
-                ServiceInternal {
-                    info: service,
-                    characteristics: characteristics
-                        .into_iter()
-                        .map(|characteristic| (characteristic.info.uuid, characteristic))
-                        .collect(),
-                },
-            );
+        timeout(self.timeouts().discover_services, async {
+            let mut services_internal = HashMap::new();
+            let services = self.session.get_services(&self.device).await?;
+            for service in services {
+                let Some(handle) = attribute_handle(service.id.clone()) else {
+                    continue;
+                };
+                let characteristics = self.session.get_characteristics(&service.id).await?;
+                let characteristics =
+                    join_all(characteristics.into_iter().map(|characteristic| async {
+                        let descriptors = self
+                            .session
+                            .get_descriptors(&characteristic.id)
+                            .await
+                            .unwrap_or(Vec::new())
+                            .into_iter()
+                            .filter_map(|descriptor| {
+                                Some((attribute_handle(descriptor.id.clone())?, descriptor))
+                            })
+                            .collect();
+                        CharacteristicInternal::new(characteristic, descriptors)
+                    }))
+                    .await;
+                let included_services = self.included_services(&service).await?;
+                services_internal.insert(
+                    handle,
+                    ServiceInternal {
+                        info: service,
+                        included_services,
+                        characteristics: characteristics
+                            .into_iter()
+                            .filter_map(|characteristic| {
+                                Some((
+                                    attribute_handle(characteristic.info.id.clone())?,
+                                    characteristic,
+                                ))
+                            })
+                            .collect(),
+                    },
+                );
+            }
+            // Only refer to services which are actually listed, so the graph can be followed.
+            let handles: BTreeSet<u16> = services_internal.keys().copied().collect();
+            for service in services_internal.values_mut() {
+                service
+                    .included_services
+                    .retain(|handle| handles.contains(handle));
+            }
+            *self.services.lock().unwrap() = services_internal;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn pair(&self) -> Result<()> {
+        self.bus
+            .call_method(
+                self.device.clone().into(),
+                "org.bluez.Device1",
+                "Pair",
+                (),
+                PAIRING_TIMEOUT,
+            )
+            .await
+    }
+
+    async fn unpair(&self) -> Result<()> {
+        self.bus
+            .call_method(
+                self.device.adapter().into(),
+                "org.bluez.Adapter1",
+                "RemoveDevice",
+                (Path::from(self.device.clone()),),
+                DBUS_TIMEOUT,
+            )
+            .await
+    }
+
+    async fn is_paired(&self) -> Result<bool> {
+        Ok(self.device_info().await?.paired)
+    }
+
+    async fn is_bonded(&self) -> Result<bool> {
+        Ok(self.device_info().await?.bonded)
+    }
+
+    async fn mtu(&self) -> Result<u16> {
+        if !self.is_connected().await? {
+            return Err(Error::NotConnected);
         }
-        *self.services.lock().unwrap() = services_internal;
-        Ok(())
+        // BlueZ has no property for this on the device itself, but reports the same value on each
+        // of its characteristics.
+        let characteristic = self.any_characteristic().await?;
+        self.bus
+            .get_property(
+                characteristic.into(),
+                "org.bluez.GattCharacteristic1",
+                "MTU",
+            )
+            .await
     }
 
     async fn write(
@@ -215,33 +407,56 @@ impl api::Peripheral for Peripheral {
         data: &[u8],
         write_type: WriteType,
     ) -> Result<()> {
-        let characteristic_info = self.characteristic_info(characteristic)?;
-        let options = WriteOptions {
-            write_type: Some(write_type.into()),
-            ..Default::default()
-        };
-        Ok(self
-            .session
-            .write_characteristic_value_with_options(&characteristic_info.id, data, options)
-            .await?)
+        timeout(self.timeouts().write, async {
+            let characteristic_info = self.characteristic_info(characteristic)?;
+            let options = WriteOptions {
+                write_type: Some(write_type.into()),
+                ..Default::default()
+            };
+            Ok(self
+                .session
+                .write_characteristic_value_with_options(&characteristic_info.id, data, options)
+                .await?)
+        })
+        .await
     }
 
     async fn read(&self, characteristic: &Characteristic) -> Result<Vec<u8>> {
-        let characteristic_info = self.characteristic_info(characteristic)?;
-        Ok(self
-            .session
-            .read_characteristic_value(&characteristic_info.id)
-            .await?)
+        timeout(self.timeouts().read, async {
+            let characteristic_info = self.characteristic_info(characteristic)?;
+            Ok(self
+                .session
+                .read_characteristic_value(&characteristic_info.id)
+                .await?)
+        })
+        .await
+    }
+
+    async fn read_at(&self, characteristic: &Characteristic, offset: usize) -> Result<Vec<u8>> {
+        timeout(self.timeouts().read, async {
+            let characteristic_info = self.characteristic_info(characteristic)?;
+            Ok(self
+                .session
+                .read_characteristic_value_with_offset(&characteristic_info.id, offset)
+                .await?)
+        })
+        .await
     }
 
     async fn subscribe(&self, characteristic: &Characteristic) -> Result<()> {
-        let characteristic_info = self.characteristic_info(characteristic)?;
-        Ok(self.session.start_notify(&characteristic_info.id).await?)
+        timeout(self.timeouts().subscribe, async {
+            let characteristic_info = self.characteristic_info(characteristic)?;
+            Ok(self.session.start_notify(&characteristic_info.id).await?)
+        })
+        .await
     }
 
     async fn unsubscribe(&self, characteristic: &Characteristic) -> Result<()> {
-        let characteristic_info = self.characteristic_info(characteristic)?;
-        Ok(self.session.stop_notify(&characteristic_info.id).await?)
+        timeout(self.timeouts().subscribe, async {
+            let characteristic_info = self.characteristic_info(characteristic)?;
+            Ok(self.session.stop_notify(&characteristic_info.id).await?)
+        })
+        .await
     }
 
     async fn notifications(&self) -> Result<Pin<Box<dyn Stream<Item = ValueNotification> + Send>>> {
@@ -253,27 +468,43 @@ impl api::Peripheral for Peripheral {
         })))
     }
 
+    async fn notifications_for(
+        &self,
+        characteristic: &Characteristic,
+        subscribe: bool,
+    ) -> Result<Pin<Box<dyn Stream<Item = Vec<u8>> + Send>>> {
+        self.notification_streams
+            .notifications_for(self, characteristic, subscribe)
+            .await
+    }
+
     async fn write_descriptor(&self, descriptor: &Descriptor, data: &[u8]) -> Result<()> {
-        let descriptor_info = self.descriptor_info(descriptor)?;
-        Ok(self
-            .session
-            .write_descriptor_value(&descriptor_info.id, data)
-            .await?)
+        timeout(self.timeouts().descriptor, async {
+            let descriptor_info = self.descriptor_info(descriptor)?;
+            Ok(self
+                .session
+                .write_descriptor_value(&descriptor_info.id, data)
+                .await?)
+        })
+        .await
     }
 
     async fn read_descriptor(&self, descriptor: &Descriptor) -> Result<Vec<u8>> {
-        let descriptor_info = self.descriptor_info(descriptor)?;
-        Ok(self
-            .session
-            .read_descriptor_value(&descriptor_info.id)
-            .await?)
+        timeout(self.timeouts().descriptor, async {
+            let descriptor_info = self.descriptor_info(descriptor)?;
+            Ok(self
+                .session
+                .read_descriptor_value(&descriptor_info.id)
+                .await?)
+        })
+        .await
     }
 }
 
 fn value_notification(
     event: BluetoothEvent,
     device_id: &DeviceId,
-    services: Arc<Mutex<HashMap<Uuid, ServiceInternal>>>,
+    services: Arc<Mutex<HashMap<u16, ServiceInternal>>>,
 ) -> Option<ValueNotification> {
     match event {
         BluetoothEvent::Characteristic {
@@ -281,21 +512,26 @@ fn value_notification(
             event: CharacteristicEvent::Value { value },
         } if id.service().device() == *device_id => {
             let services = services.lock().unwrap();
-            let uuid = find_characteristic_by_id(&services, id)?.uuid;
-            Some(ValueNotification { uuid, value })
+            let (service_uuid, characteristic) = find_characteristic_by_id(&services, &id)?;
+            Some(ValueNotification {
+                uuid: characteristic.uuid,
+                service_uuid,
+                handle: attribute_handle(id)?,
+                value,
+            })
         }
         _ => None,
     }
 }
 
-fn find_characteristic_by_id(
-    services: &HashMap<Uuid, ServiceInternal>,
-    characteristic_id: CharacteristicId,
-) -> Option<&CharacteristicInfo> {
+fn find_characteristic_by_id<'a>(
+    services: &'a HashMap<u16, ServiceInternal>,
+    characteristic_id: &CharacteristicId,
+) -> Option<(Uuid, &'a CharacteristicInfo)> {
     for service in services.values() {
         for characteristic in service.characteristics.values() {
-            if characteristic.info.id == characteristic_id {
-                return Some(&characteristic.info);
+            if characteristic.info.id == *characteristic_id {
+                return Some((service.info.uuid, &characteristic.info));
             }
         }
     }
@@ -334,43 +570,56 @@ impl From<bluez_async::AddressType> for AddressType {
 
 fn make_descriptor(
     info: &DescriptorInfo,
-    characteristic_uuid: Uuid,
-    service_uuid: Uuid,
+    handle: u16,
+    characteristic: &Characteristic,
 ) -> Descriptor {
     Descriptor {
         uuid: info.uuid,
-        characteristic_uuid,
-        service_uuid,
+        characteristic_uuid: characteristic.uuid,
+        service_uuid: characteristic.service_uuid,
+        handle,
+        service_handle: characteristic.service_handle,
+        characteristic_handle: characteristic.handle,
     }
 }
 
 fn make_characteristic(
     characteristic: &CharacteristicInternal,
-    service_uuid: Uuid,
+    handle: u16,
+    service: &ServiceInternal,
+    service_handle: u16,
 ) -> Characteristic {
     let CharacteristicInternal { info, descriptors } = characteristic;
-    Characteristic {
+    let mut result = Characteristic {
         uuid: info.uuid,
+        service_uuid: service.info.uuid,
+        handle,
+        service_handle,
         properties: info.flags.into(),
-        descriptors: descriptors
-            .iter()
-            .map(|(_, descriptor)| make_descriptor(descriptor, info.uuid, service_uuid))
-            .collect(),
-        service_uuid,
-    }
+        descriptors: BTreeSet::new(),
+    };
+    result.descriptors = descriptors
+        .iter()
+        .map(|(&descriptor_handle, descriptor)| {
+            make_descriptor(descriptor, descriptor_handle, &result)
+        })
+        .collect();
+    result
 }
 
-impl From<&ServiceInternal> for Service {
-    fn from(service: &ServiceInternal) -> Self {
-        Service {
-            uuid: service.info.uuid,
-            primary: service.info.primary,
-            characteristics: service
-                .characteristics
-                .iter()
-                .map(|(_, characteristic)| make_characteristic(characteristic, service.info.uuid))
-                .collect(),
-        }
+fn make_service(service: &ServiceInternal, handle: u16) -> Service {
+    Service {
+        uuid: service.info.uuid,
+        handle,
+        primary: service.info.primary,
+        included_services: service.included_services.clone(),
+        characteristics: service
+            .characteristics
+            .iter()
+            .map(|(&characteristic_handle, characteristic)| {
+                make_characteristic(characteristic, characteristic_handle, service, handle)
+            })
+            .collect(),
     }
 }
 
@@ -404,3 +653,46 @@ impl From<CharacteristicFlags> for CharPropFlags {
         result
     }
 }
+
+pub(crate) fn device_properties(device_info: DeviceInfo) -> PeripheralProperties {
+    PeripheralProperties {
+        address: device_info.mac_address.into(),
+        address_type: Some(device_info.address_type.into()),
+        local_name: device_info.name,
+        tx_power_level: device_info.tx_power,
+        rssi: device_info.rssi,
+        manufacturer_data: device_info.manufacturer_data,
+        service_data: device_info.service_data,
+        services: device_info.services,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn attribute_handles() {
+        let path = |path: &str| Path::from(path.to_string());
+        assert_eq!(
+            attribute_handle(path("/org/bluez/hci0/dev_11_22_33_44_55_66/service0010")),
+            Some(0x0010)
+        );
+        assert_eq!(
+            attribute_handle(path(
+                "/org/bluez/hci0/dev_11_22_33_44_55_66/service0010/char001b/desc001d"
+            )),
+            Some(0x001d)
+        );
+        assert_eq!(
+            attribute_handle(path(
+                "/org/bluez/hci0/dev_11_22_33_44_55_66/service0010/characteristic"
+            )),
+            None
+        );
+        assert_eq!(
+            attribute_handle(path("/org/bluez/hci0/dev_11_22_33_44_55_66/service00zz")),
+            None
+        );
+    }
+}
@@ -11,13 +11,23 @@
 // following copyright:
 //
 // Copyright (c) 2014 The Rust Project Developers
-use crate::api::{CentralEvent, Peripheral};
+use super::presence::DeviceTracker;
+use super::proximity::ProximityTracker;
+use super::scan_filter::{filter_events, ActiveScanFilter};
+use super::timeouts::SharedTimeouts;
+use crate::api::{
+    proximity::{Proximity, ProximityConfig},
+    CentralEvent, DeviceExpiry, Peripheral, Presence, ScanFilter, Timeouts,
+};
 use crate::platform::PeripheralId;
 use dashmap::{mapref::one::RefMut, DashMap};
+use futures::future;
 use futures::stream::{Stream, StreamExt};
 use log::trace;
 use std::pin::Pin;
+use std::sync::{Arc, Mutex};
 use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
 use tokio_stream::wrappers::BroadcastStream;
 
 #[derive(Debug)]
@@ -25,17 +35,19 @@ pub struct AdapterManager<PeripheralType>
 where
     PeripheralType: Peripheral,
 {
-    peripherals: DashMap<PeripheralId, PeripheralType>,
+    peripherals: Arc<DashMap<PeripheralId, PeripheralType>>,
     events_channel: broadcast::Sender<CentralEvent>,
+    timeouts: SharedTimeouts,
+    scan_filter: Arc<ActiveScanFilter>,
+    devices: Arc<DeviceTracker>,
+    proximity: Arc<ProximityTracker>,
+    /// The task reporting lost devices and proximity changes while a scan is in progress.
+    scan_task: Mutex<Option<JoinHandle<()>>>,
 }
 
 impl<PeripheralType: Peripheral + 'static> Default for AdapterManager<PeripheralType> {
     fn default() -> Self {
-        let (broadcast_sender, _) = broadcast::channel(16);
-        AdapterManager {
-            peripherals: DashMap::new(),
-            events_channel: broadcast_sender,
-        }
+        Self::new(SharedTimeouts::default())
     }
 }
 
@@ -43,12 +55,23 @@ impl<PeripheralType> AdapterManager<PeripheralType>
 where
     PeripheralType: Peripheral + 'static,
 {
+    pub fn new(timeouts: SharedTimeouts) -> Self {
+        let (broadcast_sender, _) = broadcast::channel(16);
+        AdapterManager {
+            peripherals: Arc::new(DashMap::new()),
+            events_channel: broadcast_sender,
+            timeouts,
+            scan_filter: Default::default(),
+            devices: Default::default(),
+            proximity: Default::default(),
+            scan_task: Default::default(),
+        }
+    }
+
     pub fn emit(&self, event: CentralEvent) {
-        match event {
-            CentralEvent::DeviceDisconnected(ref id) => {
-                self.peripherals.remove(id);
-            }
-            _ => {}
+        self.devices.record(&event);
+        if let CentralEvent::DeviceDisconnected(ref id) = event {
+            self.peripherals.remove(id);
         }
 
         if let Err(lost) = self.events_channel.send(event) {
@@ -58,13 +81,73 @@ where
 
     pub fn event_stream(&self) -> Pin<Box<dyn Stream<Item = CentralEvent> + Send>> {
         let receiver = self.events_channel.subscribe();
-        Box::pin(BroadcastStream::new(receiver).filter_map(|x| async move {
-            if x.is_ok() {
-                Some(x.unwrap())
-            } else {
-                None
-            }
-        }))
+        let events = BroadcastStream::new(receiver).filter_map(|x| async move { x.ok() });
+        let peripherals = self.peripherals.clone();
+        filter_events(events, self.scan_filter.clone(), move |id| {
+            let peripheral = peripherals.get(&id).map(|entry| entry.value().clone());
+            async move { peripheral?.properties().await.ok().flatten() }
+        })
+    }
+
+    /// Applies `filter` to the discovery and advertisement events of the adapter, and reports
+    /// lost devices and proximity changes, until [`stop_scan`](Self::stop_scan) is called. Must
+    /// be called from the context of a Tokio runtime.
+    pub fn start_scan(&self, filter: ScanFilter) {
+        self.scan_filter.start(filter);
+        let devices = self.devices.clone();
+        let proximity = self.proximity.clone();
+        let peripherals = self.peripherals.clone();
+        let events_channel = self.events_channel.clone();
+        let events = BroadcastStream::new(self.events_channel.subscribe())
+            .filter_map(|x| async move { x.ok() });
+        let task = tokio::spawn(async move {
+            let send = |event| {
+                if let Err(lost) = events_channel.send(event) {
+                    trace!("Lost central event, while nothing subscribed: {:?}", lost);
+                }
+            };
+            let watch = devices.watch(|id, remove| {
+                if remove {
+                    peripherals.remove(&id);
+                }
+                send(CentralEvent::DeviceLost(id));
+            });
+            let track = proximity.track(
+                events,
+                |id| {
+                    let peripheral = peripherals.get(&id).map(|entry| entry.value().clone());
+                    async move { peripheral?.properties().await.ok().flatten() }
+                },
+                send,
+            );
+            future::join(watch, track).await;
+        });
+        if let Some(previous) = self.scan_task.lock().unwrap().replace(task) {
+            previous.abort();
+        }
+    }
+
+    pub fn stop_scan(&self) {
+        self.scan_filter.stop();
+        if let Some(task) = self.scan_task.lock().unwrap().take() {
+            task.abort();
+        }
+    }
+
+    pub fn presence(&self, id: &PeripheralId) -> Option<Presence> {
+        self.devices.presence(id)
+    }
+
+    pub fn set_device_expiry(&self, expiry: Option<DeviceExpiry>) {
+        self.devices.set_expiry(expiry);
+    }
+
+    pub fn proximity(&self, id: &PeripheralId) -> Option<Proximity> {
+        self.proximity.proximity(id)
+    }
+
+    pub fn set_proximity_config(&self, config: ProximityConfig) {
+        self.proximity.set_config(config);
     }
 
     pub fn add_peripheral(&self, peripheral: PeripheralType) {
@@ -85,11 +168,30 @@ where
     pub fn peripheral_mut(
         &self,
         id: &PeripheralId,
-    ) -> Option<RefMut<PeripheralId, PeripheralType>> {
+    ) -> Option<RefMut<'_, PeripheralId, PeripheralType>> {
         self.peripherals.get_mut(id)
     }
 
     pub fn peripheral(&self, id: &PeripheralId) -> Option<PeripheralType> {
         self.peripherals.get(id).map(|val| val.value().clone())
     }
+
+    pub fn timeouts(&self) -> Timeouts {
+        *self.timeouts.read().unwrap()
+    }
+
+    pub fn set_timeouts(&self, timeouts: Timeouts) {
+        *self.timeouts.write().unwrap() = timeouts;
+    }
+}
+
+impl<PeripheralType> Drop for AdapterManager<PeripheralType>
+where
+    PeripheralType: Peripheral,
+{
+    fn drop(&mut self) {
+        if let Some(task) = self.scan_task.get_mut().unwrap().take() {
+            task.abort();
+        }
+    }
 }
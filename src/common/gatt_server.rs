@@ -0,0 +1,489 @@
+// btleplug Source Code File
+//
+// Copyright 2023 Nonpolynomial Labs LLC. All rights reserved.
+//
+// Licensed under the BSD 3-Clause license. See LICENSE file in the project root
+// for full license information.
+
+//! The state of a published GATT application, shared by the backends which implement
+//! [`GattServer`](crate::api::GattServer). Backends translate requests from remote centrals into
+//! calls on a [`LocalDatabase`], which enforces the characteristic properties, passes the requests
+//! on to the application's [`GattRequestHandler`] and emits the resulting [`GattServerEvent`]s.
+
+use crate::api::{
+    AttErrorCode, CharPropFlags, Characteristic, Descriptor, GattRequestHandler, GattServerEvent,
+    LocalService, Service, WriteType,
+};
+use crate::{Error, Result};
+use futures::stream::{Stream, StreamExt};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+use tokio_stream::wrappers::BroadcastStream;
+
+#[derive(Debug)]
+pub struct LocalDatabase {
+    services: Vec<Service>,
+    characteristics: HashMap<u16, Characteristic>,
+    // Only BlueZ lets remote centrals read descriptors through us.
+    #[cfg_attr(not(any(target_os = "linux", test)), allow(dead_code))]
+    descriptor_values: HashMap<u16, Vec<u8>>,
+    values: Mutex<HashMap<u16, Vec<u8>>>,
+    notifying: Mutex<HashSet<u16>>,
+    handler: Arc<dyn GattRequestHandler>,
+    events_channel: broadcast::Sender<GattServerEvent>,
+}
+
+impl LocalDatabase {
+    /// Creates a database holding the given services, assigning handles to their attributes in
+    /// order.
+    pub fn new(services: Vec<LocalService>, handler: Arc<dyn GattRequestHandler>) -> Result<Self> {
+        let mut handles = 1..=u16::MAX;
+        let mut next_handle = || {
+            handles
+                .next()
+                .ok_or_else(|| Error::Other("Too many attributes in GATT application".into()))
+        };
+        let mut database_services = Vec::new();
+        let mut characteristics = HashMap::new();
+        let mut descriptor_values = HashMap::new();
+        let mut values = HashMap::new();
+        for service in services {
+            let service_handle = next_handle()?;
+            let mut service_characteristics = BTreeSet::new();
+            for characteristic in service.characteristics {
+                let handle = next_handle()?;
+                let mut descriptors = BTreeSet::new();
+                for descriptor in characteristic.descriptors {
+                    let descriptor_handle = next_handle()?;
+                    descriptors.insert(Descriptor {
+                        uuid: descriptor.uuid,
+                        service_uuid: service.uuid,
+                        characteristic_uuid: characteristic.uuid,
+                        handle: descriptor_handle,
+                        service_handle,
+                        characteristic_handle: handle,
+                    });
+                    descriptor_values.insert(descriptor_handle, descriptor.value);
+                }
+                let characteristic_info = Characteristic {
+                    uuid: characteristic.uuid,
+                    service_uuid: service.uuid,
+                    handle,
+                    service_handle,
+                    properties: characteristic.properties,
+                    descriptors,
+                };
+                characteristics.insert(handle, characteristic_info.clone());
+                service_characteristics.insert(characteristic_info);
+                values.insert(handle, characteristic.value);
+            }
+            database_services.push(Service {
+                uuid: service.uuid,
+                handle: service_handle,
+                primary: service.primary,
+                included_services: BTreeSet::new(),
+                characteristics: service_characteristics,
+            });
+        }
+        let (events_channel, _) = broadcast::channel(16);
+        Ok(Self {
+            services: database_services,
+            characteristics,
+            descriptor_values,
+            values: Mutex::new(values),
+            notifying: Mutex::new(HashSet::new()),
+            handler,
+            events_channel,
+        })
+    }
+
+    /// The services of the application, in the order they were given.
+    #[cfg(any(target_os = "linux", test))]
+    pub fn services(&self) -> &[Service] {
+        &self.services
+    }
+
+    pub fn characteristics(&self) -> BTreeSet<Characteristic> {
+        self.services
+            .iter()
+            .flat_map(|service| service.characteristics.iter().cloned())
+            .collect()
+    }
+
+    pub fn events(&self) -> Pin<Box<dyn Stream<Item = GattServerEvent> + Send>> {
+        let receiver = self.events_channel.subscribe();
+        Box::pin(BroadcastStream::new(receiver).filter_map(|x| async move { x.ok() }))
+    }
+
+    pub fn value(&self, characteristic: &Characteristic) -> Result<Vec<u8>> {
+        self.values
+            .lock()
+            .unwrap()
+            .get(&characteristic.handle)
+            .cloned()
+            .ok_or_else(|| not_found(characteristic))
+    }
+
+    /// Sets the value of a characteristic on behalf of the application, returning whether remote
+    /// centrals should be notified of the change.
+    pub fn set_value(&self, characteristic: &Characteristic, value: Vec<u8>) -> Result<bool> {
+        *self
+            .values
+            .lock()
+            .unwrap()
+            .get_mut(&characteristic.handle)
+            .ok_or_else(|| not_found(characteristic))? = value;
+        Ok(self.is_notifying(characteristic.handle))
+    }
+
+    pub fn is_notifying(&self, handle: u16) -> bool {
+        self.notifying.lock().unwrap().contains(&handle)
+    }
+
+    /// Handles a read request from a remote central, answering it with the value the handler
+    /// returns from the given offset onwards.
+    pub async fn read(
+        &self,
+        handle: u16,
+        offset: usize,
+    ) -> std::result::Result<Vec<u8>, AttErrorCode> {
+        let characteristic = self.characteristic(handle)?;
+        if !characteristic.properties.contains(CharPropFlags::READ) {
+            return Err(AttErrorCode::ReadNotPermitted);
+        }
+        let value = self.current_value(handle);
+        let value = self.handler.read(characteristic, value).await?;
+        read_from(&value, offset)
+    }
+
+    /// Handles a write request from a remote central. The written data replaces the value from the
+    /// given offset onwards, if the handler accepts the result.
+    pub async fn write(
+        &self,
+        handle: u16,
+        data: &[u8],
+        offset: usize,
+        write_type: WriteType,
+    ) -> std::result::Result<(), AttErrorCode> {
+        let characteristic = self.characteristic(handle)?;
+        let required = match write_type {
+            WriteType::WithResponse => CharPropFlags::WRITE,
+            WriteType::WithoutResponse => CharPropFlags::WRITE_WITHOUT_RESPONSE,
+        };
+        if !characteristic.properties.contains(required) {
+            return Err(AttErrorCode::WriteNotPermitted);
+        }
+        let mut value = self.current_value(handle);
+        if offset > value.len() {
+            return Err(AttErrorCode::InvalidOffset);
+        }
+        value.truncate(offset);
+        value.extend_from_slice(data);
+        self.handler
+            .write(characteristic, &value, write_type)
+            .await?;
+        self.values.lock().unwrap().insert(handle, value.clone());
+        self.emit(GattServerEvent::CharacteristicWritten {
+            characteristic: characteristic.clone(),
+            value,
+            write_type,
+        });
+        Ok(())
+    }
+
+    /// Handles a remote central enabling or disabling notifications or indications.
+    pub fn set_notifying(
+        &self,
+        handle: u16,
+        notifying: bool,
+    ) -> std::result::Result<(), AttErrorCode> {
+        let characteristic = self.characteristic(handle)?;
+        if !characteristic
+            .properties
+            .intersects(CharPropFlags::NOTIFY | CharPropFlags::INDICATE)
+        {
+            return Err(AttErrorCode::RequestNotSupported);
+        }
+        let changed = {
+            let mut subscribed = self.notifying.lock().unwrap();
+            if notifying {
+                subscribed.insert(handle)
+            } else {
+                subscribed.remove(&handle)
+            }
+        };
+        if changed {
+            let characteristic = characteristic.clone();
+            self.emit(if notifying {
+                GattServerEvent::Subscribed { characteristic }
+            } else {
+                GattServerEvent::Unsubscribed { characteristic }
+            });
+        }
+        Ok(())
+    }
+
+    /// Handles a read request for a descriptor from a remote central.
+    #[cfg(any(target_os = "linux", test))]
+    pub fn read_descriptor(
+        &self,
+        handle: u16,
+        offset: usize,
+    ) -> std::result::Result<Vec<u8>, AttErrorCode> {
+        let value = self
+            .descriptor_values
+            .get(&handle)
+            .ok_or(AttErrorCode::InvalidHandle)?;
+        read_from(value, offset)
+    }
+
+    fn characteristic(&self, handle: u16) -> std::result::Result<&Characteristic, AttErrorCode> {
+        self.characteristics
+            .get(&handle)
+            .ok_or(AttErrorCode::InvalidHandle)
+    }
+
+    fn current_value(&self, handle: u16) -> Vec<u8> {
+        self.values.lock().unwrap()[&handle].clone()
+    }
+
+    fn emit(&self, event: GattServerEvent) {
+        // Note: we ignore send errors here which may happen while there are no receivers...
+        let _ = self.events_channel.send(event);
+    }
+}
+
+fn read_from(value: &[u8], offset: usize) -> std::result::Result<Vec<u8>, AttErrorCode> {
+    value
+        .get(offset..)
+        .map(<[u8]>::to_vec)
+        .ok_or(AttErrorCode::InvalidOffset)
+}
+
+fn not_found(characteristic: &Characteristic) -> Error {
+    Error::Other(
+        format!(
+            "Characteristic with UUID {} and handle {} not found.",
+            characteristic.uuid, characteristic.handle
+        )
+        .into(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{LocalCharacteristic, LocalDescriptor};
+    use async_trait::async_trait;
+    use uuid::Uuid;
+
+    const SERVICE: Uuid = Uuid::from_u128(0x1);
+    const CHARACTERISTIC: Uuid = Uuid::from_u128(0x2);
+    const DESCRIPTOR: Uuid = Uuid::from_u128(0x3);
+
+    /// The handle of the characteristic of the database, after that of its service.
+    const HANDLE: u16 = 2;
+
+    #[derive(Debug)]
+    struct StoredValues;
+
+    impl GattRequestHandler for StoredValues {}
+
+    /// Answers reads with a fixed value, and only accepts writes of a single byte.
+    #[derive(Debug)]
+    struct Validating;
+
+    #[async_trait]
+    impl GattRequestHandler for Validating {
+        async fn read(
+            &self,
+            _characteristic: &Characteristic,
+            _value: Vec<u8>,
+        ) -> std::result::Result<Vec<u8>, AttErrorCode> {
+            Ok(vec![7, 7])
+        }
+
+        async fn write(
+            &self,
+            _characteristic: &Characteristic,
+            value: &[u8],
+            _write_type: WriteType,
+        ) -> std::result::Result<(), AttErrorCode> {
+            if value.len() == 1 {
+                Ok(())
+            } else {
+                Err(AttErrorCode::Application(0x80))
+            }
+        }
+    }
+
+    fn characteristic(properties: CharPropFlags) -> LocalCharacteristic {
+        LocalCharacteristic {
+            uuid: CHARACTERISTIC,
+            properties,
+            value: vec![1, 2, 3],
+            descriptors: vec![LocalDescriptor {
+                uuid: DESCRIPTOR,
+                value: b"name".to_vec(),
+            }],
+        }
+    }
+
+    fn database_with(
+        properties: CharPropFlags,
+        handler: Arc<dyn GattRequestHandler>,
+    ) -> LocalDatabase {
+        LocalDatabase::new(
+            vec![LocalService {
+                uuid: SERVICE,
+                primary: true,
+                characteristics: vec![characteristic(properties)],
+            }],
+            handler,
+        )
+        .unwrap()
+    }
+
+    fn database(properties: CharPropFlags) -> LocalDatabase {
+        database_with(properties, Arc::new(StoredValues))
+    }
+
+    #[tokio::test]
+    async fn read_checks_properties_and_offset() {
+        let database = database(CharPropFlags::READ);
+        assert_eq!(database.read(HANDLE, 0).await, Ok(vec![1, 2, 3]));
+        assert_eq!(database.read(HANDLE, 2).await, Ok(vec![3]));
+        assert_eq!(database.read(HANDLE, 3).await, Ok(vec![]));
+        assert_eq!(
+            database.read(HANDLE, 4).await,
+            Err(AttErrorCode::InvalidOffset)
+        );
+        assert_eq!(
+            database.read(HANDLE + 1, 0).await,
+            Err(AttErrorCode::InvalidHandle)
+        );
+        assert_eq!(database.read_descriptor(HANDLE + 1, 1), Ok(b"ame".to_vec()));
+        assert_eq!(
+            self::database(CharPropFlags::WRITE).read(HANDLE, 0).await,
+            Err(AttErrorCode::ReadNotPermitted)
+        );
+    }
+
+    #[tokio::test]
+    async fn write_replaces_value_from_offset() {
+        let database = database(CharPropFlags::WRITE);
+        let characteristic = database.characteristics().pop_first().unwrap();
+        let mut events = database.events();
+        database
+            .write(HANDLE, &[9, 9], 1, WriteType::WithResponse)
+            .await
+            .unwrap();
+        assert_eq!(database.value(&characteristic).unwrap(), vec![1, 9, 9]);
+        assert_eq!(
+            events.next().await,
+            Some(GattServerEvent::CharacteristicWritten {
+                characteristic,
+                value: vec![1, 9, 9],
+                write_type: WriteType::WithResponse,
+            })
+        );
+        assert_eq!(
+            database
+                .write(HANDLE, &[0], 4, WriteType::WithResponse)
+                .await,
+            Err(AttErrorCode::InvalidOffset)
+        );
+        assert_eq!(
+            database
+                .write(HANDLE, &[0], 0, WriteType::WithoutResponse)
+                .await,
+            Err(AttErrorCode::WriteNotPermitted)
+        );
+    }
+
+    #[tokio::test]
+    async fn handler_answers_requests() {
+        let database = database_with(
+            CharPropFlags::READ | CharPropFlags::WRITE,
+            Arc::new(Validating),
+        );
+        let characteristic = database.characteristics().pop_first().unwrap();
+        assert_eq!(database.read(HANDLE, 1).await, Ok(vec![7]));
+        assert_eq!(
+            database
+                .write(HANDLE, &[4], 3, WriteType::WithResponse)
+                .await,
+            Err(AttErrorCode::Application(0x80))
+        );
+        assert_eq!(database.value(&characteristic).unwrap(), vec![1, 2, 3]);
+        database
+            .write(HANDLE, &[4], 0, WriteType::WithResponse)
+            .await
+            .unwrap();
+        assert_eq!(database.value(&characteristic).unwrap(), vec![4]);
+    }
+
+    #[tokio::test]
+    async fn subscriptions_are_tracked() {
+        let database = database(CharPropFlags::NOTIFY);
+        let characteristic = database.characteristics().pop_first().unwrap();
+        let mut events = database.events();
+        assert!(!database.set_value(&characteristic, vec![4]).unwrap());
+        database.set_notifying(HANDLE, true).unwrap();
+        database.set_notifying(HANDLE, true).unwrap();
+        assert!(database.set_value(&characteristic, vec![5]).unwrap());
+        database.set_notifying(HANDLE, false).unwrap();
+        assert!(matches!(
+            events.next().await,
+            Some(GattServerEvent::Subscribed { .. })
+        ));
+        assert!(matches!(
+            events.next().await,
+            Some(GattServerEvent::Unsubscribed { .. })
+        ));
+        assert_eq!(
+            self::database(CharPropFlags::READ).set_notifying(HANDLE, true),
+            Err(AttErrorCode::RequestNotSupported)
+        );
+    }
+
+    #[tokio::test]
+    async fn duplicate_uuids_are_told_apart_by_handle() {
+        let service = LocalService {
+            uuid: SERVICE,
+            primary: true,
+            characteristics: vec![
+                characteristic(CharPropFlags::READ),
+                characteristic(CharPropFlags::READ),
+            ],
+        };
+        let database =
+            LocalDatabase::new(vec![service.clone(), service], Arc::new(StoredValues)).unwrap();
+        let handles: Vec<_> = database
+            .services()
+            .iter()
+            .map(|service| {
+                (
+                    service.handle,
+                    service
+                        .characteristics
+                        .iter()
+                        .map(|characteristic| characteristic.handle)
+                        .collect::<Vec<_>>(),
+                )
+            })
+            .collect();
+        assert_eq!(handles, vec![(1, vec![2, 4]), (6, vec![7, 9])]);
+
+        let characteristic = database
+            .characteristics()
+            .into_iter()
+            .find(|characteristic| characteristic.handle == 9)
+            .unwrap();
+        database.set_value(&characteristic, vec![8]).unwrap();
+        assert_eq!(database.read(9, 0).await, Ok(vec![8]));
+        assert_eq!(database.read(7, 0).await, Ok(vec![1, 2, 3]));
+    }
+}
@@ -1,2 +1,12 @@
+#[cfg(any(not(target_os = "linux"), test, feature = "mock", feature = "replay"))]
 pub mod adapter_manager;
+#[cfg(any(target_os = "linux", test, feature = "mock"))]
+pub mod gatt_server;
+pub mod presence;
+pub mod proximity;
+pub mod scan_filter;
+pub mod subscriptions;
+pub mod timeouts;
+pub mod transfer;
+#[cfg(any(not(target_os = "linux"), test, feature = "mock", feature = "replay"))]
 pub mod util;
@@ -0,0 +1,134 @@
+// btleplug Source Code File
+//
+// Copyright 2023 Nonpolynomial Labs LLC. All rights reserved.
+//
+// Licensed under the BSD 3-Clause license. See LICENSE file in the project root
+// for full license information.
+
+//! Tracking of when devices were last heard from, to report them lost once they go quiet.
+
+use crate::api::{CentralEvent, DeviceExpiry, Presence};
+use crate::platform::PeripheralId;
+use std::collections::HashMap;
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+/// The presence of the devices of an adapter, and when to report them lost.
+#[derive(Debug, Default)]
+pub struct DeviceTracker {
+    devices: Mutex<HashMap<PeripheralId, Tracked>>,
+    expiry: RwLock<Option<DeviceExpiry>>,
+}
+
+#[derive(Debug)]
+struct Tracked {
+    presence: Presence,
+    /// When the device started counting towards its expiry: when it was last heard from, or when
+    /// the scan started if that's later.
+    since: Instant,
+    connected: bool,
+    lost: bool,
+}
+
+impl DeviceTracker {
+    /// Records the sign of life from a device which `event` is, if it is one.
+    pub fn record(&self, event: &CentralEvent) {
+        let (id, advertised, connected) = match event {
+            CentralEvent::DeviceDiscovered(id) | CentralEvent::DeviceUpdated(id) => {
+                (id, true, None)
+            }
+            CentralEvent::DeviceConnected(id) => (id, false, Some(true)),
+            CentralEvent::DeviceDisconnected(id) => (id, false, Some(false)),
+            _ => return,
+        };
+        let now = Instant::now();
+        let mut devices = self.devices.lock().unwrap();
+        let tracked = devices.entry(id.clone()).or_insert(Tracked {
+            presence: Presence {
+                last_seen: now,
+                advertisement_count: 0,
+            },
+            since: now,
+            connected: false,
+            lost: false,
+        });
+        tracked.presence.last_seen = now;
+        tracked.since = now;
+        tracked.lost = false;
+        if advertised {
+            tracked.presence.advertisement_count += 1;
+        }
+        if let Some(connected) = connected {
+            tracked.connected = connected;
+        }
+    }
+
+    pub fn presence(&self, id: &PeripheralId) -> Option<Presence> {
+        self.devices
+            .lock()
+            .unwrap()
+            .get(id)
+            .map(|tracked| tracked.presence)
+    }
+
+    pub fn expiry(&self) -> Option<DeviceExpiry> {
+        *self.expiry.read().unwrap()
+    }
+
+    pub fn set_expiry(&self, expiry: Option<DeviceExpiry>) {
+        *self.expiry.write().unwrap() = expiry;
+    }
+
+    /// Marks the devices which haven't been heard from within the expiry as lost, and returns
+    /// them along with whether they are to be removed. Devices to be removed are forgotten.
+    fn expire(&self) -> (Vec<PeripheralId>, bool) {
+        let Some(expiry) = self.expiry() else {
+            return (Vec::new(), false);
+        };
+        let now = Instant::now();
+        let mut devices = self.devices.lock().unwrap();
+        let lost: Vec<_> = devices
+            .iter_mut()
+            .filter(|(_, tracked)| {
+                !tracked.connected
+                    && !tracked.lost
+                    && now.duration_since(tracked.since) >= expiry.timeout
+            })
+            .map(|(id, tracked)| {
+                tracked.lost = true;
+                id.clone()
+            })
+            .collect();
+        if expiry.remove {
+            for id in &lost {
+                devices.remove(id);
+            }
+        }
+        (lost, expiry.remove)
+    }
+
+    /// Reports the devices which go quiet to `on_lost`, along with whether to remove them, until
+    /// dropped. Meant to run while a scan is in progress, as devices are only heard from then.
+    ///
+    /// Devices which were last heard from before this started get the full expiry from now, so
+    /// that those seen during a previous scan aren't reported lost straight away.
+    pub async fn watch(&self, on_lost: impl Fn(PeripheralId, bool)) {
+        let now = Instant::now();
+        for tracked in self.devices.lock().unwrap().values_mut() {
+            tracked.since = now;
+        }
+        loop {
+            let period = self.expiry().map_or(MAX_CHECK_PERIOD, |expiry| {
+                (expiry.timeout / 4).clamp(MIN_CHECK_PERIOD, MAX_CHECK_PERIOD)
+            });
+            tokio::time::sleep(period).await;
+            let (lost, remove) = self.expire();
+            for id in lost {
+                on_lost(id, remove);
+            }
+        }
+    }
+}
+
+const MIN_CHECK_PERIOD: Duration = Duration::from_millis(10);
+const MAX_CHECK_PERIOD: Duration = Duration::from_secs(1);
@@ -0,0 +1,126 @@
+// btleplug Source Code File
+//
+// Copyright 2023 Nonpolynomial Labs LLC. All rights reserved.
+//
+// Licensed under the BSD 3-Clause license. See LICENSE file in the project root
+// for full license information.
+
+//! Estimation of the proximity of the devices of an adapter from the RSSI measured while scanning.
+
+use crate::api::proximity::{Proximity, ProximityConfig, ProximityZone, RssiSample, RssiSmoother};
+use crate::api::{CentralEvent, PeripheralProperties};
+use crate::platform::PeripheralId;
+use futures::stream::{Stream, StreamExt};
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::sync::{Mutex, RwLock};
+use std::time::Instant;
+
+/// The proximity of the devices of an adapter.
+#[derive(Debug, Default)]
+pub struct ProximityTracker {
+    config: RwLock<ProximityConfig>,
+    devices: Mutex<HashMap<PeripheralId, DeviceProximity>>,
+}
+
+#[derive(Debug)]
+struct DeviceProximity {
+    smoother: RssiSmoother,
+    history: VecDeque<RssiSample>,
+    proximity: Option<(f64, Option<f64>)>,
+    zone: ProximityZone,
+}
+
+impl ProximityTracker {
+    pub fn set_config(&self, config: ProximityConfig) {
+        *self.config.write().unwrap() = config;
+    }
+
+    /// Adds an RSSI measurement of a device, and returns its new zone if it changed.
+    pub fn record(
+        &self,
+        id: &PeripheralId,
+        rssi: i16,
+        tx_power_level: Option<i16>,
+    ) -> Option<ProximityZone> {
+        let config = *self.config.read().unwrap();
+        let mut devices = self.devices.lock().unwrap();
+        let device = devices
+            .entry(id.clone())
+            .or_insert_with(|| DeviceProximity {
+                smoother: RssiSmoother::new(config.filter),
+                history: VecDeque::new(),
+                proximity: None,
+                zone: ProximityZone::Unknown,
+            });
+        if device.smoother.filter() != config.filter {
+            device.smoother = RssiSmoother::new(config.filter);
+        }
+        device.history.push_back(RssiSample {
+            time: Instant::now(),
+            rssi,
+        });
+        while device.history.len() > config.history_length {
+            device.history.pop_front();
+        }
+        let smoothed = device.smoother.update(rssi);
+        let distance = config.distance.estimate(smoothed, tx_power_level);
+        device.proximity = Some((smoothed, distance));
+        let zone = config.zone(distance);
+        (zone != std::mem::replace(&mut device.zone, zone)).then_some(zone)
+    }
+
+    /// Forgets the measurements of a device which was lost, and returns its new zone if it was in
+    /// a known one.
+    pub fn forget(&self, id: &PeripheralId) -> Option<ProximityZone> {
+        let device = self.devices.lock().unwrap().remove(id)?;
+        (device.zone != ProximityZone::Unknown).then_some(ProximityZone::Unknown)
+    }
+
+    pub fn proximity(&self, id: &PeripheralId) -> Option<Proximity> {
+        let devices = self.devices.lock().unwrap();
+        let device = devices.get(id)?;
+        let (rssi, distance) = device.proximity?;
+        Some(Proximity {
+            rssi,
+            distance,
+            zone: device.zone,
+            history: device.history.iter().copied().collect(),
+        })
+    }
+
+    /// Records the RSSI of the devices which `events` report advertising, looking up their
+    /// properties with `properties`, and forgets those which are lost. Zone changes are passed to
+    /// `on_change` as [`CentralEvent::ProximityChanged`] events.
+    pub async fn track<S, F, R>(&self, events: S, properties: F, on_change: impl Fn(CentralEvent))
+    where
+        S: Stream<Item = CentralEvent>,
+        F: Fn(PeripheralId) -> R,
+        R: Future<Output = Option<PeripheralProperties>>,
+    {
+        futures::pin_mut!(events);
+        while let Some(event) = events.next().await {
+            let (id, zone) = match event {
+                CentralEvent::DeviceDiscovered(id) | CentralEvent::DeviceUpdated(id) => {
+                    let zone = match properties(id.clone()).await {
+                        Some(PeripheralProperties {
+                            rssi: Some(rssi),
+                            tx_power_level,
+                            ..
+                        }) => self.record(&id, rssi, tx_power_level),
+                        _ => None,
+                    };
+                    (id, zone)
+                }
+                CentralEvent::DeviceLost(id) => {
+                    let zone = self.forget(&id);
+                    (id, zone)
+                }
+                _ => continue,
+            };
+            if let Some(zone) = zone {
+                on_change(CentralEvent::ProximityChanged { id, zone });
+            }
+        }
+    }
+}
@@ -0,0 +1,265 @@
+// btleplug Source Code File
+//
+// Copyright 2023 Nonpolynomial Labs LLC. All rights reserved.
+//
+// Licensed under the BSD 3-Clause license. See LICENSE file in the project root
+// for full license information.
+
+//! Client-side application of [`ScanFilter`] to the events of an adapter, so that every platform
+//! reports the same devices whatever it can filter natively.
+
+use crate::api::{beacon::Beacon, AddressType, CentralEvent, PeripheralProperties, ScanFilter};
+use crate::platform::PeripheralId;
+use futures::stream::{Stream, StreamExt};
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::mem::{discriminant, Discriminant};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, RwLock};
+use uuid::Uuid;
+
+/// The filter of the scan in progress on an adapter, if any.
+#[derive(Debug, Default)]
+pub struct ActiveScanFilter {
+    /// The filter, and how many scans have been started, so that event streams know when to
+    /// forget what they have reported.
+    scan: RwLock<(u64, Option<ScanFilter>)>,
+}
+
+impl ActiveScanFilter {
+    pub fn start(&self, filter: ScanFilter) {
+        let mut scan = self.scan.write().unwrap();
+        scan.0 += 1;
+        scan.1 = Some(filter);
+    }
+
+    pub fn stop(&self) {
+        self.scan.write().unwrap().1 = None;
+    }
+
+    fn current(&self) -> (u64, Option<ScanFilter>) {
+        self.scan.read().unwrap().clone()
+    }
+}
+
+/// Applies the active scan filter to the discovery and advertisement events of `events`, using
+/// `properties` to look up the current properties of a device. Other events, and all events while
+/// no scan is in progress, are passed through.
+///
+/// A device whose `DeviceDiscovered` event was filtered out is announced with `DeviceDiscovered`
+/// instead of `DeviceUpdated` once it matches, e.g. when its name arrives in a scan response. Until
+/// then, it isn't reported lost and its proximity changes aren't reported either.
+pub fn filter_events<S, F, R>(
+    events: S,
+    active: Arc<ActiveScanFilter>,
+    properties: F,
+) -> Pin<Box<dyn Stream<Item = CentralEvent> + Send>>
+where
+    S: Stream<Item = CentralEvent> + Send + 'static,
+    F: Fn(PeripheralId) -> R + Send + Sync + 'static,
+    R: Future<Output = Option<PeripheralProperties>> + Send + 'static,
+{
+    let reported = Arc::new(Mutex::new(Reported::default()));
+    Box::pin(events.filter_map(move |event| {
+        let (scan, filter) = active.current();
+        let lookup = match (&filter, advertisement_id(&event)) {
+            (Some(filter), Some(id)) if needs_properties(filter) => Some(properties(id.clone())),
+            _ => None,
+        };
+        let reported = reported.clone();
+        async move {
+            if let CentralEvent::DeviceLost(id) | CentralEvent::ProximityChanged { id, .. } = &event
+            {
+                // Devices which were filtered out were never reported, so neither is what happens
+                // to them.
+                let undiscovered = reported.lock().unwrap().undiscovered.contains(id);
+                return (!undiscovered).then_some(event);
+            }
+            let filter = match filter {
+                Some(filter) if advertisement_id(&event).is_some() => filter,
+                _ => return Some(event),
+            };
+            // A device whose properties can't be read matches as if it advertised nothing.
+            let properties = match lookup {
+                Some(lookup) => lookup.await.unwrap_or_default(),
+                None => PeripheralProperties::default(),
+            };
+            reported
+                .lock()
+                .unwrap()
+                .filter(scan, &filter, event, &properties)
+        }
+    }))
+}
+
+/// Returns whether applying the filter to an event takes the properties of the device. Looking
+/// them up may be costly, e.g. a D-Bus call on Linux, so it is skipped for filters which let every
+/// device through and don't suppress duplicates.
+fn needs_properties(filter: &ScanFilter) -> bool {
+    filter.rssi.is_some()
+        || filter.pathloss.is_some()
+        || filter.name.is_some()
+        || !filter.services.is_empty()
+        || !filter.manufacturer_data.is_empty()
+        || !filter.service_data.is_empty()
+        || !filter.addresses.is_empty()
+        || filter.suppress_duplicates
+}
+
+/// Returns the device an event about a device being discovered or advertising is about.
+fn advertisement_id(event: &CentralEvent) -> Option<&PeripheralId> {
+    match event {
+        CentralEvent::DeviceDiscovered(id)
+        | CentralEvent::DeviceUpdated(id)
+        | CentralEvent::ManufacturerDataAdvertisement { id, .. }
+        | CentralEvent::ServiceDataAdvertisement { id, .. }
+        | CentralEvent::ServicesAdvertisement { id, .. }
+        | CentralEvent::BeaconAdvertisement { id, .. } => Some(id),
+        _ => None,
+    }
+}
+
+/// What one event stream has reported during the current scan.
+#[derive(Default)]
+struct Reported {
+    scan: u64,
+    /// Devices whose `DeviceDiscovered` event was filtered out.
+    undiscovered: HashSet<PeripheralId>,
+    /// The last content reported for each device and kind of event.
+    content: HashMap<(PeripheralId, Discriminant<Content>), Content>,
+}
+
+/// The content of an event, for duplicate suppression.
+#[derive(Clone, PartialEq)]
+enum Content {
+    /// The properties of a device, other than its RSSI.
+    Device {
+        address_type: Option<AddressType>,
+        local_name: Option<String>,
+        tx_power_level: Option<i16>,
+        manufacturer_data: HashMap<u16, Vec<u8>>,
+        service_data: HashMap<Uuid, Vec<u8>>,
+        services: Vec<Uuid>,
+    },
+    ManufacturerData(HashMap<u16, Vec<u8>>),
+    ServiceData(HashMap<Uuid, Vec<u8>>),
+    Services(Vec<Uuid>),
+    Beacon(Beacon),
+}
+
+impl Reported {
+    fn filter(
+        &mut self,
+        scan: u64,
+        filter: &ScanFilter,
+        event: CentralEvent,
+        properties: &PeripheralProperties,
+    ) -> Option<CentralEvent> {
+        if scan != self.scan {
+            *self = Reported {
+                scan,
+                ..Default::default()
+            };
+        }
+        if !filter.matches(properties) {
+            if let CentralEvent::DeviceDiscovered(id) = event {
+                self.undiscovered.insert(id);
+            }
+            return None;
+        }
+        let event = match event {
+            CentralEvent::DeviceDiscovered(id) | CentralEvent::DeviceUpdated(id)
+                if self.undiscovered.remove(&id) =>
+            {
+                CentralEvent::DeviceDiscovered(id)
+            }
+            event => event,
+        };
+        if filter.suppress_duplicates && !self.is_new(&event, properties) {
+            return None;
+        }
+        Some(event)
+    }
+
+    /// Records the content of the event, returning whether it differs from the last one of the
+    /// same kind for the device.
+    fn is_new(&mut self, event: &CentralEvent, properties: &PeripheralProperties) -> bool {
+        let (id, content) = match event {
+            CentralEvent::DeviceDiscovered(id) | CentralEvent::DeviceUpdated(id) => (
+                id,
+                Content::Device {
+                    address_type: properties.address_type,
+                    local_name: properties.local_name.clone(),
+                    tx_power_level: properties.tx_power_level,
+                    manufacturer_data: properties.manufacturer_data.clone(),
+                    service_data: properties.service_data.clone(),
+                    services: properties.services.clone(),
+                },
+            ),
+            CentralEvent::ManufacturerDataAdvertisement {
+                id,
+                manufacturer_data,
+            } => (id, Content::ManufacturerData(manufacturer_data.clone())),
+            CentralEvent::ServiceDataAdvertisement { id, service_data } => {
+                (id, Content::ServiceData(service_data.clone()))
+            }
+            CentralEvent::ServicesAdvertisement { id, services } => {
+                (id, Content::Services(services.clone()))
+            }
+            CentralEvent::BeaconAdvertisement { id, beacon } => {
+                (id, Content::Beacon(beacon.clone()))
+            }
+            _ => return true,
+        };
+        let key = (id.clone(), discriminant(&content));
+        self.content.insert(key, content.clone()) != Some(content)
+    }
+}
+
+// Device IDs differ between platforms, and the mock backend knows how to make them.
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use crate::api::BDAddr;
+    use futures::future::ready;
+    use futures::stream;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Filters a `DeviceUpdated` event with the given filter, returning whether it was passed
+    /// through and how many times the properties of the device were looked up.
+    async fn filter_one(filter: ScanFilter) -> (bool, usize) {
+        let active = Arc::new(ActiveScanFilter::default());
+        active.start(filter);
+        let lookups = Arc::new(AtomicUsize::new(0));
+        let counter = lookups.clone();
+        let id = crate::mock::peripheral_id("hci0", BDAddr::from([1, 2, 3, 4, 5, 6]));
+        let events = filter_events(
+            stream::iter([CentralEvent::DeviceUpdated(id)]),
+            active,
+            move |_| {
+                counter.fetch_add(1, Ordering::Relaxed);
+                ready(Some(PeripheralProperties {
+                    rssi: Some(-60),
+                    ..Default::default()
+                }))
+            },
+        );
+        let passed = events.collect::<Vec<_>>().await.len() == 1;
+        (passed, lookups.load(Ordering::Relaxed))
+    }
+
+    #[tokio::test]
+    async fn properties_looked_up_only_when_needed() {
+        assert_eq!(filter_one(ScanFilter::default()).await, (true, 0));
+        let filter = ScanFilter {
+            rssi: Some(-70),
+            ..Default::default()
+        };
+        assert_eq!(filter_one(filter).await, (true, 1));
+        let filter = ScanFilter {
+            rssi: Some(-50),
+            ..Default::default()
+        };
+        assert_eq!(filter_one(filter).await, (false, 1));
+    }
+}
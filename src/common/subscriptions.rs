@@ -0,0 +1,192 @@
+// btleplug Source Code File
+//
+// Copyright 2023 Nonpolynomial Labs LLC. All rights reserved.
+//
+// Licensed under the BSD 3-Clause license. See LICENSE file in the project root
+// for full license information.
+
+//! Shared implementation of [`Peripheral::notifications_for`].
+
+use crate::api::{Characteristic, Peripheral};
+#[cfg(any(target_os = "linux", feature = "replay"))]
+use crate::platform::PeripheralId;
+use crate::Result;
+#[cfg(any(target_os = "linux", feature = "replay"))]
+use dashmap::DashMap;
+use futures::future::ready;
+use futures::stream::{self, Stream, StreamExt};
+use log::debug;
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+
+/// The number of streams returned by [`Peripheral::notifications_for`] which want a characteristic
+/// to stay subscribed, keyed by characteristic handle. Each device has one of these, shared by
+/// all the peripheral handles for it.
+#[derive(Debug, Default)]
+pub struct Subscriptions {
+    entries: Mutex<HashMap<u16, Entry>>,
+}
+
+/// The [`Subscriptions`] of each peripheral, for backends which create a new peripheral handle on
+/// every lookup, so that all the handles for one device share the same counts.
+#[cfg(any(target_os = "linux", feature = "replay"))]
+#[derive(Debug, Default)]
+pub struct PeripheralSubscriptions {
+    peripherals: DashMap<PeripheralId, Arc<Subscriptions>>,
+}
+
+#[cfg(any(target_os = "linux", feature = "replay"))]
+impl PeripheralSubscriptions {
+    /// Returns the subscriptions of the peripheral with the given ID.
+    pub fn get(&self, id: &PeripheralId) -> Arc<Subscriptions> {
+        self.peripherals.entry(id.clone()).or_default().clone()
+    }
+}
+
+#[derive(Debug, Default)]
+struct Entry {
+    streams: usize,
+    subscribed: bool,
+    /// Held while subscribing or unsubscribing, so that the requests for one characteristic are
+    /// sent to the device in the order the streams were created and dropped.
+    lock: Arc<tokio::sync::Mutex<()>>,
+}
+
+impl Subscriptions {
+    /// Returns a stream of the values notified for the given characteristic of `peripheral`. If
+    /// `subscribe` is true the characteristic is subscribed to when the stream is first polled
+    /// unless another stream already did, and unsubscribed from once the last such stream is
+    /// dropped. The stream ends straight away if subscribing fails.
+    pub async fn notifications_for<P: Peripheral + 'static>(
+        self: &Arc<Self>,
+        peripheral: &P,
+        characteristic: &Characteristic,
+        subscribe: bool,
+    ) -> Result<Pin<Box<dyn Stream<Item = Vec<u8>> + Send>>> {
+        let handle = characteristic.handle;
+        let values = peripheral
+            .notifications()
+            .await?
+            .filter_map(move |notification| {
+                ready((notification.handle == handle).then_some(notification.value))
+            });
+        if !subscribe {
+            return Ok(Box::pin(values));
+        }
+
+        let subscriptions = self.clone();
+        let peripheral = peripheral.clone();
+        let characteristic = characteristic.clone();
+        let subscribed = stream::once(async move {
+            subscriptions
+                .entries
+                .lock()
+                .unwrap()
+                .entry(handle)
+                .or_default()
+                .streams += 1;
+            // Created before subscribing, so that the count is released again if that fails.
+            let guard = StreamGuard {
+                subscriptions,
+                peripheral,
+                characteristic,
+            };
+            match guard
+                .subscriptions
+                .update(&guard.peripheral, &guard.characteristic)
+                .await
+            {
+                Ok(()) => Some((guard, values)),
+                Err(e) => {
+                    debug!(
+                        "Failed to subscribe to {}: {:?}",
+                        guard.characteristic.uuid, e
+                    );
+                    None
+                }
+            }
+        });
+        Ok(Box::pin(subscribed.filter_map(ready).flat_map(
+            |(guard, values)| {
+                values.map(move |value| {
+                    // The guard lives as long as the stream does.
+                    let _ = &guard;
+                    value
+                })
+            },
+        )))
+    }
+
+    /// Subscribes to or unsubscribes from the characteristic if that is out of date with whether
+    /// any streams are left for it.
+    async fn update<P: Peripheral>(
+        &self,
+        peripheral: &P,
+        characteristic: &Characteristic,
+    ) -> Result<()> {
+        let key = characteristic.handle;
+        let lock = match self.entries.lock().unwrap().get(&key) {
+            Some(entry) => entry.lock.clone(),
+            None => return Ok(()),
+        };
+        let _lock = lock.lock().await;
+        let (streams, subscribed) = match self.entries.lock().unwrap().get(&key) {
+            Some(entry) => (entry.streams, entry.subscribed),
+            None => return Ok(()),
+        };
+        if streams > 0 && !subscribed {
+            peripheral.subscribe(characteristic).await?;
+            if let Some(entry) = self.entries.lock().unwrap().get_mut(&key) {
+                entry.subscribed = true;
+            }
+        } else if streams == 0 {
+            let result = if subscribed {
+                peripheral.unsubscribe(characteristic).await
+            } else {
+                Ok(())
+            };
+            let mut entries = self.entries.lock().unwrap();
+            if let Some(entry) = entries.get_mut(&key) {
+                entry.subscribed = false;
+                // A new stream may have been created while unsubscribing, in which case it is
+                // waiting for the lock to subscribe again.
+                if entry.streams == 0 {
+                    entries.remove(&key);
+                }
+            }
+            result?;
+        }
+        Ok(())
+    }
+}
+
+struct StreamGuard<P: Peripheral + 'static> {
+    subscriptions: Arc<Subscriptions>,
+    peripheral: P,
+    characteristic: Characteristic,
+}
+
+impl<P: Peripheral + 'static> Drop for StreamGuard<P> {
+    fn drop(&mut self) {
+        let key = self.characteristic.handle;
+        if let Some(entry) = self.subscriptions.entries.lock().unwrap().get_mut(&key) {
+            entry.streams -= 1;
+        }
+        // Without a runtime there is no way to unsubscribe, so the characteristic stays subscribed
+        // until another stream for it is created and dropped.
+        if let Ok(handle) = tokio::runtime::Handle::try_current() {
+            let subscriptions = self.subscriptions.clone();
+            let peripheral = self.peripheral.clone();
+            let characteristic = self.characteristic.clone();
+            handle.spawn(async move {
+                if let Err(e) = subscriptions.update(&peripheral, &characteristic).await {
+                    debug!(
+                        "Failed to unsubscribe from {}: {:?}",
+                        characteristic.uuid, e
+                    );
+                }
+            });
+        }
+    }
+}
@@ -0,0 +1,51 @@
+// btleplug Source Code File
+//
+// Copyright 2023 Nonpolynomial Labs LLC. All rights reserved.
+//
+// Licensed under the BSD 3-Clause license. See LICENSE file in the project root
+// for full license information.
+
+//! Enforcement of [`Timeouts`] for the backends.
+
+use crate::api::{Peripheral, Timeouts};
+use crate::{Error, Result};
+use log::debug;
+use std::future::Future;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// The default timeouts shared by a manager, its adapters and their peripherals.
+pub type SharedTimeouts = Arc<RwLock<Timeouts>>;
+
+/// Runs `operation`, failing with [`Error::TimedOut`] if it takes longer than `timeout`. The
+/// operation is dropped, and with that canceled, when it times out.
+pub async fn timeout<T>(
+    timeout: Option<Duration>,
+    operation: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    match timeout {
+        Some(duration) => tokio::time::timeout(duration, operation)
+            .await
+            .unwrap_or(Err(Error::TimedOut(duration))),
+        None => operation.await,
+    }
+}
+
+/// Like [`timeout`] for connecting to `peripheral`, which is disconnected when the attempt times
+/// out so that the platform doesn't carry on connecting in the background.
+pub async fn connect<P: Peripheral>(
+    peripheral: &P,
+    operation: impl Future<Output = Result<()>>,
+) -> Result<()> {
+    let result = timeout(peripheral.timeouts().connect, operation).await;
+    if let Err(Error::TimedOut(_)) = result {
+        if let Err(e) = peripheral.disconnect().await {
+            debug!(
+                "Failed to abort connecting to {:?}: {:?}",
+                peripheral.id(),
+                e
+            );
+        }
+    }
+    result
+}
@@ -0,0 +1,81 @@
+// btleplug Source Code File
+//
+// Copyright 2023 Nonpolynomial Labs LLC. All rights reserved.
+//
+// Licensed under the BSD 3-Clause license. See LICENSE file in the project root
+// for full license information.
+
+//! Shared implementation of [`Peripheral::write_all`] and [`Peripheral::read_all`].
+
+#[cfg(not(target_os = "linux"))]
+use crate::api::AttErrorCode;
+use crate::api::{Characteristic, Peripheral, TransferOptions, WriteType, DEFAULT_MTU};
+use crate::{Error, Result};
+
+/// Returns the negotiated MTU, or the default one on platforms which don't report it.
+async fn mtu<P: Peripheral>(peripheral: &P) -> Result<u16> {
+    match peripheral.mtu().await {
+        Err(Error::NotSupported(_)) => Ok(DEFAULT_MTU),
+        result => result,
+    }
+}
+
+pub async fn write_all<P: Peripheral>(
+    peripheral: &P,
+    characteristic: &Characteristic,
+    data: &[u8],
+    write_type: WriteType,
+    options: &TransferOptions,
+) -> Result<()> {
+    let chunk_size = match options.chunk_size {
+        Some(0) => return Err(Error::Other("Chunk size must not be zero".into())),
+        Some(chunk_size) => chunk_size,
+        // The ATT header takes the same 3 bytes of a Write Request as of a Write Command.
+        None => usize::from(mtu(peripheral).await?.saturating_sub(3)).max(1),
+    };
+    let mut transferred = 0;
+    for chunk in data.chunks(chunk_size) {
+        // Each write only completes once the platform has taken it, which keeps writes without
+        // response from overflowing its queue; pacing is for devices which need more time.
+        if transferred > 0 && write_type == WriteType::WithoutResponse {
+            if let Some(pacing) = options.pacing {
+                tokio::time::sleep(pacing).await;
+            }
+        }
+        peripheral.write(characteristic, chunk, write_type).await?;
+        transferred += chunk.len();
+        options.report(transferred, Some(data.len()));
+    }
+    Ok(())
+}
+
+pub async fn read_all<P: Peripheral>(
+    peripheral: &P,
+    characteristic: &Characteristic,
+    options: &TransferOptions,
+) -> Result<Vec<u8>> {
+    // A Read Blob Response carries up to MTU - 1 bytes, and a shorter one marks the end of the
+    // value. Platforms which read the whole remainder at once return more than that.
+    let max_chunk = usize::from(mtu(peripheral).await?.saturating_sub(1));
+    let mut value = Vec::new();
+    loop {
+        let chunk = peripheral.read_at(characteristic, value.len()).await?;
+        let done = chunk.len() != max_chunk;
+        value.extend(chunk);
+        options.report(value.len(), None);
+        if done {
+            return Ok(value);
+        }
+    }
+}
+
+/// Implements [`Peripheral::read_at`] for platforms which only read whole values, by reading the
+/// whole value and returning the part from `offset` on.
+#[cfg(not(target_os = "linux"))]
+pub fn value_from_offset(value: Vec<u8>, offset: usize) -> Result<Vec<u8>> {
+    // This is what a device responds to a Read Blob Request past the end of the value with.
+    if offset > value.len() {
+        return Err(Error::Att(AttErrorCode::InvalidOffset));
+    }
+    Ok(value[offset..].to_vec())
+}
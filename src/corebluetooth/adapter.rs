@@ -1,7 +1,10 @@
 use super::internal::{run_corebluetooth_thread, CoreBluetoothEvent, CoreBluetoothMessage};
 use super::peripheral::{Peripheral, PeripheralId};
-use crate::api::{Central, CentralEvent, ScanFilter};
-use crate::common::adapter_manager::AdapterManager;
+use crate::api::{
+    proximity::ProximityConfig, Central, CentralEvent, CentralState, DeviceExpiry, Presence,
+    ScanFilter, ScanMode, Timeouts,
+};
+use crate::common::{adapter_manager::AdapterManager, timeouts::SharedTimeouts};
 use crate::{Error, Result};
 use async_trait::async_trait;
 use futures::channel::mpsc::{self, Sender};
@@ -9,7 +12,7 @@ use futures::sink::SinkExt;
 use futures::stream::{Stream, StreamExt};
 use log::*;
 use std::pin::Pin;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use tokio::task;
 
 /// Implementation of [api::Central](crate::api::Central).
@@ -17,29 +20,32 @@ use tokio::task;
 pub struct Adapter {
     manager: Arc<AdapterManager<Peripheral>>,
     sender: Sender<CoreBluetoothMessage>,
+    state: Arc<Mutex<CentralState>>,
 }
 
 impl Adapter {
-    pub(crate) async fn new() -> Result<Self> {
+    pub(crate) async fn new(timeouts: SharedTimeouts) -> Result<Self> {
         let (sender, mut receiver) = mpsc::channel(256);
         let adapter_sender = run_corebluetooth_thread(sender)?;
         // Since init currently blocked until the state update, we know the
         // receiver is dropped after that. We can pick it up here and make it
         // part of our event loop to update our peripherals.
         debug!("Waiting on adapter connect");
-        if !matches!(
-            receiver.next().await,
-            Some(CoreBluetoothEvent::AdapterConnected)
-        ) {
-            return Err(Error::Other(
-                "Adapter failed to connect.".to_string().into(),
-            ));
-        }
-        debug!("Adapter connected");
-        let manager = Arc::new(AdapterManager::default());
+        let state = match receiver.next().await {
+            Some(CoreBluetoothEvent::StateUpdate { state }) => state,
+            _ => {
+                return Err(Error::Other(
+                    "Adapter failed to connect.".to_string().into(),
+                ))
+            }
+        };
+        debug!("Adapter connected in state {:?}", state);
+        let manager = Arc::new(AdapterManager::new(timeouts));
+        let state = Arc::new(Mutex::new(state));
 
         let manager_clone = manager.clone();
         let adapter_sender_clone = adapter_sender.clone();
+        let state_clone = state.clone();
         task::spawn(async move {
             while let Some(msg) = receiver.next().await {
                 match msg {
@@ -67,7 +73,10 @@ impl Adapter {
                     CoreBluetoothEvent::DeviceDisconnected { uuid } => {
                         manager_clone.emit(CentralEvent::DeviceDisconnected(uuid.into()));
                     }
-                    _ => {}
+                    CoreBluetoothEvent::StateUpdate { state } => {
+                        *state_clone.lock().unwrap() = state;
+                        manager_clone.emit(CentralEvent::StateUpdate(state));
+                    }
                 }
             }
         });
@@ -75,6 +84,7 @@ impl Adapter {
         Ok(Adapter {
             manager,
             sender: adapter_sender,
+            state,
         })
     }
 }
@@ -88,6 +98,12 @@ impl Central for Adapter {
     }
 
     async fn start_scan(&self, filter: ScanFilter) -> Result<()> {
+        if filter.mode == ScanMode::Passive {
+            return Err(Error::NotSupported(
+                "Core Bluetooth always scans actively".to_string(),
+            ));
+        }
+        self.manager.start_scan(filter.clone());
         self.sender
             .to_owned()
             .send(CoreBluetoothMessage::StartScanning { filter })
@@ -100,6 +116,7 @@ impl Central for Adapter {
             .to_owned()
             .send(CoreBluetoothMessage::StopScanning)
             .await?;
+        self.manager.stop_scan();
         Ok(())
     }
 
@@ -121,4 +138,34 @@ impl Central for Adapter {
         // TODO: Get information about the adapter.
         Ok("CoreBluetooth".to_string())
     }
+
+    async fn adapter_state(&self) -> Result<CentralState> {
+        Ok(*self.state.lock().unwrap())
+    }
+
+    async fn set_powered(&self, _powered: bool) -> Result<()> {
+        Err(Error::NotSupported(
+            "CoreBluetooth doesn't allow apps to turn Bluetooth on or off".to_string(),
+        ))
+    }
+
+    fn timeouts(&self) -> Timeouts {
+        self.manager.timeouts()
+    }
+
+    fn set_timeouts(&self, timeouts: Timeouts) {
+        self.manager.set_timeouts(timeouts);
+    }
+
+    fn presence(&self, id: &PeripheralId) -> Option<Presence> {
+        self.manager.presence(id)
+    }
+
+    fn set_device_expiry(&self, expiry: Option<DeviceExpiry>) {
+        self.manager.set_device_expiry(expiry);
+    }
+
+    fn set_proximity_config(&self, config: ProximityConfig) {
+        self.manager.set_proximity_config(config);
+    }
 }
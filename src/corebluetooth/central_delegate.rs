@@ -17,15 +17,22 @@
 // according to those terms.
 
 use super::{
+    error::CoreBluetoothError,
     framework::{cb, ns},
+    internal::CoreBluetoothReply,
     utils::{
-        core_bluetooth::{cbuuid_to_uuid, characteristic_debug, peripheral_debug, service_debug},
+        core_bluetooth::{
+            cbuuid_to_uuid, characteristic_debug, descriptor_debug, peripheral_debug, service_debug,
+        },
         nsdata_to_vec,
         nsstring::nsstring_to_string,
         nsuuid_to_uuid,
     },
 };
-use cocoa::base::{id, nil};
+use cocoa::{
+    base::{id, nil},
+    foundation::NSInteger,
+};
 use futures::channel::mpsc::{self, Receiver, Sender};
 use futures::sink::SinkExt;
 use libc::c_void;
@@ -48,14 +55,16 @@ use std::{
 use uuid::Uuid;
 
 pub enum CentralDelegateEvent {
-    DidUpdateState,
+    DidUpdateState {
+        state: NSInteger,
+    },
     DiscoveredPeripheral {
         cbperipheral: StrongPtr,
     },
     DiscoveredServices {
         peripheral_uuid: Uuid,
-        /// Service UUID to CBService
-        services: HashMap<Uuid, StrongPtr>,
+        /// The CBServices, in the order the peripheral reported them.
+        services: Vec<StrongPtr>,
     },
     ManufacturerData {
         peripheral_uuid: Uuid,
@@ -73,18 +82,22 @@ pub enum CentralDelegateEvent {
         service_uuids: Vec<Uuid>,
         rssi: i16,
     },
-    // DiscoveredIncludedServices(Uuid, HashMap<Uuid, StrongPtr>),
+    // From here on, attributes are identified by their CoreBluetooth objects rather than their
+    // UUIDs, as a device may have several with the same UUID.
+    DiscoveredIncludedServices {
+        peripheral_uuid: Uuid,
+        service: StrongPtr,
+        included_services: Vec<StrongPtr>,
+    },
     DiscoveredCharacteristics {
         peripheral_uuid: Uuid,
-        service_uuid: Uuid,
-        /// Characteristic UUID to CBCharacteristic
-        characteristics: HashMap<Uuid, StrongPtr>,
+        service: StrongPtr,
+        characteristics: Vec<StrongPtr>,
     },
     DiscoveredCharacteristicDescriptors {
         peripheral_uuid: Uuid,
-        service_uuid: Uuid,
-        characteristic_uuid: Uuid,
-        descriptors: HashMap<Uuid, StrongPtr>,
+        characteristic: StrongPtr,
+        descriptors: Vec<StrongPtr>,
     },
     ConnectedDevice {
         peripheral_uuid: Uuid,
@@ -97,44 +110,62 @@ pub enum CentralDelegateEvent {
     },
     CharacteristicSubscribed {
         peripheral_uuid: Uuid,
-        service_uuid: Uuid,
-        characteristic_uuid: Uuid,
+        characteristic: StrongPtr,
     },
     CharacteristicUnsubscribed {
         peripheral_uuid: Uuid,
-        service_uuid: Uuid,
-        characteristic_uuid: Uuid,
+        characteristic: StrongPtr,
     },
     CharacteristicNotified {
         peripheral_uuid: Uuid,
-        service_uuid: Uuid,
-        characteristic_uuid: Uuid,
+        characteristic: StrongPtr,
         data: Vec<u8>,
     },
     CharacteristicWritten {
         peripheral_uuid: Uuid,
-        service_uuid: Uuid,
-        characteristic_uuid: Uuid,
+        characteristic: StrongPtr,
+    },
+    ReadyToSendWriteWithoutResponse {
+        peripheral_uuid: Uuid,
+    },
+    CharacteristicReadFailed {
+        peripheral_uuid: Uuid,
+        characteristic: StrongPtr,
+        error: CoreBluetoothReply,
+    },
+    CharacteristicWriteFailed {
+        peripheral_uuid: Uuid,
+        characteristic: StrongPtr,
+        error: CoreBluetoothReply,
     },
     DescriptorNotified {
         peripheral_uuid: Uuid,
-        service_uuid: Uuid,
-        characteristic_uuid: Uuid,
-        descriptor_uuid: Uuid,
+        descriptor: StrongPtr,
         data: Vec<u8>,
     },
     DescriptorWritten {
         peripheral_uuid: Uuid,
-        service_uuid: Uuid,
-        characteristic_uuid: Uuid,
-        descriptor_uuid: Uuid,
+        descriptor: StrongPtr,
+    },
+    DescriptorReadFailed {
+        peripheral_uuid: Uuid,
+        descriptor: StrongPtr,
+        error: CoreBluetoothReply,
+    },
+    DescriptorWriteFailed {
+        peripheral_uuid: Uuid,
+        descriptor: StrongPtr,
+        error: CoreBluetoothReply,
     },
 }
 
 impl Debug for CentralDelegateEvent {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         match self {
-            CentralDelegateEvent::DidUpdateState => f.debug_tuple("DidUpdateState").finish(),
+            CentralDelegateEvent::DidUpdateState { state } => f
+                .debug_struct("DidUpdateState")
+                .field("state", state)
+                .finish(),
             CentralDelegateEvent::DiscoveredPeripheral { cbperipheral } => f
                 .debug_struct("CentralDelegateEvent")
                 .field("cbperipheral", cbperipheral.deref())
@@ -145,32 +176,61 @@ impl Debug for CentralDelegateEvent {
             } => f
                 .debug_struct("DiscoveredServices")
                 .field("peripheral_uuid", peripheral_uuid)
-                .field("services", &services.keys().collect::<Vec<_>>())
+                .field(
+                    "services",
+                    &services
+                        .iter()
+                        .map(|service| service_debug(**service))
+                        .collect::<Vec<_>>(),
+                )
+                .finish(),
+            CentralDelegateEvent::DiscoveredIncludedServices {
+                peripheral_uuid,
+                service,
+                included_services,
+            } => f
+                .debug_struct("DiscoveredIncludedServices")
+                .field("peripheral_uuid", peripheral_uuid)
+                .field("service", &service_debug(**service))
+                .field(
+                    "included_services",
+                    &included_services
+                        .iter()
+                        .map(|service| service_debug(**service))
+                        .collect::<Vec<_>>(),
+                )
                 .finish(),
             CentralDelegateEvent::DiscoveredCharacteristics {
                 peripheral_uuid,
-                service_uuid,
+                service,
                 characteristics,
             } => f
                 .debug_struct("DiscoveredCharacteristics")
                 .field("peripheral_uuid", peripheral_uuid)
-                .field("service_uuid", service_uuid)
+                .field("service", &service_debug(**service))
                 .field(
                     "characteristics",
-                    &characteristics.keys().collect::<Vec<_>>(),
+                    &characteristics
+                        .iter()
+                        .map(|characteristic| characteristic_debug(**characteristic))
+                        .collect::<Vec<_>>(),
                 )
                 .finish(),
             CentralDelegateEvent::DiscoveredCharacteristicDescriptors {
                 peripheral_uuid,
-                service_uuid,
-                characteristic_uuid,
+                characteristic,
                 descriptors,
             } => f
                 .debug_struct("DiscoveredCharacteristicDescriptors")
                 .field("peripheral_uuid", peripheral_uuid)
-                .field("service_uuid", service_uuid)
-                .field("characteristic_uuid", characteristic_uuid)
-                .field("descriptors", &descriptors.keys().collect::<Vec<_>>())
+                .field("characteristic", &characteristic_debug(**characteristic))
+                .field(
+                    "descriptors",
+                    &descriptors
+                        .iter()
+                        .map(|descriptor| descriptor_debug(**descriptor))
+                        .collect::<Vec<_>>(),
+                )
                 .finish(),
             CentralDelegateEvent::ConnectedDevice { peripheral_uuid } => f
                 .debug_struct("ConnectedDevice")
@@ -186,45 +246,61 @@ impl Debug for CentralDelegateEvent {
                 .finish(),
             CentralDelegateEvent::CharacteristicSubscribed {
                 peripheral_uuid,
-                service_uuid,
-                characteristic_uuid,
+                characteristic,
             } => f
                 .debug_struct("CharacteristicSubscribed")
                 .field("peripheral_uuid", peripheral_uuid)
-                .field("service_uuid", service_uuid)
-                .field("characteristic_uuid", characteristic_uuid)
+                .field("characteristic", &characteristic_debug(**characteristic))
                 .finish(),
             CentralDelegateEvent::CharacteristicUnsubscribed {
                 peripheral_uuid,
-                service_uuid,
-                characteristic_uuid,
+                characteristic,
             } => f
                 .debug_struct("CharacteristicUnsubscribed")
                 .field("peripheral_uuid", peripheral_uuid)
-                .field("service_uuid", service_uuid)
-                .field("characteristic_uuid", characteristic_uuid)
+                .field("characteristic", &characteristic_debug(**characteristic))
                 .finish(),
             CentralDelegateEvent::CharacteristicNotified {
                 peripheral_uuid,
-                service_uuid,
-                characteristic_uuid,
+                characteristic,
                 data,
             } => f
                 .debug_struct("CharacteristicNotified")
                 .field("peripheral_uuid", peripheral_uuid)
-                .field("service_uuid", service_uuid)
-                .field("characteristic_uuid", characteristic_uuid)
+                .field("characteristic", &characteristic_debug(**characteristic))
                 .field("data", data)
                 .finish(),
             CentralDelegateEvent::CharacteristicWritten {
                 peripheral_uuid,
-                service_uuid,
-                characteristic_uuid,
+                characteristic,
             } => f
                 .debug_struct("CharacteristicWritten")
-                .field("service_uuid", service_uuid)
                 .field("peripheral_uuid", peripheral_uuid)
-                .field("characteristic_uuid", characteristic_uuid)
+                .field("characteristic", &characteristic_debug(**characteristic))
+                .finish(),
+            CentralDelegateEvent::ReadyToSendWriteWithoutResponse { peripheral_uuid } => f
+                .debug_struct("ReadyToSendWriteWithoutResponse")
+                .field("peripheral_uuid", peripheral_uuid)
+                .finish(),
+            CentralDelegateEvent::CharacteristicReadFailed {
+                peripheral_uuid,
+                characteristic,
+                error,
+            } => f
+                .debug_struct("CharacteristicReadFailed")
+                .field("peripheral_uuid", peripheral_uuid)
+                .field("characteristic", &characteristic_debug(**characteristic))
+                .field("error", error)
+                .finish(),
+            CentralDelegateEvent::CharacteristicWriteFailed {
+                peripheral_uuid,
+                characteristic,
+                error,
+            } => f
+                .debug_struct("CharacteristicWriteFailed")
+                .field("peripheral_uuid", peripheral_uuid)
+                .field("characteristic", &characteristic_debug(**characteristic))
+                .field("error", error)
                 .finish(),
             CentralDelegateEvent::ManufacturerData {
                 peripheral_uuid,
@@ -260,38 +336,48 @@ impl Debug for CentralDelegateEvent {
                 .finish(),
             CentralDelegateEvent::DescriptorNotified {
                 peripheral_uuid,
-                service_uuid,
-                characteristic_uuid,
-                descriptor_uuid,
+                descriptor,
                 data,
             } => f
                 .debug_struct("DescriptorNotified")
                 .field("peripheral_uuid", peripheral_uuid)
-                .field("service_uuid", service_uuid)
-                .field("characteristic_uuid", characteristic_uuid)
-                .field("descriptor_uuid", descriptor_uuid)
+                .field("descriptor", &descriptor_debug(**descriptor))
                 .field("data", data)
                 .finish(),
             CentralDelegateEvent::DescriptorWritten {
                 peripheral_uuid,
-                service_uuid,
-                characteristic_uuid,
-                descriptor_uuid,
+                descriptor,
             } => f
                 .debug_struct("DescriptorWritten")
-                .field("service_uuid", service_uuid)
                 .field("peripheral_uuid", peripheral_uuid)
-                .field("characteristic_uuid", characteristic_uuid)
-                .field("descriptor_uuid", descriptor_uuid)
+                .field("descriptor", &descriptor_debug(**descriptor))
+                .finish(),
+            CentralDelegateEvent::DescriptorReadFailed {
+                peripheral_uuid,
+                descriptor,
+                error,
+            } => f
+                .debug_struct("DescriptorReadFailed")
+                .field("peripheral_uuid", peripheral_uuid)
+                .field("descriptor", &descriptor_debug(**descriptor))
+                .field("error", error)
+                .finish(),
+            CentralDelegateEvent::DescriptorWriteFailed {
+                peripheral_uuid,
+                descriptor,
+                error,
+            } => f
+                .debug_struct("DescriptorWriteFailed")
+                .field("peripheral_uuid", peripheral_uuid)
+                .field("descriptor", &descriptor_debug(**descriptor))
+                .field("error", error)
                 .finish(),
         }
     }
 }
 
 pub mod CentralDelegate {
-    use crate::corebluetooth::{
-        framework::ns::number_as_i64, utils::core_bluetooth::descriptor_debug,
-    };
+    use crate::corebluetooth::framework::ns::number_as_i64;
 
     use super::*;
 
@@ -360,6 +446,8 @@ pub mod CentralDelegate {
                                 delegate_peripheral_didupdatenotificationstateforcharacteristic_error as extern fn(&mut Object, Sel, id, id, id));
                 decl.add_method(sel!(peripheral:didWriteValueForCharacteristic:error:),
                                 delegate_peripheral_didwritevalueforcharacteristic_error as extern fn(&mut Object, Sel, id, id, id));
+                decl.add_method(sel!(peripheralIsReadyToSendWriteWithoutResponse:),
+                                delegate_peripheralisreadytosendwritewithoutresponse as extern fn(&mut Object, Sel, id));
                 decl.add_method(sel!(peripheral:didReadRSSI:error:),
                                 delegate_peripheral_didreadrssi_error as extern fn(&mut Object, Sel, id, id, id));
                 decl.add_method(sel!(peripheral:didUpdateValueForDescriptor:error:),
@@ -383,6 +471,17 @@ pub mod CentralDelegate {
         }
     }
 
+    /// Turns the error a read or write failed with into the reply to fail it with.
+    fn error_reply(error: id) -> CoreBluetoothReply {
+        let domain = unsafe { nsstring_to_string(msg_send![error, domain]) };
+        let code: isize = unsafe { msg_send![error, code] };
+        CoreBluetoothReply::Failed(CoreBluetoothError {
+            domain: domain.unwrap_or_default(),
+            code,
+            description: localized_description(error),
+        })
+    }
+
     ////////////////////////////////////////////////////////////////
     //
     // Utility functions
@@ -434,10 +533,11 @@ pub mod CentralDelegate {
     extern "C" fn delegate_centralmanagerdidupdatestate(
         delegate: &mut Object,
         _cmd: Sel,
-        _central: id,
+        central: id,
     ) {
         trace!("delegate_centralmanagerdidupdatestate");
-        send_delegate_event(delegate, CentralDelegateEvent::DidUpdateState);
+        let state = cb::centralmanager_state(central);
+        send_delegate_event(delegate, CentralDelegateEvent::DidUpdateState { state });
     }
 
     // extern fn delegate_centralmanager_willrestorestate(_delegate: &mut Object, _cmd: Sel, _central: id, _dict: id) {
@@ -608,7 +708,7 @@ pub mod CentralDelegate {
         );
         if error == nil {
             let services = cb::peripheral_services(peripheral);
-            let mut service_map = HashMap::new();
+            let mut held_services = Vec::new();
             for i in 0..ns::array_count(services) {
                 // get the service out of the services array
                 let s = ns::array_objectatindex(services, i);
@@ -617,27 +717,21 @@ pub mod CentralDelegate {
                 cb::peripheral_discovercharacteristicsforservice(peripheral, s);
                 cb::peripheral_discoverincludedservicesforservice(peripheral, s);
 
-                // Create the map entry we'll need to export.
-                let uuid = cbuuid_to_uuid(cb::attribute_uuid(s));
-                let held_service;
-                unsafe {
-                    held_service = StrongPtr::retain(s);
-                }
-                service_map.insert(uuid, held_service);
+                held_services.push(unsafe { StrongPtr::retain(s) });
             }
             let peripheral_uuid = nsuuid_to_uuid(cb::peer_identifier(peripheral));
             send_delegate_event(
                 delegate,
                 CentralDelegateEvent::DiscoveredServices {
                     peripheral_uuid,
-                    services: service_map,
+                    services: held_services,
                 },
             );
         }
     }
 
     extern "C" fn delegate_peripheral_diddiscoverincludedservicesforservice_error(
-        _delegate: &mut Object,
+        delegate: &mut Object,
         _cmd: Sel,
         peripheral: id,
         service: id,
@@ -649,13 +743,25 @@ pub mod CentralDelegate {
             service_debug(service),
             localized_description(error)
         );
+        // Service discovery waits for the includes of every service, so report a failure as
+        // there being none rather than leaving it hanging.
+        let mut included_services = Vec::new();
         if error == nil {
             let includes = cb::service_includedservices(service);
             for i in 0..ns::array_count(includes) {
                 let s = ns::array_objectatindex(includes, i);
-                cb::peripheral_discovercharacteristicsforservice(peripheral, s);
+                included_services.push(unsafe { StrongPtr::retain(s) });
             }
         }
+        let peripheral_uuid = nsuuid_to_uuid(cb::peer_identifier(peripheral));
+        send_delegate_event(
+            delegate,
+            CentralDelegateEvent::DiscoveredIncludedServices {
+                peripheral_uuid,
+                service: unsafe { StrongPtr::retain(service) },
+                included_services,
+            },
+        );
     }
 
     extern "C" fn delegate_peripheral_diddiscovercharacteristicsforservice_error(
@@ -672,23 +778,19 @@ pub mod CentralDelegate {
             localized_description(error)
         );
         if error == nil {
-            let mut characteristics = HashMap::new();
+            let mut characteristics = Vec::new();
             let chars = cb::service_characteristics(service);
             for i in 0..ns::array_count(chars) {
                 let c = ns::array_objectatindex(chars, i);
                 cb::peripheral_discoverdescriptorsforcharacteristic(peripheral, c);
-                // Create the map entry we'll need to export.
-                let uuid = cbuuid_to_uuid(cb::attribute_uuid(c));
-                let held_char = unsafe { StrongPtr::retain(c) };
-                characteristics.insert(uuid, held_char);
+                characteristics.push(unsafe { StrongPtr::retain(c) });
             }
             let peripheral_uuid = nsuuid_to_uuid(cb::peer_identifier(peripheral));
-            let service_uuid = cbuuid_to_uuid(cb::attribute_uuid(service));
             send_delegate_event(
                 delegate,
                 CentralDelegateEvent::DiscoveredCharacteristics {
                     peripheral_uuid,
-                    service_uuid,
+                    service: unsafe { StrongPtr::retain(service) },
                     characteristics,
                 },
             );
@@ -709,25 +811,18 @@ pub mod CentralDelegate {
             localized_description(error)
         );
         if error == nil {
-            let mut descriptors = HashMap::new();
+            let mut descriptors = Vec::new();
             let descs = cb::characteristic_descriptors(characteristic);
             for i in 0..ns::array_count(descs) {
                 let d = ns::array_objectatindex(descs, i);
-                // Create the map entry we'll need to export.
-                let uuid = cbuuid_to_uuid(cb::attribute_uuid(d));
-                let held_desc = unsafe { StrongPtr::retain(d) };
-                descriptors.insert(uuid, held_desc);
+                descriptors.push(unsafe { StrongPtr::retain(d) });
             }
             let peripheral_uuid = nsuuid_to_uuid(cb::peer_identifier(peripheral));
-            let service = cb::characteristic_service(characteristic);
-            let service_uuid = cbuuid_to_uuid(cb::attribute_uuid(service));
-            let characteristic_uuid = cbuuid_to_uuid(cb::attribute_uuid(characteristic));
             send_delegate_event(
                 delegate,
                 CentralDelegateEvent::DiscoveredCharacteristicDescriptors {
                     peripheral_uuid,
-                    service_uuid,
-                    characteristic_uuid,
+                    characteristic: unsafe { StrongPtr::retain(characteristic) },
                     descriptors,
                 },
             );
@@ -748,17 +843,24 @@ pub mod CentralDelegate {
             localized_description(error)
         );
         if error == nil {
-            let service = cb::characteristic_service(characteristic);
             send_delegate_event(
                 delegate,
                 CentralDelegateEvent::CharacteristicNotified {
                     peripheral_uuid: nsuuid_to_uuid(cb::peer_identifier(peripheral)),
-                    service_uuid: cbuuid_to_uuid(cb::attribute_uuid(service)),
-                    characteristic_uuid: cbuuid_to_uuid(cb::attribute_uuid(characteristic)),
+                    characteristic: unsafe { StrongPtr::retain(characteristic) },
                     data: get_characteristic_value(characteristic),
                 },
             );
             // Notify BluetoothGATTCharacteristic::read_value that read was successful.
+        } else {
+            send_delegate_event(
+                delegate,
+                CentralDelegateEvent::CharacteristicReadFailed {
+                    peripheral_uuid: nsuuid_to_uuid(cb::peer_identifier(peripheral)),
+                    characteristic: unsafe { StrongPtr::retain(characteristic) },
+                    error: error_reply(error),
+                },
+            );
         }
     }
 
@@ -776,18 +878,42 @@ pub mod CentralDelegate {
             localized_description(error)
         );
         if error == nil {
-            let service = cb::characteristic_service(characteristic);
             send_delegate_event(
                 delegate,
                 CentralDelegateEvent::CharacteristicWritten {
                     peripheral_uuid: nsuuid_to_uuid(cb::peer_identifier(peripheral)),
-                    service_uuid: cbuuid_to_uuid(cb::attribute_uuid(service)),
-                    characteristic_uuid: cbuuid_to_uuid(cb::attribute_uuid(characteristic)),
+                    characteristic: unsafe { StrongPtr::retain(characteristic) },
+                },
+            );
+        } else {
+            send_delegate_event(
+                delegate,
+                CentralDelegateEvent::CharacteristicWriteFailed {
+                    peripheral_uuid: nsuuid_to_uuid(cb::peer_identifier(peripheral)),
+                    characteristic: unsafe { StrongPtr::retain(characteristic) },
+                    error: error_reply(error),
                 },
             );
         }
     }
 
+    extern "C" fn delegate_peripheralisreadytosendwritewithoutresponse(
+        delegate: &mut Object,
+        _cmd: Sel,
+        peripheral: id,
+    ) {
+        trace!(
+            "delegate_peripheralisreadytosendwritewithoutresponse {}",
+            peripheral_debug(peripheral)
+        );
+        send_delegate_event(
+            delegate,
+            CentralDelegateEvent::ReadyToSendWriteWithoutResponse {
+                peripheral_uuid: nsuuid_to_uuid(cb::peer_identifier(peripheral)),
+            },
+        );
+    }
+
     extern "C" fn delegate_peripheral_didupdatenotificationstateforcharacteristic_error(
         delegate: &mut Object,
         _cmd: Sel,
@@ -798,16 +924,13 @@ pub mod CentralDelegate {
         trace!("delegate_peripheral_didupdatenotificationstateforcharacteristic_error");
         // TODO check for error here
         let peripheral_uuid = nsuuid_to_uuid(cb::peer_identifier(peripheral));
-        let service = cb::characteristic_service(characteristic);
-        let service_uuid = cbuuid_to_uuid(cb::attribute_uuid(service));
-        let characteristic_uuid = cbuuid_to_uuid(cb::attribute_uuid(characteristic));
+        let held_characteristic = unsafe { StrongPtr::retain(characteristic) };
         if cb::characteristic_isnotifying(characteristic) == objc::runtime::YES {
             send_delegate_event(
                 delegate,
                 CentralDelegateEvent::CharacteristicSubscribed {
                     peripheral_uuid,
-                    service_uuid,
-                    characteristic_uuid,
+                    characteristic: held_characteristic,
                 },
             );
         } else {
@@ -815,8 +938,7 @@ pub mod CentralDelegate {
                 delegate,
                 CentralDelegateEvent::CharacteristicUnsubscribed {
                     peripheral_uuid,
-                    service_uuid,
-                    characteristic_uuid,
+                    characteristic: held_characteristic,
                 },
             );
         }
@@ -851,18 +973,24 @@ pub mod CentralDelegate {
         );
         if error == nil {
             let characteristic = cb::descriptor_characteristic(descriptor);
-            let service = cb::characteristic_service(characteristic);
             send_delegate_event(
                 delegate,
                 CentralDelegateEvent::DescriptorNotified {
                     peripheral_uuid: nsuuid_to_uuid(cb::peer_identifier(peripheral)),
-                    service_uuid: cbuuid_to_uuid(cb::attribute_uuid(service)),
-                    characteristic_uuid: cbuuid_to_uuid(cb::attribute_uuid(characteristic)),
-                    descriptor_uuid: cbuuid_to_uuid(cb::attribute_uuid(descriptor)),
+                    descriptor: unsafe { StrongPtr::retain(descriptor) },
                     data: get_characteristic_value(characteristic),
                 },
             );
             // Notify BluetoothGATTCharacteristic::read_value that read was successful.
+        } else {
+            send_delegate_event(
+                delegate,
+                CentralDelegateEvent::DescriptorReadFailed {
+                    peripheral_uuid: nsuuid_to_uuid(cb::peer_identifier(peripheral)),
+                    descriptor: unsafe { StrongPtr::retain(descriptor) },
+                    error: error_reply(error),
+                },
+            );
         }
     }
 
@@ -880,15 +1008,20 @@ pub mod CentralDelegate {
             localized_description(error)
         );
         if error == nil {
-            let characteristic = cb::descriptor_characteristic(descriptor);
-            let service = cb::characteristic_service(characteristic);
             send_delegate_event(
                 delegate,
                 CentralDelegateEvent::DescriptorWritten {
                     peripheral_uuid: nsuuid_to_uuid(cb::peer_identifier(peripheral)),
-                    service_uuid: cbuuid_to_uuid(cb::attribute_uuid(service)),
-                    characteristic_uuid: cbuuid_to_uuid(cb::attribute_uuid(characteristic)),
-                    descriptor_uuid: cbuuid_to_uuid(cb::attribute_uuid(descriptor)),
+                    descriptor: unsafe { StrongPtr::retain(descriptor) },
+                },
+            );
+        } else {
+            send_delegate_event(
+                delegate,
+                CentralDelegateEvent::DescriptorWriteFailed {
+                    peripheral_uuid: nsuuid_to_uuid(cb::peer_identifier(peripheral)),
+                    descriptor: unsafe { StrongPtr::retain(descriptor) },
+                    error: error_reply(error),
                 },
             );
         }
@@ -0,0 +1,122 @@
+// btleplug Source Code File
+//
+// Copyright 2023 Nonpolynomial Labs LLC. All rights reserved.
+//
+// Licensed under the BSD 3-Clause license. See LICENSE file in the project root
+// for full license information.
+
+use crate::Error;
+
+const CB_ERROR_DOMAIN: &str = "CBErrorDomain";
+const CB_ATT_ERROR_DOMAIN: &str = "CBATTErrorDomain";
+
+// Codes of CBErrorDomain.
+const CB_ERROR_NOT_CONNECTED: isize = 3;
+const CB_ERROR_PERIPHERAL_DISCONNECTED: isize = 7;
+const CB_ERROR_UNKNOWN_DEVICE: isize = 12;
+const CB_ERROR_OPERATION_NOT_SUPPORTED: isize = 13;
+const CB_ERROR_PEER_REMOVED_PAIRING_INFORMATION: isize = 14;
+const CB_ERROR_ENCRYPTION_TIMED_OUT: isize = 15;
+
+/// The `NSError` which CoreBluetooth reported an operation failed with.
+#[derive(Clone, Debug)]
+pub struct CoreBluetoothError {
+    pub domain: String,
+    pub code: isize,
+    pub description: String,
+}
+
+/// Translates the errors of CoreBluetooth. Those of `CBATTErrorDomain` are the ATT error the
+/// device responded with, and those of `CBErrorDomain` which don't correspond to any other variant
+/// are kept as their description in [`Error::Other`].
+impl From<CoreBluetoothError> for Error {
+    fn from(error: CoreBluetoothError) -> Self {
+        match (error.domain.as_str(), error.code) {
+            (CB_ATT_ERROR_DOMAIN, code) => match u8::try_from(code) {
+                Ok(code) => Error::Att(code.into()),
+                Err(_) => Error::Other(error.description.into()),
+            },
+            (CB_ERROR_DOMAIN, CB_ERROR_NOT_CONNECTED | CB_ERROR_PERIPHERAL_DISCONNECTED) => {
+                Error::NotConnected
+            }
+            (CB_ERROR_DOMAIN, CB_ERROR_UNKNOWN_DEVICE) => Error::DeviceNotFound,
+            (CB_ERROR_DOMAIN, CB_ERROR_OPERATION_NOT_SUPPORTED) => {
+                Error::NotSupported(error.description)
+            }
+            (
+                CB_ERROR_DOMAIN,
+                CB_ERROR_PEER_REMOVED_PAIRING_INFORMATION | CB_ERROR_ENCRYPTION_TIMED_OUT,
+            ) => Error::AuthenticationFailed,
+            _ => Error::Other(error.description.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::AttErrorCode;
+
+    fn translate(domain: &str, code: isize) -> Error {
+        CoreBluetoothError {
+            domain: domain.to_string(),
+            code,
+            description: "Description".to_string(),
+        }
+        .into()
+    }
+
+    #[test]
+    fn att_errors() {
+        assert!(matches!(
+            translate(CB_ATT_ERROR_DOMAIN, 0x03),
+            Error::Att(AttErrorCode::WriteNotPermitted)
+        ));
+        assert!(matches!(
+            translate(CB_ATT_ERROR_DOMAIN, 0x0f),
+            Error::Att(AttErrorCode::InsufficientEncryption)
+        ));
+        assert!(matches!(
+            translate(CB_ATT_ERROR_DOMAIN, 0x80),
+            Error::Att(AttErrorCode::Application(0x80))
+        ));
+        assert!(matches!(
+            translate(CB_ATT_ERROR_DOMAIN, 0x100),
+            Error::Other(_)
+        ));
+    }
+
+    #[test]
+    fn core_bluetooth_errors() {
+        assert!(matches!(
+            translate(CB_ERROR_DOMAIN, CB_ERROR_NOT_CONNECTED),
+            Error::NotConnected
+        ));
+        assert!(matches!(
+            translate(CB_ERROR_DOMAIN, CB_ERROR_PERIPHERAL_DISCONNECTED),
+            Error::NotConnected
+        ));
+        assert!(matches!(
+            translate(CB_ERROR_DOMAIN, CB_ERROR_UNKNOWN_DEVICE),
+            Error::DeviceNotFound
+        ));
+        assert!(matches!(
+            translate(CB_ERROR_DOMAIN, CB_ERROR_OPERATION_NOT_SUPPORTED),
+            Error::NotSupported(description) if description == "Description"
+        ));
+        assert!(matches!(
+            translate(CB_ERROR_DOMAIN, CB_ERROR_PEER_REMOVED_PAIRING_INFORMATION),
+            Error::AuthenticationFailed
+        ));
+        assert!(matches!(
+            translate(CB_ERROR_DOMAIN, CB_ERROR_ENCRYPTION_TIMED_OUT),
+            Error::AuthenticationFailed
+        ));
+        // CBErrorConnectionTimeout
+        assert!(matches!(translate(CB_ERROR_DOMAIN, 6), Error::Other(_)));
+        assert!(matches!(
+            translate("NSPOSIXErrorDomain", 3),
+            Error::Other(_)
+        ));
+    }
+}
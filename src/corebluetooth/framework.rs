@@ -18,7 +18,7 @@
 
 use cocoa::{
     base::{id, nil},
-    foundation::{NSArray, NSData, NSDictionary, NSString, NSUInteger},
+    foundation::{NSArray, NSData, NSDictionary, NSInteger, NSString, NSUInteger},
 };
 use objc::runtime::BOOL;
 use objc::{class, msg_send, sel, sel_impl};
@@ -154,6 +154,10 @@ pub mod cb {
         }
     }
 
+    pub fn centralmanager_state(cbcentralmanager: id) -> NSInteger /* CBManagerState */ {
+        unsafe { msg_send![cbcentralmanager, state] }
+    }
+
     pub fn centralmanager_stopscan(cbcentralmanager: id) {
         unsafe { msg_send![cbcentralmanager, stopScan] }
     }
@@ -177,6 +181,11 @@ pub mod cb {
         unsafe { msg_send![class!(CBManager), authorization] }
     }
 
+    // CBManagerState = NSInteger from CBManager.h
+    pub const MANAGERSTATE_UNAUTHORIZED: NSInteger = 3; // CBManagerStateUnauthorized
+    pub const MANAGERSTATE_POWEREDOFF: NSInteger = 4; // CBManagerStatePoweredOff
+    pub const MANAGERSTATE_POWEREDON: NSInteger = 5; // CBManagerStatePoweredOn
+
     #[derive(Copy, Clone, Debug, Eq, PartialEq)]
     #[repr(i64)]
     pub enum CBManagerAuthorization {
@@ -256,6 +265,17 @@ pub mod cb {
         }
     }
 
+    pub fn peripheral_cansendwritewithoutresponse(cbperipheral: id) -> BOOL {
+        unsafe { msg_send![cbperipheral, canSendWriteWithoutResponse] }
+    }
+
+    pub fn peripheral_maximumwritevaluelengthfortype(
+        cbperipheral: id,
+        write_type: usize, /* CBCharacteristicWriteType */
+    ) -> usize {
+        unsafe { msg_send![cbperipheral, maximumWriteValueLengthForType: write_type] }
+    }
+
     pub fn peripheral_setnotifyvalue_forcharacteristic(
         cbperipheral: id,
         value: BOOL,
@@ -329,10 +349,6 @@ pub mod cb {
         unsafe { msg_send![cbcharacteristic, properties] }
     }
 
-    pub fn characteristic_service(cbcharacteristic: id) -> id /* CBService* */ {
-        unsafe { msg_send![cbcharacteristic, service] }
-    }
-
     pub fn characteristic_descriptors(cbcharacteristic: id) -> id /* NSArray<CBDescriptor*>* */ {
         unsafe { msg_send![cbcharacteristic, descriptors] }
     }
@@ -10,32 +10,38 @@
 
 use super::{
     central_delegate::{CentralDelegate, CentralDelegateEvent},
+    error::CoreBluetoothError,
     framework::{
         cb::{self, CBManagerAuthorization, CBPeripheralState},
         ns,
     },
     future::{BtlePlugFuture, BtlePlugFutureStateShared},
     utils::{
-        core_bluetooth::{cbuuid_to_uuid, uuid_to_cbuuid},
+        core_bluetooth::{
+            cbuuid_to_uuid, characteristic_debug, descriptor_debug, service_debug, uuid_to_cbuuid,
+        },
         nsstring::nsstring_to_string,
         nsuuid_to_uuid,
     },
 };
 use crate::api::{
-    bleuuid::uuid_from_u16, CharPropFlags, Characteristic, Descriptor, ScanFilter, Service,
-    WriteType,
+    bleuuid::uuid_from_u16, CentralState, CharPropFlags, Characteristic, Descriptor, ScanFilter,
+    Service, ValueNotification, WriteType,
 };
 use crate::Error;
 use cocoa::{
     base::{id, nil},
-    foundation::NSArray,
+    foundation::{NSArray, NSInteger},
 };
 use futures::channel::mpsc::{self, Receiver, Sender};
 use futures::select;
 use futures::sink::SinkExt;
 use futures::stream::{Fuse, StreamExt};
 use log::{error, trace, warn};
-use objc::{rc::StrongPtr, runtime::YES};
+use objc::{
+    rc::StrongPtr,
+    runtime::{NO, YES},
+};
 use std::{
     collections::{BTreeSet, HashMap, VecDeque},
     fmt::{self, Debug, Formatter},
@@ -68,7 +74,7 @@ struct CBCharacteristic {
     pub characteristic: StrongPtr,
     pub uuid: Uuid,
     pub properties: CharPropFlags,
-    pub descriptors: HashMap<Uuid, CBDescriptor>,
+    pub descriptors: HashMap<u16, CBDescriptor>,
     pub read_future_state: VecDeque<CoreBluetoothReplyStateShared>,
     pub write_future_state: VecDeque<CoreBluetoothReplyStateShared>,
     pub subscribe_future_state: VecDeque<CoreBluetoothReplyStateShared>,
@@ -91,21 +97,15 @@ impl Debug for CBCharacteristic {
 }
 
 impl CBCharacteristic {
+    /// Wraps a characteristic whose descriptors are yet to be discovered.
     pub fn new(characteristic: StrongPtr) -> Self {
         let properties = CBCharacteristic::form_flags(*characteristic);
         let uuid = cbuuid_to_uuid(cb::attribute_uuid(*characteristic));
-        let descriptors_arr = cb::characteristic_descriptors(*characteristic);
-        let mut descriptors = HashMap::new();
-        for i in 0..ns::array_count(descriptors_arr) {
-            let d = ns::array_objectatindex(descriptors_arr, i);
-            let descriptor = CBDescriptor::new(unsafe { StrongPtr::retain(d) });
-            descriptors.insert(descriptor.uuid, descriptor);
-        }
         Self {
             characteristic,
             uuid,
             properties,
-            descriptors,
+            descriptors: HashMap::new(),
             read_future_state: VecDeque::with_capacity(10),
             write_future_state: VecDeque::with_capacity(10),
             subscribe_future_state: VecDeque::with_capacity(10),
@@ -148,14 +148,16 @@ pub enum CoreBluetoothReply {
     ReadResult(Vec<u8>),
     Connected(BTreeSet<Service>),
     State(CBPeripheralState),
+    Mtu(u16),
     Ok,
+    Failed(CoreBluetoothError),
     Err(String),
 }
 
 #[derive(Debug)]
 pub enum CBPeripheralEvent {
     Disconnected,
-    Notification(Uuid, Vec<u8>),
+    Notification(ValueNotification),
     ManufacturerData(u16, Vec<u8>, i16),
     ServiceData(HashMap<Uuid, Vec<u8>>, i16),
     Services(Vec<Uuid>, i16),
@@ -166,13 +168,46 @@ pub type CoreBluetoothReplyFuture = BtlePlugFuture<CoreBluetoothReply>;
 
 struct ServiceInternal {
     cbservice: StrongPtr,
-    characteristics: HashMap<Uuid, CBCharacteristic>,
+    uuid: Uuid,
+    /// The handles of the included services, in the order CoreBluetooth lists them.
+    included_services: Vec<u16>,
+    characteristics: HashMap<u16, CBCharacteristic>,
     pub discovered: bool,
+    pub includes_discovered: bool,
+}
+
+impl ServiceInternal {
+    fn new(cbservice: StrongPtr) -> Self {
+        let uuid = cbuuid_to_uuid(cb::attribute_uuid(*cbservice));
+        Self {
+            cbservice,
+            uuid,
+            included_services: Vec::new(),
+            characteristics: HashMap::new(),
+            discovered: false,
+            includes_discovered: false,
+        }
+    }
 }
 
+/// A write without response waiting for CoreBluetooth to have room for it.
+struct PendingWrite {
+    characteristic: StrongPtr,
+    data: Vec<u8>,
+    future: CoreBluetoothReplyStateShared,
+}
+
+/// The attributes of a peripheral are keyed by handle, as a device may have several services,
+/// characteristics or descriptors with the same UUID. CoreBluetooth doesn't expose the real ATT
+/// handles, so each attribute is assigned an identifier as it is discovered, and once discovery is
+/// complete they are all renumbered by [`assign_handles`](Self::assign_handles).
 struct CBPeripheral {
     pub peripheral: StrongPtr,
-    services: HashMap<Uuid, ServiceInternal>,
+    services: HashMap<u16, ServiceInternal>,
+    /// The handles of the services the peripheral lists, in its order.
+    service_order: Vec<u16>,
+    last_handle: u16,
+    pending_writes: VecDeque<PendingWrite>,
     pub event_sender: Sender<CBPeripheralEvent>,
     pub disconnected_future_state: Option<CoreBluetoothReplyStateShared>,
     pub connected_future_state: Option<CoreBluetoothReplyStateShared>,
@@ -187,7 +222,9 @@ impl Debug for CBPeripheral {
                 &self
                     .services
                     .iter()
-                    .map(|(service_uuid, service)| (service_uuid, service.characteristics.len()))
+                    .map(|(handle, service)| {
+                        ((handle, service.uuid), service.characteristics.len())
+                    })
                     .collect::<HashMap<_, _>>(),
             )
             .field("event_sender", &self.event_sender)
@@ -201,26 +238,75 @@ impl CBPeripheral {
         Self {
             peripheral,
             services: HashMap::new(),
+            service_order: Vec::new(),
+            last_handle: 0,
+            pending_writes: VecDeque::new(),
             event_sender,
             connected_future_state: None,
             disconnected_future_state: None,
         }
     }
 
-    pub fn set_characteristics(
-        &mut self,
-        service_uuid: Uuid,
-        characteristics: HashMap<Uuid, StrongPtr>,
-    ) {
+    fn next_handle(&mut self) -> u16 {
+        self.last_handle += 1;
+        self.last_handle
+    }
+
+    pub fn set_services(&mut self, services: Vec<StrongPtr>) {
+        self.last_handle = 0;
+        self.services = services
+            .into_iter()
+            .map(|cbservice| (self.next_handle(), ServiceInternal::new(cbservice)))
+            .collect();
+        self.service_order = (1..=self.last_handle).collect();
+    }
+
+    /// Records the services included by a service. CoreBluetooth only lists primary services on
+    /// the peripheral, so any other service is added here, and discovery started for it.
+    pub fn set_included_services(&mut self, cbservice: id, included_services: Vec<StrongPtr>) {
+        let service_handle = match self.find_service(cbservice) {
+            Some(service_handle) => service_handle,
+            None => {
+                warn!("Got included services for a service we don't know about");
+                return;
+            }
+        };
+        let mut handles = Vec::new();
+        for included in included_services {
+            let known = self
+                .services
+                .iter()
+                .find(|(_, service)| *service.cbservice == *included)
+                .map(|(&handle, _)| handle);
+            let handle = match known {
+                Some(handle) => handle,
+                None => {
+                    cb::peripheral_discovercharacteristicsforservice(*self.peripheral, *included);
+                    cb::peripheral_discoverincludedservicesforservice(*self.peripheral, *included);
+                    let handle = self.next_handle();
+                    self.services.insert(handle, ServiceInternal::new(included));
+                    handle
+                }
+            };
+            if !handles.contains(&handle) {
+                handles.push(handle);
+            }
+        }
+        let service = self.services.get_mut(&service_handle).unwrap();
+        service.included_services = handles;
+        service.includes_discovered = true;
+        self.check_discovered();
+    }
+
+    pub fn set_characteristics(&mut self, cbservice: id, characteristics: Vec<StrongPtr>) {
         let characteristics = characteristics
             .into_iter()
-            .map(|(characteristic_uuid, characteristic)| {
-                (characteristic_uuid, CBCharacteristic::new(characteristic))
-            })
+            .map(|characteristic| (self.next_handle(), CBCharacteristic::new(characteristic)))
             .collect();
         let service = self
             .services
-            .get_mut(&service_uuid)
+            .values_mut()
+            .find(|service| *service.cbservice == cbservice)
             .expect("Got characteristics for a service we don't know about");
         service.characteristics = characteristics;
         if service.characteristics.is_empty() {
@@ -231,22 +317,21 @@ impl CBPeripheral {
 
     pub fn set_characteristic_descriptors(
         &mut self,
-        service_uuid: Uuid,
-        characteristic_uuid: Uuid,
-        descriptors: HashMap<Uuid, StrongPtr>,
+        cbcharacteristic: id,
+        descriptors: Vec<StrongPtr>,
     ) {
         let descriptors = descriptors
             .into_iter()
-            .map(|(descriptor_uuid, descriptor)| (descriptor_uuid, CBDescriptor::new(descriptor)))
+            .map(|descriptor| (self.next_handle(), CBDescriptor::new(descriptor)))
             .collect();
-        let service = self
-            .services
-            .get_mut(&service_uuid)
-            .expect("Got descriptors for a service we don't know about");
+        let (service_handle, characteristic_handle) = self
+            .find_characteristic(cbcharacteristic)
+            .expect("Got descriptors for a characteristic we don't know about");
+        let service = self.services.get_mut(&service_handle).unwrap();
         let characteristic = service
             .characteristics
-            .get_mut(&characteristic_uuid)
-            .expect("Got descriptors for a characteristic we don't know about");
+            .get_mut(&characteristic_handle)
+            .unwrap();
         characteristic.descriptors = descriptors;
         characteristic.discovered = true;
 
@@ -260,6 +345,44 @@ impl CBPeripheral {
         }
     }
 
+    /// Returns the handle of the service which is the given CBService.
+    fn find_service(&self, cbservice: id) -> Option<u16> {
+        self.services
+            .iter()
+            .find(|(_, service)| *service.cbservice == cbservice)
+            .map(|(&handle, _)| handle)
+    }
+
+    /// Returns the handles of the service and characteristic which are the given CBCharacteristic.
+    fn find_characteristic(&self, cbcharacteristic: id) -> Option<(u16, u16)> {
+        self.services.iter().find_map(|(&service_handle, service)| {
+            service
+                .characteristics
+                .iter()
+                .find(|(_, characteristic)| *characteristic.characteristic == cbcharacteristic)
+                .map(|(&characteristic_handle, _)| (service_handle, characteristic_handle))
+        })
+    }
+
+    /// Returns the handles of the service, characteristic and descriptor which are the given
+    /// CBDescriptor.
+    fn find_descriptor(&self, cbdescriptor: id) -> Option<(u16, u16, u16)> {
+        self.services.iter().find_map(|(&service_handle, service)| {
+            service
+                .characteristics
+                .iter()
+                .find_map(|(&characteristic_handle, characteristic)| {
+                    characteristic
+                        .descriptors
+                        .iter()
+                        .find(|(_, descriptor)| *descriptor.descriptor == cbdescriptor)
+                        .map(|(&descriptor_handle, _)| {
+                            (service_handle, characteristic_handle, descriptor_handle)
+                        })
+                })
+        })
+    }
+
     fn check_discovered(&mut self) {
         // It's time for QUESTIONABLE ASSUMPTIONS.
         //
@@ -269,32 +392,44 @@ impl CBPeripheral {
         // service map. Once that's done, we're filled out enough and can send
         // back a Connected reply to the waiting future with all of the
         // characteristic info in it.
-        if !self.services.values().any(|service| !service.discovered) {
+        if self
+            .services
+            .values()
+            .all(|service| service.discovered && service.includes_discovered)
+        {
             if self.connected_future_state.is_none() {
                 panic!("We should still have a future at this point!");
             }
+            self.assign_handles();
             let services = self
                 .services
                 .iter()
-                .map(|(&service_uuid, service)| Service {
-                    uuid: service_uuid,
+                .map(|(&service_handle, service)| Service {
+                    uuid: service.uuid,
+                    handle: service_handle,
                     primary: cb::service_isprimary(*service.cbservice) != objc::runtime::NO,
+                    included_services: service.included_services.iter().copied().collect(),
                     characteristics: service
                         .characteristics
                         .iter()
-                        .map(|(&characteristic_uuid, characteristic)| {
+                        .map(|(&characteristic_handle, characteristic)| {
                             let descriptors = characteristic
                                 .descriptors
                                 .iter()
-                                .map(|(&descriptor_uuid, _)| Descriptor {
-                                    uuid: descriptor_uuid,
-                                    service_uuid,
-                                    characteristic_uuid,
+                                .map(|(&descriptor_handle, descriptor)| Descriptor {
+                                    uuid: descriptor.uuid,
+                                    service_uuid: service.uuid,
+                                    characteristic_uuid: characteristic.uuid,
+                                    handle: descriptor_handle,
+                                    service_handle,
+                                    characteristic_handle,
                                 })
                                 .collect();
                             Characteristic {
-                                uuid: characteristic_uuid,
-                                service_uuid,
+                                uuid: characteristic.uuid,
+                                service_uuid: service.uuid,
+                                handle: characteristic_handle,
+                                service_handle,
                                 descriptors,
                                 properties: characteristic.properties,
                             }
@@ -311,12 +446,99 @@ impl CBPeripheral {
         }
     }
 
+    /// Hands writes without response over to CoreBluetooth for as long as it has room for them,
+    /// and completes each once it has been handed over. CoreBluetooth drops writes without
+    /// response made while it has no room, and calls
+    /// `peripheralIsReadyToSendWriteWithoutResponse:` once it has room again.
+    fn send_pending_writes(&mut self) {
+        while !self.pending_writes.is_empty()
+            && cb::peripheral_cansendwritewithoutresponse(*self.peripheral) != NO
+        {
+            let write = self.pending_writes.pop_front().unwrap();
+            cb::peripheral_writevalue_forcharacteristic(
+                *self.peripheral,
+                ns::data(&write.data),
+                *write.characteristic,
+                1,
+            );
+            write
+                .future
+                .lock()
+                .unwrap()
+                .set_reply(CoreBluetoothReply::Ok);
+        }
+    }
+
+    /// Replaces the handles assigned during discovery, which depend on the order CoreBluetooth
+    /// happened to report the attributes of different services in, with handles laid out like the
+    /// attributes of a device: each service is followed by its characteristics, and each
+    /// characteristic by its descriptors, in the order CoreBluetooth lists them. Services are in
+    /// the order the peripheral lists them, followed by those only found as included services in
+    /// the order they were first included.
+    fn assign_handles(&mut self) {
+        let mut order = self.service_order.clone();
+        let mut next = 0;
+        while next < order.len() {
+            for &included in &self.services[&order[next]].included_services {
+                if !order.contains(&included) {
+                    order.push(included);
+                }
+            }
+            next += 1;
+        }
+
+        let mut discovered = std::mem::take(&mut self.services);
+        let mut service_handles = HashMap::new();
+        let mut handle = 0;
+        for old_handle in order {
+            let mut service = discovered.remove(&old_handle).unwrap();
+            handle += 1;
+            let service_handle = handle;
+            service_handles.insert(old_handle, service_handle);
+            // Attributes reported together were numbered in the order they were listed in.
+            let mut characteristics: Vec<_> = service.characteristics.drain().collect();
+            characteristics.sort_by_key(|(old_handle, _)| *old_handle);
+            for (_, mut characteristic) in characteristics {
+                handle += 1;
+                let characteristic_handle = handle;
+                let mut descriptors: Vec<_> = characteristic.descriptors.drain().collect();
+                descriptors.sort_by_key(|(old_handle, _)| *old_handle);
+                for (_, descriptor) in descriptors {
+                    handle += 1;
+                    characteristic.descriptors.insert(handle, descriptor);
+                }
+                service
+                    .characteristics
+                    .insert(characteristic_handle, characteristic);
+            }
+            self.services.insert(service_handle, service);
+        }
+        for service in self.services.values_mut() {
+            for included in &mut service.included_services {
+                *included = service_handles[included];
+            }
+        }
+        self.service_order = self
+            .service_order
+            .iter()
+            .map(|old_handle| service_handles[old_handle])
+            .collect();
+        self.last_handle = handle;
+    }
+
     pub fn confirm_disconnect(&mut self) {
         // Fulfill the disconnected future, if there is one.
         // There might not be a future if the device disconnects unexpectedly.
         if let Some(future) = self.disconnected_future_state.take() {
             future.lock().unwrap().set_reply(CoreBluetoothReply::Ok)
         }
+        for write in self.pending_writes.drain(..) {
+            write
+                .future
+                .lock()
+                .unwrap()
+                .set_reply(CoreBluetoothReply::Err(String::from("Device disconnected")));
+        }
     }
 }
 
@@ -365,46 +587,50 @@ pub enum CoreBluetoothMessage {
     },
     ReadValue {
         peripheral_uuid: Uuid,
-        service_uuid: Uuid,
-        characteristic_uuid: Uuid,
+        service_handle: u16,
+        characteristic_handle: u16,
         future: CoreBluetoothReplyStateShared,
     },
     WriteValue {
         peripheral_uuid: Uuid,
-        service_uuid: Uuid,
-        characteristic_uuid: Uuid,
+        service_handle: u16,
+        characteristic_handle: u16,
         data: Vec<u8>,
         write_type: WriteType,
         future: CoreBluetoothReplyStateShared,
     },
     Subscribe {
         peripheral_uuid: Uuid,
-        service_uuid: Uuid,
-        characteristic_uuid: Uuid,
+        service_handle: u16,
+        characteristic_handle: u16,
         future: CoreBluetoothReplyStateShared,
     },
     Unsubscribe {
         peripheral_uuid: Uuid,
-        service_uuid: Uuid,
-        characteristic_uuid: Uuid,
+        service_handle: u16,
+        characteristic_handle: u16,
         future: CoreBluetoothReplyStateShared,
     },
     IsConnected {
         peripheral_uuid: Uuid,
         future: CoreBluetoothReplyStateShared,
     },
+    Mtu {
+        peripheral_uuid: Uuid,
+        future: CoreBluetoothReplyStateShared,
+    },
     ReadDescriptorValue {
         peripheral_uuid: Uuid,
-        service_uuid: Uuid,
-        characteristic_uuid: Uuid,
-        descriptor_uuid: Uuid,
+        service_handle: u16,
+        characteristic_handle: u16,
+        descriptor_handle: u16,
         future: CoreBluetoothReplyStateShared,
     },
     WriteDescriptorValue {
         peripheral_uuid: Uuid,
-        service_uuid: Uuid,
-        characteristic_uuid: Uuid,
-        descriptor_uuid: Uuid,
+        service_handle: u16,
+        characteristic_handle: u16,
+        descriptor_handle: u16,
         data: Vec<u8>,
         future: CoreBluetoothReplyStateShared,
     },
@@ -412,7 +638,9 @@ pub enum CoreBluetoothMessage {
 
 #[derive(Debug)]
 pub enum CoreBluetoothEvent {
-    AdapterConnected,
+    StateUpdate {
+        state: CentralState,
+    },
     DeviceDiscovered {
         uuid: Uuid,
         name: Option<String>,
@@ -534,70 +762,70 @@ impl CoreBluetoothInternal {
         }
     }
 
-    fn on_discovered_services(
+    fn on_discovered_services(&mut self, peripheral_uuid: Uuid, services: Vec<StrongPtr>) {
+        trace!("Found services!");
+        for service in &services {
+            trace!("{}", service_debug(**service));
+        }
+        if let Some(p) = self.peripherals.get_mut(&peripheral_uuid) {
+            p.set_services(services);
+        }
+    }
+
+    fn on_discovered_included_services(
         &mut self,
         peripheral_uuid: Uuid,
-        service_map: HashMap<Uuid, StrongPtr>,
+        service: StrongPtr,
+        included_services: Vec<StrongPtr>,
     ) {
-        trace!("Found services!");
-        for id in service_map.keys() {
-            trace!("{}", id);
+        trace!(
+            "Found included services for peripheral {} service {}:",
+            peripheral_uuid,
+            service_debug(*service)
+        );
+        for included in &included_services {
+            trace!("{}", service_debug(**included));
         }
         if let Some(p) = self.peripherals.get_mut(&peripheral_uuid) {
-            let services = service_map
-                .into_iter()
-                .map(|(service_uuid, cbservice)| {
-                    (
-                        service_uuid,
-                        ServiceInternal {
-                            cbservice,
-                            characteristics: HashMap::new(),
-                            discovered: false,
-                        },
-                    )
-                })
-                .collect();
-            p.services = services;
+            p.set_included_services(*service, included_services);
         }
     }
 
     fn on_discovered_characteristics(
         &mut self,
         peripheral_uuid: Uuid,
-        service_uuid: Uuid,
-        characteristics: HashMap<Uuid, StrongPtr>,
+        service: StrongPtr,
+        characteristics: Vec<StrongPtr>,
     ) {
         trace!(
             "Found characteristics for peripheral {} service {}:",
             peripheral_uuid,
-            service_uuid
+            service_debug(*service)
         );
-        for id in characteristics.keys() {
-            trace!("{}", id);
+        for characteristic in &characteristics {
+            trace!("{}", characteristic_debug(**characteristic));
         }
         if let Some(p) = self.peripherals.get_mut(&peripheral_uuid) {
-            p.set_characteristics(service_uuid, characteristics);
+            p.set_characteristics(*service, characteristics);
         }
     }
 
     fn on_discovered_characteristic_descriptors(
         &mut self,
         peripheral_uuid: Uuid,
-        service_uuid: Uuid,
-        characteristic_uuid: Uuid,
-        descriptors: HashMap<Uuid, StrongPtr>,
+        characteristic: StrongPtr,
+        descriptors: Vec<StrongPtr>,
     ) {
         trace!(
-            "Found descriptors for peripheral {} service {} characteristic {}:",
+            "Found descriptors for peripheral {} characteristic {}:",
             peripheral_uuid,
-            service_uuid,
-            characteristic_uuid,
+            characteristic_debug(*characteristic),
         );
-        for id in descriptors.keys() {
-            trace!("{}", id);
+        for descriptor in &descriptors {
+            trace!("{}", descriptor_debug(**descriptor));
         }
         if let Some(p) = self.peripherals.get_mut(&peripheral_uuid) {
-            p.set_characteristic_descriptors(service_uuid, characteristic_uuid, descriptors);
+            p.set_characteristic_descriptors(*characteristic, descriptors);
         }
     }
 
@@ -654,38 +882,64 @@ impl CoreBluetoothInternal {
     fn get_characteristic(
         &mut self,
         peripheral_uuid: Uuid,
-        service_uuid: Uuid,
-        characteristic_uuid: Uuid,
+        service_handle: u16,
+        characteristic_handle: u16,
     ) -> Option<&mut CBCharacteristic> {
         self.peripherals
             .get_mut(&peripheral_uuid)?
             .services
-            .get_mut(&service_uuid)?
+            .get_mut(&service_handle)?
             .characteristics
-            .get_mut(&characteristic_uuid)
+            .get_mut(&characteristic_handle)
     }
 
     /// Get the CBDescriptor for the given descriptor of the given peripheral, if it exists.
     fn get_descriptor(
         &mut self,
         peripheral_uuid: Uuid,
-        service_uuid: Uuid,
-        characteristic_uuid: Uuid,
-        descriptor_uuid: Uuid,
+        service_handle: u16,
+        characteristic_handle: u16,
+        descriptor_handle: u16,
     ) -> Option<&mut CBDescriptor> {
-        self.get_characteristic(peripheral_uuid, service_uuid, characteristic_uuid)?
+        self.get_characteristic(peripheral_uuid, service_handle, characteristic_handle)?
             .descriptors
-            .get_mut(&descriptor_uuid)
+            .get_mut(&descriptor_handle)
     }
 
-    fn on_characteristic_subscribed(
+    /// Get our wrapper of a CBCharacteristic a delegate event was for, if we know about it.
+    fn characteristic_for_object(
         &mut self,
         peripheral_uuid: Uuid,
-        service_uuid: Uuid,
-        characteristic_uuid: Uuid,
-    ) {
+        characteristic: &StrongPtr,
+    ) -> Option<&mut CBCharacteristic> {
+        let (service_handle, characteristic_handle) = self
+            .peripherals
+            .get(&peripheral_uuid)?
+            .find_characteristic(**characteristic)?;
+        self.get_characteristic(peripheral_uuid, service_handle, characteristic_handle)
+    }
+
+    /// Get our wrapper of a CBDescriptor a delegate event was for, if we know about it.
+    fn descriptor_for_object(
+        &mut self,
+        peripheral_uuid: Uuid,
+        descriptor: &StrongPtr,
+    ) -> Option<&mut CBDescriptor> {
+        let (service_handle, characteristic_handle, descriptor_handle) = self
+            .peripherals
+            .get(&peripheral_uuid)?
+            .find_descriptor(**descriptor)?;
+        self.get_descriptor(
+            peripheral_uuid,
+            service_handle,
+            characteristic_handle,
+            descriptor_handle,
+        )
+    }
+
+    fn on_characteristic_subscribed(&mut self, peripheral_uuid: Uuid, characteristic: StrongPtr) {
         if let Some(characteristic) =
-            self.get_characteristic(peripheral_uuid, service_uuid, characteristic_uuid)
+            self.characteristic_for_object(peripheral_uuid, &characteristic)
         {
             trace!("Got subscribed event!");
             let state = characteristic.subscribe_future_state.pop_back().unwrap();
@@ -693,14 +947,9 @@ impl CoreBluetoothInternal {
         }
     }
 
-    fn on_characteristic_unsubscribed(
-        &mut self,
-        peripheral_uuid: Uuid,
-        service_uuid: Uuid,
-        characteristic_uuid: Uuid,
-    ) {
+    fn on_characteristic_unsubscribed(&mut self, peripheral_uuid: Uuid, characteristic: StrongPtr) {
         if let Some(characteristic) =
-            self.get_characteristic(peripheral_uuid, service_uuid, characteristic_uuid)
+            self.characteristic_for_object(peripheral_uuid, &characteristic)
         {
             trace!("Got unsubscribed event!");
             let state = characteristic.unsubscribe_future_state.pop_back().unwrap();
@@ -711,50 +960,54 @@ impl CoreBluetoothInternal {
     async fn on_characteristic_read(
         &mut self,
         peripheral_uuid: Uuid,
-        service_uuid: Uuid,
-        characteristic_uuid: Uuid,
+        characteristic: StrongPtr,
         data: Vec<u8>,
     ) {
         if let Some(peripheral) = self.peripherals.get_mut(&peripheral_uuid) {
-            if let Some(service) = peripheral.services.get_mut(&service_uuid) {
-                if let Some(characteristic) = service.characteristics.get_mut(&characteristic_uuid)
-                {
-                    trace!("Got read event!");
+            if let Some((service_handle, characteristic_handle)) =
+                peripheral.find_characteristic(*characteristic)
+            {
+                let service = peripheral.services.get_mut(&service_handle).unwrap();
+                let service_uuid = service.uuid;
+                let characteristic = service
+                    .characteristics
+                    .get_mut(&characteristic_handle)
+                    .unwrap();
+                trace!("Got read event!");
 
-                    let mut data_clone = Vec::new();
-                    for byte in data.iter() {
-                        data_clone.push(*byte);
-                    }
-                    // Reads and notifications both return the same callback. If
-                    // we're trying to do a read, we'll have a future we can
-                    // fulfill. Otherwise, just treat the returned value as a
-                    // notification and use the event system.
-                    if !characteristic.read_future_state.is_empty() {
-                        let state = characteristic.read_future_state.pop_back().unwrap();
-                        state
-                            .lock()
-                            .unwrap()
-                            .set_reply(CoreBluetoothReply::ReadResult(data_clone));
-                    } else if let Err(e) = peripheral
-                        .event_sender
-                        .send(CBPeripheralEvent::Notification(characteristic_uuid, data))
-                        .await
-                    {
-                        error!("Error sending notification event: {}", e);
-                    }
+                let mut data_clone = Vec::new();
+                for byte in data.iter() {
+                    data_clone.push(*byte);
+                }
+                // Reads and notifications both return the same callback. If
+                // we're trying to do a read, we'll have a future we can
+                // fulfill. Otherwise, just treat the returned value as a
+                // notification and use the event system.
+                if !characteristic.read_future_state.is_empty() {
+                    let state = characteristic.read_future_state.pop_back().unwrap();
+                    state
+                        .lock()
+                        .unwrap()
+                        .set_reply(CoreBluetoothReply::ReadResult(data_clone));
+                } else if let Err(e) = peripheral
+                    .event_sender
+                    .send(CBPeripheralEvent::Notification(ValueNotification {
+                        uuid: characteristic.uuid,
+                        service_uuid,
+                        handle: characteristic_handle,
+                        value: data,
+                    }))
+                    .await
+                {
+                    error!("Error sending notification event: {}", e);
                 }
             }
         }
     }
 
-    fn on_characteristic_written(
-        &mut self,
-        peripheral_uuid: Uuid,
-        service_uuid: Uuid,
-        characteristic_uuid: Uuid,
-    ) {
+    fn on_characteristic_written(&mut self, peripheral_uuid: Uuid, characteristic: StrongPtr) {
         if let Some(characteristic) =
-            self.get_characteristic(peripheral_uuid, service_uuid, characteristic_uuid)
+            self.characteristic_for_object(peripheral_uuid, &characteristic)
         {
             trace!("Got written event!");
             let state = characteristic.write_future_state.pop_back().unwrap();
@@ -762,6 +1015,39 @@ impl CoreBluetoothInternal {
         }
     }
 
+    fn on_characteristic_read_failed(
+        &mut self,
+        peripheral_uuid: Uuid,
+        characteristic: StrongPtr,
+        error: CoreBluetoothReply,
+    ) {
+        if let Some(characteristic) =
+            self.characteristic_for_object(peripheral_uuid, &characteristic)
+        {
+            trace!("Got read failed event!");
+            // A failed notification has no read to fail.
+            if let Some(state) = characteristic.read_future_state.pop_back() {
+                state.lock().unwrap().set_reply(error);
+            }
+        }
+    }
+
+    fn on_characteristic_write_failed(
+        &mut self,
+        peripheral_uuid: Uuid,
+        characteristic: StrongPtr,
+        error: CoreBluetoothReply,
+    ) {
+        if let Some(characteristic) =
+            self.characteristic_for_object(peripheral_uuid, &characteristic)
+        {
+            trace!("Got write failed event!");
+            if let Some(state) = characteristic.write_future_state.pop_back() {
+                state.lock().unwrap().set_reply(error);
+            }
+        }
+    }
+
     fn connect_peripheral(&mut self, peripheral_uuid: Uuid, fut: CoreBluetoothReplyStateShared) {
         trace!("Trying to connect peripheral!");
         if let Some(p) = self.peripherals.get_mut(&peripheral_uuid) {
@@ -790,51 +1076,79 @@ impl CoreBluetoothInternal {
         }
     }
 
+    fn mtu(&mut self, peripheral_uuid: Uuid, fut: CoreBluetoothReplyStateShared) {
+        let reply = match self.peripherals.get(&peripheral_uuid) {
+            Some(p) if cb::peripheral_state(*p.peripheral) == CBPeripheralState::Connected => {
+                // CoreBluetooth doesn't expose the MTU itself, only the payload size it allows,
+                // which is the MTU less the ATT header.
+                let length = cb::peripheral_maximumwritevaluelengthfortype(*p.peripheral, 1);
+                CoreBluetoothReply::Mtu(u16::try_from(length + 3).unwrap_or(u16::MAX))
+            }
+            Some(_) => CoreBluetoothReply::Err(String::from("Not connected")),
+            None => CoreBluetoothReply::Err(String::from("Unknown peripheral")),
+        };
+        fut.lock().unwrap().set_reply(reply);
+    }
+
     fn write_value(
         &mut self,
         peripheral_uuid: Uuid,
-        service_uuid: Uuid,
-        characteristic_uuid: Uuid,
+        service_handle: u16,
+        characteristic_handle: u16,
         data: Vec<u8>,
         kind: WriteType,
         fut: CoreBluetoothReplyStateShared,
     ) {
         if let Some(peripheral) = self.peripherals.get_mut(&peripheral_uuid) {
-            if let Some(service) = peripheral.services.get_mut(&service_uuid) {
-                if let Some(characteristic) = service.characteristics.get_mut(&characteristic_uuid)
-                {
-                    trace!("Writing value! With kind {:?}", kind);
-                    cb::peripheral_writevalue_forcharacteristic(
-                        *peripheral.peripheral,
-                        ns::data(&data),
-                        *characteristic.characteristic,
-                        match kind {
-                            WriteType::WithResponse => 0,
-                            WriteType::WithoutResponse => 1,
-                        },
-                    );
-                    // WriteWithoutResponse does not call the corebluetooth
-                    // callback, it just always succeeds silently.
-                    if kind == WriteType::WithoutResponse {
-                        fut.lock().unwrap().set_reply(CoreBluetoothReply::Ok);
-                    } else {
+            if let Some(characteristic) = peripheral
+                .services
+                .get_mut(&service_handle)
+                .and_then(|service| service.characteristics.get_mut(&characteristic_handle))
+            {
+                trace!("Writing value! With kind {:?}", kind);
+                match kind {
+                    WriteType::WithResponse => {
+                        cb::peripheral_writevalue_forcharacteristic(
+                            *peripheral.peripheral,
+                            ns::data(&data),
+                            *characteristic.characteristic,
+                            0,
+                        );
                         characteristic.write_future_state.push_front(fut);
                     }
+                    // WriteWithoutResponse does not call the corebluetooth callback, so these
+                    // complete once CoreBluetooth has taken them instead.
+                    WriteType::WithoutResponse => {
+                        let characteristic = characteristic.characteristic.clone();
+                        peripheral.pending_writes.push_back(PendingWrite {
+                            characteristic,
+                            data,
+                            future: fut,
+                        });
+                        peripheral.send_pending_writes();
+                    }
                 }
             }
         }
     }
 
+    fn on_ready_to_send_write_without_response(&mut self, peripheral_uuid: Uuid) {
+        if let Some(peripheral) = self.peripherals.get_mut(&peripheral_uuid) {
+            peripheral.send_pending_writes();
+        }
+    }
+
     fn read_value(
         &mut self,
         peripheral_uuid: Uuid,
-        service_uuid: Uuid,
-        characteristic_uuid: Uuid,
+        service_handle: u16,
+        characteristic_handle: u16,
         fut: CoreBluetoothReplyStateShared,
     ) {
         if let Some(peripheral) = self.peripherals.get_mut(&peripheral_uuid) {
-            if let Some(service) = peripheral.services.get_mut(&service_uuid) {
-                if let Some(characteristic) = service.characteristics.get_mut(&characteristic_uuid)
+            if let Some(service) = peripheral.services.get_mut(&service_handle) {
+                if let Some(characteristic) =
+                    service.characteristics.get_mut(&characteristic_handle)
                 {
                     trace!("Reading value!");
                     cb::peripheral_readvalue_forcharacteristic(
@@ -850,13 +1164,14 @@ impl CoreBluetoothInternal {
     fn subscribe(
         &mut self,
         peripheral_uuid: Uuid,
-        service_uuid: Uuid,
-        characteristic_uuid: Uuid,
+        service_handle: u16,
+        characteristic_handle: u16,
         fut: CoreBluetoothReplyStateShared,
     ) {
         if let Some(peripheral) = self.peripherals.get_mut(&peripheral_uuid) {
-            if let Some(service) = peripheral.services.get_mut(&service_uuid) {
-                if let Some(characteristic) = service.characteristics.get_mut(&characteristic_uuid)
+            if let Some(service) = peripheral.services.get_mut(&service_handle) {
+                if let Some(characteristic) =
+                    service.characteristics.get_mut(&characteristic_handle)
                 {
                     trace!("Setting subscribe!");
                     cb::peripheral_setnotifyvalue_forcharacteristic(
@@ -873,13 +1188,14 @@ impl CoreBluetoothInternal {
     fn unsubscribe(
         &mut self,
         peripheral_uuid: Uuid,
-        service_uuid: Uuid,
-        characteristic_uuid: Uuid,
+        service_handle: u16,
+        characteristic_handle: u16,
         fut: CoreBluetoothReplyStateShared,
     ) {
         if let Some(peripheral) = self.peripherals.get_mut(&peripheral_uuid) {
-            if let Some(service) = peripheral.services.get_mut(&service_uuid) {
-                if let Some(characteristic) = service.characteristics.get_mut(&characteristic_uuid)
+            if let Some(service) = peripheral.services.get_mut(&service_handle) {
+                if let Some(characteristic) =
+                    service.characteristics.get_mut(&characteristic_handle)
                 {
                     trace!("Setting subscribe!");
                     cb::peripheral_setnotifyvalue_forcharacteristic(
@@ -896,17 +1212,19 @@ impl CoreBluetoothInternal {
     fn write_descriptor_value(
         &mut self,
         peripheral_uuid: Uuid,
-        service_uuid: Uuid,
-        characteristic_uuid: Uuid,
-        descriptor_uuid: Uuid,
+        service_handle: u16,
+        characteristic_handle: u16,
+        descriptor_handle: u16,
         data: Vec<u8>,
         fut: CoreBluetoothReplyStateShared,
     ) {
         if let Some(peripheral) = self.peripherals.get_mut(&peripheral_uuid) {
-            if let Some(service) = peripheral.services.get_mut(&service_uuid) {
-                if let Some(characteristic) = service.characteristics.get_mut(&characteristic_uuid)
+            if let Some(service) = peripheral.services.get_mut(&service_handle) {
+                if let Some(characteristic) =
+                    service.characteristics.get_mut(&characteristic_handle)
                 {
-                    if let Some(descriptor) = characteristic.descriptors.get_mut(&descriptor_uuid) {
+                    if let Some(descriptor) = characteristic.descriptors.get_mut(&descriptor_handle)
+                    {
                         trace!("Writing descriptor value!");
                         cb::peripheral_writevalue_fordescriptor(
                             *peripheral.peripheral,
@@ -923,16 +1241,18 @@ impl CoreBluetoothInternal {
     fn read_descriptor_value(
         &mut self,
         peripheral_uuid: Uuid,
-        service_uuid: Uuid,
-        characteristic_uuid: Uuid,
-        descriptor_uuid: Uuid,
+        service_handle: u16,
+        characteristic_handle: u16,
+        descriptor_handle: u16,
         fut: CoreBluetoothReplyStateShared,
     ) {
         if let Some(peripheral) = self.peripherals.get_mut(&peripheral_uuid) {
-            if let Some(service) = peripheral.services.get_mut(&service_uuid) {
-                if let Some(characteristic) = service.characteristics.get_mut(&characteristic_uuid)
+            if let Some(service) = peripheral.services.get_mut(&service_handle) {
+                if let Some(characteristic) =
+                    service.characteristics.get_mut(&characteristic_handle)
                 {
-                    if let Some(descriptor) = characteristic.descriptors.get_mut(&descriptor_uuid) {
+                    if let Some(descriptor) = characteristic.descriptors.get_mut(&descriptor_handle)
+                    {
                         trace!("Reading descriptor value!");
                         cb::peripheral_readvalue_fordescriptor(
                             *peripheral.peripheral,
@@ -945,52 +1265,55 @@ impl CoreBluetoothInternal {
         }
     }
 
-    async fn on_descriptor_read(
+    fn on_descriptor_read(&mut self, peripheral_uuid: Uuid, descriptor: StrongPtr, data: Vec<u8>) {
+        if let Some(descriptor) = self.descriptor_for_object(peripheral_uuid, &descriptor) {
+            trace!("Got read event!");
+
+            let mut data_clone = Vec::new();
+            for byte in data.iter() {
+                data_clone.push(*byte);
+            }
+            let state = descriptor.read_future_state.pop_back().unwrap();
+            state
+                .lock()
+                .unwrap()
+                .set_reply(CoreBluetoothReply::ReadResult(data_clone));
+        }
+    }
+
+    fn on_descriptor_written(&mut self, peripheral_uuid: Uuid, descriptor: StrongPtr) {
+        if let Some(descriptor) = self.descriptor_for_object(peripheral_uuid, &descriptor) {
+            trace!("Got written event!");
+            let state = descriptor.write_future_state.pop_back().unwrap();
+            state.lock().unwrap().set_reply(CoreBluetoothReply::Ok);
+        }
+    }
+
+    fn on_descriptor_read_failed(
         &mut self,
         peripheral_uuid: Uuid,
-        service_uuid: Uuid,
-        characteristic_uuid: Uuid,
-        descriptor_uuid: Uuid,
-        data: Vec<u8>,
+        descriptor: StrongPtr,
+        error: CoreBluetoothReply,
     ) {
-        if let Some(peripheral) = self.peripherals.get_mut(&peripheral_uuid) {
-            if let Some(service) = peripheral.services.get_mut(&service_uuid) {
-                if let Some(characteristic) = service.characteristics.get_mut(&characteristic_uuid)
-                {
-                    if let Some(descriptor) = characteristic.descriptors.get_mut(&descriptor_uuid) {
-                        trace!("Got read event!");
-
-                        let mut data_clone = Vec::new();
-                        for byte in data.iter() {
-                            data_clone.push(*byte);
-                        }
-                        let state = characteristic.read_future_state.pop_back().unwrap();
-                        state
-                            .lock()
-                            .unwrap()
-                            .set_reply(CoreBluetoothReply::ReadResult(data_clone));
-                    }
-                }
+        if let Some(descriptor) = self.descriptor_for_object(peripheral_uuid, &descriptor) {
+            trace!("Got read failed event!");
+            if let Some(state) = descriptor.read_future_state.pop_back() {
+                state.lock().unwrap().set_reply(error);
             }
         }
     }
 
-    fn on_descriptor_written(
+    fn on_descriptor_write_failed(
         &mut self,
         peripheral_uuid: Uuid,
-        service_uuid: Uuid,
-        characteristic_uuid: Uuid,
-        descriptor_uuid: Uuid,
+        descriptor: StrongPtr,
+        error: CoreBluetoothReply,
     ) {
-        if let Some(descriptor) = self.get_descriptor(
-            peripheral_uuid,
-            service_uuid,
-            characteristic_uuid,
-            descriptor_uuid,
-        ) {
-            trace!("Got written event!");
-            let state = descriptor.write_future_state.pop_back().unwrap();
-            state.lock().unwrap().set_reply(CoreBluetoothReply::Ok);
+        if let Some(descriptor) = self.descriptor_for_object(peripheral_uuid, &descriptor) {
+            trace!("Got write failed event!");
+            if let Some(state) = descriptor.write_future_state.pop_back() {
+                state.lock().unwrap().set_reply(error);
+            }
         }
     }
 
@@ -998,14 +1321,13 @@ impl CoreBluetoothInternal {
         select! {
             delegate_msg = self.delegate_receiver.select_next_some() => {
                 match delegate_msg {
-                    // TODO DidUpdateState does not imply that the adapter is
-                    // on, just that it updated state.
-                    //
                     // TODO We should probably also register some sort of
                     // "ready" variable in our adapter that will cause scans/etc
                     // to fail if this hasn't updated.
-                    CentralDelegateEvent::DidUpdateState => {
-                        self.dispatch_event(CoreBluetoothEvent::AdapterConnected).await
+                    CentralDelegateEvent::DidUpdateState{state} => {
+                        self.dispatch_event(CoreBluetoothEvent::StateUpdate {
+                            state: central_state(state),
+                        }).await
                     }
                     CentralDelegateEvent::DiscoveredPeripheral{cbperipheral} => {
                         self.on_discovered_peripheral(cbperipheral).await
@@ -1013,11 +1335,14 @@ impl CoreBluetoothInternal {
                     CentralDelegateEvent::DiscoveredServices{peripheral_uuid, services} => {
                         self.on_discovered_services(peripheral_uuid, services)
                     }
-                    CentralDelegateEvent::DiscoveredCharacteristics{peripheral_uuid, service_uuid, characteristics} => {
-                        self.on_discovered_characteristics(peripheral_uuid, service_uuid, characteristics)
+                    CentralDelegateEvent::DiscoveredIncludedServices{peripheral_uuid, service, included_services} => {
+                        self.on_discovered_included_services(peripheral_uuid, service, included_services)
+                    }
+                    CentralDelegateEvent::DiscoveredCharacteristics{peripheral_uuid, service, characteristics} => {
+                        self.on_discovered_characteristics(peripheral_uuid, service, characteristics)
                     }
-                    CentralDelegateEvent::DiscoveredCharacteristicDescriptors{peripheral_uuid, service_uuid, characteristic_uuid, descriptors} => {
-                        self.on_discovered_characteristic_descriptors(peripheral_uuid, service_uuid, characteristic_uuid, descriptors)
+                    CentralDelegateEvent::DiscoveredCharacteristicDescriptors{peripheral_uuid, characteristic, descriptors} => {
+                        self.on_discovered_characteristic_descriptors(peripheral_uuid, characteristic, descriptors)
                     }
                     CentralDelegateEvent::ConnectedDevice{peripheral_uuid} => {
                             self.on_peripheral_connect(peripheral_uuid)
@@ -1030,25 +1355,34 @@ impl CoreBluetoothInternal {
                     }
                     CentralDelegateEvent::CharacteristicSubscribed{
                         peripheral_uuid,
-                        service_uuid,
-                        characteristic_uuid,
-                     } => self.on_characteristic_subscribed(peripheral_uuid, service_uuid, characteristic_uuid),
+                        characteristic,
+                     } => self.on_characteristic_subscribed(peripheral_uuid, characteristic),
                     CentralDelegateEvent::CharacteristicUnsubscribed{
                         peripheral_uuid,
-                        service_uuid,
-                        characteristic_uuid,
-                     } => self.on_characteristic_unsubscribed(peripheral_uuid, service_uuid,characteristic_uuid),
+                        characteristic,
+                     } => self.on_characteristic_unsubscribed(peripheral_uuid, characteristic),
                     CentralDelegateEvent::CharacteristicNotified{
                         peripheral_uuid,
-                        service_uuid,
-                        characteristic_uuid,
+                        characteristic,
                         data,
-                     } => self.on_characteristic_read(peripheral_uuid, service_uuid,characteristic_uuid, data).await,
+                     } => self.on_characteristic_read(peripheral_uuid, characteristic, data).await,
                     CentralDelegateEvent::CharacteristicWritten{
                         peripheral_uuid,
-                        service_uuid,
-                        characteristic_uuid,
-                    } => self.on_characteristic_written(peripheral_uuid, service_uuid, characteristic_uuid),
+                        characteristic,
+                    } => self.on_characteristic_written(peripheral_uuid, characteristic),
+                    CentralDelegateEvent::ReadyToSendWriteWithoutResponse{peripheral_uuid} => {
+                        self.on_ready_to_send_write_without_response(peripheral_uuid)
+                    }
+                    CentralDelegateEvent::CharacteristicReadFailed{
+                        peripheral_uuid,
+                        characteristic,
+                        error,
+                    } => self.on_characteristic_read_failed(peripheral_uuid, characteristic, error),
+                    CentralDelegateEvent::CharacteristicWriteFailed{
+                        peripheral_uuid,
+                        characteristic,
+                        error,
+                    } => self.on_characteristic_write_failed(peripheral_uuid, characteristic, error),
                     CentralDelegateEvent::ManufacturerData{peripheral_uuid, manufacturer_id, data, rssi} => {
                         self.on_manufacturer_data(peripheral_uuid, manufacturer_id, data, rssi).await
                     },
@@ -1060,17 +1394,23 @@ impl CoreBluetoothInternal {
                     },
                     CentralDelegateEvent::DescriptorNotified{
                         peripheral_uuid,
-                        service_uuid,
-                        characteristic_uuid,
-                        descriptor_uuid,
+                        descriptor,
                         data,
-                     } => self.on_descriptor_read(peripheral_uuid, service_uuid, characteristic_uuid, descriptor_uuid, data).await,
+                     } => self.on_descriptor_read(peripheral_uuid, descriptor, data),
                     CentralDelegateEvent::DescriptorWritten{
                         peripheral_uuid,
-                        service_uuid,
-                        characteristic_uuid,
-                        descriptor_uuid,
-                    } => self.on_descriptor_written(peripheral_uuid, service_uuid, characteristic_uuid, descriptor_uuid),
+                        descriptor,
+                    } => self.on_descriptor_written(peripheral_uuid, descriptor),
+                    CentralDelegateEvent::DescriptorReadFailed{
+                        peripheral_uuid,
+                        descriptor,
+                        error,
+                    } => self.on_descriptor_read_failed(peripheral_uuid, descriptor, error),
+                    CentralDelegateEvent::DescriptorWriteFailed{
+                        peripheral_uuid,
+                        descriptor,
+                        error,
+                    } => self.on_descriptor_write_failed(peripheral_uuid, descriptor, error),
                 };
             }
             adapter_msg = self.message_receiver.select_next_some() => {
@@ -1085,35 +1425,38 @@ impl CoreBluetoothInternal {
                     CoreBluetoothMessage::DisconnectDevice{peripheral_uuid, future} => {
                         self.disconnect_peripheral(peripheral_uuid, future);
                     }
-                    CoreBluetoothMessage::ReadValue{peripheral_uuid, service_uuid,characteristic_uuid, future} => {
-                        self.read_value(peripheral_uuid, service_uuid,characteristic_uuid, future)
+                    CoreBluetoothMessage::ReadValue{peripheral_uuid, service_handle,characteristic_handle, future} => {
+                        self.read_value(peripheral_uuid, service_handle,characteristic_handle, future)
                     }
                     CoreBluetoothMessage::WriteValue{
-                        peripheral_uuid,service_uuid,
-                        characteristic_uuid,
+                        peripheral_uuid,service_handle,
+                        characteristic_handle,
                         data,
                         write_type,
                         future,
-                    } => self.write_value(peripheral_uuid, service_uuid,characteristic_uuid, data, write_type, future),
-                    CoreBluetoothMessage::Subscribe{peripheral_uuid, service_uuid,characteristic_uuid, future} => {
-                        self.subscribe(peripheral_uuid, service_uuid,characteristic_uuid, future)
+                    } => self.write_value(peripheral_uuid, service_handle,characteristic_handle, data, write_type, future),
+                    CoreBluetoothMessage::Subscribe{peripheral_uuid, service_handle,characteristic_handle, future} => {
+                        self.subscribe(peripheral_uuid, service_handle,characteristic_handle, future)
                     }
-                    CoreBluetoothMessage::Unsubscribe{peripheral_uuid, service_uuid,characteristic_uuid, future} => {
-                        self.unsubscribe(peripheral_uuid, service_uuid,characteristic_uuid, future)
+                    CoreBluetoothMessage::Unsubscribe{peripheral_uuid, service_handle,characteristic_handle, future} => {
+                        self.unsubscribe(peripheral_uuid, service_handle,characteristic_handle, future)
                     }
                     CoreBluetoothMessage::IsConnected{peripheral_uuid, future} => {
                         self.is_connected(peripheral_uuid, future);
                     },
-                    CoreBluetoothMessage::ReadDescriptorValue{peripheral_uuid, service_uuid, characteristic_uuid, descriptor_uuid, future} => {
-                        self.read_descriptor_value(peripheral_uuid, service_uuid, characteristic_uuid, descriptor_uuid, future)
+                    CoreBluetoothMessage::Mtu{peripheral_uuid, future} => {
+                        self.mtu(peripheral_uuid, future);
+                    },
+                    CoreBluetoothMessage::ReadDescriptorValue{peripheral_uuid, service_handle, characteristic_handle, descriptor_handle, future} => {
+                        self.read_descriptor_value(peripheral_uuid, service_handle, characteristic_handle, descriptor_handle, future)
                     }
                     CoreBluetoothMessage::WriteDescriptorValue{
-                        peripheral_uuid,service_uuid,
-                        characteristic_uuid,
-                        descriptor_uuid,
+                        peripheral_uuid,service_handle,
+                        characteristic_handle,
+                        descriptor_handle,
                         data,
                         future,
-                    } => self.write_descriptor_value(peripheral_uuid, service_uuid, characteristic_uuid, descriptor_uuid, data, future),
+                    } => self.write_descriptor_value(peripheral_uuid, service_handle, characteristic_handle, descriptor_handle, data, future),
                 };
             }
         }
@@ -1156,6 +1499,16 @@ fn scan_filter_to_service_uuids(filter: ScanFilter) -> id {
     }
 }
 
+fn central_state(state: NSInteger) -> CentralState {
+    match state {
+        cb::MANAGERSTATE_POWEREDON => CentralState::PoweredOn,
+        cb::MANAGERSTATE_POWEREDOFF => CentralState::PoweredOff,
+        cb::MANAGERSTATE_UNAUTHORIZED => CentralState::Unauthorized,
+        // Unknown, resetting, unsupported, or a state added since.
+        _ => CentralState::Unavailable,
+    }
+}
+
 impl Drop for CoreBluetoothInternal {
     fn drop(&mut self) {
         trace!("BluetoothAdapter::drop");
@@ -6,16 +6,24 @@
 // for full license information.
 
 use super::adapter::Adapter;
-use crate::{api, Result};
+use crate::{
+    api::{self, Timeouts},
+    common::timeouts::SharedTimeouts,
+    Result,
+};
 use async_trait::async_trait;
 
 /// Implementation of [api::Manager](crate::api::Manager).
 #[derive(Clone, Debug)]
-pub struct Manager {}
+pub struct Manager {
+    timeouts: SharedTimeouts,
+}
 
 impl Manager {
     pub async fn new() -> Result<Self> {
-        Ok(Self {})
+        Ok(Self {
+            timeouts: Default::default(),
+        })
     }
 }
 
@@ -24,8 +32,16 @@ impl api::Manager for Manager {
     type Adapter = Adapter;
 
     async fn adapters(&self) -> Result<Vec<Adapter>> {
-        Ok(vec![Adapter::new().await?])
+        Ok(vec![Adapter::new(self.timeouts.clone()).await?])
         // TODO What do we do if there is no bluetooth adapter, like on an older
         // macbook pro? Will BluetoothAdapter::init() fail?
     }
+
+    fn timeouts(&self) -> Timeouts {
+        *self.timeouts.read().unwrap()
+    }
+
+    fn set_timeouts(&self, timeouts: Timeouts) {
+        *self.timeouts.write().unwrap() = timeouts;
+    }
 }
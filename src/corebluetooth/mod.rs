@@ -7,6 +7,7 @@
 
 pub mod adapter;
 mod central_delegate;
+mod error;
 mod framework;
 mod future;
 mod internal;
@@ -13,10 +13,16 @@ use super::{
 };
 use crate::{
     api::{
-        self, BDAddr, CentralEvent, CharPropFlags, Characteristic, Descriptor,
-        PeripheralProperties, Service, ValueNotification, WriteType,
+        self, proximity::Proximity, BDAddr, CentralEvent, CharPropFlags, Characteristic,
+        Descriptor, PeripheralProperties, Service, Timeouts, ValueNotification, WriteType,
+    },
+    common::{
+        adapter_manager::AdapterManager,
+        subscriptions::Subscriptions,
+        timeouts::{self, timeout},
+        transfer,
+        util::notifications_stream_from_broadcast_receiver,
     },
-    common::{adapter_manager::AdapterManager, util::notifications_stream_from_broadcast_receiver},
     Error, Result,
 };
 use async_trait::async_trait;
@@ -57,6 +63,7 @@ impl Display for PeripheralId {
 #[derive(Clone)]
 pub struct Peripheral {
     shared: Arc<Shared>,
+    timeout_overrides: Timeouts,
 }
 
 struct Shared {
@@ -66,6 +73,7 @@ struct Shared {
     services: Mutex<BTreeSet<Service>>,
     properties: Mutex<PeripheralProperties>,
     message_sender: Sender<CoreBluetoothMessage>,
+    notification_streams: Arc<Subscriptions>,
     // We're not actually holding a peripheral object here, that's held out in
     // the objc thread. We'll just communicate with it through our
     // receiver/sender pair.
@@ -111,6 +119,7 @@ impl Peripheral {
             notifications_channel,
             uuid,
             message_sender,
+            notification_streams: Default::default(),
         });
         let shared_clone = shared.clone();
         task::spawn(async move {
@@ -119,9 +128,7 @@ impl Peripheral {
 
             loop {
                 match event_receiver.next().await {
-                    Some(CBPeripheralEvent::Notification(uuid, data)) => {
-                        let notification = ValueNotification { uuid, value: data };
-
+                    Some(CBPeripheralEvent::Notification(notification)) => {
                         // Note: we ignore send errors here which may happen while there are no
                         // receivers...
                         let _ = shared.notifications_channel.send(notification);
@@ -165,7 +172,10 @@ impl Peripheral {
                 }
             }
         });
-        Self { shared: shared }
+        Self {
+            shared,
+            timeout_overrides: Timeouts::default(),
+        }
     }
 
     pub(super) fn update_name(&self, name: &str) {
@@ -208,10 +218,29 @@ impl api::Peripheral for Peripheral {
         Ok(Some(self.shared.properties.lock().unwrap().clone()))
     }
 
+    fn proximity(&self) -> Option<Proximity> {
+        self.shared.manager.upgrade()?.proximity(&self.id())
+    }
+
     fn services(&self) -> BTreeSet<Service> {
         self.shared.services.lock().unwrap().clone()
     }
 
+    fn timeouts(&self) -> Timeouts {
+        let adapter_timeouts = match self.shared.manager.upgrade() {
+            Some(manager) => manager.timeouts(),
+            None => Timeouts::default(),
+        };
+        adapter_timeouts.merge(self.timeout_overrides)
+    }
+
+    fn with_timeouts(&self, timeouts: Timeouts) -> Self {
+        Self {
+            shared: self.shared.clone(),
+            timeout_overrides: self.timeout_overrides.merge(timeouts),
+        }
+    }
+
     async fn is_connected(&self) -> Result<bool> {
         let fut = CoreBluetoothReplyFuture::default();
         self.shared
@@ -232,25 +261,28 @@ impl api::Peripheral for Peripheral {
     }
 
     async fn connect(&self) -> Result<()> {
-        let fut = CoreBluetoothReplyFuture::default();
-        self.shared
-            .message_sender
-            .to_owned()
-            .send(CoreBluetoothMessage::ConnectDevice {
-                peripheral_uuid: self.shared.uuid,
-                future: fut.get_state_clone(),
-            })
-            .await?;
-        match fut.await {
-            CoreBluetoothReply::Connected(services) => {
-                *(self.shared.services.lock().unwrap()) = services;
-                self.shared
-                    .emit_event(CentralEvent::DeviceConnected(self.shared.uuid.into()));
+        timeouts::connect(self, async {
+            let fut = CoreBluetoothReplyFuture::default();
+            self.shared
+                .message_sender
+                .to_owned()
+                .send(CoreBluetoothMessage::ConnectDevice {
+                    peripheral_uuid: self.shared.uuid,
+                    future: fut.get_state_clone(),
+                })
+                .await?;
+            match fut.await {
+                CoreBluetoothReply::Connected(services) => {
+                    *(self.shared.services.lock().unwrap()) = services;
+                    self.shared
+                        .emit_event(CentralEvent::DeviceConnected(self.shared.uuid.into()));
+                }
+                _ => panic!("Shouldn't get anything but connected!"),
             }
-            _ => panic!("Shouldn't get anything but connected!"),
-        }
-        trace!("Device connected!");
-        Ok(())
+            trace!("Device connected!");
+            Ok(())
+        })
+        .await
     }
 
     async fn disconnect(&self) -> Result<()> {
@@ -275,102 +307,159 @@ impl api::Peripheral for Peripheral {
     }
 
     async fn discover_services(&self) -> Result<()> {
-        // TODO: Actually discover on this, rather than on connection
-        Ok(())
+        timeout(self.timeouts().discover_services, async {
+            // TODO: Actually discover on this, rather than on connection
+            Ok(())
+        })
+        .await
     }
 
-    async fn write(
-        &self,
-        characteristic: &Characteristic,
-        data: &[u8],
-        mut write_type: WriteType,
-    ) -> Result<()> {
+    async fn pair(&self) -> Result<()> {
+        Err(Error::NotSupported(
+            "CoreBluetooth pairs on its own when a characteristic requires it".to_string(),
+        ))
+    }
+
+    async fn unpair(&self) -> Result<()> {
+        Err(Error::NotSupported(
+            "CoreBluetooth doesn't allow apps to remove pairings".to_string(),
+        ))
+    }
+
+    async fn is_paired(&self) -> Result<bool> {
+        Err(Error::NotSupported(
+            "CoreBluetooth doesn't expose whether a device is paired".to_string(),
+        ))
+    }
+
+    async fn is_bonded(&self) -> Result<bool> {
+        Err(Error::NotSupported(
+            "CoreBluetooth doesn't expose whether a device is bonded".to_string(),
+        ))
+    }
+
+    async fn mtu(&self) -> Result<u16> {
         let fut = CoreBluetoothReplyFuture::default();
-        // If we get WriteWithoutResponse for a characteristic that only
-        // supports WriteWithResponse, slam the type to WriteWithResponse.
-        // Otherwise we won't handle the future correctly.
-        if write_type == WriteType::WithoutResponse
-            && !characteristic
-                .properties
-                .contains(CharPropFlags::WRITE_WITHOUT_RESPONSE)
-        {
-            write_type = WriteType::WithResponse
-        }
         self.shared
             .message_sender
             .to_owned()
-            .send(CoreBluetoothMessage::WriteValue {
+            .send(CoreBluetoothMessage::Mtu {
                 peripheral_uuid: self.shared.uuid,
-                service_uuid: characteristic.service_uuid,
-                characteristic_uuid: characteristic.uuid,
-                data: Vec::from(data),
-                write_type,
                 future: fut.get_state_clone(),
             })
             .await?;
         match fut.await {
-            CoreBluetoothReply::Ok => {}
-            reply => panic!("Unexpected reply: {:?}", reply),
+            CoreBluetoothReply::Mtu(mtu) => Ok(mtu),
+            CoreBluetoothReply::Err(_) => Err(Error::NotConnected),
+            _ => panic!("Shouldn't get anything but an MTU!"),
         }
-        Ok(())
+    }
+
+    async fn write(
+        &self,
+        characteristic: &Characteristic,
+        data: &[u8],
+        mut write_type: WriteType,
+    ) -> Result<()> {
+        timeout(self.timeouts().write, async {
+            let fut = CoreBluetoothReplyFuture::default();
+            // If we get WriteWithoutResponse for a characteristic that only
+            // supports WriteWithResponse, slam the type to WriteWithResponse.
+            // Otherwise we won't handle the future correctly.
+            if write_type == WriteType::WithoutResponse
+                && !characteristic
+                    .properties
+                    .contains(CharPropFlags::WRITE_WITHOUT_RESPONSE)
+            {
+                write_type = WriteType::WithResponse
+            }
+            self.shared
+                .message_sender
+                .to_owned()
+                .send(CoreBluetoothMessage::WriteValue {
+                    peripheral_uuid: self.shared.uuid,
+                    service_handle: characteristic.service_handle,
+                    characteristic_handle: characteristic.handle,
+                    data: Vec::from(data),
+                    write_type,
+                    future: fut.get_state_clone(),
+                })
+                .await?;
+            match fut.await {
+                CoreBluetoothReply::Ok => Ok(()),
+                reply => Err(reply_error(reply)),
+            }
+        })
+        .await
     }
 
     async fn read(&self, characteristic: &Characteristic) -> Result<Vec<u8>> {
-        let fut = CoreBluetoothReplyFuture::default();
-        self.shared
-            .message_sender
-            .to_owned()
-            .send(CoreBluetoothMessage::ReadValue {
-                peripheral_uuid: self.shared.uuid,
-                service_uuid: characteristic.service_uuid,
-                characteristic_uuid: characteristic.uuid,
-                future: fut.get_state_clone(),
-            })
-            .await?;
-        match fut.await {
-            CoreBluetoothReply::ReadResult(chars) => Ok(chars),
-            _ => {
-                panic!("Shouldn't get anything but read result!");
+        timeout(self.timeouts().read, async {
+            let fut = CoreBluetoothReplyFuture::default();
+            self.shared
+                .message_sender
+                .to_owned()
+                .send(CoreBluetoothMessage::ReadValue {
+                    peripheral_uuid: self.shared.uuid,
+                    service_handle: characteristic.service_handle,
+                    characteristic_handle: characteristic.handle,
+                    future: fut.get_state_clone(),
+                })
+                .await?;
+            match fut.await {
+                CoreBluetoothReply::ReadResult(chars) => Ok(chars),
+                reply => Err(reply_error(reply)),
             }
-        }
+        })
+        .await
+    }
+
+    async fn read_at(&self, characteristic: &Characteristic, offset: usize) -> Result<Vec<u8>> {
+        transfer::value_from_offset(self.read(characteristic).await?, offset)
     }
 
     async fn subscribe(&self, characteristic: &Characteristic) -> Result<()> {
-        let fut = CoreBluetoothReplyFuture::default();
-        self.shared
-            .message_sender
-            .to_owned()
-            .send(CoreBluetoothMessage::Subscribe {
-                peripheral_uuid: self.shared.uuid,
-                service_uuid: characteristic.service_uuid,
-                characteristic_uuid: characteristic.uuid,
-                future: fut.get_state_clone(),
-            })
-            .await?;
-        match fut.await {
-            CoreBluetoothReply::Ok => trace!("subscribed!"),
-            _ => panic!("Didn't subscribe!"),
-        }
-        Ok(())
+        timeout(self.timeouts().subscribe, async {
+            let fut = CoreBluetoothReplyFuture::default();
+            self.shared
+                .message_sender
+                .to_owned()
+                .send(CoreBluetoothMessage::Subscribe {
+                    peripheral_uuid: self.shared.uuid,
+                    service_handle: characteristic.service_handle,
+                    characteristic_handle: characteristic.handle,
+                    future: fut.get_state_clone(),
+                })
+                .await?;
+            match fut.await {
+                CoreBluetoothReply::Ok => trace!("subscribed!"),
+                _ => panic!("Didn't subscribe!"),
+            }
+            Ok(())
+        })
+        .await
     }
 
     async fn unsubscribe(&self, characteristic: &Characteristic) -> Result<()> {
-        let fut = CoreBluetoothReplyFuture::default();
-        self.shared
-            .message_sender
-            .to_owned()
-            .send(CoreBluetoothMessage::Unsubscribe {
-                peripheral_uuid: self.shared.uuid,
-                service_uuid: characteristic.service_uuid,
-                characteristic_uuid: characteristic.uuid,
-                future: fut.get_state_clone(),
-            })
-            .await?;
-        match fut.await {
-            CoreBluetoothReply::Ok => {}
-            _ => panic!("Didn't unsubscribe!"),
-        }
-        Ok(())
+        timeout(self.timeouts().subscribe, async {
+            let fut = CoreBluetoothReplyFuture::default();
+            self.shared
+                .message_sender
+                .to_owned()
+                .send(CoreBluetoothMessage::Unsubscribe {
+                    peripheral_uuid: self.shared.uuid,
+                    service_handle: characteristic.service_handle,
+                    characteristic_handle: characteristic.handle,
+                    future: fut.get_state_clone(),
+                })
+                .await?;
+            match fut.await {
+                CoreBluetoothReply::Ok => {}
+                _ => panic!("Didn't unsubscribe!"),
+            }
+            Ok(())
+        })
+        .await
     }
 
     async fn notifications(&self) -> Result<Pin<Box<dyn Stream<Item = ValueNotification> + Send>>> {
@@ -378,46 +467,69 @@ impl api::Peripheral for Peripheral {
         Ok(notifications_stream_from_broadcast_receiver(receiver))
     }
 
-    async fn write_descriptor(&self, descriptor: &Descriptor, data: &[u8]) -> Result<()> {
-        let fut = CoreBluetoothReplyFuture::default();
+    async fn notifications_for(
+        &self,
+        characteristic: &Characteristic,
+        subscribe: bool,
+    ) -> Result<Pin<Box<dyn Stream<Item = Vec<u8>> + Send>>> {
         self.shared
-            .message_sender
-            .to_owned()
-            .send(CoreBluetoothMessage::WriteDescriptorValue {
-                peripheral_uuid: self.shared.uuid,
-                service_uuid: descriptor.service_uuid,
-                characteristic_uuid: descriptor.characteristic_uuid,
-                descriptor_uuid: descriptor.uuid,
-                data: Vec::from(data),
-                future: fut.get_state_clone(),
-            })
-            .await?;
-        match fut.await {
-            CoreBluetoothReply::Ok => {}
-            reply => panic!("Unexpected reply: {:?}", reply),
-        }
-        Ok(())
+            .notification_streams
+            .notifications_for(self, characteristic, subscribe)
+            .await
+    }
+
+    async fn write_descriptor(&self, descriptor: &Descriptor, data: &[u8]) -> Result<()> {
+        timeout(self.timeouts().descriptor, async {
+            let fut = CoreBluetoothReplyFuture::default();
+            self.shared
+                .message_sender
+                .to_owned()
+                .send(CoreBluetoothMessage::WriteDescriptorValue {
+                    peripheral_uuid: self.shared.uuid,
+                    service_handle: descriptor.service_handle,
+                    characteristic_handle: descriptor.characteristic_handle,
+                    descriptor_handle: descriptor.handle,
+                    data: Vec::from(data),
+                    future: fut.get_state_clone(),
+                })
+                .await?;
+            match fut.await {
+                CoreBluetoothReply::Ok => Ok(()),
+                reply => Err(reply_error(reply)),
+            }
+        })
+        .await
     }
 
     async fn read_descriptor(&self, descriptor: &Descriptor) -> Result<Vec<u8>> {
-        let fut = CoreBluetoothReplyFuture::default();
-        self.shared
-            .message_sender
-            .to_owned()
-            .send(CoreBluetoothMessage::ReadDescriptorValue {
-                peripheral_uuid: self.shared.uuid,
-                service_uuid: descriptor.service_uuid,
-                characteristic_uuid: descriptor.characteristic_uuid,
-                descriptor_uuid: descriptor.uuid,
-                future: fut.get_state_clone(),
-            })
-            .await?;
-        match fut.await {
-            CoreBluetoothReply::ReadResult(chars) => Ok(chars),
-            _ => {
-                panic!("Shouldn't get anything but read result!");
+        timeout(self.timeouts().descriptor, async {
+            let fut = CoreBluetoothReplyFuture::default();
+            self.shared
+                .message_sender
+                .to_owned()
+                .send(CoreBluetoothMessage::ReadDescriptorValue {
+                    peripheral_uuid: self.shared.uuid,
+                    service_handle: descriptor.service_handle,
+                    characteristic_handle: descriptor.characteristic_handle,
+                    descriptor_handle: descriptor.handle,
+                    future: fut.get_state_clone(),
+                })
+                .await?;
+            match fut.await {
+                CoreBluetoothReply::ReadResult(chars) => Ok(chars),
+                reply => Err(reply_error(reply)),
             }
-        }
+        })
+        .await
+    }
+}
+
+/// Turns the reply to a read or write which failed into an error.
+fn reply_error(reply: CoreBluetoothReply) -> Error {
+    match reply {
+        CoreBluetoothReply::Failed(error) => error.into(),
+        CoreBluetoothReply::Err(message) => Error::Other(message.into()),
+        reply => panic!("Unexpected reply: {:?}", reply),
     }
 }
 
@@ -6,7 +6,10 @@ use super::{
     peripheral::{Peripheral, PeripheralId},
 };
 use crate::{
-    api::{BDAddr, Central, CentralEvent, PeripheralProperties, ScanFilter},
+    api::{
+        BDAddr, Central, CentralEvent, CentralState, DeviceExpiry, PeripheralProperties, Presence,
+        ScanFilter, ScanMode, Timeouts,
+    },
     common::adapter_manager::AdapterManager,
     Error, Result,
 };
@@ -15,7 +18,7 @@ use futures::stream::Stream;
 use jni::{
     objects::{GlobalRef, JObject, JString},
     strings::JavaStr,
-    sys::jboolean,
+    sys::{jboolean, jint},
     JNIEnv,
 };
 use std::{
@@ -25,6 +28,12 @@ use std::{
     sync::Arc,
 };
 
+// Values of BluetoothAdapter.getState().
+const STATE_OFF: jint = 10;
+const STATE_TURNING_ON: jint = 11;
+const STATE_ON: jint = 12;
+const STATE_TURNING_OFF: jint = 13;
+
 #[derive(Clone)]
 pub struct Adapter {
     manager: Arc<AdapterManager<Peripheral>>,
@@ -90,7 +99,12 @@ impl Adapter {
 
     fn add(&self, address: BDAddr) -> Result<Peripheral> {
         let env = global_jvm().get_env()?;
-        let peripheral = Peripheral::new(&env, self.internal.as_obj(), address)?;
+        let peripheral = Peripheral::new(
+            &env,
+            self.internal.as_obj(),
+            address,
+            Arc::downgrade(&self.manager),
+        )?;
         self.manager.add_peripheral(peripheral.clone());
         Ok(peripheral)
     }
@@ -123,6 +137,15 @@ impl Adapter {
     }
 }
 
+fn central_state(state: jint) -> CentralState {
+    match state {
+        STATE_ON => CentralState::PoweredOn,
+        STATE_OFF | STATE_TURNING_ON | STATE_TURNING_OFF => CentralState::PoweredOff,
+        // The device has no Bluetooth adapter.
+        _ => CentralState::Unavailable,
+    }
+}
+
 #[async_trait]
 impl Central for Adapter {
     type Peripheral = Peripheral;
@@ -132,12 +155,32 @@ impl Central for Adapter {
         Ok("Android".to_string())
     }
 
+    async fn adapter_state(&self) -> Result<CentralState> {
+        let env = global_jvm().get_env()?;
+        let state = env
+            .call_method(&self.internal, "getState", "()I", &[])?
+            .i()?;
+        Ok(central_state(state))
+    }
+
+    async fn set_powered(&self, _powered: bool) -> Result<()> {
+        Err(Error::NotSupported(
+            "Android doesn't allow apps to turn Bluetooth on or off".to_string(),
+        ))
+    }
+
     async fn events(&self) -> Result<Pin<Box<dyn Stream<Item = CentralEvent> + Send>>> {
         Ok(self.manager.event_stream())
     }
 
     async fn start_scan(&self, filter: ScanFilter) -> Result<()> {
+        if filter.mode == ScanMode::Passive {
+            return Err(Error::NotSupported(
+                "Android doesn't offer passive scanning".to_string(),
+            ));
+        }
         let env = global_jvm().get_env()?;
+        self.manager.start_scan(filter.clone());
         let filter = JScanFilter::new(&env, filter)?;
         env.call_method(
             &self.internal,
@@ -151,6 +194,7 @@ impl Central for Adapter {
     async fn stop_scan(&self) -> Result<()> {
         let env = global_jvm().get_env()?;
         env.call_method(&self.internal, "stopScan", "()V", &[])?;
+        self.manager.stop_scan();
         Ok(())
     }
 
@@ -167,6 +211,26 @@ impl Central for Adapter {
     async fn add_peripheral(&self, address: &PeripheralId) -> Result<Peripheral> {
         self.add(address.0)
     }
+
+    fn timeouts(&self) -> Timeouts {
+        self.manager.timeouts()
+    }
+
+    fn set_timeouts(&self, timeouts: Timeouts) {
+        self.manager.set_timeouts(timeouts);
+    }
+
+    fn presence(&self, id: &PeripheralId) -> Option<Presence> {
+        self.manager.presence(id)
+    }
+
+    fn set_device_expiry(&self, expiry: Option<DeviceExpiry>) {
+        self.manager.set_device_expiry(expiry);
+    }
+
+    fn set_proximity_config(&self, config: ProximityConfig) {
+        self.manager.set_proximity_config(config);
+    }
 }
 
 pub(crate) fn adapter_report_scan_result_internal(
@@ -196,3 +260,33 @@ pub(crate) fn adapter_on_connection_state_changed_internal(
     });
     Ok(())
 }
+
+pub(crate) fn adapter_on_mtu_changed_internal(
+    env: &JNIEnv,
+    obj: JObject,
+    addr: JString,
+    mtu: jint,
+) -> crate::Result<()> {
+    let adapter = env.get_rust_field::<_, _, Adapter>(obj, "handle")?;
+    let addr_str = JavaStr::from_env(env, addr)?;
+    let addr_str = addr_str.to_str().map_err(|e| Error::Other(e.into()))?;
+    let addr = BDAddr::from_str(addr_str)?;
+    let mtu = u16::try_from(mtu).map_err(|e| Error::Other(e.into()))?;
+    adapter.manager.emit(CentralEvent::MtuChanged {
+        id: PeripheralId(addr),
+        mtu,
+    });
+    Ok(())
+}
+
+pub(crate) fn adapter_on_state_changed_internal(
+    env: &JNIEnv,
+    obj: JObject,
+    state: jint,
+) -> crate::Result<()> {
+    let adapter = env.get_rust_field::<_, _, Adapter>(obj, "handle")?;
+    adapter
+        .manager
+        .emit(CentralEvent::StateUpdate(central_state(state)));
+    Ok(())
+}
@@ -1,6 +1,10 @@
 package com.nonpolynomial.btleplug.android.impl;
 
 import android.bluetooth.BluetoothAdapter;
+import android.content.BroadcastReceiver;
+import android.content.Context;
+import android.content.Intent;
+import android.content.IntentFilter;
 import android.bluetooth.le.ScanCallback;
 import android.bluetooth.le.ScanFilter.Builder;
 import android.bluetooth.le.ScanResult;
@@ -14,8 +18,24 @@ import java.util.List;
 class Adapter {
     private long handle;
     private final Callback callback = new Callback();
+    private final StateReceiver stateReceiver = new StateReceiver();
 
-    public Adapter() {}
+    public Adapter() {
+        Context context = applicationContext();
+        if (context != null) {
+            context.registerReceiver(this.stateReceiver, new IntentFilter(BluetoothAdapter.ACTION_STATE_CHANGED));
+        }
+    }
+
+    // We aren't given a Context, so use the application's, which is found the same way
+    // android.app.AppGlobals does.
+    private static Context applicationContext() {
+        try {
+            return (Context) Class.forName("android.app.ActivityThread").getMethod("currentApplication").invoke(null);
+        } catch (ReflectiveOperationException e) {
+            return null;
+        }
+    }
 
     public void startScan(ScanFilter filter) {
         ArrayList<android.bluetooth.le.ScanFilter> filters = null;
@@ -36,14 +56,30 @@ class Adapter {
         BluetoothAdapter.getDefaultAdapter().getBluetoothLeScanner().stopScan(this.callback);
     }
 
+    public int getState() {
+        BluetoothAdapter adapter = BluetoothAdapter.getDefaultAdapter();
+        return adapter == null ? -1 : adapter.getState();
+    }
+
     private native void reportScanResult(ScanResult result);
 
     public native void onConnectionStateChanged(String address, boolean connected);
 
+    public native void onMtuChanged(String address, int mtu);
+
+    private native void onStateChanged(int state);
+
     private class Callback extends ScanCallback {
         @Override
         public void onScanResult(int callbackType, ScanResult result) {
             Adapter.this.reportScanResult(result);
         }
     }
+
+    private class StateReceiver extends BroadcastReceiver {
+        @Override
+        public void onReceive(Context context, Intent intent) {
+            Adapter.this.onStateChanged(intent.getIntExtra(BluetoothAdapter.EXTRA_STATE, BluetoothAdapter.ERROR));
+        }
+    }
 }
@@ -0,0 +1,14 @@
+package com.nonpolynomial.btleplug.android.impl;
+
+class GattException extends BluetoothException {
+    private final int status;
+
+    public GattException(int status) {
+        super();
+        this.status = status;
+    }
+
+    public int getStatus() {
+        return this.status;
+    }
+}
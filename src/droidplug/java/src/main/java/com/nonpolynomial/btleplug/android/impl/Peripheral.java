@@ -23,12 +23,14 @@ import io.github.gedgygedgy.rust.stream.Stream;
 @SuppressWarnings("unused") // Native code uses this class.
 class Peripheral {
     private static final UUID CLIENT_CHARACTERISTIC_CONFIGURATION_DESCRIPTOR = new UUID(0x00002902_0000_1000L, 0x8000_00805f9b34fbL);
+    private static final int DEFAULT_MTU = 23;
 
     private final BluetoothDevice device;
     private final Adapter adapter;
     private BluetoothGatt gatt;
     private final Callback callback;
     private boolean connected = false;
+    private int mtu = DEFAULT_MTU;
 
     private final Queue<Runnable> commandQueue = new LinkedList<>();
     private final LinkedList<WeakReference<QueueStream<BluetoothGattCharacteristic>>> notificationStreams = new LinkedList<>();
@@ -116,7 +118,15 @@ class Peripheral {
         return this.connected;
     }
 
-    public Future<byte[]> read(UUID uuid) {
+    public synchronized int getMtu() {
+        return this.mtu;
+    }
+
+    public boolean isBonded() {
+        return this.device.getBondState() == BluetoothDevice.BOND_BONDED;
+    }
+
+    public Future<byte[]> read(int handle) {
         SimpleFuture<byte[]> future = new SimpleFuture<>();
         synchronized (this) {
             this.queueCommand(() -> {
@@ -125,15 +135,19 @@ class Peripheral {
                         throw new NotConnectedException();
                     }
 
-                    BluetoothGattCharacteristic characteristic = this.getCharacteristicByUuid(uuid);
+                    BluetoothGattCharacteristic characteristic = this.getCharacteristicByHandle(handle);
                     this.setCommandCallback(new CommandCallback() {
                         @Override
                         public void onCharacteristicRead(BluetoothGatt gatt, BluetoothGattCharacteristic characteristic, int status) {
                             Peripheral.this.asyncWithFuture(future, () -> {
-                                if (!characteristic.getUuid().equals(uuid)) {
+                                if (characteristic.getInstanceId() != handle) {
                                     throw new UnexpectedCharacteristicException();
                                 }
 
+                                if (status != BluetoothGatt.GATT_SUCCESS) {
+                                    throw new GattException(status);
+                                }
+
                                 Peripheral.this.wakeCommand(future, characteristic.getValue());
                             });
                         }
@@ -147,7 +161,7 @@ class Peripheral {
         return future;
     }
 
-    public Future<Void> write(UUID uuid, byte[] data, int writeType) {
+    public Future<Void> write(int handle, byte[] data, int writeType) {
         SimpleFuture<Void> future = new SimpleFuture<>();
         synchronized (this) {
             this.queueCommand(() -> {
@@ -156,17 +170,21 @@ class Peripheral {
                         throw new NotConnectedException();
                     }
 
-                    BluetoothGattCharacteristic characteristic = this.getCharacteristicByUuid(uuid);
+                    BluetoothGattCharacteristic characteristic = this.getCharacteristicByHandle(handle);
                     characteristic.setValue(data);
                     characteristic.setWriteType(writeType);
                     this.setCommandCallback(new CommandCallback() {
                         @Override
                         public void onCharacteristicWrite(BluetoothGatt gatt, BluetoothGattCharacteristic characteristic, int status) {
                             Peripheral.this.asyncWithFuture(future, () -> {
-                                if (!characteristic.getUuid().equals(uuid)) {
+                                if (characteristic.getInstanceId() != handle) {
                                     throw new UnexpectedCharacteristicException();
                                 }
 
+                                if (status != BluetoothGatt.GATT_SUCCESS) {
+                                    throw new GattException(status);
+                                }
+
                                 Peripheral.this.wakeCommand(future, null);
                             });
                         }
@@ -208,7 +226,7 @@ class Peripheral {
         return future;
     }
 
-    public Future<Void> setCharacteristicNotification(UUID uuid, boolean enable) {
+    public Future<Void> setCharacteristicNotification(int handle, boolean enable) {
         SimpleFuture<Void> future = new SimpleFuture<>();
         synchronized (this) {
             this.queueCommand(() -> {
@@ -217,7 +235,7 @@ class Peripheral {
                         throw new NotConnectedException();
                     }
 
-                    BluetoothGattCharacteristic characteristic = this.getCharacteristicByUuid(uuid);
+                    BluetoothGattCharacteristic characteristic = this.getCharacteristicByHandle(handle);
                     if (!this.gatt.setCharacteristicNotification(characteristic, enable)) {
                         throw new RuntimeException("Unable to set characteristic notification");
                     }
@@ -233,10 +251,10 @@ class Peripheral {
                         public void onDescriptorWrite(BluetoothGatt gatt, BluetoothGattDescriptor descriptor, int status) {
                             Peripheral.this.asyncWithFuture(future, () -> {
                                 if (status != BluetoothGatt.GATT_SUCCESS) {
-                                    throw new RuntimeException("Unable to write descriptor");
+                                    throw new GattException(status);
                                 }
 
-                                if (!descriptor.getUuid().equals(CLIENT_CHARACTERISTIC_CONFIGURATION_DESCRIPTOR) || !descriptor.getCharacteristic().getUuid().equals(uuid)) {
+                                if (!descriptor.getUuid().equals(CLIENT_CHARACTERISTIC_CONFIGURATION_DESCRIPTOR) || descriptor.getCharacteristic().getInstanceId() != handle) {
                                     throw new UnexpectedCharacteristicException();
                                 }
 
@@ -258,7 +276,7 @@ class Peripheral {
         return stream;
     }
 
-    public Future<byte[]> readDescriptor(UUID characteristic, UUID uuid) {
+    public Future<byte[]> readDescriptor(int characteristic, UUID uuid) {
         SimpleFuture<byte[]> future = new SimpleFuture<>();
         synchronized (this) {
             this.queueCommand(() -> {
@@ -276,6 +294,10 @@ class Peripheral {
                                     throw new UnexpectedCharacteristicException();
                                 }
 
+                                if (status != BluetoothGatt.GATT_SUCCESS) {
+                                    throw new GattException(status);
+                                }
+
                                 Peripheral.this.wakeCommand(future, descriptor.getValue());
                             });
                         }
@@ -289,7 +311,7 @@ class Peripheral {
         return future;
     }
 
-    public Future<Void> writeDescriptor(UUID characteristic, UUID uuid, byte[] data, int writeType) {
+    public Future<Void> writeDescriptor(int characteristic, UUID uuid, byte[] data) {
         SimpleFuture<Void> future = new SimpleFuture<>();
         synchronized (this) {
             this.queueCommand(() -> {
@@ -308,6 +330,10 @@ class Peripheral {
                                     throw new UnexpectedCharacteristicException();
                                 }
 
+                                if (status != BluetoothGatt.GATT_SUCCESS) {
+                                    throw new GattException(status);
+                                }
+
                                 Peripheral.this.wakeCommand(future, null);
                             });
                         }
@@ -331,9 +357,9 @@ class Peripheral {
         return result;
     }
 
-    private BluetoothGattCharacteristic getCharacteristicByUuid(UUID uuid) {
+    private BluetoothGattCharacteristic getCharacteristicByHandle(int handle) {
         for (BluetoothGattCharacteristic characteristic : this.getCharacteristics()) {
-            if (characteristic.getUuid().equals(uuid)) {
+            if (characteristic.getInstanceId() == handle) {
                 return characteristic;
             }
         }
@@ -341,8 +367,8 @@ class Peripheral {
         throw new NoSuchCharacteristicException();
     }
 
-    private BluetoothGattDescriptor getDescriptorByUuid(UUID characteristicUuid, UUID uuid) {
-        BluetoothGattCharacteristic characteristic = getCharacteristicByUuid(characteristicUuid);
+    private BluetoothGattDescriptor getDescriptorByUuid(int characteristicHandle, UUID uuid) {
+        BluetoothGattCharacteristic characteristic = getCharacteristicByHandle(characteristicHandle);
         for (BluetoothGattDescriptor descriptor : characteristic.getDescriptors()) {
             if (descriptor.getUuid().equals(uuid)) {
                 return descriptor;
@@ -401,6 +427,7 @@ class Peripheral {
                         break;
                     case BluetoothGatt.STATE_DISCONNECTED:
                         Peripheral.this.connected = false;
+                        Peripheral.this.mtu = DEFAULT_MTU;
                         break;
                 }
                 if (Peripheral.this.commandCallback != null) {
@@ -466,6 +493,17 @@ class Peripheral {
                 }
             }
         }
+
+        @Override
+        public void onMtuChanged(BluetoothGatt gatt, int mtu, int status) {
+            if (status != BluetoothGatt.GATT_SUCCESS) {
+                return;
+            }
+            synchronized (Peripheral.this) {
+                Peripheral.this.mtu = mtu;
+            }
+            Peripheral.this.adapter.onMtuChanged(Peripheral.this.device.getAddress(), mtu);
+        }
     }
 
     private static abstract class CommandCallback extends BluetoothGattCallback {
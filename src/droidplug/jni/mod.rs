@@ -1,7 +1,10 @@
 pub mod objects;
 
 use ::jni::{objects::JObject, JNIEnv, JavaVM, NativeMethod};
-use jni::{objects::JString, sys::jboolean};
+use jni::{
+    objects::JString,
+    sys::{jboolean, jint},
+};
 use once_cell::sync::OnceCell;
 use std::ffi::c_void;
 
@@ -22,6 +25,16 @@ pub fn init(env: &JNIEnv) -> crate::Result<()> {
                     sig: "(Ljava/lang/String;Z)V".into(),
                     fn_ptr: adapter_on_connection_state_changed as *mut c_void,
                 },
+                NativeMethod {
+                    name: "onMtuChanged".into(),
+                    sig: "(Ljava/lang/String;I)V".into(),
+                    fn_ptr: adapter_on_mtu_changed as *mut c_void,
+                },
+                NativeMethod {
+                    name: "onStateChanged".into(),
+                    sig: "(I)V".into(),
+                    fn_ptr: adapter_on_state_changed as *mut c_void,
+                },
             ],
         )?;
         jni_utils::classcache::find_add_class(
@@ -61,3 +74,11 @@ extern "C" fn adapter_on_connection_state_changed(
     let _ =
         super::adapter::adapter_on_connection_state_changed_internal(&env, obj, addr, connected);
 }
+
+extern "C" fn adapter_on_mtu_changed(env: JNIEnv, obj: JObject, addr: JString, mtu: jint) {
+    let _ = super::adapter::adapter_on_mtu_changed_internal(&env, obj, addr, mtu);
+}
+
+extern "C" fn adapter_on_state_changed(env: JNIEnv, obj: JObject, state: jint) {
+    let _ = super::adapter::adapter_on_state_changed_internal(&env, obj, state);
+}
@@ -17,6 +17,8 @@ pub struct JPeripheral<'a: 'b, 'b> {
     connect: JMethodID<'a>,
     disconnect: JMethodID<'a>,
     is_connected: JMethodID<'a>,
+    get_mtu: JMethodID<'a>,
+    is_bonded: JMethodID<'a>,
     discover_services: JMethodID<'a>,
     read: JMethodID<'a>,
     write: JMethodID<'a>,
@@ -67,6 +69,8 @@ impl<'a: 'b, 'b> JPeripheral<'a, 'b> {
             "()Lio/github/gedgygedgy/rust/future/Future;",
         )?;
         let is_connected = env.get_method_id(class, "isConnected", "()Z")?;
+        let get_mtu = env.get_method_id(class, "getMtu", "()I")?;
+        let is_bonded = env.get_method_id(class, "isBonded", "()Z")?;
         let discover_services = env.get_method_id(
             class,
             "discoverServices",
@@ -75,17 +79,17 @@ impl<'a: 'b, 'b> JPeripheral<'a, 'b> {
         let read = env.get_method_id(
             class,
             "read",
-            "(Ljava/util/UUID;)Lio/github/gedgygedgy/rust/future/Future;",
+            "(I)Lio/github/gedgygedgy/rust/future/Future;",
         )?;
         let write = env.get_method_id(
             class,
             "write",
-            "(Ljava/util/UUID;[BI)Lio/github/gedgygedgy/rust/future/Future;",
+            "(I[BI)Lio/github/gedgygedgy/rust/future/Future;",
         )?;
         let set_characteristic_notification = env.get_method_id(
             class,
             "setCharacteristicNotification",
-            "(Ljava/util/UUID;Z)Lio/github/gedgygedgy/rust/future/Future;",
+            "(IZ)Lio/github/gedgygedgy/rust/future/Future;",
         )?;
         let get_notifications = env.get_method_id(
             class,
@@ -95,18 +99,20 @@ impl<'a: 'b, 'b> JPeripheral<'a, 'b> {
         let read_descriptor = env.get_method_id(
             class,
             "readDescriptor",
-            "(Ljava/util/UUID;Ljava/util/UUID;)Lio/github/gedgygedgy/rust/future/Future;",
+            "(ILjava/util/UUID;)Lio/github/gedgygedgy/rust/future/Future;",
         )?;
         let write_descriptor = env.get_method_id(
             class,
             "writeDescriptor",
-            "(Ljava/util/UUID;Ljava/util/UUID;[BI)Lio/github/gedgygedgy/rust/future/Future;",
+            "(ILjava/util/UUID;[B)Lio/github/gedgygedgy/rust/future/Future;",
         )?;
         Ok(Self {
             internal: obj,
             connect,
             disconnect,
             is_connected,
+            get_mtu,
+            is_bonded,
             discover_services,
             read,
             write,
@@ -174,6 +180,28 @@ impl<'a: 'b, 'b> JPeripheral<'a, 'b> {
             .z()
     }
 
+    pub fn get_mtu(&self) -> Result<i32> {
+        self.env
+            .call_method_unchecked(
+                self.internal,
+                self.get_mtu,
+                JavaType::Primitive(Primitive::Int),
+                &[],
+            )?
+            .i()
+    }
+
+    pub fn is_bonded(&self) -> Result<bool> {
+        self.env
+            .call_method_unchecked(
+                self.internal,
+                self.is_bonded,
+                JavaType::Primitive(Primitive::Boolean),
+                &[],
+            )?
+            .z()
+    }
+
     pub fn discover_services(&self) -> Result<JFuture<'a, 'b>> {
         let future_obj = self
             .env
@@ -187,14 +215,14 @@ impl<'a: 'b, 'b> JPeripheral<'a, 'b> {
         JFuture::from_env(self.env, future_obj)
     }
 
-    pub fn read(&self, uuid: JUuid<'a, 'b>) -> Result<JFuture<'a, 'b>> {
+    pub fn read(&self, handle: jint) -> Result<JFuture<'a, 'b>> {
         let future_obj = self
             .env
             .call_method_unchecked(
                 self.internal,
                 self.read,
                 JavaType::Object("Lio/github/gedgygedgy/rust/future/Future;".to_string()),
-                &[uuid.into()],
+                &[handle.into()],
             )?
             .l()?;
         JFuture::from_env(self.env, future_obj)
@@ -202,7 +230,7 @@ impl<'a: 'b, 'b> JPeripheral<'a, 'b> {
 
     pub fn write(
         &self,
-        uuid: JUuid<'a, 'b>,
+        handle: jint,
         data: JObject<'a>,
         write_type: jint,
     ) -> Result<JFuture<'a, 'b>> {
@@ -212,7 +240,7 @@ impl<'a: 'b, 'b> JPeripheral<'a, 'b> {
                 self.internal,
                 self.write,
                 JavaType::Object("Lio/github/gedgygedgy/rust/future/Future;".to_string()),
-                &[uuid.into(), data.into(), write_type.into()],
+                &[handle.into(), data.into(), write_type.into()],
             )?
             .l()?;
         JFuture::from_env(self.env, future_obj)
@@ -220,7 +248,7 @@ impl<'a: 'b, 'b> JPeripheral<'a, 'b> {
 
     pub fn set_characteristic_notification(
         &self,
-        uuid: JUuid<'a, 'b>,
+        handle: jint,
         enable: bool,
     ) -> Result<JFuture<'a, 'b>> {
         let future_obj = self
@@ -229,7 +257,7 @@ impl<'a: 'b, 'b> JPeripheral<'a, 'b> {
                 self.internal,
                 self.set_characteristic_notification,
                 JavaType::Object("Lio/github/gedgygedgy/rust/future/Future;".to_string()),
-                &[uuid.into(), enable.into()],
+                &[handle.into(), enable.into()],
             )?
             .l()?;
         JFuture::from_env(self.env, future_obj)
@@ -250,7 +278,7 @@ impl<'a: 'b, 'b> JPeripheral<'a, 'b> {
 
     pub fn read_descriptor(
         &self,
-        characteristic: JUuid<'a, 'b>,
+        characteristic: jint,
         uuid: JUuid<'a, 'b>,
     ) -> Result<JFuture<'a, 'b>> {
         let future_obj = self
@@ -267,7 +295,7 @@ impl<'a: 'b, 'b> JPeripheral<'a, 'b> {
 
     pub fn write_descriptor(
         &self,
-        characteristic: JUuid<'a, 'b>,
+        characteristic: jint,
         uuid: JUuid<'a, 'b>,
         data: JObject<'a>,
     ) -> Result<JFuture<'a, 'b>> {
@@ -287,7 +315,9 @@ impl<'a: 'b, 'b> JPeripheral<'a, 'b> {
 pub struct JBluetoothGattService<'a: 'b, 'b> {
     internal: JObject<'a>,
     get_uuid: JMethodID<'a>,
-    //is_primary: JMethodID<'a>,
+    get_instance_id: JMethodID<'a>,
+    get_type: JMethodID<'a>,
+    get_included_services: JMethodID<'a>,
     get_characteristics: JMethodID<'a>,
     env: &'b JNIEnv<'a>,
 }
@@ -297,30 +327,54 @@ impl<'a: 'b, 'b> JBluetoothGattService<'a, 'b> {
         let class = env.auto_local(env.find_class("android/bluetooth/BluetoothGattService")?);
 
         let get_uuid = env.get_method_id(&class, "getUuid", "()Ljava/util/UUID;")?;
-        //let is_primary = env.get_method_id(&class, "isPrimary", "()Z;")?;
+        let get_instance_id = env.get_method_id(&class, "getInstanceId", "()I")?;
+        let get_type = env.get_method_id(&class, "getType", "()I")?;
+        let get_included_services =
+            env.get_method_id(&class, "getIncludedServices", "()Ljava/util/List;")?;
         let get_characteristics =
             env.get_method_id(&class, "getCharacteristics", "()Ljava/util/List;")?;
         Ok(Self {
             internal: obj,
             get_uuid,
-            //is_primary,
+            get_instance_id,
+            get_type,
+            get_included_services,
             get_characteristics,
             env,
         })
     }
 
     pub fn is_primary(&self) -> Result<bool> {
-        /*
-        self.env
-        .call_method_unchecked(
-            self.internal,
-            self.is_primary,
-            JavaType::Primitive(Primitive::Boolean),
-            &[],
-        )?
-        .z()
-        */
-        Ok(true)
+        // BluetoothGattService.SERVICE_TYPE_PRIMARY
+        const SERVICE_TYPE_PRIMARY: jint = 0;
+        let service_type = self
+            .env
+            .call_method_unchecked(
+                self.internal,
+                self.get_type,
+                JavaType::Primitive(Primitive::Int),
+                &[],
+            )?
+            .i()?;
+        Ok(service_type == SERVICE_TYPE_PRIMARY)
+    }
+
+    pub fn get_included_services(&self) -> Result<Vec<JBluetoothGattService<'a, 'b>>> {
+        let obj = self
+            .env
+            .call_method_unchecked(
+                self.internal,
+                self.get_included_services,
+                JavaType::Object("Ljava/util/List;".to_string()),
+                &[],
+            )?
+            .l()?;
+        let service_list = JList::from_env(self.env, obj)?;
+        let mut service_vec = vec![];
+        for service in service_list.iter()? {
+            service_vec.push(JBluetoothGattService::from_env(self.env, service)?);
+        }
+        Ok(service_vec)
     }
 
     pub fn get_uuid(&self) -> Result<Uuid> {
@@ -337,6 +391,19 @@ impl<'a: 'b, 'b> JBluetoothGattService<'a, 'b> {
         Ok(uuid_obj.as_uuid()?)
     }
 
+    pub fn get_instance_id(&self) -> Result<u16> {
+        let id = self
+            .env
+            .call_method_unchecked(
+                self.internal,
+                self.get_instance_id,
+                JavaType::Primitive(Primitive::Int),
+                &[],
+            )?
+            .i()?;
+        Ok(id as u16)
+    }
+
     pub fn get_characteristics(&self) -> Result<Vec<JBluetoothGattCharacteristic>> {
         let obj = self
             .env
@@ -359,9 +426,11 @@ impl<'a: 'b, 'b> JBluetoothGattService<'a, 'b> {
 pub struct JBluetoothGattCharacteristic<'a: 'b, 'b> {
     internal: JObject<'a>,
     get_uuid: JMethodID<'a>,
+    get_instance_id: JMethodID<'a>,
     get_properties: JMethodID<'a>,
     get_value: JMethodID<'a>,
     get_descriptors: JMethodID<'a>,
+    get_service: JMethodID<'a>,
     env: &'b JNIEnv<'a>,
 }
 
@@ -371,15 +440,23 @@ impl<'a: 'b, 'b> JBluetoothGattCharacteristic<'a, 'b> {
             env.auto_local(env.find_class("android/bluetooth/BluetoothGattCharacteristic")?);
 
         let get_uuid = env.get_method_id(&class, "getUuid", "()Ljava/util/UUID;")?;
+        let get_instance_id = env.get_method_id(&class, "getInstanceId", "()I")?;
         let get_properties = env.get_method_id(&class, "getProperties", "()I")?;
         let get_descriptors = env.get_method_id(&class, "getDescriptors", "()Ljava/util/List;")?;
         let get_value = env.get_method_id(&class, "getValue", "()[B")?;
+        let get_service = env.get_method_id(
+            &class,
+            "getService",
+            "()Landroid/bluetooth/BluetoothGattService;",
+        )?;
         Ok(Self {
             internal: obj,
             get_uuid,
+            get_instance_id,
             get_properties,
             get_value,
             get_descriptors,
+            get_service,
             env,
         })
     }
@@ -398,6 +475,19 @@ impl<'a: 'b, 'b> JBluetoothGattCharacteristic<'a, 'b> {
         Ok(uuid_obj.as_uuid()?)
     }
 
+    pub fn get_instance_id(&self) -> Result<u16> {
+        let id = self
+            .env
+            .call_method_unchecked(
+                self.internal,
+                self.get_instance_id,
+                JavaType::Primitive(Primitive::Int),
+                &[],
+            )?
+            .i()?;
+        Ok(id as u16)
+    }
+
     pub fn get_properties(&self) -> Result<CharPropFlags> {
         let flags = self
             .env
@@ -424,6 +514,19 @@ impl<'a: 'b, 'b> JBluetoothGattCharacteristic<'a, 'b> {
         jni_utils::arrays::byte_array_to_vec(self.env, value.into_inner())
     }
 
+    pub fn get_service(&self) -> Result<JBluetoothGattService<'a, 'b>> {
+        let obj = self
+            .env
+            .call_method_unchecked(
+                self.internal,
+                self.get_service,
+                JavaType::Object("Landroid/bluetooth/BluetoothGattService;".to_string()),
+                &[],
+            )?
+            .l()?;
+        JBluetoothGattService::from_env(self.env, obj)
+    }
+
     pub fn get_descriptors(&self) -> Result<Vec<JBluetoothGattDescriptor>> {
         let obj = self
             .env
@@ -1,5 +1,8 @@
 use super::adapter::Adapter;
-use crate::{api, Result};
+use crate::{
+    api::{self, Central, Timeouts},
+    Result,
+};
 use async_trait::async_trait;
 
 #[derive(Clone, Debug)]
@@ -18,4 +21,12 @@ impl api::Manager for Manager {
     async fn adapters(&self) -> Result<Vec<Adapter>> {
         Ok(vec![super::global_adapter().clone()])
     }
+
+    fn timeouts(&self) -> Timeouts {
+        super::global_adapter().timeouts()
+    }
+
+    fn set_timeouts(&self, timeouts: Timeouts) {
+        super::global_adapter().set_timeouts(timeouts);
+    }
 }
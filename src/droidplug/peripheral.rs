@@ -1,7 +1,13 @@
 use crate::{
     api::{
-        self, BDAddr, Characteristic, Descriptor, PeripheralProperties, Service, ValueNotification,
-        WriteType,
+        self, proximity::Proximity, BDAddr, Characteristic, Descriptor, PeripheralProperties,
+        Service, Timeouts, ValueNotification, WriteType,
+    },
+    common::{
+        adapter_manager::AdapterManager,
+        subscriptions::Subscriptions,
+        timeouts::{self, timeout},
+        transfer,
     },
     Error, Result,
 };
@@ -25,7 +31,7 @@ use std::{
     convert::TryFrom,
     fmt::{self, Debug, Display, Formatter},
     pin::Pin,
-    sync::{Arc, Mutex},
+    sync::{Arc, Mutex, Weak},
 };
 
 use super::jni::{
@@ -67,6 +73,12 @@ fn get_poll_result<'a: 'b, 'b>(
                 "com/nonpolynomial/btleplug/android/impl/PermissionDeniedException",
             )? {
                 Ok(Err(Error::PermissionDenied))
+            } else if env.is_instance_of(
+                cause,
+                "com/nonpolynomial/btleplug/android/impl/GattException",
+            )? {
+                let status = env.call_method(cause, "getStatus", "()I", &[])?.i()?;
+                Ok(Err(gatt_error(status)))
             } else {
                 env.throw(ex)?;
                 Err(jni::errors::Error::JavaException)
@@ -75,6 +87,25 @@ fn get_poll_result<'a: 'b, 'b>(
         .result()?
 }
 
+/// The status of a GATT operation which failed because another one was in progress.
+const GATT_BUSY: i32 = 0x84;
+/// The status of a GATT operation which failed because the link couldn't be authenticated.
+const GATT_AUTH_FAIL: i32 = 0x89;
+
+/// Turns the status a GATT operation failed with into an error. Android passes on the ATT error the
+/// device responded with as the status, but also uses the range of application errors for errors
+/// of its own stack, so those can't be told apart and aren't taken for ATT errors.
+fn gatt_error(status: i32) -> Error {
+    match status {
+        GATT_BUSY => Error::InProgress,
+        GATT_AUTH_FAIL => Error::AuthenticationFailed,
+        _ => match u8::try_from(status) {
+            Ok(code @ (0x01..=0x7f | 0xfc..=0xff)) => Error::Att(code.into()),
+            _ => Error::Other(format!("GATT operation failed with status {}", status).into()),
+        },
+    }
+}
+
 struct PeripheralShared {
     services: BTreeSet<Service>,
     characteristics: BTreeSet<Characteristic>,
@@ -86,10 +117,18 @@ pub struct Peripheral {
     addr: BDAddr,
     internal: GlobalRef,
     shared: Arc<Mutex<PeripheralShared>>,
+    notification_streams: Arc<Subscriptions>,
+    manager: Weak<AdapterManager<Peripheral>>,
+    timeout_overrides: Timeouts,
 }
 
 impl Peripheral {
-    pub(crate) fn new(env: &JNIEnv, adapter: JObject, addr: BDAddr) -> Result<Self> {
+    pub(crate) fn new(
+        env: &JNIEnv,
+        adapter: JObject,
+        addr: BDAddr,
+        manager: Weak<AdapterManager<Peripheral>>,
+    ) -> Result<Self> {
         let obj = JPeripheral::new(env, adapter, addr)?;
         Ok(Self {
             addr,
@@ -99,6 +138,9 @@ impl Peripheral {
                 characteristics: BTreeSet::new(),
                 properties: None,
             })),
+            notification_streams: Default::default(),
+            manager,
+            timeout_overrides: Timeouts::default(),
         })
     }
 
@@ -126,8 +168,9 @@ impl Peripheral {
         enable: bool,
     ) -> Result<()> {
         let future = self.with_obj(|env, obj| {
-            let uuid_obj = JUuid::new(env, characteristic.uuid)?;
-            JSendFuture::try_from(obj.set_characteristic_notification(uuid_obj, enable)?)
+            JSendFuture::try_from(
+                obj.set_characteristic_notification(characteristic.handle.into(), enable)?,
+            )
         })?;
         let result_ref = future.await?;
         self.with_obj(|env, _obj| {
@@ -164,17 +207,35 @@ impl api::Peripheral for Peripheral {
         (&guard.characteristics).clone()
     }
 
+    fn timeouts(&self) -> Timeouts {
+        let adapter_timeouts = match self.manager.upgrade() {
+            Some(manager) => manager.timeouts(),
+            None => Timeouts::default(),
+        };
+        adapter_timeouts.merge(self.timeout_overrides)
+    }
+
+    fn with_timeouts(&self, timeouts: Timeouts) -> Self {
+        Self {
+            timeout_overrides: self.timeout_overrides.merge(timeouts),
+            ..self.clone()
+        }
+    }
+
     async fn is_connected(&self) -> Result<bool> {
         self.with_obj(|_env, obj| Ok(obj.is_connected()?))
     }
 
     async fn connect(&self) -> Result<()> {
-        let future = self.with_obj(|_env, obj| JSendFuture::try_from(obj.connect()?))?;
-        let result_ref = future.await?;
-        self.with_obj(|env, _obj| {
-            let result = JPollResult::from_env(env, result_ref.as_obj())?;
-            get_poll_result(env, result).map(|_| {})
+        timeouts::connect(self, async {
+            let future = self.with_obj(|_env, obj| JSendFuture::try_from(obj.connect()?))?;
+            let result_ref = future.await?;
+            self.with_obj(|env, _obj| {
+                let result = JPollResult::from_env(env, result_ref.as_obj())?;
+                get_poll_result(env, result).map(|_| {})
+            })
         })
+        .await
     }
 
     async fn disconnect(&self) -> Result<()> {
@@ -188,59 +249,110 @@ impl api::Peripheral for Peripheral {
 
     /// The set of services we've discovered for this device. This will be empty until
     /// `discover_services` is called.
+    fn proximity(&self) -> Option<Proximity> {
+        self.manager.upgrade()?.proximity(&self.id())
+    }
+
     fn services(&self) -> BTreeSet<Service> {
         let guard = self.shared.lock().unwrap();
         (&guard.services).clone()
     }
 
+    async fn pair(&self) -> Result<()> {
+        Err(Error::NotSupported(
+            "Pairing is not yet supported on Android".to_string(),
+        ))
+    }
+
+    async fn unpair(&self) -> Result<()> {
+        Err(Error::NotSupported(
+            "Android doesn't allow apps to remove pairings".to_string(),
+        ))
+    }
+
+    /// Android only reports the bond state, and doesn't keep pairings which aren't bonded.
+    async fn is_paired(&self) -> Result<bool> {
+        self.is_bonded().await
+    }
+
+    async fn is_bonded(&self) -> Result<bool> {
+        self.with_obj(|_env, obj| Ok(obj.is_bonded()?))
+    }
+
+    async fn mtu(&self) -> Result<u16> {
+        self.with_obj(|_env, obj| {
+            if !obj.is_connected()? {
+                return Err(Error::NotConnected);
+            }
+            u16::try_from(obj.get_mtu()?).map_err(|e| Error::Other(e.into()))
+        })
+    }
+
     async fn discover_services(&self) -> Result<()> {
-        let future = self.with_obj(|_env, obj| JSendFuture::try_from(obj.discover_services()?))?;
-        let result_ref = future.await?;
-        self.with_obj(|env, _obj| {
-            use std::iter::FromIterator;
+        timeout(self.timeouts().discover_services, async {
+            let future =
+                self.with_obj(|_env, obj| JSendFuture::try_from(obj.discover_services()?))?;
+            let result_ref = future.await?;
+            self.with_obj(|env, _obj| {
+                use std::iter::FromIterator;
 
-            let result = JPollResult::from_env(env, result_ref.as_obj())?;
-            let obj = get_poll_result(env, result)?;
-            let list = JList::from_env(env, obj)?;
-            let mut peripheral_services = Vec::new();
-            let mut peripheral_characteristics = Vec::new();
-
-            for service in list.iter()? {
-                let service = JBluetoothGattService::from_env(env, service)?;
-                let mut characteristics = BTreeSet::new();
-                for characteristic in service.get_characteristics()? {
-                    let mut descriptors = BTreeSet::new();
-                    for descriptor in characteristic.get_descriptors()? {
-                        descriptors.insert(Descriptor {
-                            uuid: descriptor.get_uuid()?,
+                let result = JPollResult::from_env(env, result_ref.as_obj())?;
+                let obj = get_poll_result(env, result)?;
+                let list = JList::from_env(env, obj)?;
+                let mut peripheral_services = Vec::new();
+                let mut peripheral_characteristics = Vec::new();
+
+                for service in list.iter()? {
+                    let service = JBluetoothGattService::from_env(env, service)?;
+                    let service_handle = service.get_instance_id()?;
+                    let mut included_services = BTreeSet::new();
+                    for included in service.get_included_services()? {
+                        included_services.insert(included.get_instance_id()?);
+                    }
+                    let mut characteristics = BTreeSet::new();
+                    for characteristic in service.get_characteristics()? {
+                        let handle = characteristic.get_instance_id()?;
+                        let mut descriptors = BTreeSet::new();
+                        // Android doesn't expose descriptor handles, but descriptors directly
+                        // follow their characteristic's value handle in the attribute table.
+                        for (index, descriptor) in
+                            characteristic.get_descriptors()?.into_iter().enumerate()
+                        {
+                            descriptors.insert(Descriptor {
+                                uuid: descriptor.get_uuid()?,
+                                service_uuid: service.get_uuid()?,
+                                characteristic_uuid: characteristic.get_uuid()?,
+                                handle: handle + 1 + index as u16,
+                                service_handle,
+                                characteristic_handle: handle,
+                            });
+                        }
+                        let characteristic = Characteristic {
                             service_uuid: service.get_uuid()?,
-                            characteristic_uuid: characteristic.get_uuid()?,
-                        });
+                            uuid: characteristic.get_uuid()?,
+                            handle,
+                            service_handle,
+                            properties: characteristic.get_properties()?,
+                            descriptors,
+                        };
+                        characteristics.insert(characteristic.clone());
+                        peripheral_characteristics.push(characteristic);
                     }
-                    characteristics.insert(Characteristic {
-                        service_uuid: service.get_uuid()?,
-                        uuid: characteristic.get_uuid()?,
-                        properties: characteristic.get_properties()?,
-                        descriptors: descriptors.clone(),
-                    });
-                    peripheral_characteristics.push(Characteristic {
-                        service_uuid: service.get_uuid()?,
-                        uuid: characteristic.get_uuid()?,
-                        properties: characteristic.get_properties()?,
-                        descriptors: descriptors,
-                    });
+                    peripheral_services.push(Service {
+                        uuid: service.get_uuid()?,
+                        handle: service_handle,
+                        primary: service.is_primary()?,
+                        included_services,
+                        characteristics,
+                    })
                 }
-                peripheral_services.push(Service {
-                    uuid: service.get_uuid()?,
-                    primary: service.is_primary()?,
-                    characteristics,
-                })
-            }
-            let mut guard = self.shared.lock().unwrap();
-            guard.services = BTreeSet::from_iter(peripheral_services.clone());
-            guard.characteristics = BTreeSet::from_iter(peripheral_characteristics.clone());
-            Ok(())
+                let mut guard = self.shared.lock().unwrap();
+                guard.services = BTreeSet::from_iter(peripheral_services.clone());
+                guard.characteristics = BTreeSet::from_iter(peripheral_characteristics.clone());
+                Ok(())
+            })
         })
+        .await
     }
 
     async fn write(
@@ -249,43 +361,61 @@ impl api::Peripheral for Peripheral {
         data: &[u8],
         write_type: WriteType,
     ) -> Result<()> {
-        let future = self.with_obj(|env, obj| {
-            let uuid = JUuid::new(env, characteristic.uuid)?;
-            let data_obj = jni_utils::arrays::slice_to_byte_array(env, data)?;
-            let write_type = match write_type {
-                WriteType::WithResponse => 2,
-                WriteType::WithoutResponse => 1,
-            };
-            JSendFuture::try_from(obj.write(uuid, data_obj.into(), write_type)?)
-        })?;
-        let result_ref = future.await?;
-        self.with_obj(|env, _obj| {
-            let result = JPollResult::from_env(env, result_ref.as_obj())?;
-            get_poll_result(env, result).map(|_| {})
+        timeout(self.timeouts().write, async {
+            let future = self.with_obj(|env, obj| {
+                let data_obj = jni_utils::arrays::slice_to_byte_array(env, data)?;
+                let write_type = match write_type {
+                    WriteType::WithResponse => 2,
+                    WriteType::WithoutResponse => 1,
+                };
+                JSendFuture::try_from(obj.write(
+                    characteristic.handle.into(),
+                    data_obj.into(),
+                    write_type,
+                )?)
+            })?;
+            let result_ref = future.await?;
+            self.with_obj(|env, _obj| {
+                let result = JPollResult::from_env(env, result_ref.as_obj())?;
+                get_poll_result(env, result).map(|_| {})
+            })
         })
+        .await
     }
 
     async fn read(&self, characteristic: &Characteristic) -> Result<Vec<u8>> {
-        let future = self.with_obj(|env, obj| {
-            let uuid = JUuid::new(env, characteristic.uuid)?;
-            JSendFuture::try_from(obj.read(uuid)?)
-        })?;
-        let result_ref = future.await?;
-        self.with_obj(|env, _obj| {
-            let result = JPollResult::from_env(env, result_ref.as_obj())?;
-            let bytes = get_poll_result(env, result)?;
-            Ok(byte_array_to_vec(env, bytes.into_inner())?)
+        timeout(self.timeouts().read, async {
+            let future = self.with_obj(|env, obj| {
+                JSendFuture::try_from(obj.read(characteristic.handle.into())?)
+            })?;
+            let result_ref = future.await?;
+            self.with_obj(|env, _obj| {
+                let result = JPollResult::from_env(env, result_ref.as_obj())?;
+                let bytes = get_poll_result(env, result)?;
+                Ok(byte_array_to_vec(env, bytes.into_inner())?)
+            })
         })
+        .await
+    }
+
+    async fn read_at(&self, characteristic: &Characteristic, offset: usize) -> Result<Vec<u8>> {
+        transfer::value_from_offset(self.read(characteristic).await?, offset)
     }
 
     async fn subscribe(&self, characteristic: &Characteristic) -> Result<()> {
-        self.set_characteristic_notification(characteristic, true)
-            .await
+        timeout(self.timeouts().subscribe, async {
+            self.set_characteristic_notification(characteristic, true)
+                .await
+        })
+        .await
     }
 
     async fn unsubscribe(&self, characteristic: &Characteristic) -> Result<()> {
-        self.set_characteristic_notification(characteristic, false)
-            .await
+        timeout(self.timeouts().subscribe, async {
+            self.set_characteristic_notification(characteristic, false)
+                .await
+        })
+        .await
     }
 
     async fn notifications(&self) -> Result<Pin<Box<dyn Stream<Item = ValueNotification> + Send>>> {
@@ -298,8 +428,15 @@ impl api::Peripheral for Peripheral {
                     let item = item.as_obj();
                     let characteristic = JBluetoothGattCharacteristic::from_env(&env, item)?;
                     let uuid = characteristic.get_uuid()?;
+                    let service_uuid = characteristic.get_service()?.get_uuid()?;
+                    let handle = characteristic.get_instance_id()?;
                     let value = characteristic.get_value()?;
-                    Ok(ValueNotification { uuid, value })
+                    Ok(ValueNotification {
+                        uuid,
+                        service_uuid,
+                        handle,
+                        value,
+                    })
                 }
                 Err(err) => Err(err),
             })
@@ -307,31 +444,87 @@ impl api::Peripheral for Peripheral {
         Ok(Box::pin(stream))
     }
 
+    async fn notifications_for(
+        &self,
+        characteristic: &Characteristic,
+        subscribe: bool,
+    ) -> Result<Pin<Box<dyn Stream<Item = Vec<u8>> + Send>>> {
+        self.notification_streams
+            .notifications_for(self, characteristic, subscribe)
+            .await
+    }
+
     async fn write_descriptor(&self, descriptor: &Descriptor, data: &[u8]) -> Result<()> {
-        let future = self.with_obj(|env, obj| {
-            let characteristic = JUuid::new(env, descriptor.characteristic_uuid)?;
-            let uuid = JUuid::new(env, descriptor.uuid)?;
-            let data_obj = jni_utils::arrays::slice_to_byte_array(env, data)?;
-            JSendFuture::try_from(obj.write_descriptor(characteristic, uuid, data_obj.into())?)
-        })?;
-        let result_ref = future.await?;
-        self.with_obj(|env, _obj| {
-            let result = JPollResult::from_env(env, result_ref.as_obj())?;
-            get_poll_result(env, result).map(|_| {})
+        timeout(self.timeouts().descriptor, async {
+            let future = self.with_obj(|env, obj| {
+                let characteristic = descriptor.characteristic_handle.into();
+                let uuid = JUuid::new(env, descriptor.uuid)?;
+                let data_obj = jni_utils::arrays::slice_to_byte_array(env, data)?;
+                JSendFuture::try_from(obj.write_descriptor(
+                    characteristic,
+                    uuid,
+                    data_obj.into(),
+                )?)
+            })?;
+            let result_ref = future.await?;
+            self.with_obj(|env, _obj| {
+                let result = JPollResult::from_env(env, result_ref.as_obj())?;
+                get_poll_result(env, result).map(|_| {})
+            })
         })
+        .await
     }
 
     async fn read_descriptor(&self, descriptor: &Descriptor) -> Result<Vec<u8>> {
-        let future = self.with_obj(|env, obj| {
-            let characteristic = JUuid::new(env, descriptor.characteristic_uuid)?;
-            let uuid = JUuid::new(env, descriptor.uuid)?;
-            JSendFuture::try_from(obj.read_descriptor(characteristic, uuid)?)
-        })?;
-        let result_ref = future.await?;
-        self.with_obj(|env, _obj| {
-            let result = JPollResult::from_env(env, result_ref.as_obj())?;
-            let bytes = get_poll_result(env, result)?;
-            Ok(byte_array_to_vec(env, bytes.into_inner())?)
+        timeout(self.timeouts().descriptor, async {
+            let future = self.with_obj(|env, obj| {
+                let characteristic = descriptor.characteristic_handle.into();
+                let uuid = JUuid::new(env, descriptor.uuid)?;
+                JSendFuture::try_from(obj.read_descriptor(characteristic, uuid)?)
+            })?;
+            let result_ref = future.await?;
+            self.with_obj(|env, _obj| {
+                let result = JPollResult::from_env(env, result_ref.as_obj())?;
+                let bytes = get_poll_result(env, result)?;
+                Ok(byte_array_to_vec(env, bytes.into_inner())?)
+            })
         })
+        .await
+    }
+}
+
+impl From<BDAddr> for PeripheralId {
+    fn from(address: BDAddr) -> Self {
+        PeripheralId(address)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::AttErrorCode;
+
+    #[test]
+    fn gatt_errors() {
+        assert!(matches!(gatt_error(GATT_BUSY), Error::InProgress));
+        assert!(matches!(
+            gatt_error(GATT_AUTH_FAIL),
+            Error::AuthenticationFailed
+        ));
+        assert!(matches!(
+            gatt_error(0x02),
+            Error::Att(AttErrorCode::ReadNotPermitted)
+        ));
+        assert!(matches!(
+            gatt_error(0x05),
+            Error::Att(AttErrorCode::InsufficientAuthentication)
+        ));
+        assert!(matches!(
+            gatt_error(0xfd),
+            Error::Att(AttErrorCode::CccdImproperlyConfigured)
+        ));
+        // GATT_ERROR, from Android's own stack rather than the device.
+        assert!(matches!(gatt_error(0x85), Error::Other(_)));
+        assert!(matches!(gatt_error(0x101), Error::Other(_)));
     }
 }
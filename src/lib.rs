@@ -91,22 +91,26 @@
 #[cfg(any(target_os = "macos", target_os = "ios"))]
 extern crate cocoa;
 
-use crate::api::ParseBDAddrError;
+use crate::api::{AttErrorCode, ParseBDAddrError};
 use std::result;
 use std::time::Duration;
 
 pub mod api;
 #[cfg(target_os = "linux")]
 mod bluez;
-#[cfg(not(target_os = "linux"))]
 mod common;
 #[cfg(any(target_os = "macos", target_os = "ios"))]
 mod corebluetooth;
 #[cfg(target_os = "android")]
 mod droidplug;
+#[cfg(any(test, feature = "mock"))]
+pub mod mock;
 pub mod platform;
+#[cfg(feature = "replay")]
+pub mod replay;
 #[cfg(feature = "serde")]
 pub mod serde;
+pub mod supervisor;
 #[cfg(target_os = "windows")]
 mod winrtble;
 
@@ -122,12 +126,25 @@ pub enum Error {
     #[error("Not connected")]
     NotConnected,
 
+    /// The operation can't be started while another one like it is in progress, such as a second
+    /// connection attempt to the same device.
+    #[error("Operation already in progress")]
+    InProgress,
+
+    /// Pairing or the authentication of the link failed, was rejected or was cancelled.
+    #[error("Authentication failed")]
+    AuthenticationFailed,
+
     #[error("The operation is not supported: {}", _0)]
     NotSupported(String),
 
     #[error("Timed out after {:?}", _0)]
     TimedOut(Duration),
 
+    /// The device responded to a GATT request with an ATT error.
+    #[error("ATT error: {}", _0)]
+    Att(AttErrorCode),
+
     #[error("Error parsing UUID: {0}")]
     Uuid(#[from] uuid::Error),
 
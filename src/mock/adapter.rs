@@ -0,0 +1,647 @@
+// btleplug Source Code File
+//
+// Copyright 2023 Nonpolynomial Labs LLC. All rights reserved.
+//
+// Licensed under the BSD 3-Clause license. See LICENSE file in the project root
+// for full license information.
+
+use super::{
+    advertising::{AdvertisementHandle, Advertisements},
+    gatt::GattDatabase,
+    gatt_server::GattApplication,
+    pairing::{AgentHandle, Agents},
+    peripheral::Peripheral,
+    peripheral_id,
+};
+use crate::{
+    api::{
+        proximity::ProximityConfig, Advertiser, Agent, Central, CentralEvent, CentralState,
+        DeviceExpiry, GattRequestHandler, GattServer, IoCapability, LocalAdvertisement,
+        LocalService, Pairing, PeripheralProperties, Presence, ScanFilter, Timeouts,
+    },
+    common::adapter_manager::AdapterManager,
+    platform::PeripheralId,
+    Error, Result,
+};
+use async_trait::async_trait;
+use futures::stream::Stream;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+
+/// Mock implementation of [api::Central](crate::api::Central).
+#[derive(Clone, Debug)]
+pub struct Adapter {
+    name: Arc<str>,
+    manager: Arc<AdapterManager<Peripheral>>,
+    scan_filter: Arc<Mutex<Option<ScanFilter>>>,
+    advertisements: Arc<Mutex<Advertisements>>,
+    agents: Arc<Mutex<Agents>>,
+    state: Arc<Mutex<CentralState>>,
+}
+
+impl Adapter {
+    /// Creates a new mock adapter. The name is used to build peripheral IDs the way the platform
+    /// backend would, so it should look like a real adapter name such as `hci0`.
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.into(),
+            manager: Arc::new(AdapterManager::default()),
+            scan_filter: Arc::new(Mutex::new(None)),
+            advertisements: Arc::new(Mutex::new(Advertisements::default())),
+            agents: Arc::new(Mutex::new(Agents::default())),
+            state: Arc::new(Mutex::new(CentralState::PoweredOn)),
+        }
+    }
+
+    /// Adds a simulated device which is already known to the adapter, as though it had been
+    /// discovered by an earlier scan, and emits [`CentralEvent::DeviceDiscovered`] for it.
+    ///
+    /// If a device with the same address is already known, its GATT database is replaced and its
+    /// properties are updated instead.
+    pub fn add_device(
+        &self,
+        properties: PeripheralProperties,
+        database: GattDatabase,
+    ) -> Peripheral {
+        let id = peripheral_id(&self.name, properties.address);
+        if let Some(peripheral) = self.manager.peripheral(&id) {
+            peripheral.update_properties(&properties);
+            peripheral.set_database(database);
+            self.manager.emit(CentralEvent::DeviceUpdated(id));
+            return peripheral;
+        }
+        let peripheral = Peripheral::new(
+            Arc::downgrade(&self.manager),
+            self.agents.clone(),
+            id.clone(),
+            properties,
+            database,
+        );
+        self.manager.add_peripheral(peripheral.clone());
+        self.manager.emit(CentralEvent::DeviceDiscovered(id));
+        peripheral
+    }
+
+    /// Simulates receiving an advertisement report. This is ignored unless a scan is in progress
+    /// and the advertisement includes one of the services of its filter, as most platforms filter
+    /// by service natively. The rest of the filter is applied to the events, as on every platform.
+    ///
+    /// Devices seen for the first time are created with an empty GATT database and announced with
+    /// [`CentralEvent::DeviceDiscovered`]; known devices get [`CentralEvent::DeviceUpdated`].
+    /// Manufacturer data, service data and services in the advertisement are then reported with
+    /// their respective events.
+    pub fn advertise(&self, advertisement: PeripheralProperties) {
+        match &*self.scan_filter.lock().unwrap() {
+            None => return,
+            Some(filter) => {
+                if !filter.services.is_empty()
+                    && !filter
+                        .services
+                        .iter()
+                        .any(|uuid| advertisement.services.contains(uuid))
+                {
+                    return;
+                }
+            }
+        }
+
+        let id = peripheral_id(&self.name, advertisement.address);
+        if let Some(entry) = self.manager.peripheral_mut(&id) {
+            entry.value().update_properties(&advertisement);
+            drop(entry);
+            self.manager.emit(CentralEvent::DeviceUpdated(id.clone()));
+        } else {
+            let peripheral = Peripheral::new(
+                Arc::downgrade(&self.manager),
+                self.agents.clone(),
+                id.clone(),
+                advertisement.clone(),
+                GattDatabase::default(),
+            );
+            self.manager.add_peripheral(peripheral);
+            self.manager
+                .emit(CentralEvent::DeviceDiscovered(id.clone()));
+        }
+
+        if !advertisement.manufacturer_data.is_empty() {
+            self.manager
+                .emit(CentralEvent::ManufacturerDataAdvertisement {
+                    id: id.clone(),
+                    manufacturer_data: advertisement.manufacturer_data,
+                });
+        }
+        if !advertisement.service_data.is_empty() {
+            self.manager.emit(CentralEvent::ServiceDataAdvertisement {
+                id: id.clone(),
+                service_data: advertisement.service_data,
+            });
+        }
+        if !advertisement.services.is_empty() {
+            self.manager.emit(CentralEvent::ServicesAdvertisement {
+                id,
+                services: advertisement.services,
+            });
+        }
+    }
+
+    /// Simulates the adapter changing state, e.g. the user turning Bluetooth off, and emits
+    /// [`CentralEvent::StateUpdate`] if the state changed. Leaving [`CentralState::PoweredOn`] stops
+    /// any scan in progress. Adapters start out powered on.
+    pub fn set_state(&self, state: CentralState) {
+        let previous = std::mem::replace(&mut *self.state.lock().unwrap(), state);
+        if previous != state {
+            if state != CentralState::PoweredOn {
+                *self.scan_filter.lock().unwrap() = None;
+                self.manager.stop_scan();
+            }
+            self.manager.emit(CentralEvent::StateUpdate(state));
+        }
+    }
+
+    /// Returns whether a scan is currently in progress.
+    pub fn is_scanning(&self) -> bool {
+        self.scan_filter.lock().unwrap().is_some()
+    }
+
+    /// Returns the advertisements currently being broadcast, in the order they were started.
+    pub fn advertisements(&self) -> Vec<LocalAdvertisement> {
+        self.advertisements.lock().unwrap().active()
+    }
+}
+
+#[async_trait]
+impl Central for Adapter {
+    type Peripheral = Peripheral;
+
+    async fn events(&self) -> Result<Pin<Box<dyn Stream<Item = CentralEvent> + Send>>> {
+        Ok(self.manager.event_stream())
+    }
+
+    async fn start_scan(&self, filter: ScanFilter) -> Result<()> {
+        let state = *self.state.lock().unwrap();
+        if state != CentralState::PoweredOn {
+            return Err(Error::Other(
+                format!("Can't scan while the adapter is {:?}", state).into(),
+            ));
+        }
+        self.manager.start_scan(filter.clone());
+        *self.scan_filter.lock().unwrap() = Some(filter);
+        Ok(())
+    }
+
+    async fn stop_scan(&self) -> Result<()> {
+        *self.scan_filter.lock().unwrap() = None;
+        self.manager.stop_scan();
+        Ok(())
+    }
+
+    async fn peripherals(&self) -> Result<Vec<Peripheral>> {
+        Ok(self.manager.peripherals())
+    }
+
+    async fn peripheral(&self, id: &PeripheralId) -> Result<Peripheral> {
+        self.manager.peripheral(id).ok_or(Error::DeviceNotFound)
+    }
+
+    async fn add_peripheral(&self, _address: &PeripheralId) -> Result<Peripheral> {
+        Err(Error::NotSupported(
+            "Can't add a Peripheral from a PeripheralId".to_string(),
+        ))
+    }
+
+    async fn adapter_info(&self) -> Result<String> {
+        Ok(format!("{} (mock)", self.name))
+    }
+
+    async fn adapter_state(&self) -> Result<CentralState> {
+        Ok(*self.state.lock().unwrap())
+    }
+
+    async fn set_powered(&self, powered: bool) -> Result<()> {
+        let state = *self.state.lock().unwrap();
+        match state {
+            CentralState::PoweredOn | CentralState::PoweredOff => {
+                self.set_state(if powered {
+                    CentralState::PoweredOn
+                } else {
+                    CentralState::PoweredOff
+                });
+                Ok(())
+            }
+            CentralState::Unauthorized => Err(Error::PermissionDenied),
+            CentralState::Unavailable => Err(Error::Other("The adapter is unavailable".into())),
+        }
+    }
+
+    fn timeouts(&self) -> Timeouts {
+        self.manager.timeouts()
+    }
+
+    fn set_timeouts(&self, timeouts: Timeouts) {
+        self.manager.set_timeouts(timeouts);
+    }
+
+    fn presence(&self, id: &PeripheralId) -> Option<Presence> {
+        self.manager.presence(id)
+    }
+
+    fn set_device_expiry(&self, expiry: Option<DeviceExpiry>) {
+        self.manager.set_device_expiry(expiry);
+    }
+
+    fn set_proximity_config(&self, config: ProximityConfig) {
+        self.manager.set_proximity_config(config);
+    }
+}
+
+#[async_trait]
+impl GattServer for Adapter {
+    type Application = GattApplication;
+
+    async fn register_application(
+        &self,
+        services: Vec<LocalService>,
+        handler: Arc<dyn GattRequestHandler>,
+    ) -> Result<GattApplication> {
+        GattApplication::new(services, handler)
+    }
+}
+
+#[async_trait]
+impl Advertiser for Adapter {
+    type AdvertisementHandle = AdvertisementHandle;
+
+    async fn start_advertising(
+        &self,
+        advertisement: LocalAdvertisement,
+    ) -> Result<AdvertisementHandle> {
+        advertisement.validate()?;
+        Ok(AdvertisementHandle::new(
+            self.advertisements.clone(),
+            advertisement,
+        ))
+    }
+}
+
+#[async_trait]
+impl Pairing for Adapter {
+    type AgentHandle = AgentHandle;
+
+    async fn register_agent(
+        &self,
+        agent: Arc<dyn Agent>,
+        capability: IoCapability,
+    ) -> Result<AgentHandle> {
+        Ok(AgentHandle::new(self.agents.clone(), agent, capability))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{
+        beacon::{with_beacon_events, Beacon},
+        bleuuid::uuid_from_u16,
+        proximity::{DistanceModel, ProximityZone, RssiFilter},
+        DeviceExpiry, ManufacturerDataFilter, NameFilter, Peripheral as _,
+    };
+    use futures::StreamExt;
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    fn advertisement(last_byte: u8) -> PeripheralProperties {
+        PeripheralProperties {
+            address: [1, 2, 3, 4, 5, last_byte].into(),
+            local_name: Some("Mock".to_string()),
+            manufacturer_data: HashMap::from([(0x004C, vec![1, 2, 3])]),
+            services: vec![uuid_from_u16(0x180D)],
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn advertisements_ignored_while_not_scanning() {
+        let adapter = Adapter::new("hci0");
+        adapter.advertise(advertisement(1));
+        assert!(adapter.peripherals().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn advertisements_drive_events() {
+        let adapter = Adapter::new("hci0");
+        let mut events = adapter.events().await.unwrap();
+        adapter.start_scan(ScanFilter::default()).await.unwrap();
+        adapter.advertise(advertisement(1));
+        adapter.advertise(advertisement(1));
+
+        let peripheral = adapter.peripherals().await.unwrap().pop().unwrap();
+        let id = peripheral.id();
+        assert!(
+            matches!(events.next().await, Some(CentralEvent::DeviceDiscovered(ref i)) if *i == id)
+        );
+        assert!(matches!(
+            events.next().await,
+            Some(CentralEvent::ManufacturerDataAdvertisement { .. })
+        ));
+        assert!(matches!(
+            events.next().await,
+            Some(CentralEvent::ServicesAdvertisement { .. })
+        ));
+        assert!(
+            matches!(events.next().await, Some(CentralEvent::DeviceUpdated(ref i)) if *i == id)
+        );
+        assert_eq!(
+            peripheral.properties().await.unwrap().unwrap().local_name,
+            Some("Mock".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn beacon_events_follow_advertisements() {
+        let adapter = Adapter::new("hci0");
+        adapter.start_scan(ScanFilter::default()).await.unwrap();
+        let mut events = Box::pin(with_beacon_events(adapter.events().await.unwrap()));
+        let mut plain_events = adapter.events().await.unwrap();
+        let mut ibeacon = vec![0x02, 0x15];
+        ibeacon.extend([0; 21]);
+        adapter.advertise(PeripheralProperties {
+            manufacturer_data: HashMap::from([(0x004C, ibeacon)]),
+            ..advertisement(3)
+        });
+
+        assert!(matches!(
+            events.next().await,
+            Some(CentralEvent::DeviceDiscovered(_))
+        ));
+        assert!(matches!(
+            events.next().await,
+            Some(CentralEvent::ManufacturerDataAdvertisement { .. })
+        ));
+        assert!(matches!(
+            events.next().await,
+            Some(CentralEvent::BeaconAdvertisement {
+                beacon: Beacon::IBeacon(_),
+                ..
+            })
+        ));
+        assert!(matches!(
+            events.next().await,
+            Some(CentralEvent::ServicesAdvertisement { .. })
+        ));
+
+        // Beacons aren't decoded unless asked for.
+        assert!(matches!(
+            plain_events.next().await,
+            Some(CentralEvent::DeviceDiscovered(_))
+        ));
+        assert!(matches!(
+            plain_events.next().await,
+            Some(CentralEvent::ManufacturerDataAdvertisement { .. })
+        ));
+        assert!(matches!(
+            plain_events.next().await,
+            Some(CentralEvent::ServicesAdvertisement { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn scan_filter_applies_to_services() {
+        let adapter = Adapter::new("hci0");
+        adapter
+            .start_scan(ScanFilter {
+                services: vec![uuid_from_u16(0x180F)],
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        adapter.advertise(advertisement(1));
+        assert!(adapter.peripherals().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn scan_filter_applies_to_events() {
+        let adapter = Adapter::new("hci0");
+        let mut events = adapter.events().await.unwrap();
+        adapter
+            .start_scan(ScanFilter {
+                rssi: Some(-70),
+                name: Some(NameFilter::Prefix("Mo".to_string())),
+                manufacturer_data: vec![ManufacturerDataFilter {
+                    company_id: 0x004C,
+                    data: vec![0x01, 0x00],
+                    mask: vec![0xff, 0x00],
+                }],
+                suppress_duplicates: true,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        let with_rssi = |last_byte, rssi| PeripheralProperties {
+            rssi: Some(rssi),
+            ..advertisement(last_byte)
+        };
+
+        // Too weak, then matching.
+        adapter.advertise(with_rssi(1, -90));
+        adapter.advertise(with_rssi(2, -50));
+        let id = peripheral_id("hci0", advertisement(2).address);
+        assert!(
+            matches!(events.next().await, Some(CentralEvent::DeviceDiscovered(ref i)) if *i == id)
+        );
+        assert!(matches!(
+            events.next().await,
+            Some(CentralEvent::ManufacturerDataAdvertisement { .. })
+        ));
+        assert!(matches!(
+            events.next().await,
+            Some(CentralEvent::ServicesAdvertisement { .. })
+        ));
+
+        // The first device is announced once it comes into range, and only changes are reported
+        // after that.
+        adapter.advertise(with_rssi(1, -60));
+        let id = peripheral_id("hci0", advertisement(1).address);
+        assert!(
+            matches!(events.next().await, Some(CentralEvent::DeviceDiscovered(ref i)) if *i == id)
+        );
+        assert!(matches!(
+            events.next().await,
+            Some(CentralEvent::ManufacturerDataAdvertisement { .. })
+        ));
+        assert!(matches!(
+            events.next().await,
+            Some(CentralEvent::ServicesAdvertisement { .. })
+        ));
+        adapter.advertise(with_rssi(1, -55));
+        adapter.advertise(PeripheralProperties {
+            local_name: Some("Other".to_string()),
+            ..with_rssi(3, -50)
+        });
+        adapter.advertise(PeripheralProperties {
+            manufacturer_data: HashMap::from([(0x004C, vec![1, 2, 4])]),
+            ..with_rssi(1, -55)
+        });
+        assert!(
+            matches!(events.next().await, Some(CentralEvent::DeviceUpdated(ref i)) if *i == id)
+        );
+        assert!(matches!(
+            events.next().await,
+            Some(CentralEvent::ManufacturerDataAdvertisement { ref manufacturer_data, .. })
+                if manufacturer_data[&0x004C] == [1, 2, 4]
+        ));
+    }
+
+    #[tokio::test]
+    async fn quiet_devices_are_lost() {
+        let adapter = Adapter::new("hci0");
+        adapter.set_device_expiry(Some(DeviceExpiry {
+            timeout: Duration::from_millis(50),
+            remove: true,
+        }));
+        let mut events = adapter.events().await.unwrap();
+        adapter.start_scan(ScanFilter::default()).await.unwrap();
+        adapter.advertise(advertisement(1));
+        adapter.advertise(advertisement(1));
+        let id = peripheral_id("hci0", advertisement(1).address);
+        assert_eq!(adapter.presence(&id).unwrap().advertisement_count, 2);
+
+        loop {
+            if let CentralEvent::DeviceLost(lost) = events.next().await.unwrap() {
+                assert_eq!(lost, id);
+                break;
+            }
+        }
+        assert!(adapter.peripherals().await.unwrap().is_empty());
+        assert!(adapter.presence(&id).is_none());
+    }
+
+    #[tokio::test]
+    async fn proximity_zone_changes() {
+        let adapter = Adapter::new("hci0");
+        adapter.set_proximity_config(ProximityConfig {
+            filter: RssiFilter::None,
+            distance: DistanceModel {
+                reference_rssi: Some(-60),
+                path_loss_exponent: 2.0,
+            },
+            ..Default::default()
+        });
+        let mut events = adapter.events().await.unwrap();
+        adapter.start_scan(ScanFilter::default()).await.unwrap();
+        let id = peripheral_id("hci0", advertisement(1).address);
+
+        for (rssi, zone) in [(-60, ProximityZone::Near), (-40, ProximityZone::Immediate)] {
+            adapter.advertise(PeripheralProperties {
+                rssi: Some(rssi),
+                ..advertisement(1)
+            });
+            loop {
+                if let CentralEvent::ProximityChanged {
+                    id: changed,
+                    zone: new_zone,
+                } = events.next().await.unwrap()
+                {
+                    assert_eq!(changed, id);
+                    assert_eq!(new_zone, zone);
+                    break;
+                }
+            }
+        }
+        let proximity = adapter.peripheral(&id).await.unwrap().proximity().unwrap();
+        assert_eq!(proximity.rssi, -40.0);
+        assert!((proximity.distance.unwrap() - 0.1).abs() < 1e-9);
+        assert_eq!(proximity.history.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn connection_events() {
+        let adapter = Adapter::new("hci0");
+        let peripheral = adapter.add_device(advertisement(2), GattDatabase::new());
+        let mut events = adapter.events().await.unwrap();
+
+        peripheral.connect().await.unwrap();
+        peripheral.simulate_disconnect();
+        assert!(matches!(
+            events.next().await,
+            Some(CentralEvent::DeviceConnected(_))
+        ));
+        assert!(matches!(
+            events.next().await,
+            Some(CentralEvent::DeviceDisconnected(_))
+        ));
+        assert!(!peripheral.is_connected().await.unwrap());
+
+        peripheral.set_connectable(false);
+        assert!(peripheral.connect().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn power_state_changes() {
+        let adapter = Adapter::new("hci0");
+        let mut events = adapter.events().await.unwrap();
+        adapter.start_scan(ScanFilter::default()).await.unwrap();
+
+        adapter.set_powered(false).await.unwrap();
+        assert_eq!(
+            adapter.adapter_state().await.unwrap(),
+            CentralState::PoweredOff
+        );
+        assert!(matches!(
+            events.next().await,
+            Some(CentralEvent::StateUpdate(CentralState::PoweredOff))
+        ));
+        assert!(!adapter.is_scanning());
+        assert!(adapter.start_scan(ScanFilter::default()).await.is_err());
+
+        adapter.set_state(CentralState::Unauthorized);
+        assert!(matches!(
+            adapter.set_powered(true).await,
+            Err(Error::PermissionDenied)
+        ));
+        adapter.set_state(CentralState::PoweredOn);
+        adapter.start_scan(ScanFilter::default()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn advertisements_last_until_dropped() {
+        let adapter = Adapter::new("hci0");
+        let beacon = LocalAdvertisement {
+            manufacturer_data: HashMap::from([(0x004C, vec![2, 21])]),
+            ..Default::default()
+        };
+        let first = adapter.start_advertising(beacon.clone()).await.unwrap();
+        let second = adapter
+            .start_advertising(LocalAdvertisement {
+                local_name: Some("Second".to_string()),
+                connectable: true,
+                scannable: true,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(adapter.advertisements().len(), 2);
+        drop(second);
+        assert_eq!(adapter.advertisements(), vec![beacon]);
+        drop(first);
+        assert!(adapter.advertisements().is_empty());
+
+        assert!(adapter
+            .start_advertising(LocalAdvertisement {
+                min_interval: Some(Duration::from_millis(200)),
+                max_interval: Some(Duration::from_millis(100)),
+                ..Default::default()
+            })
+            .await
+            .is_err());
+        assert!(matches!(
+            adapter
+                .start_advertising(LocalAdvertisement {
+                    connectable: true,
+                    ..Default::default()
+                })
+                .await,
+            Err(Error::NotSupported(_))
+        ));
+        assert!(adapter.advertisements().is_empty());
+    }
+}
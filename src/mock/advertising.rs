@@ -0,0 +1,53 @@
+// btleplug Source Code File
+//
+// Copyright 2023 Nonpolynomial Labs LLC. All rights reserved.
+//
+// Licensed under the BSD 3-Clause license. See LICENSE file in the project root
+// for full license information.
+
+use crate::api::LocalAdvertisement;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+/// The advertisements currently registered on a mock adapter, keyed by registration order.
+#[derive(Debug, Default)]
+pub(super) struct Advertisements {
+    next_id: usize,
+    active: BTreeMap<usize, LocalAdvertisement>,
+}
+
+impl Advertisements {
+    pub(super) fn active(&self) -> Vec<LocalAdvertisement> {
+        self.active.values().cloned().collect()
+    }
+}
+
+/// Mock advertisement handle. The advertisement is removed from
+/// [`Adapter::advertisements`](super::Adapter::advertisements) when this is dropped.
+#[derive(Debug)]
+pub struct AdvertisementHandle {
+    advertisements: Arc<Mutex<Advertisements>>,
+    id: usize,
+}
+
+impl AdvertisementHandle {
+    pub(super) fn new(
+        advertisements: Arc<Mutex<Advertisements>>,
+        advertisement: LocalAdvertisement,
+    ) -> Self {
+        let id = {
+            let mut advertisements = advertisements.lock().unwrap();
+            let id = advertisements.next_id;
+            advertisements.next_id += 1;
+            advertisements.active.insert(id, advertisement);
+            id
+        };
+        Self { advertisements, id }
+    }
+}
+
+impl Drop for AdvertisementHandle {
+    fn drop(&mut self) {
+        self.advertisements.lock().unwrap().active.remove(&self.id);
+    }
+}
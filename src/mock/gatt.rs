@@ -0,0 +1,276 @@
+// btleplug Source Code File
+//
+// Copyright 2023 Nonpolynomial Labs LLC. All rights reserved.
+//
+// Licensed under the BSD 3-Clause license. See LICENSE file in the project root
+// for full license information.
+
+use crate::api::{CharPropFlags, Characteristic, Descriptor, Service};
+use crate::{Error, Result};
+use std::collections::{BTreeMap, BTreeSet};
+use uuid::Uuid;
+
+#[derive(Clone, Debug)]
+struct ServiceEntry {
+    uuid: Uuid,
+    primary: bool,
+    included_services: BTreeSet<u16>,
+    characteristics: BTreeMap<u16, CharacteristicEntry>,
+}
+
+#[derive(Clone, Debug)]
+pub(super) struct CharacteristicEntry {
+    uuid: Uuid,
+    pub(super) properties: CharPropFlags,
+    pub(super) value: Vec<u8>,
+    descriptors: BTreeMap<u16, DescriptorEntry>,
+}
+
+#[derive(Clone, Debug)]
+struct DescriptorEntry {
+    uuid: Uuid,
+    value: Vec<u8>,
+}
+
+/// The GATT database of a simulated device: its services, characteristics and descriptors, along
+/// with their current values.
+///
+/// Each attribute is given the next free ATT handle as it is added, so a database may contain
+/// several instances of the same service, characteristic or descriptor UUID, told apart by their
+/// handles.
+///
+/// Reads from a mock [`Peripheral`](super::Peripheral) return the values stored here, and writes
+/// replace them, so a test can inspect what was written by looking at the database afterwards.
+#[derive(Clone, Debug)]
+pub struct GattDatabase {
+    services: BTreeMap<u16, ServiceEntry>,
+    next_handle: u16,
+}
+
+impl Default for GattDatabase {
+    fn default() -> Self {
+        Self {
+            services: BTreeMap::new(),
+            // Handle 0 is reserved.
+            next_handle: 1,
+        }
+    }
+}
+
+impl GattDatabase {
+    /// Creates an empty database.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a service with no characteristics, and returns its handle.
+    pub fn add_service(&mut self, uuid: Uuid, primary: bool) -> u16 {
+        let handle = self.allocate(1);
+        self.services.insert(
+            handle,
+            ServiceEntry {
+                uuid,
+                primary,
+                included_services: BTreeSet::new(),
+                characteristics: BTreeMap::new(),
+            },
+        );
+        handle
+    }
+
+    /// Adds an include declaration to the service with the given handle, referring to another
+    /// service in the database, and returns the handle of the declaration.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either service has not been added.
+    pub fn add_included_service(&mut self, service_handle: u16, included_handle: u16) -> u16 {
+        assert!(
+            self.services.contains_key(&included_handle),
+            "Including a service that's not in the database."
+        );
+        assert!(
+            self.services.contains_key(&service_handle),
+            "Adding an include to a service that's not in the database."
+        );
+        let handle = self.allocate(1);
+        self.services
+            .get_mut(&service_handle)
+            .unwrap()
+            .included_services
+            .insert(included_handle);
+        handle
+    }
+
+    /// Adds a characteristic with the given initial value to the service with the given handle,
+    /// and returns the handle of the characteristic.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the service has not been added.
+    pub fn add_characteristic(
+        &mut self,
+        service_handle: u16,
+        uuid: Uuid,
+        properties: CharPropFlags,
+        value: Vec<u8>,
+    ) -> u16 {
+        assert!(
+            self.services.contains_key(&service_handle),
+            "Adding a characteristic to a service that's not in the database."
+        );
+        // Like on a real device, the declaration is followed by a separate attribute holding the
+        // value.
+        let handle = self.allocate(2);
+        self.services
+            .get_mut(&service_handle)
+            .unwrap()
+            .characteristics
+            .insert(
+                handle,
+                CharacteristicEntry {
+                    uuid,
+                    properties,
+                    value,
+                    descriptors: BTreeMap::new(),
+                },
+            );
+        handle
+    }
+
+    /// Adds a descriptor with the given initial value to the characteristic with the given
+    /// handle, and returns the handle of the descriptor.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the characteristic has not been added.
+    pub fn add_descriptor(
+        &mut self,
+        characteristic_handle: u16,
+        uuid: Uuid,
+        value: Vec<u8>,
+    ) -> u16 {
+        assert!(
+            self.characteristic(characteristic_handle).is_some(),
+            "Adding a descriptor to a characteristic that's not in the database."
+        );
+        let handle = self.allocate(1);
+        self.services
+            .values_mut()
+            .find_map(|service| service.characteristics.get_mut(&characteristic_handle))
+            .unwrap()
+            .descriptors
+            .insert(handle, DescriptorEntry { uuid, value });
+        handle
+    }
+
+    /// Returns the current value of the characteristic with the given handle, if it exists.
+    pub fn value(&self, characteristic_handle: u16) -> Option<&[u8]> {
+        self.characteristic(characteristic_handle)
+            .map(|characteristic| characteristic.value.as_slice())
+    }
+
+    /// Returns the current value of the descriptor with the given handle, if it exists.
+    pub fn descriptor_value(&self, handle: u16) -> Option<&[u8]> {
+        self.services
+            .values()
+            .flat_map(|service| service.characteristics.values())
+            .find_map(|characteristic| characteristic.descriptors.get(&handle))
+            .map(|descriptor| descriptor.value.as_slice())
+    }
+
+    /// The services in this database, in the form a peripheral reports them after service
+    /// discovery.
+    pub fn services(&self) -> BTreeSet<Service> {
+        self.services
+            .iter()
+            .map(|(&service_handle, service)| Service {
+                uuid: service.uuid,
+                handle: service_handle,
+                primary: service.primary,
+                included_services: service.included_services.clone(),
+                characteristics: service
+                    .characteristics
+                    .iter()
+                    .map(|(&handle, characteristic)| Characteristic {
+                        uuid: characteristic.uuid,
+                        service_uuid: service.uuid,
+                        handle,
+                        service_handle,
+                        properties: characteristic.properties,
+                        descriptors: characteristic
+                            .descriptors
+                            .iter()
+                            .map(|(&descriptor_handle, descriptor)| Descriptor {
+                                uuid: descriptor.uuid,
+                                service_uuid: service.uuid,
+                                characteristic_uuid: characteristic.uuid,
+                                handle: descriptor_handle,
+                                service_handle,
+                                characteristic_handle: handle,
+                            })
+                            .collect(),
+                    })
+                    .collect(),
+            })
+            .collect()
+    }
+
+    fn allocate(&mut self, count: u16) -> u16 {
+        let handle = self.next_handle;
+        self.next_handle = handle
+            .checked_add(count)
+            .expect("The database has run out of attribute handles.");
+        handle
+    }
+
+    fn characteristic(&self, handle: u16) -> Option<&CharacteristicEntry> {
+        self.services
+            .values()
+            .find_map(|service| service.characteristics.get(&handle))
+    }
+
+    pub(super) fn characteristic_mut(
+        &mut self,
+        characteristic: &Characteristic,
+    ) -> Result<&mut CharacteristicEntry> {
+        self.services
+            .get_mut(&characteristic.service_handle)
+            .ok_or_else(|| {
+                Error::Other(
+                    format!(
+                        "Service with handle {:#06x} not found.",
+                        characteristic.service_handle
+                    )
+                    .into(),
+                )
+            })?
+            .characteristics
+            .get_mut(&characteristic.handle)
+            .ok_or_else(|| {
+                Error::Other(
+                    format!(
+                        "Characteristic with handle {:#06x} not found.",
+                        characteristic.handle
+                    )
+                    .into(),
+                )
+            })
+    }
+
+    pub(super) fn descriptor_mut(&mut self, descriptor: &Descriptor) -> Result<&mut Vec<u8>> {
+        let handle = descriptor.handle;
+        self.services
+            .get_mut(&descriptor.service_handle)
+            .and_then(|service| {
+                service
+                    .characteristics
+                    .get_mut(&descriptor.characteristic_handle)
+            })
+            .and_then(|characteristic| characteristic.descriptors.get_mut(&handle))
+            .map(|descriptor| &mut descriptor.value)
+            .ok_or_else(|| {
+                Error::Other(format!("Descriptor with handle {:#06x} not found.", handle).into())
+            })
+    }
+}
@@ -0,0 +1,198 @@
+// btleplug Source Code File
+//
+// Copyright 2023 Nonpolynomial Labs LLC. All rights reserved.
+//
+// Licensed under the BSD 3-Clause license. See LICENSE file in the project root
+// for full license information.
+
+use crate::{
+    api::{
+        self, Characteristic, GattRequestHandler, GattServerEvent, LocalService, ValueNotification,
+        WriteType,
+    },
+    common::{gatt_server::LocalDatabase, util::notifications_stream_from_broadcast_receiver},
+    Error, Result,
+};
+use async_trait::async_trait;
+use futures::stream::Stream;
+use std::collections::BTreeSet;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+/// Mock implementation of [api::GattApplication](crate::api::GattApplication).
+///
+/// Besides the API trait, this exposes methods to simulate requests from a remote central, and a
+/// stream of the notifications which would be sent to it.
+#[derive(Debug)]
+pub struct GattApplication {
+    database: LocalDatabase,
+    notifications_channel: broadcast::Sender<ValueNotification>,
+}
+
+impl GattApplication {
+    pub(super) fn new(
+        services: Vec<LocalService>,
+        handler: Arc<dyn GattRequestHandler>,
+    ) -> Result<Self> {
+        let (notifications_channel, _) = broadcast::channel(16);
+        Ok(Self {
+            database: LocalDatabase::new(services, handler)?,
+            notifications_channel,
+        })
+    }
+
+    /// Simulates a remote central reading a characteristic.
+    pub async fn simulate_read(&self, characteristic: &Characteristic) -> Result<Vec<u8>> {
+        self.database
+            .read(characteristic.handle, 0)
+            .await
+            .map_err(Error::Att)
+    }
+
+    /// Simulates a remote central writing a whole characteristic value.
+    pub async fn simulate_write(
+        &self,
+        characteristic: &Characteristic,
+        data: &[u8],
+        write_type: WriteType,
+    ) -> Result<()> {
+        self.database
+            .write(characteristic.handle, data, 0, write_type)
+            .await
+            .map_err(Error::Att)
+    }
+
+    /// Simulates a remote central enabling notifications or indications for a characteristic.
+    pub fn simulate_subscribe(&self, characteristic: &Characteristic) -> Result<()> {
+        self.database
+            .set_notifying(characteristic.handle, true)
+            .map_err(Error::Att)
+    }
+
+    /// Simulates a remote central disabling notifications or indications for a characteristic.
+    pub fn simulate_unsubscribe(&self, characteristic: &Characteristic) -> Result<()> {
+        self.database
+            .set_notifying(characteristic.handle, false)
+            .map_err(Error::Att)
+    }
+
+    /// Returns a stream of the notifications sent to subscribed remote centrals.
+    pub fn notifications(&self) -> Pin<Box<dyn Stream<Item = ValueNotification> + Send>> {
+        notifications_stream_from_broadcast_receiver(self.notifications_channel.subscribe())
+    }
+}
+
+#[async_trait]
+impl api::GattApplication for GattApplication {
+    fn characteristics(&self) -> BTreeSet<Characteristic> {
+        self.database.characteristics()
+    }
+
+    async fn events(&self) -> Result<Pin<Box<dyn Stream<Item = GattServerEvent> + Send>>> {
+        Ok(self.database.events())
+    }
+
+    async fn value(&self, characteristic: &Characteristic) -> Result<Vec<u8>> {
+        self.database.value(characteristic)
+    }
+
+    async fn set_value(&self, characteristic: &Characteristic, value: Vec<u8>) -> Result<()> {
+        if self.database.set_value(characteristic, value.clone())? {
+            // Note: we ignore send errors here which may happen while there are no receivers...
+            let _ = self.notifications_channel.send(ValueNotification {
+                uuid: characteristic.uuid,
+                service_uuid: characteristic.service_uuid,
+                handle: characteristic.handle,
+                value,
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Adapter;
+    use crate::api::{
+        bleuuid::uuid_from_u16, AttErrorCode, CharPropFlags, GattApplication as _,
+        GattRequestHandler, GattServer, GattServerEvent, LocalCharacteristic, LocalService,
+        WriteType,
+    };
+    use crate::Error;
+    use futures::StreamExt;
+    use std::sync::Arc;
+
+    const SERVICE: uuid::Uuid = uuid_from_u16(0x180F);
+    const CHARACTERISTIC: uuid::Uuid = uuid_from_u16(0x2A19);
+
+    #[derive(Debug)]
+    struct StoredValues;
+
+    impl GattRequestHandler for StoredValues {}
+
+    #[tokio::test]
+    async fn serve_requests() {
+        let adapter = Adapter::new("hci0");
+        let application = adapter
+            .register_application(
+                vec![LocalService {
+                    uuid: SERVICE,
+                    primary: true,
+                    characteristics: vec![LocalCharacteristic {
+                        uuid: CHARACTERISTIC,
+                        properties: CharPropFlags::READ
+                            | CharPropFlags::WRITE
+                            | CharPropFlags::NOTIFY,
+                        value: vec![50],
+                        descriptors: vec![],
+                    }],
+                }],
+                Arc::new(StoredValues),
+            )
+            .await
+            .unwrap();
+        let characteristic = application.characteristics().pop_first().unwrap();
+        assert_eq!(characteristic.uuid, CHARACTERISTIC);
+        let mut events = application.events().await.unwrap();
+        let mut notifications = application.notifications();
+
+        assert_eq!(
+            application.simulate_read(&characteristic).await.unwrap(),
+            vec![50]
+        );
+        assert!(matches!(
+            application
+                .simulate_write(&characteristic, &[1], WriteType::WithoutResponse)
+                .await,
+            Err(Error::Att(AttErrorCode::WriteNotPermitted))
+        ));
+        application
+            .simulate_write(&characteristic, &[60], WriteType::WithResponse)
+            .await
+            .unwrap();
+        assert_eq!(application.value(&characteristic).await.unwrap(), vec![60]);
+        assert!(matches!(
+            events.next().await,
+            Some(GattServerEvent::CharacteristicWritten { value, .. }) if value == vec![60]
+        ));
+
+        // Values set by the application are only sent to subscribed centrals.
+        application
+            .set_value(&characteristic, vec![70])
+            .await
+            .unwrap();
+        application.simulate_subscribe(&characteristic).unwrap();
+        application
+            .set_value(&characteristic, vec![80])
+            .await
+            .unwrap();
+        let notification = notifications.next().await.unwrap();
+        assert_eq!(notification.handle, characteristic.handle);
+        assert_eq!(notification.value, vec![80]);
+        assert!(matches!(
+            events.next().await,
+            Some(GattServerEvent::Subscribed { .. })
+        ));
+    }
+}
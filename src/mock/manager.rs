@@ -0,0 +1,55 @@
+// btleplug Source Code File
+//
+// Copyright 2023 Nonpolynomial Labs LLC. All rights reserved.
+//
+// Licensed under the BSD 3-Clause license. See LICENSE file in the project root
+// for full license information.
+
+use super::adapter::Adapter;
+use crate::{
+    api::{self, Central, Timeouts},
+    Result,
+};
+use async_trait::async_trait;
+use std::sync::{Arc, Mutex};
+
+/// Mock implementation of [api::Manager](crate::api::Manager).
+#[derive(Clone, Debug, Default)]
+pub struct Manager {
+    adapters: Arc<Mutex<Vec<Adapter>>>,
+    timeouts: Arc<Mutex<Timeouts>>,
+}
+
+impl Manager {
+    /// Creates a manager with no adapters. Use [`Manager::add_adapter`] to populate it.
+    pub async fn new() -> Result<Self> {
+        Ok(Self::default())
+    }
+
+    /// Adds an adapter, which will be returned by subsequent calls to `adapters()`. The adapter's
+    /// timeouts are replaced by the manager's.
+    pub fn add_adapter(&self, adapter: Adapter) {
+        adapter.set_timeouts(*self.timeouts.lock().unwrap());
+        self.adapters.lock().unwrap().push(adapter);
+    }
+}
+
+#[async_trait]
+impl api::Manager for Manager {
+    type Adapter = Adapter;
+
+    async fn adapters(&self) -> Result<Vec<Adapter>> {
+        Ok(self.adapters.lock().unwrap().clone())
+    }
+
+    fn timeouts(&self) -> Timeouts {
+        *self.timeouts.lock().unwrap()
+    }
+
+    fn set_timeouts(&self, timeouts: Timeouts) {
+        *self.timeouts.lock().unwrap() = timeouts;
+        for adapter in self.adapters.lock().unwrap().iter() {
+            adapter.set_timeouts(timeouts);
+        }
+    }
+}
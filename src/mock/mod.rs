@@ -0,0 +1,66 @@
+// btleplug Source Code File
+//
+// Copyright 2023 Nonpolynomial Labs LLC. All rights reserved.
+//
+// Licensed under the BSD 3-Clause license. See LICENSE file in the project root
+// for full license information.
+
+//! The `mock` module contains an in-memory implementation of the [`api`] traits, for
+//! testing code built on btleplug on machines without a Bluetooth radio.
+//!
+//! A mock [`Adapter`] holds a set of simulated remote devices, each of which is backed by a
+//! scriptable [`GattDatabase`]. Devices are announced either by calling [`Adapter::add_device`] or
+//! by feeding advertisements to a scanning adapter with [`Adapter::advertise`], and the resulting
+//! [`CentralEvent`](crate::api::CentralEvent)s go through the same machinery as the real platform
+//! backends. The mock types use the platform [`PeripheralId`], so
+//! they can stand in for the real ones anywhere the API traits are used generically.
+
+mod adapter;
+mod advertising;
+mod gatt;
+mod gatt_server;
+mod manager;
+mod pairing;
+mod peripheral;
+
+pub use self::{
+    adapter::Adapter, advertising::AdvertisementHandle, gatt::GattDatabase,
+    gatt_server::GattApplication, manager::Manager, pairing::AgentHandle, peripheral::Peripheral,
+};
+
+use crate::api::{self, BDAddr, Central};
+use crate::platform::PeripheralId;
+use static_assertions::assert_impl_all;
+
+assert_impl_all!(
+    Adapter: Central,
+    api::Advertiser,
+    api::GattServer,
+    api::Pairing,
+    Clone,
+    Send,
+    Sized,
+    Sync
+);
+assert_impl_all!(GattApplication: api::GattApplication, Send, Sized, Sync);
+assert_impl_all!(Manager: api::Manager, Clone, Send, Sized, Sync);
+assert_impl_all!(Peripheral: api::Peripheral, Clone, Send, Sized, Sync);
+
+/// Builds the [`PeripheralId`] the platform backend would use for a device with the given address,
+/// seen through the adapter with the given name.
+pub(crate) fn peripheral_id(adapter: &str, address: BDAddr) -> PeripheralId {
+    #[cfg(target_os = "linux")]
+    {
+        PeripheralId::from_address(adapter, address)
+    }
+    #[cfg(any(target_os = "macos", target_os = "ios"))]
+    {
+        let _ = adapter;
+        uuid::Uuid::from_u128(u64::from(address).into()).into()
+    }
+    #[cfg(any(target_os = "windows", target_os = "android"))]
+    {
+        let _ = adapter;
+        address.into()
+    }
+}
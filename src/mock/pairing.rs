@@ -0,0 +1,67 @@
+// btleplug Source Code File
+//
+// Copyright 2023 Nonpolynomial Labs LLC. All rights reserved.
+//
+// Licensed under the BSD 3-Clause license. See LICENSE file in the project root
+// for full license information.
+
+use crate::api::{Agent, IoCapability};
+use std::sync::{Arc, Mutex};
+
+/// The agent registered on a mock adapter, shared with its peripherals. As with BlueZ, registering
+/// a new agent replaces the previous one.
+#[derive(Debug, Default)]
+pub(super) struct Agents {
+    next_id: usize,
+    current: Option<(usize, Arc<dyn Agent>)>,
+}
+
+impl Agents {
+    pub(super) fn current(&self) -> Option<Arc<dyn Agent>> {
+        self.current.as_ref().map(|(_, agent)| agent.clone())
+    }
+}
+
+/// Mock agent handle. The agent is unregistered when this is dropped, unless another agent has
+/// replaced it since.
+#[derive(Debug)]
+pub struct AgentHandle {
+    agents: Arc<Mutex<Agents>>,
+    id: usize,
+    capability: IoCapability,
+}
+
+impl AgentHandle {
+    pub(super) fn new(
+        agents: Arc<Mutex<Agents>>,
+        agent: Arc<dyn Agent>,
+        capability: IoCapability,
+    ) -> Self {
+        let id = {
+            let mut agents = agents.lock().unwrap();
+            let id = agents.next_id;
+            agents.next_id += 1;
+            agents.current = Some((id, agent));
+            id
+        };
+        Self {
+            agents,
+            id,
+            capability,
+        }
+    }
+
+    /// Returns the capability the agent was registered with.
+    pub fn capability(&self) -> IoCapability {
+        self.capability
+    }
+}
+
+impl Drop for AgentHandle {
+    fn drop(&mut self) {
+        let mut agents = self.agents.lock().unwrap();
+        if matches!(agents.current, Some((id, _)) if id == self.id) {
+            agents.current = None;
+        }
+    }
+}
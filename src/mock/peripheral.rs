@@ -0,0 +1,974 @@
+// btleplug Source Code File
+//
+// Copyright 2023 Nonpolynomial Labs LLC. All rights reserved.
+//
+// Licensed under the BSD 3-Clause license. See LICENSE file in the project root
+// for full license information.
+
+use super::{gatt::GattDatabase, pairing::Agents};
+use crate::{
+    api::{
+        self, proximity::Proximity, AttErrorCode, BDAddr, CentralEvent, CharPropFlags,
+        Characteristic, Descriptor, PeripheralProperties, Service, Timeouts, ValueNotification,
+        WriteType,
+    },
+    common::{
+        adapter_manager::AdapterManager,
+        subscriptions::Subscriptions,
+        timeouts::{self, timeout},
+        util::notifications_stream_from_broadcast_receiver,
+    },
+    platform::PeripheralId,
+    Error, Result,
+};
+use async_trait::async_trait;
+use futures::stream::Stream;
+use log::trace;
+use std::{
+    collections::{BTreeSet, HashSet},
+    fmt::{self, Debug, Formatter},
+    pin::Pin,
+    sync::atomic::{AtomicBool, AtomicU16, Ordering},
+    sync::{Arc, Mutex, Weak},
+    time::Duration,
+};
+use tokio::sync::broadcast;
+
+/// Mock implementation of [api::Peripheral](crate::api::Peripheral).
+///
+/// Besides the API trait, this exposes methods to script the behaviour of the simulated remote
+/// device, such as changing its GATT database or sending notifications.
+#[derive(Clone)]
+pub struct Peripheral {
+    shared: Arc<Shared>,
+    timeout_overrides: Timeouts,
+}
+
+struct Shared {
+    adapter: Weak<AdapterManager<Peripheral>>,
+    agents: Arc<Mutex<Agents>>,
+    id: PeripheralId,
+    properties: Mutex<PeripheralProperties>,
+    database: Mutex<GattDatabase>,
+    connectable: AtomicBool,
+    connected: AtomicBool,
+    latency: Mutex<Option<Duration>>,
+    mtu: AtomicU16,
+    passkey: Mutex<Option<u32>>,
+    paired: AtomicBool,
+    services: Mutex<BTreeSet<Service>>,
+    subscriptions: Mutex<HashSet<u16>>,
+    notifications_channel: broadcast::Sender<ValueNotification>,
+    notification_streams: Arc<Subscriptions>,
+}
+
+impl Peripheral {
+    pub(super) fn new(
+        adapter: Weak<AdapterManager<Self>>,
+        agents: Arc<Mutex<Agents>>,
+        id: PeripheralId,
+        properties: PeripheralProperties,
+        database: GattDatabase,
+    ) -> Self {
+        let (broadcast_sender, _) = broadcast::channel(16);
+        Peripheral {
+            shared: Arc::new(Shared {
+                adapter,
+                agents,
+                id,
+                properties: Mutex::new(properties),
+                database: Mutex::new(database),
+                connectable: AtomicBool::new(true),
+                connected: AtomicBool::new(false),
+                latency: Mutex::new(None),
+                mtu: AtomicU16::new(api::DEFAULT_MTU),
+                passkey: Mutex::new(None),
+                paired: AtomicBool::new(false),
+                services: Mutex::new(BTreeSet::new()),
+                subscriptions: Mutex::new(HashSet::new()),
+                notifications_channel: broadcast_sender,
+                notification_streams: Default::default(),
+            }),
+            timeout_overrides: Timeouts::default(),
+        }
+    }
+
+    /// Merges a newly received advertisement into the stored properties. As on the real backends,
+    /// advertisements are cumulative: fields are only replaced if they are present.
+    pub(super) fn update_properties(&self, advertisement: &PeripheralProperties) {
+        let mut properties = self.shared.properties.lock().unwrap();
+        if advertisement.address_type.is_some() {
+            properties.address_type = advertisement.address_type;
+        }
+        if advertisement.local_name.is_some() {
+            properties.local_name = advertisement.local_name.clone();
+        }
+        if advertisement.tx_power_level.is_some() {
+            properties.tx_power_level = advertisement.tx_power_level;
+        }
+        if advertisement.rssi.is_some() {
+            properties.rssi = advertisement.rssi;
+        }
+        properties
+            .manufacturer_data
+            .extend(advertisement.manufacturer_data.clone());
+        properties
+            .service_data
+            .extend(advertisement.service_data.clone());
+        for uuid in &advertisement.services {
+            if !properties.services.contains(uuid) {
+                properties.services.push(*uuid);
+            }
+        }
+    }
+
+    /// Replaces the GATT database of the simulated device. Services which have already been
+    /// discovered are not updated until `discover_services` is called again.
+    pub fn set_database(&self, database: GattDatabase) {
+        *self.shared.database.lock().unwrap() = database;
+    }
+
+    /// Returns a snapshot of the GATT database of the simulated device, including any values
+    /// written to it.
+    pub fn database(&self) -> GattDatabase {
+        self.shared.database.lock().unwrap().clone()
+    }
+
+    /// Sets whether connection attempts to the simulated device succeed. Devices are connectable by
+    /// default.
+    pub fn set_connectable(&self, connectable: bool) {
+        self.shared
+            .connectable
+            .store(connectable, Ordering::Relaxed);
+    }
+
+    /// Simulates the device updating the value of a characteristic. The new value is stored, and a
+    /// [`ValueNotification`] is sent if notifications are currently enabled for the
+    /// characteristic.
+    pub fn notify(&self, characteristic: &Characteristic, value: Vec<u8>) -> Result<()> {
+        self.shared
+            .database
+            .lock()
+            .unwrap()
+            .characteristic_mut(characteristic)?
+            .value = value.clone();
+        let subscribed = self
+            .shared
+            .subscriptions
+            .lock()
+            .unwrap()
+            .contains(&characteristic.handle);
+        if subscribed {
+            // Note: we ignore send errors here which may happen while there are no receivers...
+            let _ = self.shared.notifications_channel.send(ValueNotification {
+                uuid: characteristic.uuid,
+                service_uuid: characteristic.service_uuid,
+                handle: characteristic.handle,
+                value,
+            });
+        }
+        Ok(())
+    }
+
+    /// Makes connection attempts and GATT operations on the simulated device take the given time
+    /// before completing, for example to exercise timeouts.
+    pub fn set_latency(&self, latency: Option<Duration>) {
+        *self.shared.latency.lock().unwrap() = latency;
+    }
+
+    /// Simulates the device negotiating a new ATT MTU. A [`CentralEvent::MtuChanged`] is emitted if
+    /// the device is connected and the MTU differs from the current one.
+    pub fn set_mtu(&self, mtu: u16) {
+        let previous = self.shared.mtu.swap(mtu, Ordering::Relaxed);
+        if previous != mtu && self.shared.connected.load(Ordering::Relaxed) {
+            self.emit_event(CentralEvent::MtuChanged {
+                id: self.shared.id.clone(),
+                mtu,
+            });
+        }
+    }
+
+    /// Sets the passkey the simulated device displays while pairing, which the registered
+    /// [`Agent`](api::Agent) must return from `request_passkey` for pairing to succeed. Without one,
+    /// the device pairs without authentication ("Just Works"). Pairing with a device which bonds is
+    /// simulated; keys are kept until it is unpaired.
+    pub fn set_passkey(&self, passkey: Option<u32>) {
+        *self.shared.passkey.lock().unwrap() = passkey;
+    }
+
+    /// Simulates the connection being dropped by the device or by the link going away.
+    pub fn simulate_disconnect(&self) {
+        self.drop_connection();
+    }
+
+    fn drop_connection(&self) {
+        if self.shared.connected.swap(false, Ordering::Relaxed) {
+            self.shared.subscriptions.lock().unwrap().clear();
+            self.emit_event(CentralEvent::DeviceDisconnected(self.shared.id.clone()));
+        }
+    }
+
+    async fn simulate_latency(&self) {
+        let latency = *self.shared.latency.lock().unwrap();
+        if let Some(latency) = latency {
+            tokio::time::sleep(latency).await;
+        }
+    }
+
+    fn ensure_connected(&self) -> Result<()> {
+        if self.shared.connected.load(Ordering::Relaxed) {
+            Ok(())
+        } else {
+            Err(Error::NotConnected)
+        }
+    }
+
+    fn characteristic_properties(&self, characteristic: &Characteristic) -> Result<CharPropFlags> {
+        Ok(self
+            .shared
+            .database
+            .lock()
+            .unwrap()
+            .characteristic_mut(characteristic)?
+            .properties)
+    }
+
+    fn emit_event(&self, event: CentralEvent) {
+        if let Some(manager) = self.shared.adapter.upgrade() {
+            manager.emit(event);
+        } else {
+            trace!("Could not emit an event. AdapterManager has been dropped");
+        }
+    }
+}
+
+impl Debug for Peripheral {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("Peripheral")
+            .field("id", &self.shared.id)
+            .field("properties", &self.shared.properties.lock().unwrap())
+            .field("connected", &self.shared.connected.load(Ordering::Relaxed))
+            .finish()
+    }
+}
+
+#[async_trait]
+impl api::Peripheral for Peripheral {
+    fn id(&self) -> PeripheralId {
+        self.shared.id.clone()
+    }
+
+    fn address(&self) -> BDAddr {
+        self.shared.properties.lock().unwrap().address
+    }
+
+    async fn properties(&self) -> Result<Option<PeripheralProperties>> {
+        Ok(Some(self.shared.properties.lock().unwrap().clone()))
+    }
+
+    fn proximity(&self) -> Option<Proximity> {
+        self.shared.adapter.upgrade()?.proximity(&self.id())
+    }
+
+    fn services(&self) -> BTreeSet<Service> {
+        self.shared.services.lock().unwrap().clone()
+    }
+
+    fn timeouts(&self) -> Timeouts {
+        let adapter_timeouts = match self.shared.adapter.upgrade() {
+            Some(manager) => manager.timeouts(),
+            None => Timeouts::default(),
+        };
+        adapter_timeouts.merge(self.timeout_overrides)
+    }
+
+    fn with_timeouts(&self, timeouts: Timeouts) -> Self {
+        Self {
+            shared: self.shared.clone(),
+            timeout_overrides: self.timeout_overrides.merge(timeouts),
+        }
+    }
+
+    async fn is_connected(&self) -> Result<bool> {
+        Ok(self.shared.connected.load(Ordering::Relaxed))
+    }
+
+    async fn connect(&self) -> Result<()> {
+        timeouts::connect(self, async {
+            self.simulate_latency().await;
+            if !self.shared.connectable.load(Ordering::Relaxed) {
+                return Err(Error::Other("Device is not connectable".into()));
+            }
+            if !self.shared.connected.swap(true, Ordering::Relaxed) {
+                self.emit_event(CentralEvent::DeviceConnected(self.shared.id.clone()));
+            }
+            Ok(())
+        })
+        .await
+    }
+
+    async fn disconnect(&self) -> Result<()> {
+        self.drop_connection();
+        Ok(())
+    }
+
+    async fn discover_services(&self) -> Result<()> {
+        timeout(self.timeouts().discover_services, async {
+            self.simulate_latency().await;
+            self.ensure_connected()?;
+            *self.shared.services.lock().unwrap() = self.shared.database.lock().unwrap().services();
+            Ok(())
+        })
+        .await
+    }
+
+    async fn pair(&self) -> Result<()> {
+        if self.shared.paired.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+        self.connect().await?;
+        let passkey = *self.shared.passkey.lock().unwrap();
+        if let Some(passkey) = passkey {
+            let agent = self
+                .shared
+                .agents
+                .lock()
+                .unwrap()
+                .current()
+                .ok_or_else(|| Error::Other("No agent to enter the passkey".into()))?;
+            if agent.request_passkey(&self.shared.id).await != Some(passkey) {
+                return Err(Error::AuthenticationFailed);
+            }
+        }
+        self.shared.paired.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    async fn unpair(&self) -> Result<()> {
+        self.shared.paired.store(false, Ordering::Relaxed);
+        self.drop_connection();
+        Ok(())
+    }
+
+    async fn is_paired(&self) -> Result<bool> {
+        Ok(self.shared.paired.load(Ordering::Relaxed))
+    }
+
+    async fn is_bonded(&self) -> Result<bool> {
+        self.is_paired().await
+    }
+
+    async fn mtu(&self) -> Result<u16> {
+        self.ensure_connected()?;
+        Ok(self.shared.mtu.load(Ordering::Relaxed))
+    }
+
+    async fn write(
+        &self,
+        characteristic: &Characteristic,
+        data: &[u8],
+        write_type: WriteType,
+    ) -> Result<()> {
+        timeout(self.timeouts().write, async {
+            self.simulate_latency().await;
+            self.ensure_connected()?;
+            let mut database = self.shared.database.lock().unwrap();
+            let entry = database.characteristic_mut(characteristic)?;
+            match write_type {
+                // A device responds to a write request it can't carry out with an ATT error.
+                WriteType::WithResponse if !entry.properties.contains(CharPropFlags::WRITE) => {
+                    return Err(Error::Att(AttErrorCode::WriteNotPermitted));
+                }
+                WriteType::WithoutResponse
+                    if !entry
+                        .properties
+                        .contains(CharPropFlags::WRITE_WITHOUT_RESPONSE) =>
+                {
+                    return Err(Error::NotSupported(format!(
+                        "Characteristic {} does not support {:?} writes",
+                        characteristic.uuid, write_type
+                    )));
+                }
+                _ => {}
+            }
+            entry.value = data.to_vec();
+            Ok(())
+        })
+        .await
+    }
+
+    async fn read(&self, characteristic: &Characteristic) -> Result<Vec<u8>> {
+        timeout(self.timeouts().read, async {
+            self.simulate_latency().await;
+            self.ensure_connected()?;
+            let mut database = self.shared.database.lock().unwrap();
+            let entry = database.characteristic_mut(characteristic)?;
+            if !entry.properties.contains(CharPropFlags::READ) {
+                return Err(Error::Att(AttErrorCode::ReadNotPermitted));
+            }
+            Ok(entry.value.clone())
+        })
+        .await
+    }
+
+    async fn read_at(&self, characteristic: &Characteristic, offset: usize) -> Result<Vec<u8>> {
+        let value = self.read(characteristic).await?;
+        // Like a Read Blob Request, this returns at most what fits in a single response.
+        let mtu = usize::from(self.shared.mtu.load(Ordering::Relaxed));
+        let end = value
+            .len()
+            .min(offset.saturating_add(mtu.saturating_sub(1).max(1)));
+        value
+            .get(offset..end)
+            .map(<[u8]>::to_vec)
+            .ok_or(Error::Att(AttErrorCode::InvalidOffset))
+    }
+
+    async fn subscribe(&self, characteristic: &Characteristic) -> Result<()> {
+        timeout(self.timeouts().subscribe, async {
+            self.simulate_latency().await;
+            self.ensure_connected()?;
+            let properties = self.characteristic_properties(characteristic)?;
+            if !properties.intersects(CharPropFlags::NOTIFY | CharPropFlags::INDICATE) {
+                return Err(Error::NotSupported(format!(
+                    "Characteristic {} does not support notifications or indications",
+                    characteristic.uuid
+                )));
+            }
+            self.shared
+                .subscriptions
+                .lock()
+                .unwrap()
+                .insert(characteristic.handle);
+            Ok(())
+        })
+        .await
+    }
+
+    async fn unsubscribe(&self, characteristic: &Characteristic) -> Result<()> {
+        timeout(self.timeouts().subscribe, async {
+            self.simulate_latency().await;
+            self.ensure_connected()?;
+            self.characteristic_properties(characteristic)?;
+            self.shared
+                .subscriptions
+                .lock()
+                .unwrap()
+                .remove(&characteristic.handle);
+            Ok(())
+        })
+        .await
+    }
+
+    async fn notifications(&self) -> Result<Pin<Box<dyn Stream<Item = ValueNotification> + Send>>> {
+        let receiver = self.shared.notifications_channel.subscribe();
+        Ok(notifications_stream_from_broadcast_receiver(receiver))
+    }
+
+    async fn notifications_for(
+        &self,
+        characteristic: &Characteristic,
+        subscribe: bool,
+    ) -> Result<Pin<Box<dyn Stream<Item = Vec<u8>> + Send>>> {
+        self.shared
+            .notification_streams
+            .notifications_for(self, characteristic, subscribe)
+            .await
+    }
+
+    async fn write_descriptor(&self, descriptor: &Descriptor, data: &[u8]) -> Result<()> {
+        timeout(self.timeouts().descriptor, async {
+            self.simulate_latency().await;
+            self.ensure_connected()?;
+            *self
+                .shared
+                .database
+                .lock()
+                .unwrap()
+                .descriptor_mut(descriptor)? = data.to_vec();
+            Ok(())
+        })
+        .await
+    }
+
+    async fn read_descriptor(&self, descriptor: &Descriptor) -> Result<Vec<u8>> {
+        timeout(self.timeouts().descriptor, async {
+            self.simulate_latency().await;
+            self.ensure_connected()?;
+            Ok(self
+                .shared
+                .database
+                .lock()
+                .unwrap()
+                .descriptor_mut(descriptor)?
+                .clone())
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{Adapter, GattDatabase};
+    use crate::api::{
+        bleuuid::uuid_from_u16, gatt::metadata::Format, Agent, AttErrorCode, Central as _,
+        CentralEvent, CharPropFlags, Descriptor, IoCapability, Pairing as _, Peripheral as _,
+        PeripheralProperties, Timeouts, TransferOptions, WriteType, DEFAULT_MTU,
+    };
+    use crate::{platform::PeripheralId, Error};
+    use futures::future::ready;
+    use futures::{FutureExt, StreamExt};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    const SERVICE: uuid::Uuid = uuid_from_u16(0x180F);
+    const CHARACTERISTIC: uuid::Uuid = uuid_from_u16(0x2A19);
+
+    fn database() -> GattDatabase {
+        let mut database = GattDatabase::new();
+        let service = database.add_service(SERVICE, true);
+        database.add_characteristic(
+            service,
+            CHARACTERISTIC,
+            CharPropFlags::READ | CharPropFlags::WRITE | CharPropFlags::NOTIFY,
+            vec![42],
+        );
+        database
+    }
+
+    fn peripheral() -> super::Peripheral {
+        let properties = PeripheralProperties {
+            address: [1, 2, 3, 4, 5, 6].into(),
+            ..Default::default()
+        };
+        Adapter::new("hci0").add_device(properties, database())
+    }
+
+    #[tokio::test]
+    async fn operations_require_connection() {
+        let peripheral = peripheral();
+        assert!(matches!(
+            peripheral.discover_services().await,
+            Err(Error::NotConnected)
+        ));
+        assert!(peripheral.services().is_empty());
+
+        peripheral.connect().await.unwrap();
+        peripheral.discover_services().await.unwrap();
+        assert_eq!(peripheral.services(), database().services());
+    }
+
+    #[tokio::test]
+    async fn read_write() {
+        let peripheral = peripheral();
+        peripheral.connect().await.unwrap();
+        peripheral.discover_services().await.unwrap();
+        let characteristic = peripheral.characteristics().into_iter().next().unwrap();
+
+        assert_eq!(peripheral.read(&characteristic).await.unwrap(), vec![42]);
+        peripheral
+            .write(&characteristic, &[1, 2], WriteType::WithResponse)
+            .await
+            .unwrap();
+        assert_eq!(peripheral.read(&characteristic).await.unwrap(), vec![1, 2]);
+        assert_eq!(
+            peripheral.database().value(characteristic.handle),
+            Some(&[1, 2][..])
+        );
+        assert!(matches!(
+            peripheral
+                .write(&characteristic, &[3], WriteType::WithoutResponse)
+                .await,
+            Err(Error::NotSupported(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn att_errors() {
+        let mut database = GattDatabase::new();
+        let service = database.add_service(SERVICE, true);
+        database.add_characteristic(service, CHARACTERISTIC, CharPropFlags::NOTIFY, vec![42]);
+        let notify_only =
+            Adapter::new("hci0").add_device(PeripheralProperties::default(), database);
+        notify_only.connect().await.unwrap();
+        notify_only.discover_services().await.unwrap();
+        let characteristic = notify_only.characteristics().into_iter().next().unwrap();
+
+        assert!(matches!(
+            notify_only.read(&characteristic).await,
+            Err(Error::Att(AttErrorCode::ReadNotPermitted))
+        ));
+        assert!(matches!(
+            notify_only
+                .write(&characteristic, &[1], WriteType::WithResponse)
+                .await,
+            Err(Error::Att(AttErrorCode::WriteNotPermitted))
+        ));
+
+        let peripheral = peripheral();
+        peripheral.connect().await.unwrap();
+        peripheral.discover_services().await.unwrap();
+        let characteristic = peripheral.characteristics().into_iter().next().unwrap();
+        assert!(matches!(
+            peripheral.read_at(&characteristic, 2).await,
+            Err(Error::Att(AttErrorCode::InvalidOffset))
+        ));
+    }
+
+    #[tokio::test]
+    async fn notifications_only_when_subscribed() {
+        let peripheral = peripheral();
+        peripheral.connect().await.unwrap();
+        peripheral.discover_services().await.unwrap();
+        let characteristic = peripheral.characteristics().into_iter().next().unwrap();
+        let mut notifications = peripheral.notifications().await.unwrap();
+
+        peripheral.notify(&characteristic, vec![1]).unwrap();
+        peripheral.subscribe(&characteristic).await.unwrap();
+        peripheral.notify(&characteristic, vec![2]).unwrap();
+
+        let notification = notifications.next().await.unwrap();
+        assert_eq!(notification.uuid, CHARACTERISTIC);
+        assert_eq!(notification.value, vec![2]);
+    }
+
+    #[tokio::test]
+    async fn duplicate_uuids_are_told_apart_by_handle() {
+        let mut database = GattDatabase::new();
+        let first_service = database.add_service(SERVICE, true);
+        let second_service = database.add_service(SERVICE, true);
+        let properties = CharPropFlags::READ | CharPropFlags::NOTIFY;
+        let first = database.add_characteristic(first_service, CHARACTERISTIC, properties, vec![1]);
+        let second =
+            database.add_characteristic(second_service, CHARACTERISTIC, properties, vec![2]);
+        let third =
+            database.add_characteristic(second_service, CHARACTERISTIC, properties, vec![3]);
+        let peripheral = Adapter::new("hci0").add_device(PeripheralProperties::default(), database);
+        peripheral.connect().await.unwrap();
+        peripheral.discover_services().await.unwrap();
+
+        assert_eq!(peripheral.services().len(), 2);
+        let characteristics = peripheral.characteristics();
+        assert_eq!(characteristics.len(), 3);
+        let by_handle = |handle| {
+            characteristics
+                .iter()
+                .find(|characteristic| characteristic.handle == handle)
+                .unwrap()
+                .clone()
+        };
+        assert_eq!(by_handle(first).service_handle, first_service);
+        assert_eq!(by_handle(third).service_handle, second_service);
+        assert_eq!(peripheral.read(&by_handle(second)).await.unwrap(), vec![2]);
+        assert_eq!(peripheral.read(&by_handle(third)).await.unwrap(), vec![3]);
+
+        let mut values = peripheral
+            .notifications_for(&by_handle(third), true)
+            .await
+            .unwrap();
+        // The stream subscribes when it is first polled.
+        assert_eq!(values.next().now_or_never(), None);
+        peripheral.subscribe(&by_handle(second)).await.unwrap();
+        peripheral.notify(&by_handle(second), vec![4]).unwrap();
+        peripheral.notify(&by_handle(third), vec![5]).unwrap();
+        assert_eq!(values.next().await, Some(vec![5]));
+    }
+
+    #[tokio::test]
+    async fn included_services_are_listed_by_handle() {
+        let mut database = GattDatabase::new();
+        let primary = database.add_service(SERVICE, true);
+        let secondary = database.add_service(uuid_from_u16(0x1812), false);
+        database.add_included_service(primary, secondary);
+        let peripheral = Adapter::new("hci0").add_device(PeripheralProperties::default(), database);
+        peripheral.connect().await.unwrap();
+        peripheral.discover_services().await.unwrap();
+
+        let services = peripheral.services();
+        let by_handle = |handle| services.iter().find(|service| service.handle == handle);
+        let primary = by_handle(primary).unwrap();
+        assert_eq!(primary.included_services, [secondary].into());
+        let included = by_handle(*primary.included_services.first().unwrap()).unwrap();
+        assert!(!included.primary);
+        assert!(included.included_services.is_empty());
+    }
+
+    #[tokio::test]
+    async fn characteristic_metadata_is_read_from_descriptors() {
+        let mut database = GattDatabase::new();
+        let service = database.add_service(SERVICE, true);
+        let characteristic = database.add_characteristic(
+            service,
+            CHARACTERISTIC,
+            CharPropFlags::READ | CharPropFlags::EXTENDED_PROPERTIES,
+            vec![50],
+        );
+        database.add_descriptor(characteristic, uuid_from_u16(0x2901), b"Level".to_vec());
+        database.add_descriptor(
+            characteristic,
+            uuid_from_u16(0x2904),
+            vec![0x04, 0x00, 0xAD, 0x27, 0x01, 0x00, 0x00],
+        );
+        database.add_descriptor(characteristic, uuid_from_u16(0x2900), vec![0x01, 0x00]);
+        database.add_descriptor(characteristic, uuid_from_u16(0x2906), vec![0, 100]);
+        let peripheral = Adapter::new("hci0").add_device(PeripheralProperties::default(), database);
+        peripheral.connect().await.unwrap();
+        peripheral.discover_services().await.unwrap();
+
+        let characteristic = peripheral.characteristics().into_iter().next().unwrap();
+        let metadata = peripheral
+            .characteristic_metadata(&characteristic)
+            .await
+            .unwrap();
+        assert_eq!(metadata.user_description.as_deref(), Some("Level"));
+        let format = metadata.presentation_format().unwrap();
+        assert_eq!(format.format, Format::Uint8);
+        assert_eq!(format.unit, uuid_from_u16(0x27AD));
+        assert!(metadata.extended_properties.unwrap().reliable_write);
+        assert_eq!(
+            metadata.valid_range.as_ref().unwrap().bounds(format),
+            Some((0.0, 100.0))
+        );
+    }
+
+    #[tokio::test]
+    async fn characteristic_metadata_skips_unusable_descriptors() {
+        let mut database = GattDatabase::new();
+        let service = database.add_service(SERVICE, true);
+        let characteristic =
+            database.add_characteristic(service, CHARACTERISTIC, CharPropFlags::READ, vec![50]);
+        database.add_descriptor(characteristic, uuid_from_u16(0x2901), b"Level".to_vec());
+        // Too short for a presentation format.
+        database.add_descriptor(characteristic, uuid_from_u16(0x2904), vec![0x04]);
+        let peripheral = Adapter::new("hci0").add_device(PeripheralProperties::default(), database);
+        peripheral.connect().await.unwrap();
+        peripheral.discover_services().await.unwrap();
+
+        let mut characteristic = peripheral.characteristics().into_iter().next().unwrap();
+        // A descriptor which the device fails to read.
+        characteristic.descriptors.insert(Descriptor {
+            uuid: uuid_from_u16(0x2900),
+            service_uuid: SERVICE,
+            characteristic_uuid: CHARACTERISTIC,
+            handle: 0x00ff,
+            service_handle: characteristic.service_handle,
+            characteristic_handle: characteristic.handle,
+        });
+        let metadata = peripheral
+            .characteristic_metadata(&characteristic)
+            .await
+            .unwrap();
+        assert_eq!(metadata.user_description.as_deref(), Some("Level"));
+        assert!(metadata.presentation_formats.is_empty());
+        assert!(metadata.extended_properties.is_none());
+    }
+
+    #[tokio::test]
+    async fn notifications_for_manages_subscription() {
+        let peripheral = peripheral();
+        peripheral.connect().await.unwrap();
+        peripheral.discover_services().await.unwrap();
+        let characteristic = peripheral.characteristics().into_iter().next().unwrap();
+        let subscribed = || {
+            peripheral
+                .shared
+                .subscriptions
+                .lock()
+                .unwrap()
+                .contains(&characteristic.handle)
+        };
+
+        let mut first = peripheral
+            .notifications_for(&characteristic, true)
+            .await
+            .unwrap();
+        let mut second = peripheral
+            .notifications_for(&characteristic, true)
+            .await
+            .unwrap();
+        // Nothing is subscribed to until the streams are polled.
+        assert!(!subscribed());
+        assert_eq!(first.next().now_or_never(), None);
+        assert_eq!(second.next().now_or_never(), None);
+        assert!(subscribed());
+        peripheral.notify(&characteristic, vec![1]).unwrap();
+        assert_eq!(first.next().await, Some(vec![1]));
+        assert_eq!(second.next().await, Some(vec![1]));
+
+        drop(first);
+        tokio::task::yield_now().await;
+        assert!(subscribed());
+        drop(second);
+        // Unsubscribing happens on a spawned task.
+        for _ in 0..10 {
+            if !subscribed() {
+                break;
+            }
+            tokio::task::yield_now().await;
+        }
+        assert!(!subscribed());
+
+        // A stream which fails to subscribe ends.
+        peripheral.disconnect().await.unwrap();
+        let mut failed = peripheral
+            .notifications_for(&characteristic, true)
+            .await
+            .unwrap();
+        assert_eq!(failed.next().await, None);
+    }
+
+    #[tokio::test]
+    async fn operations_time_out() {
+        let adapter = Adapter::new("hci0");
+        let properties = PeripheralProperties {
+            address: [1, 2, 3, 4, 5, 6].into(),
+            ..Default::default()
+        };
+        let peripheral = adapter.add_device(properties, database());
+        adapter.set_timeouts(Timeouts::all(Duration::from_millis(10)));
+        peripheral.set_latency(Some(Duration::from_millis(100)));
+
+        assert!(matches!(
+            peripheral.connect().await,
+            Err(Error::TimedOut(timeout)) if timeout == Duration::from_millis(10)
+        ));
+        assert!(!peripheral.is_connected().await.unwrap());
+
+        let patient = peripheral.with_timeouts(Timeouts {
+            connect: Some(Duration::from_secs(10)),
+            discover_services: Some(Duration::from_secs(10)),
+            ..Default::default()
+        });
+        patient.connect().await.unwrap();
+        patient.discover_services().await.unwrap();
+        let characteristic = peripheral.characteristics().into_iter().next().unwrap();
+        assert!(matches!(
+            patient.read(&characteristic).await,
+            Err(Error::TimedOut(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn mtu_changes_are_reported() {
+        let adapter = Adapter::new("hci0");
+        let properties = PeripheralProperties {
+            address: [1, 2, 3, 4, 5, 6].into(),
+            ..Default::default()
+        };
+        let peripheral = adapter.add_device(properties, database());
+        let events = adapter.events().await.unwrap();
+        assert!(matches!(peripheral.mtu().await, Err(Error::NotConnected)));
+
+        peripheral.connect().await.unwrap();
+        assert_eq!(peripheral.mtu().await.unwrap(), DEFAULT_MTU);
+        assert_eq!(
+            peripheral.max_write_without_response_size().await.unwrap(),
+            20
+        );
+
+        peripheral.set_mtu(247);
+        assert_eq!(peripheral.mtu().await.unwrap(), 247);
+        assert_eq!(
+            peripheral.max_write_without_response_size().await.unwrap(),
+            244
+        );
+        let mut mtu_changes = events.filter_map(|event| {
+            ready(match event {
+                CentralEvent::MtuChanged { mtu, .. } => Some(mtu),
+                _ => None,
+            })
+        });
+        assert_eq!(mtu_changes.next().await, Some(247));
+    }
+
+    #[tokio::test]
+    async fn chunked_writes_and_long_reads() {
+        let peripheral = peripheral();
+        peripheral.connect().await.unwrap();
+        peripheral.discover_services().await.unwrap();
+        let characteristic = peripheral.characteristics().into_iter().next().unwrap();
+        let data: Vec<u8> = (0..100).collect();
+
+        let progress = Arc::new(Mutex::new(vec![]));
+        let reported = progress.clone();
+        let options = TransferOptions::default()
+            .with_progress(move |progress| reported.lock().unwrap().push(progress.transferred));
+        peripheral
+            .write_all(&characteristic, &data, WriteType::WithResponse, options)
+            .await
+            .unwrap();
+        assert_eq!(*progress.lock().unwrap(), vec![20, 40, 60, 80, 100]);
+        // Each chunk is a separate write, so the last one is left.
+        assert_eq!(
+            peripheral.database().value(characteristic.handle),
+            Some(&data[80..])
+        );
+
+        // Values which fill the last Read Blob Response exactly need one more read to find the end.
+        for length in [100, 44] {
+            peripheral
+                .notify(&characteristic, data[..length].to_vec())
+                .unwrap();
+            let value = peripheral
+                .read_all(&characteristic, TransferOptions::default())
+                .await
+                .unwrap();
+            assert_eq!(value, &data[..length]);
+        }
+
+        // Reads still make progress at a nonsensical MTU.
+        peripheral.set_mtu(0);
+        assert_eq!(
+            peripheral.read_at(&characteristic, 1).await.unwrap(),
+            &data[1..2]
+        );
+    }
+
+    #[derive(Debug)]
+    struct PasskeyAgent(u32);
+
+    #[async_trait::async_trait]
+    impl Agent for PasskeyAgent {
+        async fn request_passkey(&self, _peripheral: &PeripheralId) -> Option<u32> {
+            Some(self.0)
+        }
+    }
+
+    #[tokio::test]
+    async fn pairing_asks_the_agent_for_the_passkey() {
+        let adapter = Adapter::new("hci0");
+        let peripheral = adapter.add_device(
+            PeripheralProperties {
+                address: [1, 2, 3, 4, 5, 6].into(),
+                ..Default::default()
+            },
+            database(),
+        );
+        peripheral.set_passkey(Some(123456));
+        assert!(matches!(peripheral.pair().await, Err(Error::Other(_))));
+
+        let wrong = adapter
+            .register_agent(Arc::new(PasskeyAgent(654321)), IoCapability::KeyboardOnly)
+            .await
+            .unwrap();
+        assert!(matches!(
+            peripheral.pair().await,
+            Err(Error::AuthenticationFailed)
+        ));
+        assert!(!peripheral.is_paired().await.unwrap());
+
+        // The new agent replaces the old one, which stays replaced when its handle is dropped.
+        let _agent = adapter
+            .register_agent(Arc::new(PasskeyAgent(123456)), IoCapability::KeyboardOnly)
+            .await
+            .unwrap();
+        drop(wrong);
+        peripheral.pair().await.unwrap();
+        assert!(peripheral.is_connected().await.unwrap());
+        assert!(peripheral.is_bonded().await.unwrap());
+
+        peripheral.unpair().await.unwrap();
+        assert!(!peripheral.is_paired().await.unwrap());
+        assert!(!peripheral.is_connected().await.unwrap());
+    }
+}
@@ -3,7 +3,8 @@
 
 #[cfg(target_os = "linux")]
 pub use crate::bluez::{
-    adapter::Adapter, manager::Manager, peripheral::Peripheral, peripheral::PeripheralId,
+    adapter::Adapter, advertising::AdvertisementHandle, gatt_server::GattApplication,
+    manager::Manager, peripheral::Peripheral, peripheral::PeripheralId,
 };
 #[cfg(any(target_os = "macos", target_os = "ios"))]
 pub use crate::corebluetooth::{
@@ -29,6 +30,10 @@ use std::{
 assert_impl_all!(Adapter: Central, Clone, Debug, Send, Sized, Sync);
 assert_impl_all!(Manager: api::Manager, Clone, Debug, Send, Sized, Sync);
 assert_impl_all!(Peripheral: api::Peripheral, Clone, Debug, Send, Sized, Sync);
+#[cfg(target_os = "linux")]
+assert_impl_all!(Adapter: api::Advertiser, api::GattServer, api::Pairing);
+#[cfg(target_os = "linux")]
+assert_impl_all!(GattApplication: api::GattApplication, Debug, Send, Sized, Sync);
 assert_impl_all!(
     PeripheralId: Clone,
     Debug,
@@ -0,0 +1,348 @@
+// btleplug Source Code File
+//
+// Copyright 2023 Nonpolynomial Labs LLC. All rights reserved.
+//
+// Licensed under the BSD 3-Clause license. See LICENSE file in the project root
+// for full license information.
+
+use super::{peripheral::Peripheral, Entry, Responses, Session, Timing};
+use crate::{
+    api::{
+        proximity::ProximityConfig, Central, CentralEvent, CentralState, DeviceExpiry, Presence,
+        ScanFilter, Timeouts,
+    },
+    common::adapter_manager::AdapterManager,
+    platform::PeripheralId,
+    Error, Result,
+};
+use async_trait::async_trait;
+use futures::stream::Stream;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use tokio::time::{sleep, Instant};
+
+/// Replay implementation of [api::Central](crate::api::Central).
+///
+/// Nothing happens until [`Adapter::play`] is called, which emits the recorded events, property
+/// updates and notifications of the session on their original schedule, scaled by the given
+/// [`Timing`]. Scanning has no effect on playback. The adapter is powered on until playback says
+/// otherwise.
+#[derive(Clone, Debug)]
+pub struct Adapter {
+    manager: Arc<AdapterManager<Peripheral>>,
+    session: Arc<Session>,
+    responses: Arc<Responses>,
+    timing: Timing,
+    state: Arc<Mutex<CentralState>>,
+}
+
+impl Adapter {
+    /// Creates an adapter replaying the given session.
+    pub fn new(session: Session, timing: Timing) -> Self {
+        Self {
+            manager: Arc::new(AdapterManager::default()),
+            responses: Arc::new(Responses::new(&session)),
+            session: Arc::new(session),
+            timing,
+            state: Arc::new(Mutex::new(CentralState::PoweredOn)),
+        }
+    }
+
+    /// Plays back the unsolicited entries of the session, returning once the last one has been
+    /// emitted. Operations on peripherals can be called while this is running; their responses
+    /// don't depend on how far playback has progressed.
+    pub async fn play(&self) {
+        let start = Instant::now();
+        for record in self.session.records() {
+            if let Some(offset) = self.timing.scale(record.timestamp) {
+                // Unlike `sleep_until`, this copes with offsets too far in the future.
+                sleep(offset.saturating_sub(start.elapsed())).await;
+            }
+            match &record.entry {
+                Entry::Properties { id, properties } => {
+                    self.peripheral_entry(id).set_properties(properties.clone());
+                }
+                Entry::Notification { id, notification } => {
+                    self.peripheral_entry(id).notify(notification.clone());
+                }
+                Entry::Event(event) => {
+                    match event {
+                        CentralEvent::DeviceConnected(id) => {
+                            self.peripheral_entry(id).set_connected(true)
+                        }
+                        CentralEvent::DeviceDisconnected(id) => {
+                            self.peripheral_entry(id).set_connected(false)
+                        }
+                        CentralEvent::DeviceDiscovered(id) | CentralEvent::DeviceUpdated(id) => {
+                            self.peripheral_entry(id);
+                        }
+                        CentralEvent::StateUpdate(state) => *self.state.lock().unwrap() = *state,
+                        _ => {}
+                    }
+                    self.manager.emit(event.clone());
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Returns the peripheral with the given ID, creating it if it's not known yet.
+    fn peripheral_entry(&self, id: &PeripheralId) -> Peripheral {
+        if let Some(peripheral) = self.manager.peripheral(id) {
+            return peripheral;
+        }
+        let peripheral = Peripheral::new(
+            id.clone(),
+            self.responses.clone(),
+            Arc::downgrade(&self.manager),
+        );
+        self.manager.add_peripheral(peripheral.clone());
+        peripheral
+    }
+}
+
+#[async_trait]
+impl Central for Adapter {
+    type Peripheral = Peripheral;
+
+    async fn events(&self) -> Result<Pin<Box<dyn Stream<Item = CentralEvent> + Send>>> {
+        Ok(self.manager.event_stream())
+    }
+
+    async fn start_scan(&self, _filter: ScanFilter) -> Result<()> {
+        Ok(())
+    }
+
+    async fn stop_scan(&self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn peripherals(&self) -> Result<Vec<Peripheral>> {
+        Ok(self.manager.peripherals())
+    }
+
+    async fn peripheral(&self, id: &PeripheralId) -> Result<Peripheral> {
+        self.manager.peripheral(id).ok_or(Error::DeviceNotFound)
+    }
+
+    async fn add_peripheral(&self, _address: &PeripheralId) -> Result<Peripheral> {
+        Err(Error::NotSupported(
+            "Can't add a Peripheral from a PeripheralId".to_string(),
+        ))
+    }
+
+    async fn adapter_info(&self) -> Result<String> {
+        Ok(format!("Replay ({} records)", self.session.records().len()))
+    }
+
+    async fn adapter_state(&self) -> Result<CentralState> {
+        Ok(*self.state.lock().unwrap())
+    }
+
+    async fn set_powered(&self, _powered: bool) -> Result<()> {
+        Err(Error::NotSupported(
+            "Can't change the state of a replayed adapter".to_string(),
+        ))
+    }
+
+    fn timeouts(&self) -> Timeouts {
+        self.manager.timeouts()
+    }
+
+    fn set_timeouts(&self, timeouts: Timeouts) {
+        self.manager.set_timeouts(timeouts);
+    }
+
+    fn presence(&self, id: &PeripheralId) -> Option<Presence> {
+        self.manager.presence(id)
+    }
+
+    fn set_device_expiry(&self, expiry: Option<DeviceExpiry>) {
+        self.manager.set_device_expiry(expiry);
+    }
+
+    fn set_proximity_config(&self, config: ProximityConfig) {
+        self.manager.set_proximity_config(config);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{Entry, Record, Recorder, Session, Timing};
+    use super::Adapter;
+    use crate::api::{
+        bleuuid::uuid_from_u16, AttErrorCode, Central, CentralEvent, CharPropFlags,
+        Peripheral as _, PeripheralProperties, ScanFilter, WriteType,
+    };
+    use crate::mock::{self, GattDatabase};
+    use crate::Error;
+    use futures::{FutureExt, StreamExt};
+    use std::io::{self, Write};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use tokio::time::Instant;
+
+    const SERVICE: uuid::Uuid = uuid_from_u16(0x180F);
+    const CHARACTERISTIC: uuid::Uuid = uuid_from_u16(0x2A19);
+
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    async fn record_session() -> Session {
+        let buffer = SharedBuffer::default();
+        let recorder = Recorder::new(buffer.clone());
+        let adapter = mock::Adapter::new("hci0");
+        let central = recorder.record(adapter.clone()).await.unwrap();
+
+        central.start_scan(ScanFilter::default()).await.unwrap();
+        adapter.advertise(PeripheralProperties {
+            address: [1, 2, 3, 4, 5, 6].into(),
+            local_name: Some("Recorded".to_string()),
+            ..Default::default()
+        });
+        let mut database = GattDatabase::new();
+        let service = database.add_service(SERVICE, true);
+        database.add_characteristic(
+            service,
+            CHARACTERISTIC,
+            CharPropFlags::READ | CharPropFlags::NOTIFY,
+            vec![99],
+        );
+        let mock_peripheral = adapter.peripherals().await.unwrap().pop().unwrap();
+        mock_peripheral.set_database(database);
+
+        let peripheral = central.peripherals().await.unwrap().pop().unwrap();
+        peripheral.connect().await.unwrap();
+        peripheral.discover_services().await.unwrap();
+        let characteristic = peripheral.characteristics().into_iter().next().unwrap();
+        peripheral.read(&characteristic).await.unwrap();
+        assert!(peripheral
+            .write(&characteristic, &[1], WriteType::WithResponse)
+            .await
+            .is_err());
+        peripheral.subscribe(&characteristic).await.unwrap();
+        mock_peripheral.notify(&characteristic, vec![100]).unwrap();
+
+        // Let the recording tasks catch up with the events and notifications. Time is paused, so
+        // this returns once they are idle.
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let data = buffer.0.lock().unwrap().clone();
+        Session::from_reader(&data[..]).unwrap()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn record_and_replay() {
+        let session = record_session().await;
+        let adapter = Adapter::new(session, Timing::Immediate);
+        let mut events = adapter.events().await.unwrap();
+        adapter.play().await;
+
+        assert!(matches!(
+            events.next().await,
+            Some(CentralEvent::DeviceDiscovered(_))
+        ));
+        let peripheral = adapter.peripherals().await.unwrap().pop().unwrap();
+        assert_eq!(
+            peripheral.properties().await.unwrap().unwrap().local_name,
+            Some("Recorded".to_string())
+        );
+
+        let mut notifications = peripheral.notifications().await.unwrap();
+        peripheral.connect().await.unwrap();
+        peripheral.discover_services().await.unwrap();
+        let characteristic = peripheral.characteristics().into_iter().next().unwrap();
+        assert_eq!(characteristic.uuid, CHARACTERISTIC);
+        assert_eq!(peripheral.read(&characteristic).await.unwrap(), vec![99]);
+        assert!(matches!(
+            peripheral
+                .write(&characteristic, &[1], WriteType::WithResponse)
+                .await,
+            Err(Error::Att(AttErrorCode::WriteNotPermitted))
+        ));
+        // Each recorded response is only used once.
+        assert!(peripheral.read(&characteristic).await.is_err());
+
+        // The notification was emitted during playback, before we were listening for it, so play
+        // the session again now that we are.
+        adapter.play().await;
+        assert_eq!(notifications.next().await.unwrap().value, vec![100]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn playback_timing() {
+        let session = Session {
+            records: vec![Record {
+                timestamp: Duration::from_secs(10),
+                entry: Entry::Event(CentralEvent::DeviceDiscovered(mock::peripheral_id(
+                    "hci0",
+                    [1, 2, 3, 4, 5, 6].into(),
+                ))),
+            }],
+        };
+        for (timing, expected) in [
+            (Timing::RealTime, Duration::from_secs(10)),
+            (Timing::Accelerated(10.0), Duration::from_secs(1)),
+            (Timing::Accelerated(0.0), Duration::ZERO),
+            (Timing::Accelerated(-1.0), Duration::ZERO),
+            (Timing::Accelerated(f64::NAN), Duration::ZERO),
+            (Timing::Immediate, Duration::ZERO),
+        ] {
+            let adapter = Adapter::new(session.clone(), timing);
+            let start = Instant::now();
+            adapter.play().await;
+            assert_eq!(start.elapsed(), expected, "{:?}", timing);
+        }
+    }
+
+    #[tokio::test]
+    async fn recorded_handles_share_subscriptions() {
+        let adapter = mock::Adapter::new("hci0");
+        let mut database = GattDatabase::new();
+        let service = database.add_service(SERVICE, true);
+        database.add_characteristic(service, CHARACTERISTIC, CharPropFlags::NOTIFY, vec![1]);
+        let properties = PeripheralProperties {
+            address: [1, 2, 3, 4, 5, 6].into(),
+            ..Default::default()
+        };
+        let mock_peripheral = adapter.add_device(properties, database);
+        let central = Recorder::new(io::sink()).record(adapter).await.unwrap();
+
+        // Two components looking up the same device separately.
+        let first = central.peripheral(&mock_peripheral.id()).await.unwrap();
+        let second = central.peripheral(&mock_peripheral.id()).await.unwrap();
+        first.connect().await.unwrap();
+        first.discover_services().await.unwrap();
+        let characteristic = first.characteristics().into_iter().next().unwrap();
+        let mut first_values = first
+            .notifications_for(&characteristic, true)
+            .await
+            .unwrap();
+        let mut second_values = second
+            .notifications_for(&characteristic, true)
+            .await
+            .unwrap();
+        assert_eq!(first_values.next().now_or_never(), None);
+        assert_eq!(second_values.next().now_or_never(), None);
+
+        drop(first_values);
+        // Give the task which would unsubscribe a chance to run.
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+        mock_peripheral.notify(&characteristic, vec![2]).unwrap();
+        assert_eq!(
+            tokio::time::timeout(Duration::from_secs(1), second_values.next()).await,
+            Ok(Some(vec![2]))
+        );
+    }
+}
@@ -0,0 +1,300 @@
+// btleplug Source Code File
+//
+// Copyright 2023 Nonpolynomial Labs LLC. All rights reserved.
+//
+// Licensed under the BSD 3-Clause license. See LICENSE file in the project root
+// for full license information.
+
+//! The `replay` module records sessions from any platform backend and plays them back later
+//! without a Bluetooth radio.
+//!
+//! A [`Recorder`] wraps a [`Central`](crate::api::Central) so that every
+//! [`CentralEvent`], [`PeripheralProperties`] snapshot, GATT operation
+//! and [`ValueNotification`] going through it is written to a file, one JSON record per line, with
+//! the time since recording started. The resulting [`Session`] can then be loaded and fed to a
+//! replay [`Adapter`], which implements the same API traits: events and notifications are emitted
+//! again on their original schedule (optionally sped up), and GATT operations are answered with the
+//! responses that were recorded for them.
+//!
+//! Peripheral IDs are stored in the format of the platform they were recorded on, so a session can
+//! only be replayed on the same platform.
+
+mod adapter;
+mod peripheral;
+mod recorder;
+
+pub use self::{
+    adapter::Adapter,
+    peripheral::Peripheral,
+    recorder::{Recorder, RecordingAdapter, RecordingPeripheral},
+};
+
+use crate::api::{
+    AttErrorCode, CentralEvent, Characteristic, Descriptor, PeripheralProperties, Service,
+    ValueNotification, WriteType,
+};
+use crate::platform::PeripheralId;
+use crate::{Error, Result};
+use serde_cr::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// A single entry of a recorded session.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(crate = "serde_cr")]
+pub struct Record {
+    /// The time since the start of the recording at which this entry was captured.
+    pub timestamp: Duration,
+    /// What happened.
+    pub entry: Entry,
+}
+
+/// Something which happened during a recorded session.
+///
+/// Events, property snapshots and notifications happen on their own and are replayed on the
+/// original schedule. The other entries are the results of operations called by the application,
+/// and are replayed as the response to the same operation.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(crate = "serde_cr")]
+pub enum Entry {
+    Event(CentralEvent),
+    Properties {
+        id: PeripheralId,
+        properties: Option<PeripheralProperties>,
+    },
+    Notification {
+        id: PeripheralId,
+        notification: ValueNotification,
+    },
+    Connect {
+        id: PeripheralId,
+        result: RecordedResult<()>,
+    },
+    Disconnect {
+        id: PeripheralId,
+        result: RecordedResult<()>,
+    },
+    DiscoverServices {
+        id: PeripheralId,
+        result: RecordedResult<BTreeSet<Service>>,
+    },
+    Pair {
+        id: PeripheralId,
+        result: RecordedResult<()>,
+    },
+    Unpair {
+        id: PeripheralId,
+        result: RecordedResult<()>,
+    },
+    IsPaired {
+        id: PeripheralId,
+        result: RecordedResult<bool>,
+    },
+    IsBonded {
+        id: PeripheralId,
+        result: RecordedResult<bool>,
+    },
+    Mtu {
+        id: PeripheralId,
+        result: RecordedResult<u16>,
+    },
+    Read {
+        id: PeripheralId,
+        characteristic: Characteristic,
+        result: RecordedResult<Vec<u8>>,
+    },
+    ReadAt {
+        id: PeripheralId,
+        characteristic: Characteristic,
+        offset: usize,
+        result: RecordedResult<Vec<u8>>,
+    },
+    Write {
+        id: PeripheralId,
+        characteristic: Characteristic,
+        data: Vec<u8>,
+        write_type: WriteType,
+        result: RecordedResult<()>,
+    },
+    Subscribe {
+        id: PeripheralId,
+        characteristic: Characteristic,
+        result: RecordedResult<()>,
+    },
+    Unsubscribe {
+        id: PeripheralId,
+        characteristic: Characteristic,
+        result: RecordedResult<()>,
+    },
+    ReadDescriptor {
+        id: PeripheralId,
+        descriptor: Descriptor,
+        result: RecordedResult<Vec<u8>>,
+    },
+    WriteDescriptor {
+        id: PeripheralId,
+        descriptor: Descriptor,
+        data: Vec<u8>,
+        result: RecordedResult<()>,
+    },
+}
+
+impl Entry {
+    /// Whether this entry happened on its own, rather than as the result of an operation called by
+    /// the application.
+    fn is_unsolicited(&self) -> bool {
+        matches!(
+            self,
+            Entry::Event(_) | Entry::Properties { .. } | Entry::Notification { .. }
+        )
+    }
+}
+
+/// The outcome of a recorded operation.
+pub type RecordedResult<T> = std::result::Result<T, RecordedError>;
+
+/// A serializable form of [`Error`], as returned by a recorded operation.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(crate = "serde_cr")]
+pub enum RecordedError {
+    PermissionDenied,
+    DeviceNotFound,
+    NotConnected,
+    InProgress,
+    AuthenticationFailed,
+    NotSupported(String),
+    TimedOut(Duration),
+    Att(AttErrorCode),
+    Other(String),
+}
+
+impl From<&Error> for RecordedError {
+    fn from(error: &Error) -> Self {
+        match error {
+            Error::PermissionDenied => RecordedError::PermissionDenied,
+            Error::DeviceNotFound => RecordedError::DeviceNotFound,
+            Error::NotConnected => RecordedError::NotConnected,
+            Error::InProgress => RecordedError::InProgress,
+            Error::AuthenticationFailed => RecordedError::AuthenticationFailed,
+            Error::NotSupported(message) => RecordedError::NotSupported(message.clone()),
+            Error::TimedOut(duration) => RecordedError::TimedOut(*duration),
+            Error::Att(code) => RecordedError::Att(*code),
+            error => RecordedError::Other(error.to_string()),
+        }
+    }
+}
+
+impl From<RecordedError> for Error {
+    fn from(error: RecordedError) -> Self {
+        match error {
+            RecordedError::PermissionDenied => Error::PermissionDenied,
+            RecordedError::DeviceNotFound => Error::DeviceNotFound,
+            RecordedError::NotConnected => Error::NotConnected,
+            RecordedError::InProgress => Error::InProgress,
+            RecordedError::AuthenticationFailed => Error::AuthenticationFailed,
+            RecordedError::NotSupported(message) => Error::NotSupported(message),
+            RecordedError::TimedOut(duration) => Error::TimedOut(duration),
+            RecordedError::Att(code) => Error::Att(code),
+            RecordedError::Other(message) => Error::Other(message.into()),
+        }
+    }
+}
+
+/// A recorded session, as written by a [`Recorder`].
+#[derive(Clone, Debug, Default)]
+pub struct Session {
+    records: Vec<Record>,
+}
+
+impl Session {
+    /// Creates a session from a list of records, which must be in chronological order.
+    pub fn new(records: Vec<Record>) -> Self {
+        Self { records }
+    }
+
+    /// Loads a session from a file written by a [`Recorder`].
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let file = File::open(path).map_err(|e| Error::Other(Box::new(e)))?;
+        Self::from_reader(file)
+    }
+
+    /// Reads a session in the format written by a [`Recorder`], one JSON record per line.
+    pub fn from_reader(reader: impl Read) -> Result<Self> {
+        let mut records = vec![];
+        for line in BufReader::new(reader).lines() {
+            let line = line.map_err(|e| Error::Other(Box::new(e)))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            records.push(serde_json::from_str(&line).map_err(|e| Error::Other(Box::new(e)))?);
+        }
+        Ok(Self { records })
+    }
+
+    /// The records of this session, in chronological order.
+    pub fn records(&self) -> &[Record] {
+        &self.records
+    }
+}
+
+/// How fast a replay [`Adapter`] plays back unsolicited entries of a session.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Timing {
+    /// Entries are played back with the same timing as they were recorded.
+    RealTime,
+    /// Entries are played back this many times faster than they were recorded. A factor which
+    /// isn't positive plays them back as fast as possible, like `Immediate`.
+    Accelerated(f64),
+    /// Entries are played back as fast as possible, in order.
+    Immediate,
+}
+
+impl Timing {
+    fn scale(&self, timestamp: Duration) -> Option<Duration> {
+        match *self {
+            Timing::RealTime => Some(timestamp),
+            // NaN isn't positive either.
+            Timing::Accelerated(factor) if factor > 0.0 => Some(
+                Duration::try_from_secs_f64(timestamp.as_secs_f64() / factor)
+                    .unwrap_or(Duration::MAX),
+            ),
+            Timing::Accelerated(_) | Timing::Immediate => None,
+        }
+    }
+}
+
+/// The recorded responses to operations which haven't been replayed yet. Each response is used at
+/// most once, in recorded order.
+#[derive(Debug, Default)]
+struct Responses {
+    entries: Mutex<Vec<Option<Entry>>>,
+}
+
+impl Responses {
+    fn new(session: &Session) -> Self {
+        Self {
+            entries: Mutex::new(
+                session
+                    .records
+                    .iter()
+                    .filter(|record| !record.entry.is_unsolicited())
+                    .map(|record| Some(record.entry.clone()))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Takes the first remaining response for which `matches` returns a result.
+    fn take<T>(&self, mut matches: impl FnMut(&Entry) -> Option<T>) -> Option<T> {
+        let mut entries = self.entries.lock().unwrap();
+        entries.iter_mut().find_map(|slot| {
+            let result = matches(slot.as_ref()?)?;
+            *slot = None;
+            Some(result)
+        })
+    }
+}
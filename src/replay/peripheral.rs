@@ -0,0 +1,340 @@
+// btleplug Source Code File
+//
+// Copyright 2023 Nonpolynomial Labs LLC. All rights reserved.
+//
+// Licensed under the BSD 3-Clause license. See LICENSE file in the project root
+// for full license information.
+
+use super::{Entry, RecordedResult, Responses};
+use crate::{
+    api::{
+        self, proximity::Proximity, BDAddr, Characteristic, Descriptor, PeripheralProperties,
+        Service, Timeouts, ValueNotification, WriteType,
+    },
+    common::{
+        adapter_manager::AdapterManager, subscriptions::Subscriptions,
+        util::notifications_stream_from_broadcast_receiver,
+    },
+    platform::PeripheralId,
+    Error, Result,
+};
+use async_trait::async_trait;
+use futures::stream::Stream;
+use std::{
+    collections::BTreeSet,
+    fmt::{self, Debug, Formatter},
+    pin::Pin,
+    sync::atomic::{AtomicBool, Ordering},
+    sync::{Arc, Mutex, Weak},
+};
+use tokio::sync::broadcast;
+
+/// Replay implementation of [api::Peripheral](crate::api::Peripheral).
+///
+/// Operations are answered with the next recorded response to the same operation on the same
+/// peripheral (and characteristic or descriptor, if any). Arguments such as the data written are
+/// not compared against the recording. Responses are immediate, so operations only time out where
+/// they did in the recording.
+#[derive(Clone)]
+pub struct Peripheral {
+    shared: Arc<Shared>,
+    timeout_overrides: Timeouts,
+}
+
+struct Shared {
+    adapter: Weak<AdapterManager<Peripheral>>,
+    id: PeripheralId,
+    responses: Arc<Responses>,
+    properties: Mutex<Option<PeripheralProperties>>,
+    services: Mutex<BTreeSet<Service>>,
+    connected: AtomicBool,
+    notifications_channel: broadcast::Sender<ValueNotification>,
+    notification_streams: Arc<Subscriptions>,
+}
+
+impl Peripheral {
+    pub(super) fn new(
+        id: PeripheralId,
+        responses: Arc<Responses>,
+        adapter: Weak<AdapterManager<Peripheral>>,
+    ) -> Self {
+        let (broadcast_sender, _) = broadcast::channel(16);
+        Peripheral {
+            shared: Arc::new(Shared {
+                adapter,
+                id,
+                responses,
+                properties: Mutex::new(None),
+                services: Mutex::new(BTreeSet::new()),
+                connected: AtomicBool::new(false),
+                notifications_channel: broadcast_sender,
+                notification_streams: Default::default(),
+            }),
+            timeout_overrides: Timeouts::default(),
+        }
+    }
+
+    pub(super) fn set_properties(&self, properties: Option<PeripheralProperties>) {
+        *self.shared.properties.lock().unwrap() = properties;
+    }
+
+    pub(super) fn set_connected(&self, connected: bool) {
+        self.shared.connected.store(connected, Ordering::Relaxed);
+    }
+
+    pub(super) fn notify(&self, notification: ValueNotification) {
+        // Note: we ignore send errors here which may happen while there are no receivers...
+        let _ = self.shared.notifications_channel.send(notification);
+    }
+
+    fn response<T>(
+        &self,
+        operation: &str,
+        mut matches: impl FnMut(&Entry) -> Option<RecordedResult<T>>,
+    ) -> Result<T> {
+        let id = &self.shared.id;
+        self.shared
+            .responses
+            .take(|entry| matches(entry))
+            .ok_or_else(|| {
+                Error::Other(format!("No recorded response to {} for {}", operation, id).into())
+            })?
+            .map_err(Error::from)
+    }
+}
+
+impl Debug for Peripheral {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("Peripheral")
+            .field("id", &self.shared.id)
+            .field("properties", &self.shared.properties.lock().unwrap())
+            .field("connected", &self.shared.connected.load(Ordering::Relaxed))
+            .finish()
+    }
+}
+
+#[async_trait]
+impl api::Peripheral for Peripheral {
+    fn id(&self) -> PeripheralId {
+        self.shared.id.clone()
+    }
+
+    fn address(&self) -> BDAddr {
+        self.shared
+            .properties
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|properties| properties.address)
+            .unwrap_or_default()
+    }
+
+    async fn properties(&self) -> Result<Option<PeripheralProperties>> {
+        Ok(self.shared.properties.lock().unwrap().clone())
+    }
+
+    fn proximity(&self) -> Option<Proximity> {
+        self.shared.adapter.upgrade()?.proximity(&self.id())
+    }
+
+    fn services(&self) -> BTreeSet<Service> {
+        self.shared.services.lock().unwrap().clone()
+    }
+
+    fn timeouts(&self) -> Timeouts {
+        let adapter_timeouts = match self.shared.adapter.upgrade() {
+            Some(manager) => manager.timeouts(),
+            None => Timeouts::default(),
+        };
+        adapter_timeouts.merge(self.timeout_overrides)
+    }
+
+    fn with_timeouts(&self, timeouts: Timeouts) -> Self {
+        Self {
+            shared: self.shared.clone(),
+            timeout_overrides: self.timeout_overrides.merge(timeouts),
+        }
+    }
+
+    async fn is_connected(&self) -> Result<bool> {
+        Ok(self.shared.connected.load(Ordering::Relaxed))
+    }
+
+    async fn connect(&self) -> Result<()> {
+        let id = &self.shared.id;
+        self.response("connect", |entry| match entry {
+            Entry::Connect { id: i, result } if i == id => Some(result.clone()),
+            _ => None,
+        })?;
+        self.set_connected(true);
+        Ok(())
+    }
+
+    async fn disconnect(&self) -> Result<()> {
+        let id = &self.shared.id;
+        self.response("disconnect", |entry| match entry {
+            Entry::Disconnect { id: i, result } if i == id => Some(result.clone()),
+            _ => None,
+        })?;
+        self.set_connected(false);
+        Ok(())
+    }
+
+    async fn discover_services(&self) -> Result<()> {
+        let id = &self.shared.id;
+        let services = self.response("discover_services", |entry| match entry {
+            Entry::DiscoverServices { id: i, result } if i == id => Some(result.clone()),
+            _ => None,
+        })?;
+        *self.shared.services.lock().unwrap() = services;
+        Ok(())
+    }
+
+    async fn pair(&self) -> Result<()> {
+        let id = &self.shared.id;
+        self.response("pair", |entry| match entry {
+            Entry::Pair { id: i, result } if i == id => Some(result.clone()),
+            _ => None,
+        })
+    }
+
+    async fn unpair(&self) -> Result<()> {
+        let id = &self.shared.id;
+        self.response("unpair", |entry| match entry {
+            Entry::Unpair { id: i, result } if i == id => Some(result.clone()),
+            _ => None,
+        })
+    }
+
+    async fn is_paired(&self) -> Result<bool> {
+        let id = &self.shared.id;
+        self.response("is_paired", |entry| match entry {
+            Entry::IsPaired { id: i, result } if i == id => Some(result.clone()),
+            _ => None,
+        })
+    }
+
+    async fn is_bonded(&self) -> Result<bool> {
+        let id = &self.shared.id;
+        self.response("is_bonded", |entry| match entry {
+            Entry::IsBonded { id: i, result } if i == id => Some(result.clone()),
+            _ => None,
+        })
+    }
+
+    async fn mtu(&self) -> Result<u16> {
+        let id = &self.shared.id;
+        self.response("mtu", |entry| match entry {
+            Entry::Mtu { id: i, result } if i == id => Some(result.clone()),
+            _ => None,
+        })
+    }
+
+    async fn write(
+        &self,
+        characteristic: &Characteristic,
+        _data: &[u8],
+        _write_type: WriteType,
+    ) -> Result<()> {
+        let id = &self.shared.id;
+        self.response("write", |entry| match entry {
+            Entry::Write {
+                id: i,
+                characteristic: c,
+                result,
+                ..
+            } if i == id && c == characteristic => Some(result.clone()),
+            _ => None,
+        })
+    }
+
+    async fn read(&self, characteristic: &Characteristic) -> Result<Vec<u8>> {
+        let id = &self.shared.id;
+        self.response("read", |entry| match entry {
+            Entry::Read {
+                id: i,
+                characteristic: c,
+                result,
+            } if i == id && c == characteristic => Some(result.clone()),
+            _ => None,
+        })
+    }
+
+    async fn read_at(&self, characteristic: &Characteristic, offset: usize) -> Result<Vec<u8>> {
+        let id = &self.shared.id;
+        self.response("read_at", |entry| match entry {
+            Entry::ReadAt {
+                id: i,
+                characteristic: c,
+                offset: o,
+                result,
+            } if i == id && c == characteristic && *o == offset => Some(result.clone()),
+            _ => None,
+        })
+    }
+
+    async fn subscribe(&self, characteristic: &Characteristic) -> Result<()> {
+        let id = &self.shared.id;
+        self.response("subscribe", |entry| match entry {
+            Entry::Subscribe {
+                id: i,
+                characteristic: c,
+                result,
+            } if i == id && c == characteristic => Some(result.clone()),
+            _ => None,
+        })
+    }
+
+    async fn unsubscribe(&self, characteristic: &Characteristic) -> Result<()> {
+        let id = &self.shared.id;
+        self.response("unsubscribe", |entry| match entry {
+            Entry::Unsubscribe {
+                id: i,
+                characteristic: c,
+                result,
+            } if i == id && c == characteristic => Some(result.clone()),
+            _ => None,
+        })
+    }
+
+    async fn notifications(&self) -> Result<Pin<Box<dyn Stream<Item = ValueNotification> + Send>>> {
+        let receiver = self.shared.notifications_channel.subscribe();
+        Ok(notifications_stream_from_broadcast_receiver(receiver))
+    }
+
+    async fn notifications_for(
+        &self,
+        characteristic: &Characteristic,
+        subscribe: bool,
+    ) -> Result<Pin<Box<dyn Stream<Item = Vec<u8>> + Send>>> {
+        self.shared
+            .notification_streams
+            .notifications_for(self, characteristic, subscribe)
+            .await
+    }
+
+    async fn write_descriptor(&self, descriptor: &Descriptor, _data: &[u8]) -> Result<()> {
+        let id = &self.shared.id;
+        self.response("write_descriptor", |entry| match entry {
+            Entry::WriteDescriptor {
+                id: i,
+                descriptor: d,
+                result,
+                ..
+            } if i == id && d == descriptor => Some(result.clone()),
+            _ => None,
+        })
+    }
+
+    async fn read_descriptor(&self, descriptor: &Descriptor) -> Result<Vec<u8>> {
+        let id = &self.shared.id;
+        self.response("read_descriptor", |entry| match entry {
+            Entry::ReadDescriptor {
+                id: i,
+                descriptor: d,
+                result,
+            } if i == id && d == descriptor => Some(result.clone()),
+            _ => None,
+        })
+    }
+}
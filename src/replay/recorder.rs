@@ -0,0 +1,500 @@
+// btleplug Source Code File
+//
+// Copyright 2023 Nonpolynomial Labs LLC. All rights reserved.
+//
+// Licensed under the BSD 3-Clause license. See LICENSE file in the project root
+// for full license information.
+
+use super::{Entry, Record, RecordedError, RecordedResult};
+use crate::{
+    api::{
+        proximity::{Proximity, ProximityConfig},
+        BDAddr, Central, CentralEvent, CentralState, Characteristic, Descriptor, DeviceExpiry,
+        Peripheral, PeripheralProperties, Presence, ScanFilter, Service, Timeouts,
+        ValueNotification, WriteType,
+    },
+    common::subscriptions::{PeripheralSubscriptions, Subscriptions},
+    platform::PeripheralId,
+    Error, Result,
+};
+use async_trait::async_trait;
+use futures::stream::{Stream, StreamExt};
+use log::warn;
+use std::{
+    collections::{BTreeSet, HashSet},
+    fmt::{self, Debug, Formatter},
+    fs::File,
+    io::{LineWriter, Write},
+    path::Path,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+/// Records a session to a writer, one JSON [`Record`] per line, in the format read by
+/// [`Session`](super::Session).
+#[derive(Clone)]
+pub struct Recorder {
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    start: Instant,
+    writer: Mutex<Box<dyn Write + Send>>,
+    watched_peripherals: Mutex<HashSet<PeripheralId>>,
+    /// Shared by all the wrapped handles for each peripheral.
+    notification_streams: PeripheralSubscriptions,
+}
+
+impl Recorder {
+    /// Creates a recorder writing to the given writer. The timestamps of all records are relative
+    /// to when this is called.
+    pub fn new(writer: impl Write + Send + 'static) -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                start: Instant::now(),
+                writer: Mutex::new(Box::new(writer)),
+                watched_peripherals: Mutex::new(HashSet::new()),
+                notification_streams: Default::default(),
+            }),
+        }
+    }
+
+    /// Creates a recorder writing to a new file at the given path, replacing any existing file.
+    pub fn create(path: impl AsRef<Path>) -> Result<Self> {
+        let file = File::create(path).map_err(|e| Error::Other(Box::new(e)))?;
+        Ok(Self::new(LineWriter::new(file)))
+    }
+
+    /// Starts recording a central. All events emitted by the central from now on are recorded,
+    /// whether or not the application listens to them, along with the properties of the peripheral
+    /// they concern. Operations are recorded when they are called through the returned adapter.
+    pub async fn record<C>(&self, central: C) -> Result<RecordingAdapter<C>>
+    where
+        C: Central + 'static,
+        C::Peripheral: 'static,
+    {
+        let mut events = central.events().await?;
+        let recorder = self.clone();
+        let events_central = central.clone();
+        tokio::spawn(async move {
+            while let Some(event) = events.next().await {
+                // Record the properties before the event itself, so that they are already up to
+                // date when the event is replayed.
+                if let CentralEvent::DeviceDiscovered(id) | CentralEvent::DeviceUpdated(id) = &event
+                {
+                    if let Ok(peripheral) = events_central.peripheral(id).await {
+                        if let Ok(properties) = peripheral.properties().await {
+                            recorder.write(Entry::Properties {
+                                id: id.clone(),
+                                properties,
+                            });
+                        }
+                    }
+                }
+                recorder.write(Entry::Event(event));
+            }
+        });
+        Ok(RecordingAdapter {
+            central,
+            recorder: self.clone(),
+        })
+    }
+
+    async fn wrap<P: Peripheral + 'static>(&self, peripheral: P) -> RecordingPeripheral<P> {
+        let newly_watched = self
+            .shared
+            .watched_peripherals
+            .lock()
+            .unwrap()
+            .insert(peripheral.id());
+        if newly_watched {
+            match peripheral.notifications().await {
+                Ok(mut notifications) => {
+                    let recorder = self.clone();
+                    let id = peripheral.id();
+                    tokio::spawn(async move {
+                        while let Some(notification) = notifications.next().await {
+                            recorder.write(Entry::Notification {
+                                id: id.clone(),
+                                notification,
+                            });
+                        }
+                    });
+                }
+                Err(e) => warn!("Not recording notifications for {}: {}", peripheral.id(), e),
+            }
+        }
+        RecordingPeripheral {
+            notification_streams: self.shared.notification_streams.get(&peripheral.id()),
+            peripheral,
+            recorder: self.clone(),
+        }
+    }
+
+    fn write(&self, entry: Entry) {
+        let record = Record {
+            timestamp: self.shared.start.elapsed(),
+            entry,
+        };
+        let mut writer = self.shared.writer.lock().unwrap();
+        let result = serde_json::to_writer(&mut *writer, &record)
+            .map_err(std::io::Error::from)
+            .and_then(|()| writer.write_all(b"\n"));
+        if let Err(e) = result {
+            warn!("Failed to write record: {}", e);
+        }
+    }
+
+    fn write_result<T, U>(
+        &self,
+        result: &Result<T>,
+        recorded: impl FnOnce(&T) -> U,
+        entry: impl FnOnce(RecordedResult<U>) -> Entry,
+    ) {
+        let result = match result {
+            Ok(value) => Ok(recorded(value)),
+            Err(e) => Err(RecordedError::from(e)),
+        };
+        self.write(entry(result));
+    }
+}
+
+impl Debug for Recorder {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("Recorder")
+            .field("start", &self.shared.start)
+            .finish()
+    }
+}
+
+/// A [`Central`] which records everything going through it. Created by [`Recorder::record`].
+#[derive(Clone, Debug)]
+pub struct RecordingAdapter<C> {
+    central: C,
+    recorder: Recorder,
+}
+
+#[async_trait]
+impl<C> Central for RecordingAdapter<C>
+where
+    C: Central + 'static,
+    C::Peripheral: 'static,
+{
+    type Peripheral = RecordingPeripheral<C::Peripheral>;
+
+    async fn events(&self) -> Result<Pin<Box<dyn Stream<Item = CentralEvent> + Send>>> {
+        self.central.events().await
+    }
+
+    async fn start_scan(&self, filter: ScanFilter) -> Result<()> {
+        self.central.start_scan(filter).await
+    }
+
+    async fn stop_scan(&self) -> Result<()> {
+        self.central.stop_scan().await
+    }
+
+    async fn peripherals(&self) -> Result<Vec<Self::Peripheral>> {
+        let mut peripherals = vec![];
+        for peripheral in self.central.peripherals().await? {
+            peripherals.push(self.recorder.wrap(peripheral).await);
+        }
+        Ok(peripherals)
+    }
+
+    async fn peripheral(&self, id: &PeripheralId) -> Result<Self::Peripheral> {
+        let peripheral = self.central.peripheral(id).await?;
+        Ok(self.recorder.wrap(peripheral).await)
+    }
+
+    async fn add_peripheral(&self, address: &PeripheralId) -> Result<Self::Peripheral> {
+        let peripheral = self.central.add_peripheral(address).await?;
+        Ok(self.recorder.wrap(peripheral).await)
+    }
+
+    async fn adapter_info(&self) -> Result<String> {
+        self.central.adapter_info().await
+    }
+
+    async fn adapter_state(&self) -> Result<CentralState> {
+        self.central.adapter_state().await
+    }
+
+    async fn set_powered(&self, powered: bool) -> Result<()> {
+        self.central.set_powered(powered).await
+    }
+
+    fn timeouts(&self) -> Timeouts {
+        self.central.timeouts()
+    }
+
+    fn set_timeouts(&self, timeouts: Timeouts) {
+        self.central.set_timeouts(timeouts);
+    }
+
+    fn presence(&self, id: &PeripheralId) -> Option<Presence> {
+        self.central.presence(id)
+    }
+
+    fn set_device_expiry(&self, expiry: Option<DeviceExpiry>) {
+        self.central.set_device_expiry(expiry);
+    }
+
+    fn set_proximity_config(&self, config: ProximityConfig) {
+        self.central.set_proximity_config(config);
+    }
+}
+
+/// A [`Peripheral`] which records the operations called on it, as well as all of its
+/// notifications.
+///
+/// The wrapped peripheral must be `'static` for this to implement [`Peripheral`], as the streams
+/// returned by [`notifications_for`](Peripheral::notifications_for) keep a clone of this to
+/// unsubscribe through once they are dropped.
+#[derive(Clone, Debug)]
+pub struct RecordingPeripheral<P> {
+    peripheral: P,
+    recorder: Recorder,
+    notification_streams: Arc<Subscriptions>,
+}
+
+#[async_trait]
+impl<P: Peripheral + 'static> Peripheral for RecordingPeripheral<P> {
+    fn id(&self) -> PeripheralId {
+        self.peripheral.id()
+    }
+
+    fn address(&self) -> BDAddr {
+        self.peripheral.address()
+    }
+
+    async fn properties(&self) -> Result<Option<PeripheralProperties>> {
+        let properties = self.peripheral.properties().await?;
+        self.recorder.write(Entry::Properties {
+            id: self.id(),
+            properties: properties.clone(),
+        });
+        Ok(properties)
+    }
+
+    fn proximity(&self) -> Option<Proximity> {
+        self.peripheral.proximity()
+    }
+
+    fn services(&self) -> BTreeSet<Service> {
+        self.peripheral.services()
+    }
+
+    fn timeouts(&self) -> Timeouts {
+        self.peripheral.timeouts()
+    }
+
+    fn with_timeouts(&self, timeouts: Timeouts) -> Self {
+        Self {
+            peripheral: self.peripheral.with_timeouts(timeouts),
+            recorder: self.recorder.clone(),
+            notification_streams: self.notification_streams.clone(),
+        }
+    }
+
+    async fn is_connected(&self) -> Result<bool> {
+        self.peripheral.is_connected().await
+    }
+
+    async fn connect(&self) -> Result<()> {
+        let result = self.peripheral.connect().await;
+        let id = self.id();
+        self.recorder
+            .write_result(&result, |_| (), |result| Entry::Connect { id, result });
+        result
+    }
+
+    async fn disconnect(&self) -> Result<()> {
+        let result = self.peripheral.disconnect().await;
+        let id = self.id();
+        self.recorder
+            .write_result(&result, |_| (), |result| Entry::Disconnect { id, result });
+        result
+    }
+
+    async fn discover_services(&self) -> Result<()> {
+        let result = self.peripheral.discover_services().await;
+        let id = self.id();
+        let services = self.peripheral.services();
+        self.recorder.write_result(
+            &result,
+            |_| services,
+            |result| Entry::DiscoverServices { id, result },
+        );
+        result
+    }
+
+    async fn pair(&self) -> Result<()> {
+        let result = self.peripheral.pair().await;
+        let id = self.id();
+        self.recorder
+            .write_result(&result, |_| (), |result| Entry::Pair { id, result });
+        result
+    }
+
+    async fn unpair(&self) -> Result<()> {
+        let result = self.peripheral.unpair().await;
+        let id = self.id();
+        self.recorder
+            .write_result(&result, |_| (), |result| Entry::Unpair { id, result });
+        result
+    }
+
+    async fn is_paired(&self) -> Result<bool> {
+        let result = self.peripheral.is_paired().await;
+        let id = self.id();
+        self.recorder.write_result(
+            &result,
+            |paired| *paired,
+            |result| Entry::IsPaired { id, result },
+        );
+        result
+    }
+
+    async fn is_bonded(&self) -> Result<bool> {
+        let result = self.peripheral.is_bonded().await;
+        let id = self.id();
+        self.recorder.write_result(
+            &result,
+            |bonded| *bonded,
+            |result| Entry::IsBonded { id, result },
+        );
+        result
+    }
+
+    async fn mtu(&self) -> Result<u16> {
+        let result = self.peripheral.mtu().await;
+        self.recorder.write_result(
+            &result,
+            |mtu| *mtu,
+            |result| Entry::Mtu {
+                id: self.id(),
+                result,
+            },
+        );
+        result
+    }
+
+    async fn write(
+        &self,
+        characteristic: &Characteristic,
+        data: &[u8],
+        write_type: WriteType,
+    ) -> Result<()> {
+        let result = self
+            .peripheral
+            .write(characteristic, data, write_type)
+            .await;
+        self.recorder.write_result(
+            &result,
+            |_| (),
+            |result| Entry::Write {
+                id: self.id(),
+                characteristic: characteristic.clone(),
+                data: data.to_vec(),
+                write_type,
+                result,
+            },
+        );
+        result
+    }
+
+    async fn read(&self, characteristic: &Characteristic) -> Result<Vec<u8>> {
+        let result = self.peripheral.read(characteristic).await;
+        self.recorder
+            .write_result(&result, Vec::clone, |result| Entry::Read {
+                id: self.id(),
+                characteristic: characteristic.clone(),
+                result,
+            });
+        result
+    }
+
+    async fn read_at(&self, characteristic: &Characteristic, offset: usize) -> Result<Vec<u8>> {
+        let result = self.peripheral.read_at(characteristic, offset).await;
+        self.recorder
+            .write_result(&result, Vec::clone, |result| Entry::ReadAt {
+                id: self.id(),
+                characteristic: characteristic.clone(),
+                offset,
+                result,
+            });
+        result
+    }
+
+    async fn subscribe(&self, characteristic: &Characteristic) -> Result<()> {
+        let result = self.peripheral.subscribe(characteristic).await;
+        self.recorder.write_result(
+            &result,
+            |_| (),
+            |result| Entry::Subscribe {
+                id: self.id(),
+                characteristic: characteristic.clone(),
+                result,
+            },
+        );
+        result
+    }
+
+    async fn unsubscribe(&self, characteristic: &Characteristic) -> Result<()> {
+        let result = self.peripheral.unsubscribe(characteristic).await;
+        self.recorder.write_result(
+            &result,
+            |_| (),
+            |result| Entry::Unsubscribe {
+                id: self.id(),
+                characteristic: characteristic.clone(),
+                result,
+            },
+        );
+        result
+    }
+
+    async fn notifications(&self) -> Result<Pin<Box<dyn Stream<Item = ValueNotification> + Send>>> {
+        self.peripheral.notifications().await
+    }
+
+    async fn notifications_for(
+        &self,
+        characteristic: &Characteristic,
+        subscribe: bool,
+    ) -> Result<Pin<Box<dyn Stream<Item = Vec<u8>> + Send>>> {
+        // Goes through this peripheral rather than the wrapped one, so that the subscription
+        // changes are recorded.
+        self.notification_streams
+            .notifications_for(self, characteristic, subscribe)
+            .await
+    }
+
+    async fn write_descriptor(&self, descriptor: &Descriptor, data: &[u8]) -> Result<()> {
+        let result = self.peripheral.write_descriptor(descriptor, data).await;
+        self.recorder.write_result(
+            &result,
+            |_| (),
+            |result| Entry::WriteDescriptor {
+                id: self.id(),
+                descriptor: descriptor.clone(),
+                data: data.to_vec(),
+                result,
+            },
+        );
+        result
+    }
+
+    async fn read_descriptor(&self, descriptor: &Descriptor) -> Result<Vec<u8>> {
+        let result = self.peripheral.read_descriptor(descriptor).await;
+        self.recorder
+            .write_result(&result, Vec::clone, |result| Entry::ReadDescriptor {
+                id: self.id(),
+                descriptor: descriptor.clone(),
+                result,
+            });
+        result
+    }
+}
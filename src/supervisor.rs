@@ -0,0 +1,389 @@
+// btleplug Source Code File
+//
+// Copyright 2023 Nonpolynomial Labs LLC. All rights reserved.
+//
+// Licensed under the BSD 3-Clause license. See LICENSE file in the project root
+// for full license information.
+
+//! Keeps a connection to a peripheral alive.
+//!
+//! A [`ConnectionSupervisor`] connects to a peripheral, discovers its services and watches the
+//! adapter for [`CentralEvent::DeviceDisconnected`]. Whenever the connection drops it reconnects
+//! according to a [`ReconnectPolicy`], rediscovers the services and subscribes again to the
+//! characteristics which were subscribed to through the supervisor. The application follows along
+//! through a stream of [`ConnectionState`]s:
+//!
+//! ```no_run
+//! use btleplug::api::{Central, Manager as _};
+//! use btleplug::platform::Manager;
+//! use btleplug::supervisor::{ConnectionState, ConnectionSupervisor, ReconnectPolicy};
+//! use futures::stream::StreamExt;
+//! # use std::error::Error;
+//!
+//! # async fn example() -> Result<(), Box<dyn Error>> {
+//! let manager = Manager::new().await?;
+//! let central = manager.adapters().await?.into_iter().next().unwrap();
+//! let peripheral = central.peripherals().await?.into_iter().next().unwrap();
+//!
+//! let supervisor =
+//!     ConnectionSupervisor::start(&central, peripheral, ReconnectPolicy::default()).await?;
+//! let mut states = supervisor.states();
+//! while let Some(state) = states.next().await {
+//!     if state == ConnectionState::Connected {
+//!         // Services are discovered and subscriptions are restored.
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::api::{Central, CentralEvent, Characteristic, Peripheral};
+use crate::{Error, Result};
+use futures::stream::{Stream, StreamExt};
+use log::debug;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "serde")]
+use serde_cr as serde;
+use std::collections::BTreeSet;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio_stream::wrappers::WatchStream;
+
+/// How a [`ConnectionSupervisor`] goes about reconnecting.
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_cr")
+)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReconnectPolicy {
+    /// How long to wait after the first failed attempt. The first attempt after the connection
+    /// drops is made straight away.
+    pub initial_delay: Duration,
+    /// The longest to wait between two attempts.
+    pub max_delay: Duration,
+    /// What the delay is multiplied by after each failed attempt. It must be finite and not
+    /// negative.
+    pub backoff_factor: f64,
+    /// How many attempts in a row may fail before the supervisor gives up, or `None` to keep
+    /// trying forever.
+    pub max_attempts: Option<u32>,
+    /// Whether to subscribe again to the characteristics subscribed to through the supervisor
+    /// after reconnecting.
+    pub resubscribe: bool,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(30),
+            backoff_factor: 2.0,
+            max_attempts: None,
+            resubscribe: true,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    fn validate(&self) -> Result<()> {
+        if !self.backoff_factor.is_finite() || self.backoff_factor < 0.0 {
+            return Err(Error::Other(
+                format!("Invalid backoff factor {}", self.backoff_factor).into(),
+            ));
+        }
+        Ok(())
+    }
+
+    fn next_delay(&self, delay: Duration) -> Duration {
+        // A delay too long to represent is as good as the longest one.
+        Duration::try_from_secs_f64(delay.as_secs_f64() * self.backoff_factor)
+            .map_or(self.max_delay, |delay| delay.min(self.max_delay))
+    }
+}
+
+/// The state of a supervised connection.
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_cr")
+)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ConnectionState {
+    /// Connecting and restoring the session. `attempt` counts the attempts since the connection
+    /// was last up, starting at 1.
+    Connecting { attempt: u32 },
+    /// Connected, with the services discovered and the subscriptions restored.
+    Connected,
+    /// An attempt failed, and the next one is made after `retry_in`.
+    Disconnected { retry_in: Duration },
+    /// The supervisor gave up after [`ReconnectPolicy::max_attempts`] failed attempts, or the
+    /// adapter's event stream ended.
+    Stopped,
+}
+
+/// Reconnects to a peripheral whenever its connection drops. See the [module](self) documentation.
+///
+/// Dropping the supervisor stops it, without disconnecting the peripheral.
+#[derive(Debug)]
+pub struct ConnectionSupervisor<P> {
+    peripheral: P,
+    subscriptions: Arc<Mutex<BTreeSet<Characteristic>>>,
+    state: watch::Receiver<ConnectionState>,
+    task: JoinHandle<()>,
+}
+
+impl<P: Peripheral + 'static> ConnectionSupervisor<P> {
+    /// Starts supervising `peripheral`, which must belong to `central`. The supervisor connects
+    /// straight away, unless the peripheral is already connected. Fails if the policy's backoff
+    /// factor is negative or not finite.
+    pub async fn start<C>(central: &C, peripheral: P, policy: ReconnectPolicy) -> Result<Self>
+    where
+        C: Central<Peripheral = P>,
+    {
+        policy.validate()?;
+        // Subscribe to events before connecting, so that no disconnection can be missed.
+        let events = central.events().await?;
+        let subscriptions = Arc::new(Mutex::new(BTreeSet::new()));
+        let (sender, state) = watch::channel(ConnectionState::Connecting { attempt: 1 });
+        let task = tokio::spawn(supervise(
+            peripheral.clone(),
+            events,
+            policy,
+            subscriptions.clone(),
+            sender,
+        ));
+        Ok(Self {
+            peripheral,
+            subscriptions,
+            state,
+            task,
+        })
+    }
+
+    /// Returns the supervised peripheral.
+    pub fn peripheral(&self) -> &P {
+        &self.peripheral
+    }
+
+    /// Returns the current state of the connection.
+    pub fn state(&self) -> ConnectionState {
+        *self.state.borrow()
+    }
+
+    /// Returns a stream of the connection state, starting with the current one. States which are
+    /// replaced before the stream is polled again are skipped.
+    pub fn states(&self) -> Pin<Box<dyn Stream<Item = ConnectionState> + Send>> {
+        Box::pin(WatchStream::new(self.state.clone()))
+    }
+
+    /// Subscribes to the characteristic, and again after every reconnection if the policy says so.
+    pub async fn subscribe(&self, characteristic: &Characteristic) -> Result<()> {
+        self.peripheral.subscribe(characteristic).await?;
+        self.subscriptions
+            .lock()
+            .unwrap()
+            .insert(characteristic.clone());
+        Ok(())
+    }
+
+    /// Unsubscribes from the characteristic, which is then no longer subscribed to after
+    /// reconnecting.
+    pub async fn unsubscribe(&self, characteristic: &Characteristic) -> Result<()> {
+        self.subscriptions.lock().unwrap().remove(characteristic);
+        self.peripheral.unsubscribe(characteristic).await
+    }
+}
+
+impl<P> Drop for ConnectionSupervisor<P> {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn supervise<P: Peripheral>(
+    peripheral: P,
+    mut events: Pin<Box<dyn Stream<Item = CentralEvent> + Send>>,
+    policy: ReconnectPolicy,
+    subscriptions: Arc<Mutex<BTreeSet<Characteristic>>>,
+    state: watch::Sender<ConnectionState>,
+) {
+    let id = peripheral.id();
+    loop {
+        let mut attempt = 1;
+        let mut delay = policy.initial_delay;
+        loop {
+            let _ = state.send(ConnectionState::Connecting { attempt });
+            match restore(&peripheral, &policy, &subscriptions).await {
+                Ok(()) => break,
+                Err(e) => debug!("Attempt {} to reconnect to {:?} failed: {}", attempt, id, e),
+            }
+            if policy.max_attempts.is_some_and(|max| attempt >= max) {
+                let _ = state.send(ConnectionState::Stopped);
+                return;
+            }
+            let _ = state.send(ConnectionState::Disconnected { retry_in: delay });
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+            delay = policy.next_delay(delay);
+        }
+        let _ = state.send(ConnectionState::Connected);
+
+        loop {
+            match events.next().await {
+                Some(CentralEvent::DeviceDisconnected(disconnected)) if disconnected == id => break,
+                Some(_) => {}
+                None => {
+                    let _ = state.send(ConnectionState::Stopped);
+                    return;
+                }
+            }
+        }
+    }
+}
+
+async fn restore<P: Peripheral>(
+    peripheral: &P,
+    policy: &ReconnectPolicy,
+    subscriptions: &Mutex<BTreeSet<Characteristic>>,
+) -> Result<()> {
+    if !peripheral.is_connected().await? {
+        peripheral.connect().await?;
+    }
+    peripheral.discover_services().await?;
+    if policy.resubscribe {
+        let characteristics = subscriptions.lock().unwrap().clone();
+        for characteristic in &characteristics {
+            peripheral.subscribe(characteristic).await?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{bleuuid::uuid_from_u16, CharPropFlags, PeripheralProperties};
+    use crate::mock::{Adapter, GattDatabase};
+
+    const SERVICE: uuid::Uuid = uuid_from_u16(0x180F);
+    const CHARACTERISTIC: uuid::Uuid = uuid_from_u16(0x2A19);
+
+    fn device(adapter: &Adapter) -> crate::mock::Peripheral {
+        let mut database = GattDatabase::new();
+        let service = database.add_service(SERVICE, true);
+        database.add_characteristic(service, CHARACTERISTIC, CharPropFlags::NOTIFY, vec![50]);
+        let properties = PeripheralProperties {
+            address: [1, 2, 3, 4, 5, 6].into(),
+            ..Default::default()
+        };
+        adapter.add_device(properties, database)
+    }
+
+    fn policy() -> ReconnectPolicy {
+        ReconnectPolicy {
+            initial_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(4),
+            ..Default::default()
+        }
+    }
+
+    async fn wait_for<P: Peripheral + 'static>(
+        supervisor: &ConnectionSupervisor<P>,
+        expected: impl Fn(ConnectionState) -> bool,
+    ) {
+        let mut states = supervisor.states();
+        while let Some(state) = states.next().await {
+            if expected(state) {
+                return;
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn reconnects_and_resubscribes() {
+        let adapter = Adapter::new("hci0");
+        let peripheral = device(&adapter);
+        let supervisor = ConnectionSupervisor::start(&adapter, peripheral.clone(), policy())
+            .await
+            .unwrap();
+        wait_for(&supervisor, |state| state == ConnectionState::Connected).await;
+        let characteristic = peripheral.characteristics().into_iter().next().unwrap();
+        supervisor.subscribe(&characteristic).await.unwrap();
+
+        peripheral.set_connectable(false);
+        peripheral.simulate_disconnect();
+        wait_for(&supervisor, |state| {
+            matches!(state, ConnectionState::Disconnected { .. })
+        })
+        .await;
+        peripheral.set_connectable(true);
+        wait_for(&supervisor, |state| state == ConnectionState::Connected).await;
+
+        let mut notifications = peripheral.notifications().await.unwrap();
+        peripheral.notify(&characteristic, vec![49]).unwrap();
+        assert_eq!(notifications.next().await.unwrap().value, vec![49]);
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_attempts() {
+        let adapter = Adapter::new("hci0");
+        let peripheral = device(&adapter);
+        peripheral.set_connectable(false);
+        let policy = ReconnectPolicy {
+            max_attempts: Some(3),
+            ..policy()
+        };
+        let supervisor = ConnectionSupervisor::start(&adapter, peripheral, policy)
+            .await
+            .unwrap();
+        wait_for(&supervisor, |state| state == ConnectionState::Stopped).await;
+        assert_eq!(supervisor.state(), ConnectionState::Stopped);
+    }
+
+    #[test]
+    fn backoff() {
+        let policy = ReconnectPolicy {
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(30),
+            ..Default::default()
+        };
+        assert_eq!(
+            policy.next_delay(Duration::from_secs(1)),
+            Duration::from_secs(2)
+        );
+        assert_eq!(
+            policy.next_delay(Duration::from_secs(20)),
+            Duration::from_secs(30)
+        );
+        let policy = ReconnectPolicy {
+            backoff_factor: f64::MAX,
+            ..policy
+        };
+        assert_eq!(
+            policy.next_delay(Duration::from_secs(20)),
+            Duration::from_secs(30)
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_backoff_factors() {
+        let adapter = Adapter::new("hci0");
+        let peripheral = device(&adapter);
+        for backoff_factor in [-1.0, f64::NAN, f64::INFINITY] {
+            let policy = ReconnectPolicy {
+                backoff_factor,
+                ..policy()
+            };
+            assert!(
+                ConnectionSupervisor::start(&adapter, peripheral.clone(), policy)
+                    .await
+                    .is_err()
+            );
+        }
+    }
+}
@@ -13,8 +13,11 @@
 
 use super::{ble::watcher::BLEWatcher, peripheral::Peripheral, peripheral::PeripheralId};
 use crate::{
-    api::{BDAddr, Central, CentralEvent, ScanFilter},
-    common::adapter_manager::AdapterManager,
+    api::{
+        proximity::ProximityConfig, BDAddr, Central, CentralEvent, CentralState, DeviceExpiry,
+        Presence, ScanFilter, Timeouts,
+    },
+    common::{adapter_manager::AdapterManager, timeouts::SharedTimeouts},
     Error, Result,
 };
 use async_trait::async_trait;
@@ -23,19 +26,58 @@ use std::convert::TryInto;
 use std::fmt::{self, Debug, Formatter};
 use std::pin::Pin;
 use std::sync::{Arc, Mutex};
+use windows::{
+    Devices::Radios::{Radio, RadioAccessStatus, RadioState},
+    Foundation::{EventRegistrationToken, TypedEventHandler},
+};
 
 /// Implementation of [api::Central](crate::api::Central).
 #[derive(Clone)]
 pub struct Adapter {
     watcher: Arc<Mutex<BLEWatcher>>,
     manager: Arc<AdapterManager<Peripheral>>,
+    radio: Arc<RadioHandle>,
+}
+
+/// The Bluetooth radio of an adapter, with a handler reporting its state changes which is removed
+/// when the last clone of the adapter is dropped.
+struct RadioHandle {
+    radio: Radio,
+    state_token: EventRegistrationToken,
+}
+
+impl Drop for RadioHandle {
+    fn drop(&mut self) {
+        let _ = self.radio.RemoveStateChanged(self.state_token);
+    }
 }
 
 impl Adapter {
-    pub(crate) fn new() -> Self {
+    pub(crate) fn new(radio: Radio, timeouts: SharedTimeouts) -> Result<Self> {
         let watcher = Arc::new(Mutex::new(BLEWatcher::new()));
-        let manager = Arc::new(AdapterManager::default());
-        Adapter { watcher, manager }
+        let manager = Arc::new(AdapterManager::new(timeouts));
+        let weak_manager = Arc::downgrade(&manager);
+        let state_handler = TypedEventHandler::new(move |sender: &Option<Radio>, _| {
+            if let (Some(sender), Some(manager)) = (sender, weak_manager.upgrade()) {
+                manager.emit(CentralEvent::StateUpdate(central_state(sender.State()?)));
+            }
+            Ok(())
+        });
+        let state_token = radio.StateChanged(&state_handler)?;
+        Ok(Adapter {
+            watcher,
+            manager,
+            radio: Arc::new(RadioHandle { radio, state_token }),
+        })
+    }
+}
+
+fn central_state(state: RadioState) -> CentralState {
+    match state {
+        RadioState::On => CentralState::PoweredOn,
+        RadioState::Off => CentralState::PoweredOff,
+        // Disabled means turned off by airplane mode or a hardware switch, which we can't undo.
+        _ => CentralState::Unavailable,
     }
 }
 
@@ -58,6 +100,7 @@ impl Central for Adapter {
     async fn start_scan(&self, filter: ScanFilter) -> Result<()> {
         let watcher = self.watcher.lock().unwrap();
         let manager = self.manager.clone();
+        manager.start_scan(filter.clone());
         watcher.start(
             filter,
             Box::new(move |args| {
@@ -79,6 +122,7 @@ impl Central for Adapter {
     async fn stop_scan(&self) -> Result<()> {
         let watcher = self.watcher.lock().unwrap();
         watcher.stop().unwrap();
+        self.manager.stop_scan();
         Ok(())
     }
 
@@ -100,4 +144,45 @@ impl Central for Adapter {
         // TODO: Get information about the adapter.
         Ok("WinRT".to_string())
     }
+
+    async fn adapter_state(&self) -> Result<CentralState> {
+        Ok(central_state(self.radio.radio.State()?))
+    }
+
+    async fn set_powered(&self, powered: bool) -> Result<()> {
+        let state = if powered {
+            RadioState::On
+        } else {
+            RadioState::Off
+        };
+        match self.radio.radio.SetStateAsync(state)?.await? {
+            RadioAccessStatus::Allowed => Ok(()),
+            RadioAccessStatus::DeniedByUser | RadioAccessStatus::DeniedBySystem => {
+                Err(Error::PermissionDenied)
+            }
+            status => Err(Error::Other(
+                format!("Failed to change the radio state: {:?}", status).into(),
+            )),
+        }
+    }
+
+    fn timeouts(&self) -> Timeouts {
+        self.manager.timeouts()
+    }
+
+    fn set_timeouts(&self, timeouts: Timeouts) {
+        self.manager.set_timeouts(timeouts);
+    }
+
+    fn presence(&self, id: &PeripheralId) -> Option<Presence> {
+        self.manager.presence(id)
+    }
+
+    fn set_device_expiry(&self, expiry: Option<DeviceExpiry>) {
+        self.manager.set_device_expiry(expiry);
+    }
+
+    fn set_proximity_config(&self, config: ProximityConfig) {
+        self.manager.set_proximity_config(config);
+    }
 }
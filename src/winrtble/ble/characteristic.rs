@@ -47,14 +47,14 @@ impl Into<GattWriteOption> for WriteType {
 #[derive(Debug)]
 pub struct BLECharacteristic {
     characteristic: GattCharacteristic,
-    pub descriptors: HashMap<Uuid, BLEDescriptor>,
+    pub descriptors: HashMap<u16, BLEDescriptor>,
     notify_token: Option<EventRegistrationToken>,
 }
 
 impl BLECharacteristic {
     pub fn new(
         characteristic: GattCharacteristic,
-        descriptors: HashMap<Uuid, BLEDescriptor>,
+        descriptors: HashMap<u16, BLEDescriptor>,
     ) -> Self {
         BLECharacteristic {
             characteristic,
@@ -68,13 +68,16 @@ impl BLECharacteristic {
         writer.WriteBytes(data)?;
         let operation = self
             .characteristic
-            .WriteValueWithOptionAsync(&writer.DetachBuffer()?, write_type.into())?;
+            .WriteValueWithResultAndOptionAsync(&writer.DetachBuffer()?, write_type.into())?;
         let result = operation.await?;
-        if result == GattCommunicationStatus::Success {
+        let status = result.Status()?;
+        if status == GattCommunicationStatus::Success {
             Ok(())
         } else {
-            Err(Error::Other(
-                format!("Windows UWP threw error on write: {:?}", result).into(),
+            Err(utils::to_gatt_error(
+                status,
+                result.ProtocolError(),
+                "write",
             ))
         }
     }
@@ -84,7 +87,8 @@ impl BLECharacteristic {
             .characteristic
             .ReadValueWithCacheModeAsync(BluetoothCacheMode::Uncached)?
             .await?;
-        if result.Status()? == GattCommunicationStatus::Success {
+        let status = result.Status()?;
+        if status == GattCommunicationStatus::Success {
             let value = result.Value()?;
             let reader = DataReader::FromBuffer(&value)?;
             let len = reader.UnconsumedBufferLength()? as usize;
@@ -92,9 +96,7 @@ impl BLECharacteristic {
             reader.ReadBytes(&mut input[0..len])?;
             Ok(input)
         } else {
-            Err(Error::Other(
-                format!("Windows UWP threw error on read: {:?}", result).into(),
-            ))
+            Err(utils::to_gatt_error(status, result.ProtocolError(), "read"))
         }
     }
 
@@ -122,16 +124,19 @@ impl BLECharacteristic {
             return Err(Error::NotSupported("Can not subscribe to attribute".into()));
         }
 
-        let status = self
+        let result = self
             .characteristic
-            .WriteClientCharacteristicConfigurationDescriptorAsync(config)?
+            .WriteClientCharacteristicConfigurationDescriptorWithResultAsync(config)?
             .await?;
+        let status = result.Status()?;
         trace!("subscribe {:?}", status);
         if status == GattCommunicationStatus::Success {
             Ok(())
         } else {
-            Err(Error::Other(
-                format!("Windows UWP threw error on subscribe: {:?}", status).into(),
+            Err(utils::to_gatt_error(
+                status,
+                result.ProtocolError(),
+                "subscribe",
             ))
         }
     }
@@ -142,16 +147,19 @@ impl BLECharacteristic {
         }
         self.notify_token = None;
         let config = GattClientCharacteristicConfigurationDescriptorValue::None;
-        let status = self
+        let result = self
             .characteristic
-            .WriteClientCharacteristicConfigurationDescriptorAsync(config)?
+            .WriteClientCharacteristicConfigurationDescriptorWithResultAsync(config)?
             .await?;
+        let status = result.Status()?;
         trace!("unsubscribe {:?}", status);
         if status == GattCommunicationStatus::Success {
             Ok(())
         } else {
-            Err(Error::Other(
-                format!("Windows UWP threw error on unsubscribe: {:?}", status).into(),
+            Err(utils::to_gatt_error(
+                status,
+                result.ProtocolError(),
+                "unsubscribe",
             ))
         }
     }
@@ -160,18 +168,25 @@ impl BLECharacteristic {
         utils::to_uuid(&self.characteristic.Uuid().unwrap())
     }
 
-    pub fn to_characteristic(&self, service_uuid: Uuid) -> Characteristic {
+    pub fn handle(&self) -> u16 {
+        self.characteristic.AttributeHandle().unwrap()
+    }
+
+    pub fn to_characteristic(&self, service_uuid: Uuid, service_handle: u16) -> Characteristic {
         let uuid = self.uuid();
+        let handle = self.handle();
         let properties =
             utils::to_char_props(&self.characteristic.CharacteristicProperties().unwrap());
         let descriptors = self
             .descriptors
             .values()
-            .map(|descriptor| descriptor.to_descriptor(service_uuid, uuid))
+            .map(|descriptor| descriptor.to_descriptor(service_uuid, service_handle, uuid, handle))
             .collect();
         Characteristic {
             uuid,
             service_uuid,
+            handle,
+            service_handle,
             descriptors,
             properties,
         }
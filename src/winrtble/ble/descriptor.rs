@@ -12,7 +12,7 @@
 // Copyright (c) 2014 The Rust Project Developers
 
 use super::super::utils;
-use crate::{api::Descriptor, Error, Result};
+use crate::{api::Descriptor, Result};
 
 use uuid::Uuid;
 use windows::{
@@ -37,25 +37,42 @@ impl BLEDescriptor {
         utils::to_uuid(&self.descriptor.Uuid().unwrap())
     }
 
-    pub fn to_descriptor(&self, service_uuid: Uuid, characteristic_uuid: Uuid) -> Descriptor {
-        let uuid = self.uuid();
+    pub fn handle(&self) -> u16 {
+        self.descriptor.AttributeHandle().unwrap()
+    }
+
+    pub fn to_descriptor(
+        &self,
+        service_uuid: Uuid,
+        service_handle: u16,
+        characteristic_uuid: Uuid,
+        characteristic_handle: u16,
+    ) -> Descriptor {
         Descriptor {
-            uuid,
+            uuid: self.uuid(),
             service_uuid,
             characteristic_uuid,
+            handle: self.handle(),
+            service_handle,
+            characteristic_handle,
         }
     }
 
     pub async fn write_value(&self, data: &[u8]) -> Result<()> {
         let writer = DataWriter::new()?;
         writer.WriteBytes(data)?;
-        let operation = self.descriptor.WriteValueAsync(&writer.DetachBuffer()?)?;
+        let operation = self
+            .descriptor
+            .WriteValueWithResultAsync(&writer.DetachBuffer()?)?;
         let result = operation.await?;
-        if result == GattCommunicationStatus::Success {
+        let status = result.Status()?;
+        if status == GattCommunicationStatus::Success {
             Ok(())
         } else {
-            Err(Error::Other(
-                format!("Windows UWP threw error on write descriptor: {:?}", result).into(),
+            Err(utils::to_gatt_error(
+                status,
+                result.ProtocolError(),
+                "write descriptor",
             ))
         }
     }
@@ -65,7 +82,8 @@ impl BLEDescriptor {
             .descriptor
             .ReadValueWithCacheModeAsync(BluetoothCacheMode::Uncached)?
             .await?;
-        if result.Status()? == GattCommunicationStatus::Success {
+        let status = result.Status()?;
+        if status == GattCommunicationStatus::Success {
             let value = result.Value()?;
             let reader = DataReader::FromBuffer(&value)?;
             let len = reader.UnconsumedBufferLength()? as usize;
@@ -73,8 +91,10 @@ impl BLEDescriptor {
             reader.ReadBytes(&mut input[0..len])?;
             Ok(input)
         } else {
-            Err(Error::Other(
-                format!("Windows UWP threw error on read: {:?}", result).into(),
+            Err(utils::to_gatt_error(
+                status,
+                result.ProtocolError(),
+                "read descriptor",
             ))
         }
     }
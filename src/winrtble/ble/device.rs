@@ -18,27 +18,39 @@ use windows::{
         BluetoothCacheMode, BluetoothConnectionStatus, BluetoothLEDevice,
         GenericAttributeProfile::{
             GattCharacteristic, GattCommunicationStatus, GattDescriptor, GattDeviceService,
-            GattDeviceServicesResult,
+            GattDeviceServicesResult, GattSession,
         },
     },
+    Devices::Enumeration::{
+        DeviceInformationPairing, DevicePairingResultStatus, DeviceUnpairingResultStatus,
+    },
     Foundation::{EventRegistrationToken, TypedEventHandler},
 };
 
 pub type ConnectedEventHandler = Box<dyn Fn(bool) + Send>;
+pub type MtuChangedEventHandler = Box<dyn Fn(u16) + Send>;
 
 pub struct BLEDevice {
     device: BluetoothLEDevice,
     connection_token: EventRegistrationToken,
+    session: GattSession,
+    mtu_token: EventRegistrationToken,
 }
 
 impl BLEDevice {
     pub async fn new(
         address: BDAddr,
         connection_status_changed: ConnectedEventHandler,
+        mtu_changed: MtuChangedEventHandler,
     ) -> Result<Self> {
         let async_op = BluetoothLEDevice::FromBluetoothAddressAsync(address.into())
             .map_err(|_| Error::DeviceNotFound)?;
         let device = async_op.await.map_err(|_| Error::DeviceNotFound)?;
+        let winrt_error = |e| Error::Other(format!("{:?}", e).into());
+        let session_op =
+            GattSession::FromDeviceIdAsync(&device.BluetoothDeviceId().map_err(winrt_error)?)
+                .map_err(winrt_error)?;
+        let session = session_op.await.map_err(winrt_error)?;
         let connection_status_handler =
             TypedEventHandler::new(move |sender: &Option<BluetoothLEDevice>, _| {
                 if let Some(sender) = sender {
@@ -56,9 +68,23 @@ impl BLEDevice {
             .ConnectionStatusChanged(&connection_status_handler)
             .map_err(|_| Error::Other("Could not add connection status handler".into()))?;
 
+        let mtu_handler = TypedEventHandler::new(move |sender: &Option<GattSession>, _| {
+            if let Some(sender) = sender {
+                if let Ok(mtu) = sender.MaxPduSize() {
+                    mtu_changed(mtu);
+                }
+            }
+            Ok(())
+        });
+        let mtu_token = session
+            .MaxPduSizeChanged(&mtu_handler)
+            .map_err(|_| Error::Other("Could not add MTU changed handler".into()))?;
+
         Ok(BLEDevice {
             device,
             connection_token,
+            session,
+            mtu_token,
         })
     }
 
@@ -92,6 +118,59 @@ impl BLEDevice {
         Ok(status == BluetoothConnectionStatus::Connected)
     }
 
+    /// Returns the ATT MTU, which WinRT calls the maximum PDU size.
+    pub fn mtu(&self) -> Result<u16> {
+        self.session
+            .MaxPduSize()
+            .map_err(|e| Error::Other(format!("{:?}", e).into()))
+    }
+
+    fn pairing(&self) -> Result<DeviceInformationPairing> {
+        self.device
+            .DeviceInformation()
+            .and_then(|information| information.Pairing())
+            .map_err(|e| Error::Other(format!("{:?}", e).into()))
+    }
+
+    pub fn is_paired(&self) -> Result<bool> {
+        self.pairing()?
+            .IsPaired()
+            .map_err(|e| Error::Other(format!("{:?}", e).into()))
+    }
+
+    /// Pairs with the device, letting Windows show its own pairing dialog if the pairing needs
+    /// authentication.
+    pub async fn pair(&self) -> Result<()> {
+        let winrt_error = |e| Error::Other(format!("{:?}", e).into());
+        let async_op = self.pairing()?.PairAsync().map_err(winrt_error)?;
+        let status = async_op
+            .await
+            .map_err(winrt_error)?
+            .Status()
+            .map_err(winrt_error)?;
+        match status {
+            DevicePairingResultStatus::Paired | DevicePairingResultStatus::AlreadyPaired => Ok(()),
+            status => Err(utils::to_pairing_error(status)),
+        }
+    }
+
+    pub async fn unpair(&self) -> Result<()> {
+        let winrt_error = |e| Error::Other(format!("{:?}", e).into());
+        let async_op = self.pairing()?.UnpairAsync().map_err(winrt_error)?;
+        let status = async_op
+            .await
+            .map_err(winrt_error)?
+            .Status()
+            .map_err(winrt_error)?;
+        match status {
+            DeviceUnpairingResultStatus::Unpaired
+            | DeviceUnpairingResultStatus::AlreadyUnpaired => Ok(()),
+            status => Err(Error::Other(
+                format!("Unpairing failed: {:?}", status).into(),
+            )),
+        }
+    }
+
     pub async fn get_characteristics(
         service: &GattDeviceService,
     ) -> Result<Vec<GattCharacteristic>> {
@@ -110,6 +189,28 @@ impl BLEDevice {
         }
     }
 
+    pub async fn get_included_services(
+        service: &GattDeviceService,
+    ) -> Result<Vec<GattDeviceService>> {
+        let async_result = service
+            .GetIncludedServicesWithCacheModeAsync(BluetoothCacheMode::Uncached)?
+            .await?;
+        let status = async_result.Status();
+        if status == Ok(GattCommunicationStatus::Success) {
+            let results = async_result.Services()?;
+            debug!("included services {:?}", results.Size());
+            Ok(results.into_iter().collect())
+        } else {
+            Err(Error::Other(
+                format!(
+                    "get_included_services for {:?} failed: {:?}",
+                    service, status
+                )
+                .into(),
+            ))
+        }
+    }
+
     pub async fn get_characteristic_descriptors(
         characteristic: &GattCharacteristic,
     ) -> Result<Vec<GattDescriptor>> {
@@ -160,6 +261,16 @@ impl Drop for BLEDevice {
             debug!("Drop:remove_connection_status_changed {:?}", err);
         }
 
+        let result = self.session.RemoveMaxPduSizeChanged(self.mtu_token);
+        if let Err(err) = result {
+            debug!("Drop:remove_max_pdu_size_changed {:?}", err);
+        }
+
+        let result = self.session.Close();
+        if let Err(err) = result {
+            debug!("Drop:close session {:?}", err);
+        }
+
         let result = self.device.Close();
         if let Err(err) = result {
             debug!("Drop:close {:?}", err);
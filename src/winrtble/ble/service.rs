@@ -1,12 +1,15 @@
 use super::characteristic::BLECharacteristic;
 use crate::api::Service;
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use uuid::Uuid;
 
 #[derive(Debug)]
 pub struct BLEService {
     pub uuid: Uuid,
-    pub characteristics: HashMap<Uuid, BLECharacteristic>,
+    pub handle: u16,
+    pub primary: bool,
+    pub included_services: BTreeSet<u16>,
+    pub characteristics: HashMap<u16, BLECharacteristic>,
 }
 
 impl BLEService {
@@ -14,11 +17,13 @@ impl BLEService {
         let characteristics = self
             .characteristics
             .values()
-            .map(|ble_characteristic| ble_characteristic.to_characteristic(self.uuid))
+            .map(|ble_characteristic| ble_characteristic.to_characteristic(self.uuid, self.handle))
             .collect();
         Service {
             uuid: self.uuid,
-            primary: true,
+            handle: self.handle,
+            primary: self.primary,
+            included_services: self.included_services.clone(),
             characteristics,
         }
     }
@@ -11,7 +11,10 @@
 //
 // Copyright (c) 2014 The Rust Project Developers
 
-use crate::{api::ScanFilter, Error, Result};
+use crate::{
+    api::{ScanFilter, ScanMode},
+    Error, Result,
+};
 use windows::{Devices::Bluetooth::Advertisement::*, Foundation::TypedEventHandler};
 
 pub type AdvertismentEventHandler = Box<dyn Fn(&BluetoothLEAdvertisementReceivedEventArgs) + Send>;
@@ -34,7 +37,6 @@ impl BLEWatcher {
     }
 
     pub fn start(&self, filter: ScanFilter, on_received: AdvertismentEventHandler) -> Result<()> {
-        let ScanFilter { services } = filter;
         let ad = self
             .watcher
             .AdvertisementFilter()
@@ -43,14 +45,16 @@ impl BLEWatcher {
             .unwrap();
         let ad_services = ad.ServiceUuids().unwrap();
         ad_services.Clear().unwrap();
-        for service in services {
+        for service in filter.services {
             ad_services
                 .Append(windows::core::GUID::from(service.as_u128()))
                 .unwrap();
         }
-        self.watcher
-            .SetScanningMode(BluetoothLEScanningMode::Active)
-            .unwrap();
+        let scanning_mode = match filter.mode {
+            ScanMode::Active => BluetoothLEScanningMode::Active,
+            ScanMode::Passive => BluetoothLEScanningMode::Passive,
+        };
+        self.watcher.SetScanningMode(scanning_mode).unwrap();
         let handler: TypedEventHandler<
             BluetoothLEAdvertisementWatcher,
             BluetoothLEAdvertisementReceivedEventArgs,
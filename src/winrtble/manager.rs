@@ -12,17 +12,25 @@
 // Copyright (c) 2014 The Rust Project Developers
 
 use super::adapter::Adapter;
-use crate::{api, Result};
+use crate::{
+    api::{self, Timeouts},
+    common::timeouts::SharedTimeouts,
+    Result,
+};
 use async_trait::async_trait;
 use windows::Devices::Radios::{Radio, RadioKind};
 
 /// Implementation of [api::Manager](crate::api::Manager).
 #[derive(Clone, Debug)]
-pub struct Manager {}
+pub struct Manager {
+    timeouts: SharedTimeouts,
+}
 
 impl Manager {
     pub async fn new() -> Result<Self> {
-        Ok(Self {})
+        Ok(Self {
+            timeouts: Default::default(),
+        })
     }
 }
 
@@ -32,10 +40,18 @@ impl api::Manager for Manager {
 
     async fn adapters(&self) -> Result<Vec<Adapter>> {
         let radios = Radio::GetRadiosAsync()?.await?;
-        Ok(radios
+        radios
             .into_iter()
             .filter(|radio| radio.Kind() == Ok(RadioKind::Bluetooth))
-            .map(|_| Adapter::new())
-            .collect())
+            .map(|radio| Adapter::new(radio, self.timeouts.clone()))
+            .collect()
+    }
+
+    fn timeouts(&self) -> Timeouts {
+        *self.timeouts.read().unwrap()
+    }
+
+    fn set_timeouts(&self, timeouts: Timeouts) {
+        *self.timeouts.write().unwrap() = timeouts;
     }
 }
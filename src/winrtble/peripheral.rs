@@ -12,16 +12,23 @@
 // Copyright (c) 2014 The Rust Project Developers
 
 use super::{
-    advertisement_data_type, ble::characteristic::BLECharacteristic,
-    ble::descriptor::BLEDescriptor, ble::device::BLEDevice, ble::service::BLEService, utils,
+    ble::characteristic::BLECharacteristic, ble::descriptor::BLEDescriptor, ble::device::BLEDevice,
+    ble::service::BLEService, utils,
 };
 use crate::{
     api::{
-        bleuuid::{uuid_from_u16, uuid_from_u32},
+        advertisement::{AdStructure, AdvertisingData},
+        proximity::Proximity,
         AddressType, BDAddr, CentralEvent, Characteristic, Descriptor, Peripheral as ApiPeripheral,
-        PeripheralProperties, Service, ValueNotification, WriteType,
+        PeripheralProperties, Service, Timeouts, ValueNotification, WriteType,
+    },
+    common::{
+        adapter_manager::AdapterManager,
+        subscriptions::Subscriptions,
+        timeouts::{self, timeout},
+        transfer,
+        util::notifications_stream_from_broadcast_receiver,
     },
-    common::{adapter_manager::AdapterManager, util::notifications_stream_from_broadcast_receiver},
     Error, Result,
 };
 use async_trait::async_trait;
@@ -33,8 +40,7 @@ use serde::{Deserialize, Serialize};
 #[cfg(feature = "serde")]
 use serde_cr as serde;
 use std::{
-    collections::{BTreeSet, HashMap, HashSet},
-    convert::TryInto,
+    collections::{BTreeSet, HashMap, HashSet, VecDeque},
     fmt::{self, Debug, Display, Formatter},
     pin::Pin,
     sync::atomic::{AtomicBool, Ordering},
@@ -64,6 +70,7 @@ impl Display for PeripheralId {
 #[derive(Clone)]
 pub struct Peripheral {
     shared: Arc<Shared>,
+    timeout_overrides: Timeouts,
 }
 
 struct Shared {
@@ -71,8 +78,9 @@ struct Shared {
     adapter: Weak<AdapterManager<Peripheral>>,
     address: BDAddr,
     connected: AtomicBool,
-    ble_services: DashMap<Uuid, BLEService>,
+    ble_services: DashMap<u16, BLEService>,
     notifications_channel: broadcast::Sender<ValueNotification>,
+    notification_streams: Arc<Subscriptions>,
 
     // Mutable, advertised, state...
     address_type: RwLock<Option<AddressType>>,
@@ -95,6 +103,7 @@ impl Peripheral {
                 connected: AtomicBool::new(false),
                 ble_services: DashMap::new(),
                 notifications_channel: broadcast_sender,
+                notification_streams: Default::default(),
                 address_type: RwLock::new(None),
                 local_name: RwLock::new(None),
                 last_tx_power_level: RwLock::new(None),
@@ -103,6 +112,7 @@ impl Peripheral {
                 latest_service_data: RwLock::new(HashMap::new()),
                 services: RwLock::new(HashSet::new()),
             }),
+            timeout_overrides: Timeouts::default(),
         }
     }
 
@@ -165,49 +175,24 @@ impl Peripheral {
         // The Windows Runtime API (as of 19041) does not directly expose Service Data as a friendly API (like Manufacturer Data above)
         // Instead they provide data sections for access to raw advertising data. That is processed here.
         if let Ok(data_sections) = advertisement.DataSections() {
-            // See if we have any advertised service data before taking a lock to update...
-            let mut found_service_data = false;
-            for section in &data_sections {
-                match section.DataType().unwrap() {
-                    advertisement_data_type::SERVICE_DATA_16_BIT_UUID
-                    | advertisement_data_type::SERVICE_DATA_32_BIT_UUID
-                    | advertisement_data_type::SERVICE_DATA_128_BIT_UUID => {
-                        found_service_data = true;
-                        break;
-                    }
-                    _ => {}
-                }
-            }
-            if found_service_data {
-                let mut service_data_guard = self.shared.latest_service_data.write().unwrap();
-
-                *service_data_guard = data_sections
+            // Sections which fail to parse are malformed, and skipped rather than failing the
+            // whole advertisement.
+            let data = AdvertisingData {
+                structures: data_sections
                     .into_iter()
                     .filter_map(|d| {
-                        let data = utils::to_vec(&d.Data().unwrap());
-
-                        match d.DataType().unwrap() {
-                            advertisement_data_type::SERVICE_DATA_16_BIT_UUID => {
-                                let (uuid, data) = data.split_at(2);
-                                let uuid =
-                                    uuid_from_u16(u16::from_le_bytes(uuid.try_into().unwrap()));
-                                Some((uuid, data.to_owned()))
-                            }
-                            advertisement_data_type::SERVICE_DATA_32_BIT_UUID => {
-                                let (uuid, data) = data.split_at(4);
-                                let uuid =
-                                    uuid_from_u32(u32::from_le_bytes(uuid.try_into().unwrap()));
-                                Some((uuid, data.to_owned()))
-                            }
-                            advertisement_data_type::SERVICE_DATA_128_BIT_UUID => {
-                                let (uuid, data) = data.split_at(16);
-                                let uuid = Uuid::from_slice(uuid).unwrap();
-                                Some((uuid, data.to_owned()))
-                            }
-                            _ => None,
-                        }
+                        AdStructure::parse(
+                            d.DataType().unwrap(),
+                            &utils::to_vec(&d.Data().unwrap()),
+                        )
+                        .ok()
                     })
-                    .collect();
+                    .collect(),
+            };
+            let service_data = data.service_data();
+            if !service_data.is_empty() {
+                let mut service_data_guard = self.shared.latest_service_data.write().unwrap();
+                *service_data_guard = service_data;
 
                 // Emit event of newly received advertisement
                 self.emit_event(CentralEvent::ServiceDataAdvertisement {
@@ -342,6 +327,10 @@ impl ApiPeripheral for Peripheral {
         Ok(Some(self.derive_properties()))
     }
 
+    fn proximity(&self) -> Option<Proximity> {
+        self.shared.adapter.upgrade()?.proximity(&self.id())
+    }
+
     fn services(&self) -> BTreeSet<Service> {
         self.shared
             .ble_services
@@ -350,6 +339,21 @@ impl ApiPeripheral for Peripheral {
             .collect()
     }
 
+    fn timeouts(&self) -> Timeouts {
+        let adapter_timeouts = match self.shared.adapter.upgrade() {
+            Some(manager) => manager.timeouts(),
+            None => Timeouts::default(),
+        };
+        adapter_timeouts.merge(self.timeout_overrides)
+    }
+
+    fn with_timeouts(&self, timeouts: Timeouts) -> Self {
+        Self {
+            shared: self.shared.clone(),
+            timeout_overrides: self.timeout_overrides.merge(timeouts),
+        }
+    }
+
     /// Returns true iff we are currently connected to the device.
     async fn is_connected(&self) -> Result<bool> {
         Ok(self.shared.connected.load(Ordering::Relaxed))
@@ -359,31 +363,43 @@ impl ApiPeripheral for Peripheral {
     /// Ok there has been successful connection. Note that peripherals allow only one connection at
     /// a time. Operations that attempt to communicate with a device will fail until it is connected.
     async fn connect(&self) -> Result<()> {
-        let shared_clone = Arc::downgrade(&self.shared);
-        let adapter_clone = self.shared.adapter.clone();
-        let address = self.shared.address;
-        let device = BLEDevice::new(
-            self.shared.address,
-            Box::new(move |is_connected| {
-                if let Some(shared) = shared_clone.upgrade() {
-                    shared.connected.store(is_connected, Ordering::Relaxed);
-                }
-
-                if !is_connected {
-                    if let Some(adapter) = adapter_clone.upgrade() {
-                        adapter.emit(CentralEvent::DeviceDisconnected(address.into()));
+        timeouts::connect(self, async {
+            let shared_clone = Arc::downgrade(&self.shared);
+            let adapter_clone = self.shared.adapter.clone();
+            let mtu_adapter = self.shared.adapter.clone();
+            let address = self.shared.address;
+            let device = BLEDevice::new(
+                self.shared.address,
+                Box::new(move |is_connected| {
+                    if let Some(shared) = shared_clone.upgrade() {
+                        shared.connected.store(is_connected, Ordering::Relaxed);
                     }
-                }
-            }),
-        )
-        .await?;
 
-        device.connect().await?;
-        let mut d = self.shared.device.lock().await;
-        *d = Some(device);
-        self.shared.connected.store(true, Ordering::Relaxed);
-        self.emit_event(CentralEvent::DeviceConnected(self.shared.address.into()));
-        Ok(())
+                    if !is_connected {
+                        if let Some(adapter) = adapter_clone.upgrade() {
+                            adapter.emit(CentralEvent::DeviceDisconnected(address.into()));
+                        }
+                    }
+                }),
+                Box::new(move |mtu| {
+                    if let Some(adapter) = mtu_adapter.upgrade() {
+                        adapter.emit(CentralEvent::MtuChanged {
+                            id: address.into(),
+                            mtu,
+                        });
+                    }
+                }),
+            )
+            .await?;
+
+            device.connect().await?;
+            let mut d = self.shared.device.lock().await;
+            *d = Some(device);
+            self.shared.connected.store(true, Ordering::Relaxed);
+            self.emit_event(CentralEvent::DeviceConnected(self.shared.address.into()));
+            Ok(())
+        })
+        .await
     }
 
     /// Terminates a connection to the device. This is a synchronous operation.
@@ -395,66 +411,146 @@ impl ApiPeripheral for Peripheral {
         Ok(())
     }
 
+    async fn pair(&self) -> Result<()> {
+        if self.shared.device.lock().await.is_none() {
+            self.connect().await?;
+        }
+        let device = self.shared.device.lock().await;
+        match *device {
+            Some(ref device) => device.pair().await,
+            None => Err(Error::NotConnected),
+        }
+    }
+
+    async fn unpair(&self) -> Result<()> {
+        let device = self.shared.device.lock().await;
+        match *device {
+            Some(ref device) => device.unpair().await,
+            None => Err(Error::NotConnected),
+        }
+    }
+
+    async fn is_paired(&self) -> Result<bool> {
+        let device = self.shared.device.lock().await;
+        match *device {
+            Some(ref device) => device.is_paired(),
+            None => Err(Error::NotConnected),
+        }
+    }
+
+    /// Windows keeps the keys of every device it pairs with, so this is the same as
+    /// [`is_paired`](api::Peripheral::is_paired).
+    async fn is_bonded(&self) -> Result<bool> {
+        self.is_paired().await
+    }
+
+    async fn mtu(&self) -> Result<u16> {
+        let device = self.shared.device.lock().await;
+        match *device {
+            Some(ref device) if self.shared.connected.load(Ordering::Relaxed) => device.mtu(),
+            _ => Err(Error::NotConnected),
+        }
+    }
+
     /// Discovers all characteristics for the device. This is a synchronous operation.
     async fn discover_services(&self) -> Result<()> {
-        let device = self.shared.device.lock().await;
-        if let Some(ref device) = *device {
-            let gatt_services = device.discover_services().await?;
-            for service in &gatt_services {
-                let uuid = utils::to_uuid(&service.Uuid().unwrap());
-                if !self.shared.ble_services.contains_key(&uuid) {
-                    match BLEDevice::get_characteristics(&service).await {
-                        Ok(characteristics) => {
-                            let characteristics =
-                                characteristics.into_iter().map(|characteristic| async {
-                                    match BLEDevice::get_characteristic_descriptors(&characteristic)
-                                        .await
-                                    {
-                                        Ok(descriptors) => {
-                                            let descriptors: HashMap<Uuid, BLEDescriptor> =
-                                                descriptors
-                                                    .into_iter()
-                                                    .map(|descriptor| {
-                                                        let descriptor =
-                                                            BLEDescriptor::new(descriptor);
-                                                        (descriptor.uuid(), descriptor)
-                                                    })
-                                                    .collect();
-                                            Ok((characteristic, descriptors))
-                                        }
-                                        Err(e) => {
-                                            error!("get_characteristic_descriptors_async {:?}", e);
-                                            Err(e)
-                                        }
+        timeout(self.timeouts().discover_services, async {
+            let device = self.shared.device.lock().await;
+            if let Some(ref device) = *device {
+                // Windows only lists the primary services of the device, so any other services
+                // they include are queued up to be discovered after them.
+                let mut pending: VecDeque<_> = device
+                    .discover_services()
+                    .await?
+                    .into_iter()
+                    .map(|service| (service, true))
+                    .collect();
+                let mut seen = HashSet::new();
+                for (service, _) in &pending {
+                    seen.insert(service.AttributeHandle()?);
+                }
+                while let Some((service, primary)) = pending.pop_front() {
+                    let uuid = utils::to_uuid(&service.Uuid().unwrap());
+                    let handle = service.AttributeHandle()?;
+                    if !self.shared.ble_services.contains_key(&handle) {
+                        let mut included_services = BTreeSet::new();
+                        match BLEDevice::get_included_services(&service).await {
+                            Ok(services) => {
+                                for included in services {
+                                    let included_handle = included.AttributeHandle()?;
+                                    included_services.insert(included_handle);
+                                    if seen.insert(included_handle) {
+                                        pending.push_back((included, false));
                                     }
-                                });
-                            let characteristics = futures::future::try_join_all(characteristics)
-                                .await?
-                                .into_iter()
-                                .map(|(characteristic, descriptors)| {
-                                    let characteristic =
-                                        BLECharacteristic::new(characteristic, descriptors);
-                                    (characteristic.uuid(), characteristic)
-                                })
-                                .collect();
-
-                            self.shared.ble_services.insert(
-                                uuid,
-                                BLEService {
-                                    uuid,
-                                    characteristics,
-                                },
-                            );
+                                }
+                            }
+                            Err(e) => {
+                                error!("get_included_services_async {:?}", e);
+                            }
                         }
-                        Err(e) => {
-                            error!("get_characteristics_async {:?}", e);
+                        match BLEDevice::get_characteristics(&service).await {
+                            Ok(characteristics) => {
+                                let characteristics =
+                                    characteristics.into_iter().map(|characteristic| async {
+                                        match BLEDevice::get_characteristic_descriptors(
+                                            &characteristic,
+                                        )
+                                        .await
+                                        {
+                                            Ok(descriptors) => {
+                                                let descriptors: HashMap<u16, BLEDescriptor> =
+                                                    descriptors
+                                                        .into_iter()
+                                                        .map(|descriptor| {
+                                                            let descriptor =
+                                                                BLEDescriptor::new(descriptor);
+                                                            (descriptor.handle(), descriptor)
+                                                        })
+                                                        .collect();
+                                                Ok((characteristic, descriptors))
+                                            }
+                                            Err(e) => {
+                                                error!(
+                                                    "get_characteristic_descriptors_async {:?}",
+                                                    e
+                                                );
+                                                Err(e)
+                                            }
+                                        }
+                                    });
+                                let characteristics =
+                                    futures::future::try_join_all(characteristics)
+                                        .await?
+                                        .into_iter()
+                                        .map(|(characteristic, descriptors)| {
+                                            let characteristic =
+                                                BLECharacteristic::new(characteristic, descriptors);
+                                            (characteristic.handle(), characteristic)
+                                        })
+                                        .collect();
+
+                                self.shared.ble_services.insert(
+                                    handle,
+                                    BLEService {
+                                        uuid,
+                                        handle,
+                                        primary,
+                                        included_services,
+                                        characteristics,
+                                    },
+                                );
+                            }
+                            Err(e) => {
+                                error!("get_characteristics_async {:?}", e);
+                            }
                         }
                     }
                 }
+                return Ok(());
             }
-            return Ok(());
-        }
-        Err(Error::NotConnected)
+            Err(Error::NotConnected)
+        })
+        .await
     }
 
     /// Write some data to the characteristic. Returns an error if the write couldn't be send or (in
@@ -465,70 +561,95 @@ impl ApiPeripheral for Peripheral {
         data: &[u8],
         write_type: WriteType,
     ) -> Result<()> {
-        let ble_service = &*self
-            .shared
-            .ble_services
-            .get(&characteristic.service_uuid)
-            .ok_or_else(|| Error::NotSupported("Service not found for write".into()))?;
-        let ble_characteristic = ble_service
-            .characteristics
-            .get(&characteristic.uuid)
-            .ok_or_else(|| Error::NotSupported("Characteristic not found for write".into()))?;
-        ble_characteristic.write_value(data, write_type).await
+        timeout(self.timeouts().write, async {
+            let ble_service = &*self
+                .shared
+                .ble_services
+                .get(&characteristic.service_handle)
+                .ok_or_else(|| Error::NotSupported("Service not found for write".into()))?;
+            let ble_characteristic = ble_service
+                .characteristics
+                .get(&characteristic.handle)
+                .ok_or_else(|| Error::NotSupported("Characteristic not found for write".into()))?;
+            ble_characteristic.write_value(data, write_type).await
+        })
+        .await
     }
 
     /// Enables either notify or indicate (depending on support) for the specified characteristic.
     /// This is a synchronous call.
+    async fn read_at(&self, characteristic: &Characteristic, offset: usize) -> Result<Vec<u8>> {
+        transfer::value_from_offset(self.read(characteristic).await?, offset)
+    }
+
     async fn subscribe(&self, characteristic: &Characteristic) -> Result<()> {
-        let ble_service = &mut *self
-            .shared
-            .ble_services
-            .get_mut(&characteristic.service_uuid)
-            .ok_or_else(|| Error::NotSupported("Service not found for subscribe".into()))?;
-        let ble_characteristic = ble_service
-            .characteristics
-            .get_mut(&characteristic.uuid)
-            .ok_or_else(|| Error::NotSupported("Characteristic not found for subscribe".into()))?;
-        let notifications_sender = self.shared.notifications_channel.clone();
-        let uuid = characteristic.uuid;
-        ble_characteristic
-            .subscribe(Box::new(move |value| {
-                let notification = ValueNotification { uuid: uuid, value };
-                // Note: we ignore send errors here which may happen while there are no
-                // receivers...
-                let _ = notifications_sender.send(notification);
-            }))
-            .await
+        timeout(self.timeouts().subscribe, async {
+            let ble_service = &mut *self
+                .shared
+                .ble_services
+                .get_mut(&characteristic.service_handle)
+                .ok_or_else(|| Error::NotSupported("Service not found for subscribe".into()))?;
+            let ble_characteristic = ble_service
+                .characteristics
+                .get_mut(&characteristic.handle)
+                .ok_or_else(|| {
+                    Error::NotSupported("Characteristic not found for subscribe".into())
+                })?;
+            let notifications_sender = self.shared.notifications_channel.clone();
+            let uuid = characteristic.uuid;
+            let service_uuid = characteristic.service_uuid;
+            let handle = characteristic.handle;
+            ble_characteristic
+                .subscribe(Box::new(move |value| {
+                    let notification = ValueNotification {
+                        uuid,
+                        service_uuid,
+                        handle,
+                        value,
+                    };
+                    // Note: we ignore send errors here which may happen while there are no
+                    // receivers...
+                    let _ = notifications_sender.send(notification);
+                }))
+                .await
+        })
+        .await
     }
 
     /// Disables either notify or indicate (depending on support) for the specified characteristic.
     /// This is a synchronous call.
     async fn unsubscribe(&self, characteristic: &Characteristic) -> Result<()> {
-        let ble_service = &mut *self
-            .shared
-            .ble_services
-            .get_mut(&characteristic.service_uuid)
-            .ok_or_else(|| Error::NotSupported("Service not found for unsubscribe".into()))?;
-        let ble_characteristic = ble_service
-            .characteristics
-            .get_mut(&characteristic.uuid)
-            .ok_or_else(|| {
-                Error::NotSupported("Characteristic not found for unsubscribe".into())
-            })?;
-        ble_characteristic.unsubscribe().await
+        timeout(self.timeouts().subscribe, async {
+            let ble_service = &mut *self
+                .shared
+                .ble_services
+                .get_mut(&characteristic.service_handle)
+                .ok_or_else(|| Error::NotSupported("Service not found for unsubscribe".into()))?;
+            let ble_characteristic = ble_service
+                .characteristics
+                .get_mut(&characteristic.handle)
+                .ok_or_else(|| {
+                    Error::NotSupported("Characteristic not found for unsubscribe".into())
+                })?;
+            ble_characteristic.unsubscribe().await
+        })
+        .await
     }
 
     async fn read(&self, characteristic: &Characteristic) -> Result<Vec<u8>> {
-        let ble_service = &*self
-            .shared
-            .ble_services
-            .get(&characteristic.service_uuid)
-            .ok_or_else(|| Error::NotSupported("Service not found for read".into()))?;
-        let ble_characteristic = ble_service
-            .characteristics
-            .get(&characteristic.uuid)
-            .ok_or_else(|| Error::NotSupported("Characteristic not found for read".into()))?;
-        ble_characteristic.read_value().await
+        timeout(self.timeouts().read, async {
+            let ble_service = &*self
+                .shared
+                .ble_services
+                .get(&characteristic.service_handle)
+                .ok_or_else(|| Error::NotSupported("Service not found for read".into()))?;
+            let ble_characteristic = ble_service
+                .characteristics
+                .get(&characteristic.handle)
+                .ok_or_else(|| Error::NotSupported("Characteristic not found for read".into()))?;
+            ble_characteristic.read_value().await
+        })
+        .await
     }
 
     async fn notifications(&self) -> Result<Pin<Box<dyn Stream<Item = ValueNotification> + Send>>> {
@@ -536,38 +657,55 @@ impl ApiPeripheral for Peripheral {
         Ok(notifications_stream_from_broadcast_receiver(receiver))
     }
 
+    async fn notifications_for(
+        &self,
+        characteristic: &Characteristic,
+        subscribe: bool,
+    ) -> Result<Pin<Box<dyn Stream<Item = Vec<u8>> + Send>>> {
+        self.shared
+            .notification_streams
+            .notifications_for(self, characteristic, subscribe)
+            .await
+    }
+
     async fn write_descriptor(&self, descriptor: &Descriptor, data: &[u8]) -> Result<()> {
-        let ble_service = &*self
-            .shared
-            .ble_services
-            .get(&descriptor.service_uuid)
-            .ok_or_else(|| Error::NotSupported("Service not found for write".into()))?;
-        let ble_characteristic = ble_service
-            .characteristics
-            .get(&descriptor.characteristic_uuid)
-            .ok_or_else(|| Error::NotSupported("Characteristic not found for write".into()))?;
-        let ble_descriptor = ble_characteristic
-            .descriptors
-            .get(&descriptor.uuid)
-            .ok_or_else(|| Error::NotSupported("Descriptor not found for write".into()))?;
-        ble_descriptor.write_value(data).await
+        timeout(self.timeouts().descriptor, async {
+            let ble_service = &*self
+                .shared
+                .ble_services
+                .get(&descriptor.service_handle)
+                .ok_or_else(|| Error::NotSupported("Service not found for write".into()))?;
+            let ble_characteristic = ble_service
+                .characteristics
+                .get(&descriptor.characteristic_handle)
+                .ok_or_else(|| Error::NotSupported("Characteristic not found for write".into()))?;
+            let ble_descriptor = ble_characteristic
+                .descriptors
+                .get(&descriptor.handle)
+                .ok_or_else(|| Error::NotSupported("Descriptor not found for write".into()))?;
+            ble_descriptor.write_value(data).await
+        })
+        .await
     }
 
     async fn read_descriptor(&self, descriptor: &Descriptor) -> Result<Vec<u8>> {
-        let ble_service = &*self
-            .shared
-            .ble_services
-            .get(&descriptor.service_uuid)
-            .ok_or_else(|| Error::NotSupported("Service not found for read".into()))?;
-        let ble_characteristic = ble_service
-            .characteristics
-            .get(&descriptor.uuid)
-            .ok_or_else(|| Error::NotSupported("Characteristic not found for read".into()))?;
-        let ble_descriptor = ble_characteristic
-            .descriptors
-            .get(&descriptor.uuid)
-            .ok_or_else(|| Error::NotSupported("Descriptor not found for write".into()))?;
-        ble_descriptor.read_value().await
+        timeout(self.timeouts().descriptor, async {
+            let ble_service = &*self
+                .shared
+                .ble_services
+                .get(&descriptor.service_handle)
+                .ok_or_else(|| Error::NotSupported("Service not found for read".into()))?;
+            let ble_characteristic = ble_service
+                .characteristics
+                .get(&descriptor.characteristic_handle)
+                .ok_or_else(|| Error::NotSupported("Characteristic not found for read".into()))?;
+            let ble_descriptor = ble_characteristic
+                .descriptors
+                .get(&descriptor.handle)
+                .ok_or_else(|| Error::NotSupported("Descriptor not found for write".into()))?;
+            ble_descriptor.read_value().await
+        })
+        .await
     }
 }
 
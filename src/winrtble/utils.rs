@@ -20,6 +20,8 @@ use windows::{
         GattCharacteristicProperties, GattClientCharacteristicConfigurationDescriptorValue,
         GattCommunicationStatus,
     },
+    Devices::Enumeration::DevicePairingResultStatus,
+    Foundation::IReference,
     Storage::Streams::{DataReader, IBuffer},
 };
 
@@ -37,6 +39,51 @@ pub fn to_error(status: GattCommunicationStatus) -> Result<()> {
     }
 }
 
+/// Turns the status of a GATT operation which didn't succeed into an error, with the ATT error code
+/// the device responded with if it was a protocol error.
+pub fn to_gatt_error(
+    status: GattCommunicationStatus,
+    protocol_error: windows::core::Result<IReference<u8>>,
+    operation: &str,
+) -> Error {
+    gatt_error(
+        status,
+        protocol_error.and_then(|code| code.Value()).ok(),
+        operation,
+    )
+}
+
+fn gatt_error(
+    status: GattCommunicationStatus,
+    protocol_error: Option<u8>,
+    operation: &str,
+) -> Error {
+    if status == GattCommunicationStatus::Unreachable {
+        return Error::NotConnected;
+    } else if status == GattCommunicationStatus::AccessDenied {
+        return Error::PermissionDenied;
+    }
+    match protocol_error {
+        Some(code) if status == GattCommunicationStatus::ProtocolError => Error::Att(code.into()),
+        _ => Error::Other(format!("Windows UWP threw error on {}: {:?}", operation, status).into()),
+    }
+}
+
+/// Turns the status of a pairing which didn't succeed into an error.
+pub fn to_pairing_error(status: DevicePairingResultStatus) -> Error {
+    match status {
+        DevicePairingResultStatus::AuthenticationFailure
+        | DevicePairingResultStatus::AuthenticationTimeout
+        | DevicePairingResultStatus::AuthenticationNotAllowed
+        | DevicePairingResultStatus::ProtectionLevelCouldNotBeMet
+        | DevicePairingResultStatus::RejectedByHandler
+        | DevicePairingResultStatus::PairingCanceled => Error::AuthenticationFailed,
+        DevicePairingResultStatus::OperationAlreadyInProgress => Error::InProgress,
+        DevicePairingResultStatus::AccessDenied => Error::PermissionDenied,
+        status => Error::Other(format!("Pairing failed: {:?}", status).into()),
+    }
+}
+
 pub fn to_descriptor_value(
     properties: GattCharacteristicProperties,
 ) -> GattClientCharacteristicConfigurationDescriptorValue {
@@ -108,6 +155,7 @@ pub fn to_char_props(props: &GattCharacteristicProperties) -> CharPropFlags {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::api::AttErrorCode;
 
     #[test]
     fn check_uuid_to_guid_conversion() {
@@ -130,4 +178,52 @@ mod tests {
         let uuid_expected = Uuid::from_str(uuid_str).unwrap();
         assert_eq!(uuid_converted, uuid_expected);
     }
+
+    #[test]
+    fn gatt_errors() {
+        assert!(matches!(
+            gatt_error(GattCommunicationStatus::Unreachable, None, "read"),
+            Error::NotConnected
+        ));
+        assert!(matches!(
+            gatt_error(GattCommunicationStatus::AccessDenied, None, "read"),
+            Error::PermissionDenied
+        ));
+        assert!(matches!(
+            gatt_error(GattCommunicationStatus::ProtocolError, Some(0x03), "write"),
+            Error::Att(AttErrorCode::WriteNotPermitted)
+        ));
+        assert!(matches!(
+            gatt_error(GattCommunicationStatus::ProtocolError, Some(0x80), "write"),
+            Error::Att(AttErrorCode::Application(0x80))
+        ));
+        assert!(matches!(
+            gatt_error(GattCommunicationStatus::ProtocolError, None, "write"),
+            Error::Other(_)
+        ));
+    }
+
+    #[test]
+    fn pairing_errors() {
+        assert!(matches!(
+            to_pairing_error(DevicePairingResultStatus::AuthenticationFailure),
+            Error::AuthenticationFailed
+        ));
+        assert!(matches!(
+            to_pairing_error(DevicePairingResultStatus::PairingCanceled),
+            Error::AuthenticationFailed
+        ));
+        assert!(matches!(
+            to_pairing_error(DevicePairingResultStatus::OperationAlreadyInProgress),
+            Error::InProgress
+        ));
+        assert!(matches!(
+            to_pairing_error(DevicePairingResultStatus::AccessDenied),
+            Error::PermissionDenied
+        ));
+        assert!(matches!(
+            to_pairing_error(DevicePairingResultStatus::NoSupportedProfiles),
+            Error::Other(_)
+        ));
+    }
 }